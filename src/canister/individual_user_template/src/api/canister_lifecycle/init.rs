@@ -1,4 +1,10 @@
-use crate::{data_model::CanisterData, CANISTER_DATA, PUMP_N_DUMP};
+use crate::{
+    data_model::{
+        state_schema::{set_state_schema_version, STATE_SCHEMA},
+        CanisterData,
+    },
+    CANISTER_DATA, PUMP_N_DUMP,
+};
 use ic_cdk_macros::init;
 use shared_utils::canister_specific::individual_user_template::types::arg::IndividualUserTemplateInitArgs;
 
@@ -14,6 +20,8 @@ fn init(init_args: IndividualUserTemplateInitArgs) {
         let mut data = canister_data_ref_cell.borrow_mut();
         init_impl(init_args, &mut data);
     });
+
+    set_state_schema_version(STATE_SCHEMA.current_version());
 }
 
 fn init_impl(init_args: IndividualUserTemplateInitArgs, data: &mut CanisterData) {
@@ -28,6 +36,10 @@ fn init_impl(init_args: IndividualUserTemplateInitArgs, data: &mut CanisterData)
 
     data.profile.principal_id = init_args.profile_owner;
 
+    if let Some(creator_token_limit) = init_args.creator_token_limit {
+        data.creator_token_limit.network_limit = creator_token_limit;
+    }
+
     data.version_details.version_number = init_args.upgrade_version_number.unwrap_or_default();
     data.version_details.version = init_args.version;
 }
@@ -68,6 +80,7 @@ mod test {
             ),
             version: String::from("v1.0.0"),
             pump_dump_onboarding_reward: Some(default_pump_dump_onboarding_reward()),
+            creator_token_limit: Some(5),
         };
         let mut data = CanisterData::default();
 
@@ -94,5 +107,6 @@ mod test {
         );
 
         assert!(data.version_details.version.eq("v1.0.0"));
+        assert_eq!(data.creator_token_limit.get_limit(), 5);
     }
 }
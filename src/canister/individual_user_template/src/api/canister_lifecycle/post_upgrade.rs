@@ -1,10 +1,25 @@
-use ciborium::de;
 use ic_cdk::api::call::ArgDecoderConfig;
 use ic_cdk_macros::post_upgrade;
 use ic_stable_structures::reader::Reader;
 use std::borrow::BorrowMut;
 
-use crate::{data_model::memory, PUMP_N_DUMP};
+use crate::{
+    api::{
+        cdao::{
+            deployment::restart_cdao_deployment_recovery_after_upgrade,
+            token_holdings::restart_token_holdings_refresh_after_upgrade,
+            transfer_token_to_user_canister::restart_creator_token_transfer_retry_after_upgrade,
+        },
+        pump_n_dump::restart_earnings_summary_after_upgrade,
+    },
+    data_model::{
+        memory,
+        state_schema::{
+            decode_upgrade_state, get_state_schema_version, set_state_schema_version, STATE_SCHEMA,
+        },
+    },
+    PUMP_N_DUMP,
+};
 
 use shared_utils::canister_specific::individual_user_template::types::arg::IndividualUserTemplateInitArgs;
 
@@ -14,6 +29,10 @@ use crate::CANISTER_DATA;
 fn post_upgrade() {
     restore_data_from_stable_memory();
     save_upgrade_args_to_memory();
+    restart_cdao_deployment_recovery_after_upgrade();
+    restart_token_holdings_refresh_after_upgrade();
+    restart_creator_token_transfer_retry_after_upgrade();
+    restart_earnings_summary_after_upgrade();
 }
 
 fn restore_data_from_stable_memory() {
@@ -27,25 +46,28 @@ fn restore_data_from_stable_memory() {
     let mut canister_data_bytes = vec![0; heap_data_len];
     upgrade_reader.read(&mut canister_data_bytes).unwrap();
 
-    let canister_data =
-        de::from_reader(&*canister_data_bytes).expect("Failed to deserialize heap data");
-
-    CANISTER_DATA.with_borrow_mut(|cdata| {
-        *cdata = canister_data;
-    });
-
     upgrade_reader.read(&mut heap_data_len_bytes).unwrap();
     heap_data_len = u32::from_le_bytes(heap_data_len_bytes) as usize;
 
     let mut pump_n_dump_data_bytes = vec![0; heap_data_len];
     upgrade_reader.read(&mut pump_n_dump_data_bytes).unwrap();
 
-    let token_bet_data = de::from_reader(&*pump_n_dump_data_bytes)
-        .expect("Failed to deserialize pump and dump heap data");
+    let (canister_data, token_bet_data) = decode_upgrade_state(
+        get_state_schema_version(),
+        &canister_data_bytes,
+        &pump_n_dump_data_bytes,
+    )
+    .unwrap_or_else(|e| panic!("Failed to restore state after upgrade {}", e));
+
+    CANISTER_DATA.with_borrow_mut(|cdata| {
+        *cdata = canister_data;
+        cdata.backfill_signed_up_at();
+    });
 
     PUMP_N_DUMP.with_borrow_mut(|token_bet_game| {
         *token_bet_game = token_bet_data;
     });
+    set_state_schema_version(STATE_SCHEMA.current_version());
 }
 
 fn save_upgrade_args_to_memory() {
@@ -71,6 +93,10 @@ fn save_upgrade_args_to_memory() {
             canister_data_ref_cell.version_details.version_number = upgrade_version_number;
         }
 
+        if let Some(creator_token_limit) = upgrade_args.creator_token_limit {
+            canister_data_ref_cell.creator_token_limit.network_limit = creator_token_limit;
+        }
+
         canister_data_ref_cell.borrow_mut().version_details.version = upgrade_args.version;
     });
 }
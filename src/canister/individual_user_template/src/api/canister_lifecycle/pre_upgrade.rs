@@ -4,6 +4,7 @@ use ic_cdk_macros::pre_upgrade;
 use ic_stable_structures::writer::Writer;
 
 use crate::data_model::memory;
+use crate::data_model::state_schema::{set_state_schema_version, STATE_SCHEMA};
 use crate::{CANISTER_DATA, PUMP_N_DUMP};
 
 
@@ -35,4 +36,6 @@ fn pre_upgrade() {
 
     writer.write(&len.to_le_bytes()).unwrap();
     writer.write(&state_bytes).unwrap();
+
+    set_state_schema_version(STATE_SCHEMA.current_version());
 }
\ No newline at end of file
@@ -0,0 +1,10 @@
+use std::time::SystemTime;
+
+use ic_cdk_macros::query;
+
+use crate::CANISTER_DATA;
+
+#[query]
+fn get_signed_up_at() -> Option<SystemTime> {
+    CANISTER_DATA.with_borrow(|canister_data| canister_data.signed_up_at)
+}
@@ -1,13 +1,17 @@
-use crate::util::{
-    cycles::notify_to_recharge_canister,
-    migration::{IndividualUser, Migration},
-    subnet_orchestrator,
+use crate::{
+    util::{
+        cycles::notify_to_recharge_canister,
+        guards::is_caller_profile_owner,
+        migration::{IndividualUser, Migration},
+    },
+    CANISTER_DATA,
 };
 use candid::Principal;
 use ic_cdk::caller;
-use ic_cdk_macros::update;
+use ic_cdk_macros::{query, update};
 use shared_utils::canister_specific::individual_user_template::types::{
-    migration::MigrationErrors, post::Post,
+    migration::{MigrationErrors, PendingMigration},
+    post::Post,
 };
 
 #[update]
@@ -19,11 +23,23 @@ pub async fn transfer_tokens_and_posts(
 
     let caller = caller();
     let user = IndividualUser::from_canister_data().await?;
-    let to_individual_user = IndividualUser::new(to_account_canister_id, to_account, None).await?;
+    let to_individual_user = IndividualUser::new(to_account_canister_id, to_account).await?;
     user.transfer_tokens_and_posts(caller, to_individual_user)
         .await
 }
 
+/// Called by the owner of this Yral canister to agree to receive the posts and the balance
+/// of their Hot or Not account. Only the requested account can migrate into this canister.
+#[update(guard = "is_caller_profile_owner")]
+pub async fn request_migration_from_hotornot(
+    from_account: Principal,
+) -> Result<(), MigrationErrors> {
+    notify_to_recharge_canister();
+
+    let user = IndividualUser::from_canister_data().await?;
+    user.request_migration_from_hot_or_not(from_account)
+}
+
 #[update]
 pub async fn receive_data_from_hotornot(
     from_account: Principal,
@@ -34,9 +50,16 @@ pub async fn receive_data_from_hotornot(
 
     let user = IndividualUser::from_canister_data().await?;
 
-    let from_individual_user = IndividualUser::new(caller(), from_account, None).await?;
+    let from_individual_user = IndividualUser::new(caller(), from_account).await?;
 
     user.recieve_tokens_and_posts(from_individual_user, amount, posts)
         .await?;
     Ok(())
 }
+
+/// A migration into or out of this canister that is not finished yet. Finished migrations
+/// are reported in the profile's `migration_info`.
+#[query]
+pub fn get_pending_migration() -> Option<PendingMigration> {
+    CANISTER_DATA.with_borrow(|canister_data| canister_data.pending_migration)
+}
@@ -1,7 +1,10 @@
 use ic_cdk_macros::update;
 use shared_utils::{
     canister_specific::individual_user_template::types::session::SessionType,
-    common::utils::permissions::is_caller_controller_or_global_admin_v2,
+    common::utils::{
+        permissions::is_caller_controller_or_global_admin_v2,
+        system_time::get_current_system_time_from_ic,
+    },
 };
 
 use crate::{util::cycles::notify_to_recharge_canister, CANISTER_DATA};
@@ -20,6 +23,9 @@ fn update_session_type(session_type: SessionType) -> Result<String, String> {
             return Err("Session Already marked as Registered Session".to_string());
         }
         canister_data.session_type = Some(session_type);
+        if session_type == SessionType::RegisteredSession {
+            canister_data.signed_up_at = Some(get_current_system_time_from_ic());
+        }
 
         Ok("Success".to_string())
     })
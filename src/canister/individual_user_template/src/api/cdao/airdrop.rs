@@ -1,18 +1,32 @@
+use std::time::SystemTime;
+
 use candid::{Nat, Principal};
 use ic_base_types::PrincipalId;
+use ic_cdk::api::management_canister::main::{canister_info, CanisterInfoRequest};
 use ic_cdk_macros::update;
 use ic_sns_root::pb::v1::{ListSnsCanistersRequest, ListSnsCanistersResponse};
 use icrc_ledger_types::icrc1::{
     account::Account,
     transfer::{Memo, TransferArg, TransferError},
 };
-use shared_utils::canister_specific::individual_user_template::types::{
-    error::AirdropError, profile::UserProfileDetailsForFrontendV2,
+use shared_utils::{
+    canister_specific::individual_user_template::types::{
+        cdao::{AirdropClaimantDetails, AirdropConfig},
+        error::AirdropError,
+        follow::FollowEntryDetail,
+        profile::UserProfileDetailsForFrontendV2,
+        session::SessionType,
+    },
+    common::utils::system_time::get_current_system_time,
 };
 
-use crate::{util::cycles::notify_to_recharge_canister, CANISTER_DATA};
+use crate::{
+    util::{
+        cycles::notify_to_recharge_canister, subnet_orchestrator::get_all_subnet_orchestrators,
+    },
+    CANISTER_DATA,
+};
 
-// TODO: Add checks to verify if the user_canister is from our network of yral canisters
 #[update]
 async fn request_airdrop(
     token_root: Principal,
@@ -28,20 +42,24 @@ async fn request_airdrop(
         return Err(AirdropError::CanisterPrincipalDoNotMatch);
     }
 
-    let amount = amount.min(100_000_000_000u64.into()); // 1000 * 10^8
+    verify_user_canister_is_in_network(current_caller, user_canister).await?;
+
+    let claimant_details = get_claimant_details(token_root, current_caller, user_canister).await?;
 
-    set_airdrop_claiming(token_root, current_caller);
+    let amount = reserve_airdrop(token_root, current_caller, amount, &claimant_details)?;
 
-    request_airdrop_internal(token_root, current_caller, memo, amount)
+    request_airdrop_internal(token_root, current_caller, memo, amount.clone())
         .await
         .inspect_err(|_| {
+            // nothing to roll back if the token was removed in the meantime
             CANISTER_DATA.with_borrow_mut(|cans_data| {
-                cans_data
+                if let Some(cdao) = cans_data
                     .cdao_canisters
                     .iter_mut()
                     .find(|cdao| cdao.root == token_root)
-                    .map(|cdao| cdao.airdrop_info.set_airdrop_unclaimed(current_caller))
-                    .unwrap(); // can safely unwrap updating the states for the airdrop for the user creates it in place if not exists
+                {
+                    cdao.airdrop_info.release_airdrop(current_caller, amount)
+                }
             });
         })?; // rollback to unclaimed if error
 
@@ -49,6 +67,130 @@ async fn request_airdrop(
     Ok(())
 }
 
+#[update]
+fn set_airdrop_config(token_root: Principal, config: AirdropConfig) -> Result<(), AirdropError> {
+    let my_principal_id = CANISTER_DATA.with_borrow(|cans_data| cans_data.profile.principal_id);
+    if my_principal_id != Some(ic_cdk::caller()) {
+        return Err(AirdropError::Unauthorized);
+    }
+
+    config.validate()?;
+
+    CANISTER_DATA.with_borrow_mut(|cans_data| {
+        let cdao = cans_data
+            .cdao_canisters
+            .iter_mut()
+            .find(|cdao| cdao.root == token_root)
+            .ok_or(AirdropError::InvalidRoot)?;
+
+        if cdao.airdrop_info.total_claimed > config.total_budget {
+            return Err(AirdropError::InvalidAirdropConfig(
+                "Total budget is lower than the amount already claimed".into(),
+            ));
+        }
+
+        cdao.airdrop_info.config = Some(config);
+        Ok(())
+    })
+}
+
+/// The user canister has to be the one a registered subnet orchestrator assigned to the
+/// caller, claimants can be on any subnet
+async fn verify_user_canister_is_in_network(
+    current_caller: Principal,
+    user_canister: Principal,
+) -> Result<(), AirdropError> {
+    let subnet_orchestrators = get_all_subnet_orchestrators().await?;
+
+    let (user_canister_info,) = canister_info(CanisterInfoRequest {
+        canister_id: user_canister,
+        num_requested_changes: None,
+    })
+    .await?;
+    let user_subnet_orchestrator = user_canister_info
+        .controllers
+        .into_iter()
+        .find(|controller| subnet_orchestrators.contains(controller))
+        .ok_or(AirdropError::CanisterNotInNetwork)?;
+
+    let (registered_canister,): (Option<Principal>,) = ic_cdk::call(
+        user_subnet_orchestrator,
+        "get_user_canister_id_from_user_principal_id",
+        (current_caller,),
+    )
+    .await?;
+
+    if registered_canister != Some(user_canister) {
+        return Err(AirdropError::CanisterNotInNetwork);
+    }
+
+    Ok(())
+}
+
+/// Only fetches the details the airdrop's eligibility rules need
+async fn get_claimant_details(
+    token_root: Principal,
+    current_caller: Principal,
+    user_canister: Principal,
+) -> Result<AirdropClaimantDetails, AirdropError> {
+    let eligibility = CANISTER_DATA
+        .with_borrow(|cans_data| {
+            cans_data
+                .cdao_canisters
+                .iter()
+                .find(|cdao| cdao.root == token_root)
+                .map(|cdao| cdao.airdrop_info.config.clone())
+        })
+        .ok_or(AirdropError::InvalidRoot)?
+        .map(|config| config.eligibility)
+        .unwrap_or_default();
+
+    let mut claimant_details = AirdropClaimantDetails {
+        follows_creator: CANISTER_DATA.with_borrow(|cans_data| {
+            cans_data.follow_data.follower.contains(&FollowEntryDetail {
+                principal_id: current_caller,
+                canister_id: user_canister,
+            })
+        }),
+        ..Default::default()
+    };
+
+    if eligibility.registered_session_only {
+        let (session_type,): (Result<SessionType, String>,) =
+            ic_cdk::call(user_canister, "get_session_type", ()).await?;
+        claimant_details.session_type = session_type.ok();
+    }
+
+    if eligibility.min_account_age_secs.is_some() {
+        let (signed_up_at,): (Option<SystemTime>,) =
+            ic_cdk::call(user_canister, "get_signed_up_at", ()).await?;
+        claimant_details.signed_up_at = signed_up_at;
+    }
+
+    Ok(claimant_details)
+}
+
+fn reserve_airdrop(
+    token_root: Principal,
+    current_caller: Principal,
+    amount: Nat,
+    claimant_details: &AirdropClaimantDetails,
+) -> Result<Nat, AirdropError> {
+    let current_time = get_current_system_time();
+
+    let amount = CANISTER_DATA.with_borrow_mut(|cans_data| {
+        cans_data
+            .cdao_canisters
+            .iter_mut()
+            .find(|cdao| cdao.root == token_root)
+            .ok_or(AirdropError::InvalidRoot)?
+            .airdrop_info
+            .reserve_airdrop(current_caller, amount, claimant_details, current_time)
+    })?;
+
+    Ok(amount)
+}
+
 async fn request_airdrop_internal(
     token_root: Principal,
     current_caller: Principal,
@@ -73,34 +215,6 @@ async fn get_profile_info(
     Ok(profile_info)
 }
 
-fn is_airdrop_unclaimed(
-    token_root: Principal,
-    current_caller: &Principal,
-) -> Result<bool, AirdropError> {
-    CANISTER_DATA
-        .with_borrow(|cans_data| {
-            cans_data
-                .cdao_canisters
-                .iter()
-                .find(|cdao| cdao.root == token_root)
-                .map(|cdao| cdao.airdrop_info.is_airdrop_unclaimed(current_caller))
-        })
-        .ok_or(AirdropError::InvalidRoot)
-}
-
-fn set_airdrop_claiming(token_root: Principal, current_caller: Principal) {
-    CANISTER_DATA.with_borrow_mut(|cans_data| {
-        if let Some(cdao) = cans_data
-            .cdao_canisters
-            .iter_mut()
-            .find(|cdao| cdao.root == token_root)
-        {
-            cdao.airdrop_info.set_airdrop_claiming(current_caller)
-        }
-    });
-    ic_cdk::println!("Setting airdrop claiming for user: {:?}", current_caller);
-}
-
 async fn get_ledger(token_root: Principal) -> Result<PrincipalId, AirdropError> {
     let res: (ListSnsCanistersResponse,) = ic_cdk::call(
         token_root,
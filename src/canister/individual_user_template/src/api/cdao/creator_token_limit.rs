@@ -0,0 +1,27 @@
+use ic_cdk_macros::{query, update};
+use shared_utils::{
+    canister_specific::individual_user_template::types::cdao::CreatorTokenLimit,
+    common::utils::permissions::is_caller_controller,
+};
+
+use crate::CANISTER_DATA;
+
+#[query]
+fn get_creator_token_limit() -> CreatorTokenLimit {
+    CANISTER_DATA.with_borrow(|canister_data| canister_data.creator_token_limit)
+}
+
+#[update(guard = "is_caller_controller")]
+fn update_creator_token_limit(limit: usize) {
+    CANISTER_DATA.with_borrow_mut(|canister_data| {
+        canister_data.creator_token_limit.network_limit = limit;
+    });
+}
+
+/// Verified creators can be allowed more (or fewer) tokens than the rest of the network
+#[update(guard = "is_caller_controller")]
+fn set_creator_token_limit_override(limit: Option<usize>) {
+    CANISTER_DATA.with_borrow_mut(|canister_data| {
+        canister_data.creator_token_limit.creator_override = limit;
+    });
+}
@@ -0,0 +1,293 @@
+use std::collections::BTreeSet;
+
+use candid::{Nat, Principal};
+use futures::future::join_all;
+use ic_cdk::api::management_canister::main::{canister_status, CanisterIdRecord};
+use ic_cdk_macros::update;
+use icrc_ledger_types::{
+    icrc1::account::Account,
+    icrc3::transactions::{GetTransactionsRequest, GetTransactionsResponse},
+};
+use shared_utils::{
+    canister_specific::individual_user_template::types::{
+        cdao::{CreatorTokenDeletionReport, DeployedCdaoCanisters},
+        error::DeleteCreatorTokenError,
+    },
+    types::sns_canisters::swap::{self, GetDerivedStateArg, GetLifecycleArg},
+};
+
+use crate::{
+    util::{
+        cycles::{notify_to_recharge_canister, request_cycles_from_subnet_orchestrator},
+        subnet_orchestrator::SubnetOrchestrator,
+    },
+    CANISTER_DATA,
+};
+
+use super::utils::try_uninstall_code_and_return_empty_canisters_to_subnet_backup_pool;
+
+pub const UNINSTALL_RECHARGE_AMOUNT: u128 = 500_000_000_000; //0.5T cycles
+
+const LEDGER_TRANSACTIONS_PER_REQUEST: u64 = 1000;
+const BALANCE_REQUESTS_IN_PARALLEL: usize = 10;
+
+// values of the SNS swap's `Lifecycle`
+const SWAP_LIFECYCLE_PENDING: i32 = 1;
+const SWAP_LIFECYCLE_OPEN: i32 = 2;
+const SWAP_LIFECYCLE_COMMITTED: i32 = 3;
+const SWAP_LIFECYCLE_ABORTED: i32 = 4;
+const SWAP_LIFECYCLE_ADOPTED: i32 = 5;
+
+/// Deletes a single creator token. Can be called by the owner of the canister
+/// or by the subnet orchestrator on behalf of the admins.
+/// Tokens with an open swap or with holders are only deleted when forced, as are tokens
+/// whose swap or holders could not be determined. Only the subnet orchestrator can force
+/// a deletion, the owner cannot take a token away from its holders.
+#[update]
+async fn delete_creator_token(
+    root: Principal,
+    force: bool,
+) -> Result<CreatorTokenDeletionReport, DeleteCreatorTokenError> {
+    notify_to_recharge_canister();
+
+    let current_caller = ic_cdk::caller();
+    let my_principal_id =
+        CANISTER_DATA.with_borrow(|canister_data| canister_data.profile.principal_id);
+    if !ic_cdk::api::is_controller(&current_caller)
+        && (force || my_principal_id != Some(current_caller))
+    {
+        return Err(DeleteCreatorTokenError::Unauthorized);
+    }
+
+    let deployed_canisters = check_creator_token_can_be_deleted(root, force).await?;
+
+    // the checks awaited, another deletion may have removed the token meanwhile
+    if !remove_sns_creator_token(&deployed_canisters) {
+        return Err(DeleteCreatorTokenError::TokenNotFound);
+    }
+
+    let _ = request_cycles_from_subnet_orchestrator(5 * UNINSTALL_RECHARGE_AMOUNT).await;
+
+    let canister_ids = deployed_canisters.get_canister_ids();
+    let mut canister_cycles = vec![];
+    for canister_id in canister_ids.iter().copied() {
+        let cycles = canister_status(CanisterIdRecord { canister_id })
+            .await
+            .map(|(canister_status,)| canister_status.cycles)
+            .unwrap_or_default();
+        canister_cycles.push((canister_id, cycles));
+    }
+
+    let failed_canister_ids =
+        try_uninstall_code_and_return_empty_canisters_to_subnet_backup_pool(canister_ids).await;
+
+    let mut report = CreatorTokenDeletionReport {
+        root,
+        returned_canister_ids: vec![],
+        failed_canister_ids,
+        reclaimed_cycles: Nat::from(0_u32),
+    };
+
+    canister_cycles
+        .into_iter()
+        .filter(|(canister_id, _)| !report.failed_canister_ids.contains(canister_id))
+        .for_each(|(canister_id, cycles)| {
+            report.returned_canister_ids.push(canister_id);
+            report.reclaimed_cycles += cycles;
+        });
+
+    ic_cdk::println!(
+        "Deleted creator SNS canisters with Root Canister Id {}, reclaimed {} cycles",
+        root.to_text(),
+        report.reclaimed_cycles
+    );
+
+    Ok(report)
+}
+
+async fn check_creator_token_can_be_deleted(
+    root: Principal,
+    force: bool,
+) -> Result<DeployedCdaoCanisters, DeleteCreatorTokenError> {
+    let (deployed_canisters, creator_principal_id) =
+        CANISTER_DATA.with_borrow(|canister_data| {
+            let deployed_canisters = canister_data
+                .cdao_canisters
+                .iter()
+                .find(|cdao| cdao.root == root)
+                .cloned()
+                .ok_or(DeleteCreatorTokenError::TokenNotFound)?;
+
+            Ok::<_, DeleteCreatorTokenError>((
+                deployed_canisters,
+                canister_data.profile.principal_id,
+            ))
+        })?;
+
+    if force {
+        return Ok(deployed_canisters);
+    }
+
+    let number_of_airdrop_claimants = deployed_canisters.airdrop_info.get_number_of_claimants();
+    if number_of_airdrop_claimants > 0 {
+        return Err(DeleteCreatorTokenError::HoldersExist(
+            number_of_airdrop_claimants,
+        ));
+    }
+
+    let number_of_swap_participants =
+        get_number_of_swap_participants(deployed_canisters.swap).await?;
+    let number_of_holders =
+        get_number_of_ledger_holders(&deployed_canisters, creator_principal_id).await?;
+    if number_of_swap_participants + number_of_holders > 0 {
+        return Err(DeleteCreatorTokenError::HoldersExist(
+            number_of_swap_participants + number_of_holders,
+        ));
+    }
+
+    Ok(deployed_canisters)
+}
+
+/// Asks the swap itself, the swaps of tokens deployed before their deployment was
+/// recorded are covered as well
+async fn get_number_of_swap_participants(
+    swap_canister: Principal,
+) -> Result<u64, DeleteCreatorTokenError> {
+    let (lifecycle,) = swap::Service(swap_canister)
+        .get_lifecycle(GetLifecycleArg {})
+        .await?;
+
+    match lifecycle.lifecycle {
+        Some(SWAP_LIFECYCLE_PENDING | SWAP_LIFECYCLE_ABORTED) => Ok(0),
+        Some(SWAP_LIFECYCLE_OPEN | SWAP_LIFECYCLE_ADOPTED) => {
+            Err(DeleteCreatorTokenError::SwapOpen)
+        }
+        Some(SWAP_LIFECYCLE_COMMITTED) => {
+            let (derived_state,) = swap::Service(swap_canister)
+                .get_derived_state(GetDerivedStateArg {})
+                .await?;
+
+            match (
+                derived_state.direct_participant_count,
+                derived_state.cf_participant_count,
+            ) {
+                (Some(direct_participant_count), cf_participant_count) => {
+                    Ok(direct_participant_count + cf_participant_count.unwrap_or_default())
+                }
+                (None, _) => Err(DeleteCreatorTokenError::SwapStatusUnknown),
+            }
+        }
+        _ => Err(DeleteCreatorTokenError::SwapStatusUnknown),
+    }
+}
+
+/// Accounts other than the creator's and the SNS canisters' that still hold the token.
+/// Every account that ever received the token is found in the ledger's transactions.
+/// Neurons are held by the governance canister, their holders are the swap participants.
+async fn get_number_of_ledger_holders(
+    deployed_canisters: &DeployedCdaoCanisters,
+    creator_principal_id: Option<Principal>,
+) -> Result<u64, DeleteCreatorTokenError> {
+    let ledger = deployed_canisters.ledger;
+    let excluded_owners: BTreeSet<Principal> = deployed_canisters
+        .get_canister_ids()
+        .into_iter()
+        .chain(creator_principal_id)
+        .chain([ic_cdk::id()])
+        .collect();
+
+    let mut recipients = BTreeSet::new();
+    let mut start = 0_u64;
+    loop {
+        let (response,): (GetTransactionsResponse,) = ic_cdk::call(
+            ledger,
+            "get_transactions",
+            (GetTransactionsRequest {
+                start: Nat::from(start),
+                length: Nat::from(LEDGER_TRANSACTIONS_PER_REQUEST),
+            },),
+        )
+        .await?;
+
+        // archived transactions are not read, their recipients would be missed
+        if !response.archived_transactions.is_empty() {
+            return Err(DeleteCreatorTokenError::HoldersUnknown);
+        }
+
+        let number_of_transactions = response.transactions.len() as u64;
+        recipients.extend(response.transactions.into_iter().filter_map(|transaction| {
+            transaction
+                .mint
+                .map(|mint| mint.to)
+                .or(transaction.transfer.map(|transfer| transfer.to))
+        }));
+
+        start += number_of_transactions;
+        if number_of_transactions == 0 || response.log_length <= start {
+            break;
+        }
+    }
+
+    let recipients: Vec<Account> = recipients
+        .into_iter()
+        .filter(|account: &Account| !excluded_owners.contains(&account.owner))
+        .collect();
+
+    let mut number_of_holders = 0;
+    for accounts in recipients.chunks(BALANCE_REQUESTS_IN_PARALLEL) {
+        let balances = join_all(
+            accounts
+                .iter()
+                .map(|account| ic_cdk::call::<_, (Nat,)>(ledger, "icrc1_balance_of", (*account,))),
+        )
+        .await;
+
+        for balance in balances {
+            let (balance,) = balance?;
+            if balance > Nat::from(0_u32) {
+                number_of_holders += 1;
+            }
+        }
+    }
+
+    Ok(number_of_holders)
+}
+
+/// Removed right away so the token stops counting towards the creator token limit
+/// and cannot be deleted twice while its canisters are being uninstalled. Its completed
+/// deployment goes as well, so the token is not described to the subnet orchestrator
+/// again. Returns false if the token was already removed.
+pub(super) fn remove_sns_creator_token(deployed_canisters: &DeployedCdaoCanisters) -> bool {
+    let removed = CANISTER_DATA.with_borrow_mut(|canister_data| {
+        let number_of_tokens = canister_data.cdao_canisters.len();
+        canister_data
+            .cdao_canisters
+            .retain(|cdao| cdao.root != deployed_canisters.root);
+        canister_data.token_roots.remove(&deployed_canisters.root);
+        canister_data
+            .token_holdings
+            .remove(&deployed_canisters.root);
+        canister_data
+            .cdao_deployments
+            .remove_completed_deployment(deployed_canisters.root);
+
+        canister_data.cdao_canisters.len() < number_of_tokens
+    });
+    if !removed {
+        return false;
+    }
+
+    let send_deleted_creator_dao_token_res =
+        SubnetOrchestrator::new().and_then(|subnet_orchestrator| {
+            subnet_orchestrator.send_deleted_creator_dao_token(deployed_canisters.root)
+        });
+
+    if let Err(e) = send_deleted_creator_dao_token_res {
+        ic_cdk::println!(
+            "Error sending deleted creator token to subnet orchestrator {}",
+            e
+        )
+    }
+
+    true
+}
@@ -0,0 +1,589 @@
+use std::{
+    cell::Cell,
+    future::Future,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use candid::{Encode, Principal};
+use futures::{stream::FuturesUnordered, StreamExt};
+use ic_base_types::PrincipalId;
+use ic_cdk::api::{
+    call::RejectionCode,
+    management_canister::main::{
+        deposit_cycles, install_code, update_settings, CanisterIdRecord, CanisterInstallMode,
+        CanisterSettings, InstallCodeArgument, UpdateSettingsArgument,
+    },
+};
+use ic_cdk_macros::{query, update};
+use ic_cdk_timers::TimerId;
+use ic_sns_governance::pb::v1::governance::Version as SnsVersion;
+use ic_sns_init::SnsCanisterIds;
+use ic_sns_wasm::pb::v1::{GetWasmRequest, GetWasmResponse};
+use shared_utils::{
+    canister_specific::individual_user_template::types::{
+        cdao::{CdaoDeploymentStatus, CdaoDeploymentStep, DeployedCdaoCanisters},
+        error::CdaoDeployError,
+    },
+    common::{
+        types::known_principal::KnownPrincipalType, utils::system_time::get_current_system_time,
+    },
+    constant::{
+        CREATOR_DAO_DEPLOYMENT_STALE_AFTER_SECS, MAX_ATTEMPTS_FOR_CREATOR_DAO_DEPLOYMENT,
+        NNS_LEDGER_CANISTER_ID, SNS_TOKEN_ARCHIVE_MODULE_HASH, SNS_TOKEN_GOVERNANCE_MODULE_HASH,
+        SNS_TOKEN_INDEX_MODULE_HASH, SNS_TOKEN_LEDGER_MODULE_HASH, SNS_TOKEN_ROOT_MODULE_HASH,
+        SNS_TOKEN_SWAP_MODULE_HASH, USER_SNS_CANISTER_INITIAL_CYCLES,
+    },
+};
+
+use crate::{
+    data_model::cdao_deployment::{CdaoDeployment, NUMBER_OF_SNS_CANISTERS},
+    util::{
+        cycles::{notify_to_recharge_canister, request_cycles_from_subnet_orchestrator},
+        subnet_orchestrator::SubnetOrchestrator,
+    },
+    CANISTER_DATA,
+};
+
+use super::{
+    token_holdings::schedule_token_holdings_refresh,
+    utils::uninstall_code_and_return_empty_canisters_to_subnet_backup_pool,
+};
+
+thread_local! {
+    static CDAO_DEPLOYMENT_RECOVERY_TIMER: Cell<Option<TimerId>> = const { Cell::new(None) };
+}
+
+#[query]
+fn get_cdao_deployment_status(deployment_id: u64) -> Option<CdaoDeploymentStatus> {
+    CANISTER_DATA.with_borrow(|canister_data| {
+        canister_data
+            .cdao_deployments
+            .get(deployment_id)
+            .map(|deployment| deployment.get_status(deployment_id))
+    })
+}
+
+#[update]
+async fn resume_cdao_deployment(
+    deployment_id: u64,
+) -> Result<DeployedCdaoCanisters, CdaoDeployError> {
+    notify_to_recharge_canister();
+
+    let my_principal_id =
+        CANISTER_DATA.with_borrow(|canister_data| canister_data.profile.principal_id);
+    if my_principal_id != Some(ic_cdk::caller()) {
+        return Err(CdaoDeployError::Unauthenticated);
+    }
+
+    let current_time = get_current_system_time();
+    let can_resume = CANISTER_DATA
+        .with_borrow(|canister_data| {
+            canister_data
+                .cdao_deployments
+                .get(deployment_id)
+                .map(|deployment| deployment.can_resume(current_time))
+        })
+        .ok_or(CdaoDeployError::DeploymentNotFound(deployment_id))?;
+
+    if !can_resume {
+        return Err(CdaoDeployError::DeploymentNotResumable(deployment_id));
+    }
+
+    run_cdao_deployment(deployment_id).await
+}
+
+fn with_deployment_mut<T>(
+    deployment_id: u64,
+    f: impl FnOnce(&mut CdaoDeployment) -> T,
+) -> Result<T, CdaoDeployError> {
+    CANISTER_DATA.with_borrow_mut(|canister_data| {
+        canister_data
+            .cdao_deployments
+            .get_mut(deployment_id)
+            .map(f)
+            .ok_or(CdaoDeployError::DeploymentNotFound(deployment_id))
+    })
+}
+
+/// Writes on behalf of a run, refused once a newer attempt has resumed the deployment
+fn with_current_attempt_mut<T>(
+    deployment_id: u64,
+    attempt: u32,
+    f: impl FnOnce(&mut CdaoDeployment) -> T,
+) -> Result<T, CdaoDeployError> {
+    with_deployment_mut(deployment_id, |deployment| {
+        deployment
+            .is_current_attempt(attempt)
+            .then(|| f(deployment))
+            .ok_or(CdaoDeployError::DeploymentSuperseded(deployment_id))
+    })?
+}
+
+fn get_deployment(deployment_id: u64) -> Result<CdaoDeployment, CdaoDeployError> {
+    CANISTER_DATA.with_borrow(|canister_data| {
+        canister_data
+            .cdao_deployments
+            .get(deployment_id)
+            .cloned()
+            .ok_or(CdaoDeployError::DeploymentNotFound(deployment_id))
+    })
+}
+
+/// Runs the remaining steps of the deployment, checkpointing progress along the way.
+/// Failed deployments are left to be resumed, unless they ran out of attempts.
+pub(super) async fn run_cdao_deployment(
+    deployment_id: u64,
+) -> Result<DeployedCdaoCanisters, CdaoDeployError> {
+    let attempt = with_deployment_mut(deployment_id, |deployment| {
+        deployment.start_attempt(get_current_system_time())
+    })?;
+    schedule_cdao_deployment_recovery();
+
+    let result = execute_cdao_deployment_steps(deployment_id, attempt).await;
+
+    if let Err(e) = &result {
+        ic_cdk::println!("Creator DAO deployment {} failed: {:?}", deployment_id, e);
+
+        // a superseded attempt leaves the deployment to the attempt that resumed it
+        let is_current_attempt = with_current_attempt_mut(deployment_id, attempt, |deployment| {
+            deployment.fail_attempt(format!("{:?}", e), get_current_system_time())
+        })
+        .is_ok();
+
+        if is_current_attempt
+            && (matches!(e, CdaoDeployError::InvalidInitPayload(_))
+                || attempt >= MAX_ATTEMPTS_FOR_CREATOR_DAO_DEPLOYMENT)
+        {
+            roll_back_cdao_deployment(deployment_id);
+        }
+    }
+
+    result
+}
+
+async fn execute_cdao_deployment_steps(
+    deployment_id: u64,
+    attempt: u32,
+) -> Result<DeployedCdaoCanisters, CdaoDeployError> {
+    loop {
+        let step = get_deployment(deployment_id)?.step;
+
+        match step {
+            CdaoDeploymentStep::AllottingCanisters => {
+                allot_sns_canisters(deployment_id, attempt).await?
+            }
+            CdaoDeploymentStep::DepositingCycles => {
+                deposit_cycles_to_sns_canisters(deployment_id, attempt).await?
+            }
+            CdaoDeploymentStep::InstallingWasms => {
+                install_sns_wasms(deployment_id, attempt).await?
+            }
+            CdaoDeploymentStep::UpdatingControllers => {
+                update_sns_controllers(deployment_id, attempt).await?
+            }
+            CdaoDeploymentStep::Completed | CdaoDeploymentStep::RolledBack => {
+                return Err(CdaoDeployError::DeploymentNotResumable(deployment_id))
+            }
+        }
+
+        if let Some(deployed_cans) = advance_cdao_deployment(deployment_id, attempt)? {
+            return Ok(deployed_cans);
+        }
+    }
+}
+
+/// Moves the deployment to its next step. Registers the deployed canisters
+/// in the same message as completing the deployment, so it happens exactly once.
+fn advance_cdao_deployment(
+    deployment_id: u64,
+    attempt: u32,
+) -> Result<Option<DeployedCdaoCanisters>, CdaoDeployError> {
+    let deployed_cans = CANISTER_DATA.with_borrow_mut(|canister_data| {
+        let deployment = canister_data
+            .cdao_deployments
+            .get_mut(deployment_id)
+            .ok_or(CdaoDeployError::DeploymentNotFound(deployment_id))?;
+        if !deployment.is_current_attempt(attempt) {
+            return Err(CdaoDeployError::DeploymentSuperseded(deployment_id));
+        }
+
+        deployment.advance(get_current_system_time());
+        if deployment.step != CdaoDeploymentStep::Completed {
+            return Ok(None);
+        }
+
+        let deployed_cans = deployment
+            .get_deployed_cdao_canisters()
+            .ok_or(CdaoDeployError::DeploymentNotResumable(deployment_id))?;
+
+        canister_data.cdao_canisters.push(deployed_cans.clone());
+        canister_data.token_roots.insert(deployed_cans.root, ());
+
+        Ok::<_, CdaoDeployError>(Some(deployed_cans))
+    })?;
+
+    if let Some(deployed_cans) = &deployed_cans {
+        schedule_token_holdings_refresh();
+
+        let token_metadata = CANISTER_DATA.with_borrow(|canister_data| {
+            canister_data.profile.principal_id.and_then(|profile_id| {
+                canister_data.cdao_deployments.get_token_metadata(
+                    deployed_cans.root,
+                    profile_id,
+                    ic_cdk::id(),
+                    get_current_system_time(),
+                )
+            })
+        });
+
+        let send_creator_dao_stats_res =
+            SubnetOrchestrator::new().and_then(|subnet_orchestrator| {
+                subnet_orchestrator.send_creator_dao_stats(
+                    [deployed_cans.root].into_iter().collect(),
+                    token_metadata.into_iter().collect(),
+                )
+            });
+
+        if let Err(e) = send_creator_dao_stats_res {
+            ic_cdk::println!("Error sending creator stats to subnet orchestrator {}", e)
+        }
+    }
+
+    Ok(deployed_cans)
+}
+
+fn roll_back_cdao_deployment(deployment_id: u64) {
+    let canister_ids = with_deployment_mut(deployment_id, |deployment| {
+        deployment.roll_back(get_current_system_time());
+        deployment.canister_ids.clone()
+    });
+
+    if let Ok(canister_ids) = canister_ids {
+        ic_cdk::spawn(
+            uninstall_code_and_return_empty_canisters_to_subnet_backup_pool(canister_ids),
+        );
+    }
+}
+
+/// Runs the task for every canister still pending in the current step,
+/// checkpointing each canister as soon as its task succeeds.
+async fn run_for_pending_canisters<Fut>(
+    deployment_id: u64,
+    attempt: u32,
+    task: impl Fn(Principal) -> Fut,
+) -> Result<(), CdaoDeployError>
+where
+    Fut: Future<Output = Result<Principal, CdaoDeployError>>,
+{
+    let mut tasks = get_deployment(deployment_id)?
+        .get_pending_canisters()
+        .into_iter()
+        .map(task)
+        .collect::<FuturesUnordered<_>>();
+
+    let mut first_error = None;
+    while let Some(result) = tasks.next().await {
+        match result {
+            Ok(canister_id) => with_current_attempt_mut(deployment_id, attempt, |deployment| {
+                deployment.checkpoint_canister(canister_id, get_current_system_time())
+            })?,
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+
+    first_error.map_or(Ok(()), Err)
+}
+
+async fn allot_sns_canisters(deployment_id: u64, attempt: u32) -> Result<(), CdaoDeployError> {
+    let remaining =
+        NUMBER_OF_SNS_CANISTERS.saturating_sub(get_deployment(deployment_id)?.canister_ids.len());
+
+    let subnet_orchestrator = SubnetOrchestrator::new()
+        .map_err(|e| CdaoDeployError::CallError(RejectionCode::CanisterError, e))?;
+
+    let mut allot_futs = (0..remaining)
+        .map(|_| subnet_orchestrator.allot_empty_canister())
+        .collect::<FuturesUnordered<_>>();
+
+    let mut first_error = None;
+    let mut surplus_canister_ids = vec![];
+    while let Some(result) = allot_futs.next().await {
+        match result {
+            Ok(canister_id) => {
+                let added = with_current_attempt_mut(deployment_id, attempt, |deployment| {
+                    deployment.add_canister(canister_id, get_current_system_time())
+                });
+                match added {
+                    Ok(true) => {}
+                    Ok(false) => surplus_canister_ids.push(canister_id),
+                    Err(e) => {
+                        surplus_canister_ids.push(canister_id);
+                        first_error.get_or_insert(e);
+                    }
+                }
+            }
+            Err(e) => {
+                first_error
+                    .get_or_insert(CdaoDeployError::CallError(RejectionCode::CanisterError, e));
+            }
+        }
+    }
+
+    // canisters the deployment has no room for go back to the pool instead of leaking
+    if !surplus_canister_ids.is_empty() {
+        ic_cdk::spawn(
+            uninstall_code_and_return_empty_canisters_to_subnet_backup_pool(surplus_canister_ids),
+        );
+    }
+
+    first_error.map_or(Ok(()), Err)
+}
+
+async fn deposit_cycles_to_sns_canisters(
+    deployment_id: u64,
+    attempt: u32,
+) -> Result<(), CdaoDeployError> {
+    let pending = get_deployment(deployment_id)?.get_pending_canisters().len() as u128;
+    if pending == 0 {
+        return Ok(());
+    }
+
+    // Alloting 0.5T more to the user canister to be on safer side while deploying canisters
+    request_cycles_from_subnet_orchestrator((pending + 1) * USER_SNS_CANISTER_INITIAL_CYCLES)
+        .await
+        .map_err(CdaoDeployError::CycleError)?;
+
+    run_for_pending_canisters(deployment_id, attempt, |canister_id| async move {
+        deposit_cycles(
+            CanisterIdRecord { canister_id },
+            USER_SNS_CANISTER_INITIAL_CYCLES,
+        )
+        .await?;
+        Ok(canister_id)
+    })
+    .await
+}
+
+async fn install_sns_wasms(deployment_id: u64, attempt: u32) -> Result<(), CdaoDeployError> {
+    let deployment = get_deployment(deployment_id)?;
+    let [governance, ledger, root, swap, index] = deployment.canister_ids[..] else {
+        return Err(CdaoDeployError::DeploymentNotResumable(deployment_id));
+    };
+
+    let sns_canisters = SnsCanisterIds {
+        governance: PrincipalId::from(governance),
+        ledger: PrincipalId::from(ledger),
+        root: PrincipalId::from(root),
+        swap: PrincipalId::from(swap),
+        index: PrincipalId::from(index),
+    };
+
+    let gov_hash = hex::decode(SNS_TOKEN_GOVERNANCE_MODULE_HASH).unwrap();
+    let ledger_hash = hex::decode(SNS_TOKEN_LEDGER_MODULE_HASH).unwrap();
+    let root_hash = hex::decode(SNS_TOKEN_ROOT_MODULE_HASH).unwrap();
+    let swap_hash = hex::decode(SNS_TOKEN_SWAP_MODULE_HASH).unwrap();
+    let index_hash = hex::decode(SNS_TOKEN_INDEX_MODULE_HASH).unwrap();
+    let arhive_hash = hex::decode(SNS_TOKEN_ARCHIVE_MODULE_HASH).unwrap();
+
+    let sns_version = SnsVersion {
+        governance_wasm_hash: gov_hash.clone(),
+        ledger_wasm_hash: ledger_hash.clone(),
+        root_wasm_hash: root_hash.clone(),
+        swap_wasm_hash: swap_hash.clone(),
+        index_wasm_hash: index_hash.clone(),
+        archive_wasm_hash: arhive_hash,
+    };
+
+    let mut payloads = deployment
+        .init_payload
+        .build_canister_payloads(&sns_canisters, Some(sns_version), true)
+        .map_err(CdaoDeployError::InvalidInitPayload)?;
+    let swap_due_at = with_current_attempt_mut(deployment_id, attempt, |deployment| {
+        deployment.get_swap_due_at(get_current_system_time())
+    })?;
+    let swap_due_seconds = swap_due_at
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    payloads.swap.swap_start_timestamp_seconds =
+        Some(swap_due_seconds.saturating_sub(deployment.swap_time));
+    payloads.swap.swap_due_timestamp_seconds = Some(swap_due_seconds);
+    payloads.swap.icp_ledger_canister_id = NNS_LEDGER_CANISTER_ID.into();
+    payloads.swap.nns_governance_canister_id = ic_cdk::id().to_string();
+
+    let wasms_and_args = [
+        (gov_hash, Encode!(&payloads.governance).unwrap()),
+        (ledger_hash, Encode!(&payloads.ledger).unwrap()),
+        (root_hash, Encode!(&payloads.root).unwrap()),
+        (swap_hash, Encode!(&payloads.swap).unwrap()),
+        (index_hash, Encode!(&payloads.index_ng).unwrap()),
+    ];
+
+    let sns_wasm = CANISTER_DATA
+        .with(|cdata| {
+            cdata
+                .borrow()
+                .known_principal_ids
+                .get(&KnownPrincipalType::CanisterIdSnsWasm)
+                .copied()
+        })
+        .ok_or(CdaoDeployError::SnsWasmUnavailable(
+            "SNS WASM not specified in config".to_string(),
+        ))?;
+
+    // a previous attempt might have installed code without getting to checkpoint it
+    let mode = if deployment.attempts > 1 {
+        CanisterInstallMode::Reinstall
+    } else {
+        CanisterInstallMode::Install
+    };
+
+    run_for_pending_canisters(deployment_id, attempt, |canister_id| {
+        let wasm_and_arg = deployment
+            .canister_ids
+            .iter()
+            .position(|id| *id == canister_id)
+            .map(|position| wasms_and_args[position].clone());
+
+        async move {
+            let (hash, arg) =
+                wasm_and_arg.ok_or(CdaoDeployError::DeploymentNotResumable(deployment_id))?;
+            let req = GetWasmRequest { hash };
+            let (wasm_res,) =
+                ic_cdk::call::<_, (GetWasmResponse,)>(sns_wasm, "get_wasm", (req,)).await?;
+            let wasm = wasm_res.wasm.ok_or_else(|| {
+                CdaoDeployError::SnsWasmUnavailable(format!(
+                    "No SNS wasm for canister {}",
+                    canister_id
+                ))
+            })?;
+
+            install_code(InstallCodeArgument {
+                mode,
+                canister_id,
+                wasm_module: wasm.wasm,
+                arg,
+            })
+            .await?;
+
+            Ok(canister_id)
+        }
+    })
+    .await
+}
+
+async fn update_sns_controllers(deployment_id: u64, attempt: u32) -> Result<(), CdaoDeployError> {
+    let deployment = get_deployment(deployment_id)?;
+    let [governance, _ledger, root, swap, _index] = deployment.canister_ids[..] else {
+        return Err(CdaoDeployError::DeploymentNotResumable(deployment_id));
+    };
+
+    let admin_canister = CANISTER_DATA
+        .with(|cdata| {
+            cdata
+                .borrow()
+                .known_principal_ids
+                .get(&KnownPrincipalType::UserIdGlobalSuperAdmin)
+                .copied()
+        })
+        .expect("Super admin not specified in config");
+
+    let user_can = ic_cdk::id();
+
+    run_for_pending_canisters(deployment_id, attempt, |canister_id| {
+        let controllers = if canister_id == root {
+            vec![admin_canister, user_can, governance]
+        } else if canister_id == swap {
+            vec![
+                admin_canister,
+                user_can,
+                root,
+                ic_nns_constants::ROOT_CANISTER_ID.into(),
+            ]
+        } else {
+            vec![admin_canister, user_can, root]
+        };
+
+        async move {
+            update_settings(UpdateSettingsArgument {
+                canister_id,
+                settings: CanisterSettings {
+                    controllers: Some(controllers),
+                    ..Default::default()
+                },
+            })
+            .await?;
+
+            Ok(canister_id)
+        }
+    })
+    .await
+}
+
+fn schedule_cdao_deployment_recovery() {
+    let timer_id = ic_cdk_timers::set_timer(
+        Duration::from_secs(CREATOR_DAO_DEPLOYMENT_STALE_AFTER_SECS),
+        || ic_cdk::spawn(recover_stuck_cdao_deployments()),
+    );
+
+    if let Some(previous_timer_id) = CDAO_DEPLOYMENT_RECOVERY_TIMER.replace(Some(timer_id)) {
+        ic_cdk_timers::clear_timer(previous_timer_id);
+    }
+}
+
+/// Resumes deployments that failed or got stuck, and rolls back
+/// the ones that are out of attempts.
+async fn recover_stuck_cdao_deployments() {
+    CDAO_DEPLOYMENT_RECOVERY_TIMER.set(None);
+    let current_time = get_current_system_time();
+
+    let (to_resume, to_roll_back): (Vec<_>, Vec<_>) = CANISTER_DATA.with_borrow(|canister_data| {
+        canister_data
+            .cdao_deployments
+            .get_unfinished_deployment_ids()
+            .into_iter()
+            .filter_map(|deployment_id| {
+                let deployment = canister_data.cdao_deployments.get(deployment_id)?;
+                deployment
+                    .can_resume(current_time)
+                    .then_some((deployment_id, deployment.attempts))
+            })
+            .partition(|(_, attempts)| *attempts < MAX_ATTEMPTS_FOR_CREATOR_DAO_DEPLOYMENT)
+    });
+
+    to_roll_back
+        .into_iter()
+        .for_each(|(deployment_id, _)| roll_back_cdao_deployment(deployment_id));
+
+    for (deployment_id, _) in to_resume {
+        let _ = run_cdao_deployment(deployment_id).await;
+    }
+
+    let has_unfinished_deployments = CANISTER_DATA.with_borrow(|canister_data| {
+        !canister_data
+            .cdao_deployments
+            .get_unfinished_deployment_ids()
+            .is_empty()
+    });
+
+    if has_unfinished_deployments && CDAO_DEPLOYMENT_RECOVERY_TIMER.get().is_none() {
+        schedule_cdao_deployment_recovery();
+    }
+}
+
+/// Timers do not survive upgrades, reschedule recovery for deployments left unfinished
+pub(crate) fn restart_cdao_deployment_recovery_after_upgrade() {
+    let has_unfinished_deployments = CANISTER_DATA.with_borrow_mut(|canister_data| {
+        canister_data.cdao_deployments.stop_running_deployments();
+        !canister_data
+            .cdao_deployments
+            .get_unfinished_deployment_ids()
+            .is_empty()
+    });
+
+    if has_unfinished_deployments {
+        schedule_cdao_deployment_recovery();
+    }
+}
@@ -1,100 +1,30 @@
 mod airdrop;
 mod token;
-use std::collections::{HashMap, HashSet, VecDeque};
-
-use candid::{Encode, Principal};
-use futures::{
-    stream::{FuturesOrdered, FuturesUnordered},
-    StreamExt, TryStreamExt,
-};
-use ic_base_types::PrincipalId;
-use ic_cdk::{
-    api::{
-        call::{CallResult, RejectionCode},
-        management_canister::main::{
-            create_canister, deposit_cycles, install_code, uninstall_code, update_settings,
-            CanisterIdRecord, CanisterInstallMode, CanisterSettings, CreateCanisterArgument,
-            InstallCodeArgument, UpdateSettingsArgument,
-        },
-    },
-    query, update,
-};
-use ic_sns_governance::pb::v1::governance::Version as SnsVersion;
-use ic_sns_init::{pb::v1::SnsInitPayload, SnsCanisterIds};
-use ic_sns_wasm::pb::v1::{GetWasmRequest, GetWasmResponse};
-// use ic_sns_swap::pb::v1::{SettleNeuronsFundParticipationRequest, SettleNeuronsFundParticipationResponse};
-use ic_nns_governance::neurons_fund::NeuronsFundSnapshot;
-use ic_nns_governance::pb::v1::{
-    SettleNeuronsFundParticipationRequest, SettleNeuronsFundParticipationResponse,
-};
+use ic_cdk::{query, update};
+use ic_sns_init::pb::v1::SnsInitPayload;
 use shared_utils::{
     canister_specific::individual_user_template::types::{
-        cdao::{AirdropInfo, DeployedCdaoCanisters},
-        error::CdaoDeployError,
-        session::SessionType,
-    },
-    common::{types::known_principal::KnownPrincipalType, utils::task::run_task_concurrently},
-    constant::{
-        MAX_LIMIT_FOR_CREATOR_DAO_SNS_TOKEN, NNS_LEDGER_CANISTER_ID, SNS_TOKEN_ARCHIVE_MODULE_HASH,
-        SNS_TOKEN_GOVERNANCE_MODULE_HASH, SNS_TOKEN_INDEX_MODULE_HASH,
-        SNS_TOKEN_LEDGER_MODULE_HASH, SNS_TOKEN_ROOT_MODULE_HASH, SNS_TOKEN_SWAP_MODULE_HASH,
-        USER_SNS_CANISTER_INITIAL_CYCLES,
+        cdao::DeployedCdaoCanisters, error::CdaoDeployError, session::SessionType,
     },
+    common::utils::system_time::get_current_system_time,
 };
-use utils::uninstall_code_and_return_empty_canisters_to_subnet_backup_pool;
 
-use crate::{
-    util::{
-        cycles::{notify_to_recharge_canister, request_cycles_from_subnet_orchestrator},
-        subnet_orchestrator::{self, SubnetOrchestrator},
-    },
-    CANISTER_DATA,
-};
+use crate::{util::cycles::notify_to_recharge_canister, CANISTER_DATA};
+use deployment::run_cdao_deployment;
 
+pub mod creator_token_limit;
 pub mod delete_all_sns_creator_token;
+pub mod delete_sns_creator_token;
+pub mod deployment;
 pub mod send_creator_dao_stats_to_subnet_orchestrator;
+pub mod settle_neurons_fund_participation;
+pub mod swap_participation;
+pub mod token_holdings;
+pub mod transfer_token_to_user_canister;
 pub mod upgrade_creator_dao_governance_canisters;
+pub mod upgrade_creator_dao_sns_canisters;
 pub mod utils;
 
-#[update]
-pub async fn settle_neurons_fund_participation(
-    request: SettleNeuronsFundParticipationRequest,
-) -> SettleNeuronsFundParticipationResponse {
-    let response = Ok(NeuronsFundSnapshot::empty());
-    let intermediate = SettleNeuronsFundParticipationResponse::from(response);
-    SettleNeuronsFundParticipationResponse::from(intermediate)
-}
-
-async fn install_canister_wasm(
-    wasm: Vec<u8>,
-    arg: Vec<u8>,
-    canister_id: PrincipalId,
-) -> Result<(), CdaoDeployError> {
-    let install_arg = InstallCodeArgument {
-        mode: CanisterInstallMode::Install,
-        canister_id: canister_id.0,
-        wasm_module: wasm,
-        arg,
-    };
-    install_code(install_arg).await?;
-    Ok(())
-}
-
-async fn update_controllers(
-    canister_id: PrincipalId,
-    controllers: Vec<Principal>,
-) -> Result<(), CdaoDeployError> {
-    update_settings(UpdateSettingsArgument {
-        canister_id: canister_id.0,
-        settings: CanisterSettings {
-            controllers: Some(controllers),
-            ..Default::default()
-        },
-    })
-    .await?;
-    Ok(())
-}
-
 #[query]
 async fn deployed_cdao_canisters() -> Vec<DeployedCdaoCanisters> {
     CANISTER_DATA.with(|cdata| cdata.borrow().cdao_canisters.clone())
@@ -115,280 +45,36 @@ async fn deploy_cdao_sns(
         return Err(CdaoDeployError::Unauthenticated);
     };
 
-    let (registered, limit_hit) = CANISTER_DATA.with(|cdata| {
+    let (registered, token_limit, limit_hit) = CANISTER_DATA.with(|cdata| {
         let cdata = cdata.borrow();
         let registered = matches!(cdata.session_type, Some(SessionType::RegisteredSession));
+        let token_limit = cdata.creator_token_limit.get_limit();
         (
             registered,
-            cdata.cdao_canisters.len() >= MAX_LIMIT_FOR_CREATOR_DAO_SNS_TOKEN,
+            token_limit,
+            cdata.cdao_canisters.len() >= token_limit,
         )
     });
 
     if limit_hit {
-        return Err(CdaoDeployError::TokenLimit(
-            MAX_LIMIT_FOR_CREATOR_DAO_SNS_TOKEN,
-        ));
-    }
-
-    // Alloting 0.5T more to the user canister to be on safer side while deploying canisters
-    request_cycles_from_subnet_orchestrator(6 * USER_SNS_CANISTER_INITIAL_CYCLES)
-        .await
-        .map_err(|e| CdaoDeployError::CycleError(e))?;
-
-    let subnet_orchestrator = SubnetOrchestrator::new()
-        .map_err(|e| CdaoDeployError::CallError(RejectionCode::CanisterError, e))?;
-
-    // TODO: handle this bit of code.
-
-    let canister_ids_futures = (0..5).map(|_| subnet_orchestrator.allot_empty_canister());
-
-    let canister_ids_stream = futures::stream::iter(canister_ids_futures)
-        .boxed()
-        .buffer_unordered(5);
-
-    let canister_ids_results: Vec<Result<Principal, String>> =
-        canister_ids_stream.collect::<_>().await;
-
-    let canister_id_result = canister_ids_results
-        .iter()
-        .find(|canister_id_result| canister_id_result.is_err());
-
-    let mut canister_ids: Vec<Principal> = vec![];
-
-    if let Some(canister_id_error) = canister_id_result {
-        if let Err(e) = canister_id_error {
-            let canister_ids: Vec<Principal> = canister_ids_results
-                .iter()
-                .filter(|canister_id_result| canister_id_result.is_ok())
-                .map(|canister_id_result| canister_id_result.clone().unwrap())
-                .collect();
-
-            ic_cdk::spawn(
-                uninstall_code_and_return_empty_canisters_to_subnet_backup_pool(canister_ids),
-            );
-
-            return Err(CdaoDeployError::CallError(
-                RejectionCode::CanisterError,
-                e.clone(),
-            ));
-        }
-    } else {
-        canister_ids = canister_ids_results
-            .into_iter()
-            .map(|canister_id_res| canister_id_res.unwrap())
-            .collect();
+        return Err(CdaoDeployError::TokenLimit(token_limit));
     }
 
-    let deposit_cycles_result: CallResult<()> = canister_ids
-        .iter()
-        .map(|canister_id| {
-            deposit_cycles(
-                CanisterIdRecord {
-                    canister_id: *canister_id,
-                },
-                USER_SNS_CANISTER_INITIAL_CYCLES,
-            )
-        })
-        .collect::<FuturesUnordered<_>>()
-        .try_collect()
-        .await;
-
-    if let Err(e) = deposit_cycles_result {
-        ic_cdk::spawn(
-            uninstall_code_and_return_empty_canisters_to_subnet_backup_pool(canister_ids.clone()),
-        );
-        return Err(CdaoDeployError::CallError(e.0, e.1));
+    if let Some(deployment_id) = CANISTER_DATA.with_borrow(|cdata| {
+        cdata
+            .cdao_deployments
+            .get_unfinished_deployment_ids()
+            .first()
+            .copied()
+    }) {
+        return Err(CdaoDeployError::DeploymentInProgress(deployment_id));
     }
 
-    let canisters: Vec<PrincipalId> = canister_ids
-        .iter()
-        .copied()
-        .map(|canister_id| PrincipalId::from(canister_id))
-        .collect();
-
-    let governance = canisters[0];
-    let ledger = canisters[1];
-    let root = canisters[2];
-    let swap = canisters[3];
-    let index = canisters[4];
-
-    let sns_canisters = SnsCanisterIds {
-        governance,
-        ledger,
-        root,
-        swap,
-        index,
-    };
-
-    let gov_hash = hex::decode(SNS_TOKEN_GOVERNANCE_MODULE_HASH).unwrap();
-    let ledger_hash = hex::decode(SNS_TOKEN_LEDGER_MODULE_HASH).unwrap();
-    let root_hash = hex::decode(SNS_TOKEN_ROOT_MODULE_HASH).unwrap();
-    let swap_hash = hex::decode(SNS_TOKEN_SWAP_MODULE_HASH).unwrap();
-    let index_hash = hex::decode(SNS_TOKEN_INDEX_MODULE_HASH).unwrap();
-    let arhive_hash = hex::decode(SNS_TOKEN_ARCHIVE_MODULE_HASH).unwrap();
-
-    let sns_version = SnsVersion {
-        governance_wasm_hash: gov_hash.clone(),
-        ledger_wasm_hash: ledger_hash.clone(),
-        root_wasm_hash: root_hash.clone(),
-        swap_wasm_hash: swap_hash.clone(),
-        index_wasm_hash: index_hash.clone(),
-        archive_wasm_hash: arhive_hash.clone(),
-    };
-
-    let mut payloads = init_payload
-        .build_canister_payloads(&sns_canisters, Some(sns_version), true)
-        .map_err(CdaoDeployError::InvalidInitPayload)?;
-    let time_seconds = ic_cdk::api::time() / 1_000_000_000;
-    payloads.swap.swap_start_timestamp_seconds = Some(time_seconds);
-    payloads.swap.swap_due_timestamp_seconds = Some(time_seconds + swap_time);
-    payloads.swap.icp_ledger_canister_id = NNS_LEDGER_CANISTER_ID.into();
-    payloads.swap.nns_governance_canister_id = ic_cdk::id().to_string();
-
-    let sns_wasm = CANISTER_DATA
-        .with(|cdata| {
-            cdata
-                .borrow()
-                .known_principal_ids
-                .get(&KnownPrincipalType::CanisterIdSnsWasm)
-                .copied()
-        })
-        .expect("SNS WASM not specified in config");
-
-    ic_cdk::println!("gov_hash: {:?}", gov_hash);
-
-    let wasm_bins_result: Result<_, _> = [gov_hash, ledger_hash, root_hash, swap_hash, index_hash]
-        .into_iter()
-        .map(|hash| async move {
-            let req = GetWasmRequest { hash };
-            let wasm_res =
-                ic_cdk::call::<_, (GetWasmResponse,)>(sns_wasm, "get_wasm", (req,)).await?;
-            Ok::<_, CdaoDeployError>(wasm_res.0.wasm.unwrap().wasm)
-        })
-        .collect::<FuturesOrdered<_>>()
-        .try_collect()
-        .await;
-
-    if let Err(e) = wasm_bins_result {
-        ic_cdk::spawn(
-            uninstall_code_and_return_empty_canisters_to_subnet_backup_pool(canister_ids),
-        );
-        return Err(e);
-    }
-    let mut wasm_bins: VecDeque<_> = wasm_bins_result.unwrap();
-
-    let mut sns_install_futs = FuturesUnordered::new();
-    sns_install_futs.push(install_canister_wasm(
-        wasm_bins.pop_front().unwrap(),
-        Encode!(&payloads.governance).unwrap(),
-        governance,
-    ));
-    sns_install_futs.push(install_canister_wasm(
-        wasm_bins.pop_front().unwrap(),
-        Encode!(&payloads.ledger).unwrap(),
-        ledger,
-    ));
-    sns_install_futs.push(install_canister_wasm(
-        wasm_bins.pop_front().unwrap(),
-        Encode!(&payloads.root).unwrap(),
-        root,
-    ));
-    sns_install_futs.push(install_canister_wasm(
-        wasm_bins.pop_front().unwrap(),
-        Encode!(&payloads.swap).unwrap(),
-        swap,
-    ));
-
-    sns_install_futs.push(install_canister_wasm(
-        wasm_bins.pop_front().unwrap(),
-        Encode!(&payloads.index_ng).unwrap(),
-        index,
-    ));
-    while sns_install_futs
-        .try_next()
-        .await
-        .inspect_err(|e| {
-            ic_cdk::spawn(
-                uninstall_code_and_return_empty_canisters_to_subnet_backup_pool(
-                    canister_ids.clone(),
-                ),
-            )
-        })?
-        .is_some()
-    {}
-
-    let admin_canister = CANISTER_DATA
-        .with(|cdata| {
-            cdata
-                .borrow()
-                .known_principal_ids
-                .get(&KnownPrincipalType::UserIdGlobalSuperAdmin)
-                .copied()
-        })
-        .expect("Super admin not specified in config");
-
-    let user_can = ic_cdk::id();
-    let mut update_ctrls_futs = FuturesUnordered::new();
-    update_ctrls_futs.push(update_controllers(
-        governance,
-        vec![admin_canister, user_can, root.0],
-    ));
-    update_ctrls_futs.push(update_controllers(
-        root,
-        vec![admin_canister, user_can, governance.0],
-    ));
-    update_ctrls_futs.push(update_controllers(
-        ledger,
-        vec![admin_canister, user_can, root.0],
-    ));
-    update_ctrls_futs.push(update_controllers(
-        swap,
-        vec![
-            admin_canister,
-            user_can,
-            root.0,
-            ic_nns_constants::ROOT_CANISTER_ID.into(),
-        ],
-    ));
-    update_ctrls_futs.push(update_controllers(
-        index,
-        vec![admin_canister, user_can, root.0],
-    ));
-    while update_ctrls_futs
-        .try_next()
-        .await
-        .inspect_err(|_e| {
-            ic_cdk::spawn(
-                uninstall_code_and_return_empty_canisters_to_subnet_backup_pool(
-                    canister_ids.clone(),
-                ),
-            );
-        })?
-        .is_some()
-    {}
-
-    let deployed_cans = DeployedCdaoCanisters {
-        governance: governance.0,
-        ledger: ledger.0,
-        root: root.0,
-        swap: swap.0,
-        index: index.0,
-        airdrop_info: AirdropInfo {
-            principals_who_successfully_claimed: HashMap::new(),
-        },
-    };
-    CANISTER_DATA.with(|cdata| {
-        let mut cdata = cdata.borrow_mut();
-        cdata.cdao_canisters.push(deployed_cans.clone());
-        cdata.token_roots.insert(root.0, ());
+    let deployment_id = CANISTER_DATA.with_borrow_mut(|cdata| {
+        cdata
+            .cdao_deployments
+            .start(init_payload, swap_time, get_current_system_time())
     });
 
-    let send_creator_dao_stats_res =
-        subnet_orchestrator.send_creator_dao_stats(vec![root.into()].into_iter().collect());
-
-    if let Err(e) = send_creator_dao_stats_res {
-        ic_cdk::println!("Error sending creator stats to subnet orchestrator {}", e)
-    }
-
-    Ok(deployed_cans)
+    run_cdao_deployment(deployment_id).await
 }
@@ -3,7 +3,7 @@ use std::collections::HashSet;
 use candid::Principal;
 use ic_cdk_macros::update;
 use shared_utils::{
-    common::utils::permissions::is_caller_controller,
+    common::utils::{permissions::is_caller_controller, system_time::get_current_system_time},
     types::creator_dao_stats::IndividualUserCreatorDaoEntry,
 };
 
@@ -24,9 +24,24 @@ fn send_creator_dao_stats_to_subnet_orchestrator() -> Result<IndividualUserCreat
             .map(|deployed_canisters| deployed_canisters.root)
             .collect();
 
+        let individual_profile_id = canister_data.profile.principal_id.unwrap();
+        let token_metadata = canister_data
+            .cdao_canisters
+            .iter()
+            .filter_map(|deployed_canisters| {
+                canister_data.cdao_deployments.get_token_metadata(
+                    deployed_canisters.root,
+                    individual_profile_id,
+                    ic_cdk::id(),
+                    get_current_system_time(),
+                )
+            })
+            .collect();
+
         Ok(IndividualUserCreatorDaoEntry {
-            individual_profile_id: canister_data.profile.principal_id.unwrap(),
+            individual_profile_id,
             deployed_canisters: root_canisters,
+            token_metadata: Some(token_metadata),
         })
     })
 }
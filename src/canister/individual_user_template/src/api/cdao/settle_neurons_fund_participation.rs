@@ -0,0 +1,179 @@
+use candid::Principal;
+use ic_cdk::caller;
+use ic_cdk_macros::{query, update};
+use ic_nns_governance::{
+    neurons_fund::NeuronsFundSnapshot,
+    pb::v1::{
+        governance_error::ErrorType, settle_neurons_fund_participation_request, GovernanceError,
+        SettleNeuronsFundParticipationRequest, SettleNeuronsFundParticipationResponse,
+    },
+};
+use shared_utils::{
+    canister_specific::individual_user_template::types::cdao::{
+        CdaoSwapOutcome, CdaoSwapParticipant, CdaoSwapSettlement,
+    },
+    common::utils::system_time::get_current_system_time,
+    types::sns_canisters::swap::{self, ListDirectParticipantsRequest},
+};
+
+use crate::CANISTER_DATA;
+
+// participants read from the swap per request
+const SWAP_PARTICIPANTS_PER_REQUEST: u32 = 10_000;
+
+/// Creator swaps are deployed with this canister as their `nns_governance_canister_id`,
+/// so the swap reports its final outcome here when it is finalized. Creator tokens
+/// never receive neurons fund participation, so the snapshot is always empty. The
+/// participants are fetched on every call until they could be recorded, the swap
+/// retries settling until it gets a response.
+#[update]
+pub async fn settle_neurons_fund_participation(
+    request: SettleNeuronsFundParticipationRequest,
+) -> SettleNeuronsFundParticipationResponse {
+    let swap_canister = caller();
+
+    let response = match record_swap_settlement(swap_canister, request) {
+        Ok(_) => {
+            if !has_direct_participants(swap_canister) {
+                update_direct_participants(swap_canister).await;
+            }
+            Ok(NeuronsFundSnapshot::empty())
+        }
+        Err(e) => Err(e),
+    };
+
+    SettleNeuronsFundParticipationResponse::from(response)
+}
+
+#[query]
+fn get_cdao_swap_settlement(root: Principal) -> Option<CdaoSwapSettlement> {
+    CANISTER_DATA.with_borrow(|canister_data| {
+        canister_data
+            .cdao_canisters
+            .iter()
+            .find(|canisters| canisters.root == root)
+            .and_then(|canisters| canisters.swap_settlement.clone())
+    })
+}
+
+/// Returns whether the settlement was recorded by this call. The swap retries settling
+/// until it gets a response, so a repeated request with the same outcome succeeds
+/// without changing the stored settlement.
+fn record_swap_settlement(
+    swap_canister: Principal,
+    request: SettleNeuronsFundParticipationRequest,
+) -> Result<bool, GovernanceError> {
+    let outcome = match request.result {
+        Some(settle_neurons_fund_participation_request::Result::Committed(committed)) => {
+            CdaoSwapOutcome::Committed {
+                total_direct_participation_icp_e8s: committed
+                    .total_direct_participation_icp_e8s
+                    .unwrap_or_default(),
+                total_neurons_fund_participation_icp_e8s: committed
+                    .total_neurons_fund_participation_icp_e8s
+                    .unwrap_or_default(),
+            }
+        }
+        Some(settle_neurons_fund_participation_request::Result::Aborted(_)) => {
+            CdaoSwapOutcome::Aborted
+        }
+        None => {
+            return Err(GovernanceError::new_with_message(
+                ErrorType::InvalidCommand,
+                "Settlement request has no result",
+            ))
+        }
+    };
+
+    CANISTER_DATA.with_borrow_mut(|canister_data| {
+        let canisters = canister_data
+            .cdao_canisters
+            .iter_mut()
+            .find(|canisters| canisters.swap == swap_canister)
+            .ok_or(GovernanceError::new_with_message(
+                ErrorType::NotAuthorized,
+                "Caller is not a swap canister of this user",
+            ))?;
+
+        if let Some(settlement) = &canisters.swap_settlement {
+            if settlement.outcome.is_committed() != outcome.is_committed() {
+                return Err(GovernanceError::new_with_message(
+                    ErrorType::PreconditionFailed,
+                    "Swap was already settled with a different outcome",
+                ));
+            }
+            return Ok(false);
+        }
+
+        canisters.swap_settlement = Some(CdaoSwapSettlement {
+            nns_proposal_id: request.nns_proposal_id,
+            outcome,
+            direct_participants: None,
+            settled_at: get_current_system_time(),
+        });
+
+        Ok(true)
+    })
+}
+
+fn has_direct_participants(swap_canister: Principal) -> bool {
+    CANISTER_DATA.with_borrow(|canister_data| {
+        canister_data
+            .cdao_canisters
+            .iter()
+            .find(|canisters| canisters.swap == swap_canister)
+            .and_then(|canisters| canisters.swap_settlement.as_ref())
+            .is_some_and(|settlement| settlement.direct_participants.is_some())
+    })
+}
+
+async fn update_direct_participants(swap_canister: Principal) {
+    let mut direct_participants = vec![];
+    let mut offset = 0;
+    loop {
+        let res = swap::Service(swap_canister)
+            .list_direct_participants(ListDirectParticipantsRequest {
+                offset: Some(offset),
+                limit: Some(SWAP_PARTICIPANTS_PER_REQUEST),
+            })
+            .await;
+        let participants = match res {
+            Ok((res,)) => res.participants,
+            Err(e) => {
+                ic_cdk::println!(
+                    "Failed fetching participants of swap {}. Error: {}",
+                    swap_canister,
+                    e.1
+                );
+                return;
+            }
+        };
+
+        let is_last_page = participants.len() < SWAP_PARTICIPANTS_PER_REQUEST as usize;
+        offset += participants.len() as u32;
+        direct_participants.extend(participants.into_iter().filter_map(|participant| {
+            Some(CdaoSwapParticipant {
+                principal_id: participant.participant_id?,
+                amount_icp_e8s: participant
+                    .participation
+                    .and_then(|buyer_state| buyer_state.icp)
+                    .map(|icp| icp.amount_e8s)
+                    .unwrap_or_default(),
+            })
+        }));
+        if is_last_page {
+            break;
+        }
+    }
+
+    CANISTER_DATA.with_borrow_mut(|canister_data| {
+        if let Some(settlement) = canister_data
+            .cdao_canisters
+            .iter_mut()
+            .find(|canisters| canisters.swap == swap_canister)
+            .and_then(|canisters| canisters.swap_settlement.as_mut())
+        {
+            settlement.direct_participants = Some(direct_participants);
+        }
+    });
+}
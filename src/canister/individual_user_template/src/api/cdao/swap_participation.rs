@@ -0,0 +1,359 @@
+use std::time::UNIX_EPOCH;
+
+use candid::{Nat, Principal};
+use ic_cdk_macros::{query, update};
+use ic_sns_root::pb::v1::{ListSnsCanistersRequest, ListSnsCanistersResponse};
+use icrc_ledger_types::{
+    icrc1::{account::Account, transfer::Memo},
+    icrc2::transfer_from::{TransferFromArgs, TransferFromError},
+};
+use shared_utils::{
+    canister_specific::individual_user_template::types::{
+        cdao::{SwapParticipation, SwapParticipationStatus, SwapPaymentToken},
+        error::SwapParticipationError,
+    },
+    common::{
+        types::known_principal::KnownPrincipalType, utils::system_time::get_current_system_time,
+    },
+    constant::NNS_LEDGER_CANISTER_ID,
+    pagination::PaginationError,
+    types::sns_canisters::swap::{
+        self, GetInitArg, NewSaleTicketRequest, NotifyPaymentFailureArg, RefreshBuyerTokensRequest,
+        Result2,
+    },
+};
+
+use crate::CANISTER_DATA;
+
+/// Commits ICP or DOLLR from the user's wallet to a creator token swap. The payment is
+/// pulled with the allowance the user gave this canister on the ledger the swap accepts,
+/// so this canister takes part in the swap as the buyer.
+#[update]
+async fn participate_in_creator_token_swap(
+    token_root: Principal,
+    amount_e8s: u64,
+) -> Result<SwapParticipation, SwapParticipationError> {
+    let user_principal = get_user_principal_if_caller_is_owner()?;
+
+    let (sns_canisters,): (ListSnsCanistersResponse,) = ic_cdk::call(
+        token_root,
+        "list_sns_canisters",
+        (ListSnsCanistersRequest {},),
+    )
+    .await?;
+    let swap_canister: Principal = sns_canisters
+        .swap
+        .ok_or(SwapParticipationError::InvalidRoot)?
+        .into();
+
+    if let Some(pending) = CANISTER_DATA.with_borrow(|canister_data| {
+        canister_data
+            .swap_participations
+            .get_pending_participation(swap_canister)
+            .map(|participation| participation.id)
+    }) {
+        return Err(SwapParticipationError::ParticipationInProgress(pending));
+    }
+
+    let (init,) = swap::Service(swap_canister).get_init(GetInitArg {}).await?;
+    let init = init.init.ok_or(SwapParticipationError::InvalidRoot)?;
+    let payment_ledger = Principal::from_text(&init.icp_ledger_canister_id)
+        .map_err(|_| SwapParticipationError::InvalidRoot)?;
+    let payment_token = get_payment_token(payment_ledger)?;
+
+    let (ticket_response,) = swap::Service(swap_canister)
+        .new_sale_ticket(NewSaleTicketRequest {
+            subaccount: None,
+            amount_icp_e8s: amount_e8s,
+        })
+        .await?;
+    let ticket = match ticket_response.result {
+        Some(Result2::Ok(ok)) => ok.ticket.ok_or(SwapParticipationError::TicketRejected(0))?,
+        Some(Result2::Err(e)) => return Err(SwapParticipationError::TicketRejected(e.error_type)),
+        None => return Err(SwapParticipationError::TicketRejected(0)),
+    };
+
+    let participation_id = CANISTER_DATA.with_borrow_mut(|canister_data| {
+        canister_data.swap_participations.record(
+            token_root,
+            swap_canister,
+            payment_token,
+            ticket.ticket_id,
+            amount_e8s,
+            get_current_system_time(),
+        )
+    });
+
+    pay_for_participation(participation_id, user_principal).await
+}
+
+/// Pulls the payment for the participation. The transfer is identified by the
+/// participation, so a retry after an unknown outcome is deduplicated by the ledger.
+async fn pay_for_participation(
+    participation_id: u64,
+    user_principal: Principal,
+) -> Result<SwapParticipation, SwapParticipationError> {
+    let participation = get_participation(participation_id)?;
+    let payment_ledger = get_payment_ledger(participation.payment_token)?;
+
+    let transfer_res: Result<(Result<Nat, TransferFromError>,), _> = ic_cdk::call(
+        payment_ledger,
+        "icrc2_transfer_from",
+        (TransferFromArgs {
+            spender_subaccount: None,
+            from: Account {
+                owner: user_principal,
+                subaccount: None,
+            },
+            to: Account {
+                owner: participation.swap_canister,
+                subaccount: Some(get_buyer_subaccount(ic_cdk::id())),
+            },
+            amount: Nat::from(participation.amount_e8s),
+            fee: None,
+            memo: Some(Memo::from(participation_id)),
+            created_at_time: Some(
+                participation
+                    .created_at
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_nanos() as u64,
+            ),
+        },),
+    )
+    .await;
+
+    let block_index = match transfer_res {
+        Ok((Ok(block_index),))
+        | Ok((Err(TransferFromError::Duplicate {
+            duplicate_of: block_index,
+        }),)) => block_index,
+        // past the ledger's deduplication window, only the swap can tell whether the
+        // earlier attempt paid
+        Ok((Err(TransferFromError::TooOld),)) => {
+            let participation = refresh_buyer_tokens(participation_id).await;
+            if participation.is_pending() {
+                close_ticket_after_failed_payment(
+                    participation_id,
+                    format!("{:?}", TransferFromError::TooOld),
+                )
+                .await;
+            }
+            return get_participation(participation_id);
+        }
+        Ok((Err(e),)) => {
+            close_ticket_after_failed_payment(participation_id, format!("{:?}", e)).await;
+            return Err(SwapParticipationError::TransferFrom(e));
+        }
+        // the ticket stays open, refreshing the participation settles the payment
+        Err(e) => {
+            set_participation_status(
+                participation_id,
+                SwapParticipationStatus::PaymentUnknown { error: e.1.clone() },
+            )?;
+            return Err(e.into());
+        }
+    };
+
+    set_participation_status(
+        participation_id,
+        SwapParticipationStatus::PaymentTransferred { block_index },
+    )?;
+
+    Ok(refresh_buyer_tokens(participation_id).await)
+}
+
+/// Settles a participation left pending because the ledger or the swap could not be
+/// reached. A payment with an unknown outcome is retried, a payment that was made but
+/// not committed yet is reported to the swap again.
+#[update]
+async fn refresh_creator_token_swap_participation(
+    participation_id: u64,
+) -> Result<SwapParticipation, SwapParticipationError> {
+    let user_principal = get_user_principal_if_caller_is_owner()?;
+
+    let participation = get_participation(participation_id)?;
+
+    match participation.status {
+        SwapParticipationStatus::PaymentUnknown { .. } => {
+            pay_for_participation(participation_id, user_principal).await
+        }
+        SwapParticipationStatus::PaymentTransferred { .. } => {
+            Ok(refresh_buyer_tokens(participation_id).await)
+        }
+        _ => Ok(participation),
+    }
+}
+
+#[query]
+fn get_creator_token_swap_participations_with_pagination_cursor(
+    from_inclusive_index: u64,
+    limit: u64,
+) -> Result<Vec<SwapParticipation>, PaginationError> {
+    CANISTER_DATA.with_borrow(|canister_data| {
+        canister_data
+            .swap_participations
+            .get_participations_with_pagination_cursor(from_inclusive_index, limit)
+    })
+}
+
+fn get_user_principal_if_caller_is_owner() -> Result<Principal, SwapParticipationError> {
+    let my_principal_id = CANISTER_DATA
+        .with(|canister_data_ref_cell| canister_data_ref_cell.borrow().profile.principal_id);
+
+    match my_principal_id {
+        Some(principal_id) if principal_id == ic_cdk::caller() => Ok(principal_id),
+        _ => Err(SwapParticipationError::Unauthenticated),
+    }
+}
+
+fn get_participation(participation_id: u64) -> Result<SwapParticipation, SwapParticipationError> {
+    CANISTER_DATA.with_borrow(|canister_data| {
+        canister_data
+            .swap_participations
+            .get(participation_id)
+            .cloned()
+            .ok_or(SwapParticipationError::ParticipationNotFound)
+    })
+}
+
+fn get_payment_token(
+    payment_ledger: Principal,
+) -> Result<SwapPaymentToken, SwapParticipationError> {
+    if payment_ledger.to_text() == NNS_LEDGER_CANISTER_ID {
+        return Ok(SwapPaymentToken::Icp);
+    }
+
+    let dollr_ledger = CANISTER_DATA.with_borrow(|canister_data| {
+        canister_data
+            .known_principal_ids
+            .get(&KnownPrincipalType::CanisterIdSnsLedger)
+            .copied()
+    });
+    if dollr_ledger == Some(payment_ledger) {
+        return Ok(SwapPaymentToken::Dollr);
+    }
+
+    Err(SwapParticipationError::UnsupportedPaymentLedger(
+        payment_ledger,
+    ))
+}
+
+fn get_payment_ledger(
+    payment_token: SwapPaymentToken,
+) -> Result<Principal, SwapParticipationError> {
+    match payment_token {
+        SwapPaymentToken::Icp => Ok(Principal::from_text(NNS_LEDGER_CANISTER_ID)
+            .expect("NNS ledger canister id to be valid")),
+        SwapPaymentToken::Dollr => CANISTER_DATA
+            .with_borrow(|canister_data| {
+                canister_data
+                    .known_principal_ids
+                    .get(&KnownPrincipalType::CanisterIdSnsLedger)
+                    .copied()
+            })
+            .ok_or(SwapParticipationError::PaymentLedgerUnavailable),
+    }
+}
+
+/// The swap holds each buyer's payment in a subaccount derived from the buyer's principal
+fn get_buyer_subaccount(buyer: Principal) -> [u8; 32] {
+    let buyer_bytes = buyer.as_slice();
+    let mut subaccount = [0; 32];
+    subaccount[0] = buyer_bytes.len() as u8;
+    subaccount[1..1 + buyer_bytes.len()].copy_from_slice(buyer_bytes);
+
+    subaccount
+}
+
+/// The swap keeps the ticket open until it is told the payment failed, which would
+/// block any later participation in it
+async fn close_ticket_after_failed_payment(participation_id: u64, error: String) {
+    let _ = set_participation_status(
+        participation_id,
+        SwapParticipationStatus::PaymentFailed { error },
+    );
+
+    let Some(swap_canister) = CANISTER_DATA.with_borrow(|canister_data| {
+        canister_data
+            .swap_participations
+            .get(participation_id)
+            .map(|participation| participation.swap_canister)
+    }) else {
+        return;
+    };
+
+    if let Err(e) = swap::Service(swap_canister)
+        .notify_payment_failure(NotifyPaymentFailureArg {})
+        .await
+    {
+        ic_cdk::println!(
+            "Failed closing ticket on swap {}. Error: {}",
+            swap_canister,
+            e.1
+        );
+    }
+}
+
+/// Leaves the participation as transferred if the swap could not be reached, so it can
+/// be refreshed again later
+async fn refresh_buyer_tokens(participation_id: u64) -> SwapParticipation {
+    let (swap_canister, payment_token) = CANISTER_DATA.with_borrow(|canister_data| {
+        let participation = canister_data
+            .swap_participations
+            .get(participation_id)
+            .expect("Participation to exist");
+        (participation.swap_canister, participation.payment_token)
+    });
+
+    let confirmation_text = swap::Service(swap_canister)
+        .get_init(GetInitArg {})
+        .await
+        .ok()
+        .and_then(|(init,)| init.init)
+        .and_then(|init| init.confirmation_text);
+
+    let res = swap::Service(swap_canister)
+        .refresh_buyer_tokens(RefreshBuyerTokensRequest {
+            confirmation_text,
+            buyer: ic_cdk::id().to_text(),
+        })
+        .await;
+
+    match res {
+        Ok((refreshed,)) => {
+            let _ = set_participation_status(
+                participation_id,
+                SwapParticipationStatus::Committed {
+                    accepted_participation_e8s: refreshed.icp_accepted_participation_e8s,
+                },
+            );
+        }
+        Err(e) => ic_cdk::println!(
+            "Failed refreshing {:?} participation on swap {}. Error: {}",
+            payment_token,
+            swap_canister,
+            e.1
+        ),
+    }
+
+    CANISTER_DATA.with_borrow(|canister_data| {
+        canister_data
+            .swap_participations
+            .get(participation_id)
+            .cloned()
+            .expect("Participation to exist")
+    })
+}
+
+fn set_participation_status(
+    participation_id: u64,
+    status: SwapParticipationStatus,
+) -> Result<SwapParticipation, SwapParticipationError> {
+    CANISTER_DATA.with_borrow_mut(|canister_data| {
+        canister_data
+            .swap_participations
+            .set_status(participation_id, status, get_current_system_time())
+            .ok_or(SwapParticipationError::ParticipationNotFound)
+    })
+}
@@ -0,0 +1,193 @@
+use std::{cell::Cell, time::Duration};
+
+use candid::{Nat, Principal};
+use ic_cdk::{query, update};
+use ic_cdk_timers::TimerId;
+use ic_sns_root::pb::v1::{ListSnsCanistersRequest, ListSnsCanistersResponse};
+use icrc_ledger_types::icrc1::account::Account;
+use shared_utils::{
+    canister_specific::individual_user_template::types::{
+        error::CdaoTokenError, token::TokenHolding,
+    },
+    common::utils::{system_time::get_current_system_time, task::run_task_concurrently},
+    constant::TOKEN_HOLDINGS_REFRESH_INTERVAL_SECS,
+    pagination::{self, PaginationError},
+};
+
+use crate::CANISTER_DATA;
+
+thread_local! {
+    static TOKEN_HOLDINGS_REFRESH_TIMER: Cell<Option<TimerId>> = const { Cell::new(None) };
+}
+
+#[query]
+fn get_token_holdings_of_this_user_with_pagination_cursor(
+    from_inclusive_index: u64,
+    limit: u64,
+) -> Result<Vec<TokenHolding>, PaginationError> {
+    CANISTER_DATA.with_borrow(|cdata| {
+        let (from_inclusive_index, limit) = pagination::get_pagination_bounds_cursor(
+            from_inclusive_index,
+            limit,
+            cdata.token_holdings.len() as u64,
+        )?;
+        let holdings = cdata
+            .token_holdings
+            .values()
+            .skip(from_inclusive_index as usize)
+            .take(limit as usize)
+            .cloned()
+            .collect();
+        Ok(holdings)
+    })
+}
+
+/// Refreshes the cached balance of a single token right away.
+/// Returns None if the token was dropped because its balance is zero.
+#[update]
+async fn refresh_token_holding(root: Principal) -> Result<Option<TokenHolding>, CdaoTokenError> {
+    let current_caller = ic_cdk::caller();
+    let my_principal_id = CANISTER_DATA
+        .with(|canister_data_ref_cell| canister_data_ref_cell.borrow().profile.principal_id);
+    if my_principal_id != Some(current_caller) {
+        return Err(CdaoTokenError::Unauthenticated);
+    };
+
+    let is_held = CANISTER_DATA.with_borrow(|cdata| cdata.token_roots.contains_key(&root));
+    if !is_held {
+        return Err(CdaoTokenError::InvalidRoot);
+    }
+
+    update_token_holding(root).await
+}
+
+/// Fetches the balance of the user for the token, ledger metadata is only fetched
+/// if it is not cached yet.
+pub(super) async fn fetch_token_holding(root: Principal) -> Result<TokenHolding, CdaoTokenError> {
+    let cached_holding =
+        CANISTER_DATA.with_borrow(|cdata| cdata.token_holdings.get(&root).cloned());
+
+    let (ledger, symbol, decimals) = match cached_holding {
+        Some(holding) => (holding.ledger, holding.symbol, holding.decimals),
+        None => {
+            let res: (ListSnsCanistersResponse,) =
+                ic_cdk::call(root, "list_sns_canisters", (ListSnsCanistersRequest {},)).await?;
+            let ledger: Principal = res.0.ledger.ok_or(CdaoTokenError::InvalidRoot)?.into();
+
+            let (symbol,): (String,) = ic_cdk::call(ledger, "icrc1_symbol", ()).await?;
+            let (decimals,): (u8,) = ic_cdk::call(ledger, "icrc1_decimals", ()).await?;
+
+            (ledger, symbol, decimals)
+        }
+    };
+
+    let my_principal_id = CANISTER_DATA
+        .with(|canister_data_ref_cell| canister_data_ref_cell.borrow().profile.principal_id)
+        .ok_or(CdaoTokenError::Unauthenticated)?;
+    let acc = Account {
+        owner: my_principal_id,
+        subaccount: None,
+    };
+    let (balance,): (Nat,) = ic_cdk::call(ledger, "icrc1_balance_of", (acc,)).await?;
+
+    Ok(TokenHolding {
+        root,
+        ledger,
+        symbol,
+        decimals,
+        balance,
+        refreshed_at: get_current_system_time(),
+    })
+}
+
+/// Stores the latest balance of the token. Tokens that ran out of balance are dropped
+/// from the user's tokens, except the ones this user created.
+pub(super) async fn update_token_holding(
+    root: Principal,
+) -> Result<Option<TokenHolding>, CdaoTokenError> {
+    let holding = fetch_token_holding(root).await?;
+
+    Ok(CANISTER_DATA.with_borrow_mut(|cdata| {
+        let is_created_by_user = cdata
+            .cdao_canisters
+            .iter()
+            .any(|deployed_canisters| deployed_canisters.root == root);
+
+        if holding.balance == 0_u32 && !is_created_by_user {
+            cdata.token_roots.remove(&root);
+            cdata.token_holdings.remove(&root);
+            return None;
+        }
+
+        // the token might have been removed while the balance was being fetched
+        if !cdata.token_roots.contains_key(&root) {
+            return None;
+        }
+
+        cdata.token_holdings.insert(root, holding.clone());
+        Some(holding)
+    }))
+}
+
+pub(crate) fn schedule_token_holdings_refresh() {
+    schedule_token_holdings_refresh_after(Duration::from_secs(
+        TOKEN_HOLDINGS_REFRESH_INTERVAL_SECS,
+    ));
+}
+
+pub(crate) fn schedule_token_holdings_refresh_after(delay: Duration) {
+    if TOKEN_HOLDINGS_REFRESH_TIMER.get().is_some() {
+        return;
+    }
+
+    let timer_id = ic_cdk_timers::set_timer(delay, || ic_cdk::spawn(refresh_all_token_holdings()));
+    TOKEN_HOLDINGS_REFRESH_TIMER.set(Some(timer_id));
+}
+
+async fn refresh_all_token_holdings() {
+    TOKEN_HOLDINGS_REFRESH_TIMER.set(None);
+
+    let token_roots: Vec<Principal> =
+        CANISTER_DATA.with_borrow(|cdata| cdata.token_roots.iter().map(|(root, _)| root).collect());
+
+    let futures = token_roots
+        .into_iter()
+        .map(|root| async move { update_token_holding(root).await.map_err(|e| (root, e)) });
+
+    run_task_concurrently(
+        futures,
+        10,
+        |result| {
+            if let Err((root, e)) = result {
+                ic_cdk::println!("Failed refreshing token holding {}. Error: {:?}", root, e);
+            }
+        },
+        || false,
+    )
+    .await;
+
+    let has_tokens = CANISTER_DATA.with_borrow(|cdata| !cdata.token_roots.is_empty());
+    if has_tokens {
+        schedule_token_holdings_refresh();
+    }
+}
+
+/// Timers do not survive upgrades, reschedule the refresh if the user holds any tokens.
+/// Tokens without a cached holding, such as the ones held before holdings were cached,
+/// are backfilled right away.
+pub(crate) fn restart_token_holdings_refresh_after_upgrade() {
+    let (has_tokens, has_missing_holdings) = CANISTER_DATA.with_borrow(|cdata| {
+        (
+            !cdata.token_roots.is_empty(),
+            cdata
+                .token_roots
+                .iter()
+                .any(|(root, _)| !cdata.token_holdings.contains_key(&root)),
+        )
+    });
+    if has_missing_holdings {
+        schedule_token_holdings_refresh_after(Duration::ZERO);
+    } else if has_tokens {
+        schedule_token_holdings_refresh();
+    }
+}
@@ -0,0 +1,417 @@
+use std::{cell::Cell, time::Duration};
+
+use candid::{Nat, Principal};
+use ic_cdk::{
+    api::management_canister::main::{canister_info, CanisterInfoRequest},
+    query, update,
+};
+use ic_cdk_timers::TimerId;
+use ic_sns_root::pb::v1::{ListSnsCanistersRequest, ListSnsCanistersResponse};
+use icrc_ledger_types::{
+    icrc1::{
+        account::Account,
+        transfer::{Memo, TransferArg, TransferError},
+    },
+    icrc3::transactions::{GetTransactionsRequest, GetTransactionsResponse},
+};
+use shared_utils::{
+    canister_specific::individual_user_template::types::{
+        error::CdaoTokenError,
+        profile::UserProfileDetailsForFrontendV2,
+        token::{CreatorTokenTransfer, CreatorTokenTransferStatus},
+    },
+    common::utils::system_time::get_current_system_time,
+    constant::{
+        CREATOR_TOKEN_LEDGER_DEDUPLICATION_WINDOW_SECS, CREATOR_TOKEN_TRANSFER_RETRY_AFTER_SECS,
+        MAX_ATTEMPTS_FOR_CREATOR_TOKEN_TRANSFER_NOTIFICATION,
+    },
+    pagination::PaginationError,
+};
+
+use crate::{util::subnet_orchestrator::get_all_subnet_orchestrators, CANISTER_DATA};
+
+use super::token_holdings::update_token_holding;
+
+// ledger transactions read per request when looking up a transfer
+const LEDGER_TRANSACTIONS_PER_REQUEST: u64 = 1000;
+// how far in the future of the ledger's time a transfer's created_at_time may be
+const LEDGER_PERMITTED_DRIFT: Duration = Duration::from_secs(60);
+
+thread_local! {
+    static CREATOR_TOKEN_TRANSFER_RETRY_TIMER: Cell<Option<TimerId>> = const { Cell::new(None) };
+}
+
+/// Transfers the token and then notifies the receiving canister to add it. The transfer
+/// is recorded before it is sent, so a transfer whose outcome is unknown is retried
+/// later with the same arguments and deduplicated by the ledger, or looked up in the
+/// ledger's transactions once it is too old to be deduplicated. The ledger transfer
+/// cannot be undone, so a failed notification is recorded and retried later instead
+/// of failing the call.
+#[update]
+async fn transfer_token_to_user_canister(
+    token_root: Principal,
+    target_canister: Principal,
+    memo: Option<Memo>,
+    amount: Nat,
+) -> Result<(), CdaoTokenError> {
+    // * access control
+    let current_caller = ic_cdk::caller();
+    let my_principal_id = CANISTER_DATA
+        .with(|canister_data_ref_cell| canister_data_ref_cell.borrow().profile.principal_id);
+    if my_principal_id != Some(current_caller) {
+        return Err(CdaoTokenError::Unauthenticated);
+    };
+
+    verify_target_is_registered_user_canister(target_canister).await?;
+
+    let res: (ListSnsCanistersResponse,) = ic_cdk::call(
+        token_root,
+        "list_sns_canisters",
+        (ListSnsCanistersRequest {},),
+    )
+    .await?;
+    let ledger = res.0.ledger.ok_or(CdaoTokenError::InvalidRoot)?;
+
+    let transfer_id = CANISTER_DATA.with_borrow_mut(|canister_data| {
+        canister_data.creator_token_transfers.record(
+            token_root,
+            ledger.into(),
+            target_canister,
+            amount,
+            memo,
+            get_current_system_time(),
+        )
+    });
+
+    let transfer_res = send_creator_token_transfer(transfer_id).await;
+    if let Err(CdaoTokenError::CallError(..)) = &transfer_res {
+        schedule_creator_token_transfer_retry();
+    }
+    transfer_res?;
+
+    if !notify_receiver_of_creator_token_transfer(transfer_id).await {
+        schedule_creator_token_transfer_retry();
+    }
+
+    if let Err(e) = update_token_holding(token_root).await {
+        ic_cdk::println!(
+            "Failed refreshing token holding {}. Error: {:?}",
+            token_root,
+            e
+        );
+    }
+
+    Ok(())
+}
+
+#[query]
+fn get_creator_token_transfers_with_pagination_cursor(
+    from_inclusive_index: u64,
+    limit: u64,
+) -> Result<Vec<CreatorTokenTransfer>, PaginationError> {
+    CANISTER_DATA.with_borrow(|canister_data| {
+        canister_data
+            .creator_token_transfers
+            .get_transfers_with_pagination_cursor(from_inclusive_index, limit)
+    })
+}
+
+/// The target has to be the canister a registered subnet orchestrator assigned to
+/// the target's user
+async fn verify_target_is_registered_user_canister(
+    target_canister: Principal,
+) -> Result<(), CdaoTokenError> {
+    let subnet_orchestrators = get_all_subnet_orchestrators().await?;
+
+    let (target_canister_info,) = canister_info(CanisterInfoRequest {
+        canister_id: target_canister,
+        num_requested_changes: None,
+    })
+    .await?;
+    let target_subnet_orchestrator = target_canister_info
+        .controllers
+        .into_iter()
+        .find(|controller| subnet_orchestrators.contains(controller))
+        .ok_or(CdaoTokenError::UnregisteredTargetCanister)?;
+
+    let (target_profile,): (UserProfileDetailsForFrontendV2,) =
+        ic_cdk::call(target_canister, "get_profile_details_v2", ()).await?;
+    let (registered_canister,): (Option<Principal>,) = ic_cdk::call(
+        target_subnet_orchestrator,
+        "get_user_canister_id_from_user_principal_id",
+        (target_profile.principal_id,),
+    )
+    .await?;
+
+    if registered_canister != Some(target_canister) {
+        return Err(CdaoTokenError::UnregisteredTargetCanister);
+    }
+
+    Ok(())
+}
+
+/// Sends the recorded transfer to the ledger. A call error leaves it pending to be
+/// sent again, a retry of a transfer that went through is reported as a duplicate.
+async fn send_creator_token_transfer(transfer_id: u64) -> Result<(), CdaoTokenError> {
+    let transfer = CANISTER_DATA
+        .with_borrow(|canister_data| {
+            canister_data
+                .creator_token_transfers
+                .get(transfer_id)
+                .cloned()
+        })
+        .ok_or(CdaoTokenError::TransferNotFound(transfer_id))?;
+    // only transfers recorded after they went through have no ledger, they are never sent again
+    let ledger = transfer.ledger.ok_or(CdaoTokenError::InvalidRoot)?;
+
+    let transfer_args = TransferArg {
+        from_subaccount: None,
+        to: Account {
+            owner: transfer.target_canister,
+            subaccount: None,
+        },
+        fee: None,
+        created_at_time: Some(transfer.get_created_at_time()),
+        memo: transfer.memo,
+        amount: transfer.amount,
+    };
+    let transfer_res = ic_cdk::call::<_, (Result<Nat, TransferError>,)>(
+        ledger,
+        "icrc1_transfer",
+        (transfer_args,),
+    )
+    .await;
+
+    CANISTER_DATA.with_borrow_mut(|canister_data| {
+        let transfers = &mut canister_data.creator_token_transfers;
+        let current_time = get_current_system_time();
+
+        match transfer_res {
+            Ok((Ok(block_index),))
+            | Ok((Err(TransferError::Duplicate {
+                duplicate_of: block_index,
+            }),)) => {
+                transfers.mark_transferred(transfer_id, block_index, current_time);
+                Ok(())
+            }
+            Ok((Err(e),)) => {
+                transfers.mark_transfer_failed(transfer_id, format!("{:?}", e), current_time);
+                Err(CdaoTokenError::Transfer(e))
+            }
+            Err(e) => {
+                transfers.mark_transfer_attempt_failed(
+                    transfer_id,
+                    format!("{:?} {}", e.0, e.1),
+                    current_time,
+                );
+                Err(e.into())
+            }
+        }
+    })
+}
+
+/// Resolves a pending transfer that is too old to be sent again. It went through if one
+/// of its attempts is found in the ledger's transactions, and failed otherwise. It is
+/// left pending if the ledger cannot be read. Returns whether it went through.
+async fn resolve_creator_token_transfer_from_ledger(transfer_id: u64) -> bool {
+    let Some(transfer) = CANISTER_DATA.with_borrow(|canister_data| {
+        canister_data
+            .creator_token_transfers
+            .get(transfer_id)
+            .cloned()
+    }) else {
+        return false;
+    };
+    let Some(ledger) = transfer.ledger else {
+        return false;
+    };
+
+    let lookup_res = find_creator_token_transfer_on_ledger(ledger, &transfer).await;
+
+    CANISTER_DATA.with_borrow_mut(|canister_data| {
+        let transfers = &mut canister_data.creator_token_transfers;
+        let current_time = get_current_system_time();
+
+        match lookup_res {
+            Ok(Some(block_index)) => {
+                transfers.mark_transferred(transfer_id, block_index, current_time);
+                true
+            }
+            Ok(None) => {
+                transfers.mark_transfer_failed(
+                    transfer_id,
+                    "Transfer not found on the ledger".into(),
+                    current_time,
+                );
+                false
+            }
+            Err(e) => {
+                transfers.mark_transfer_attempt_failed(transfer_id, e, current_time);
+                false
+            }
+        }
+    })
+}
+
+/// Reads the ledger's transactions from the latest back to the time the transfer was
+/// created, and returns the block index of the attempt that went through
+async fn find_creator_token_transfer_on_ledger(
+    ledger: Principal,
+    transfer: &CreatorTokenTransfer,
+) -> Result<Option<Nat>, String> {
+    let earliest_timestamp = transfer
+        .get_created_at_time()
+        .saturating_sub(LEDGER_PERMITTED_DRIFT.as_nanos() as u64);
+
+    let get_transactions = |start: u64, length: u64| async move {
+        ic_cdk::call::<_, (GetTransactionsResponse,)>(
+            ledger,
+            "get_transactions",
+            (GetTransactionsRequest {
+                start: Nat::from(start),
+                length: Nat::from(length),
+            },),
+        )
+        .await
+        .map(|res| res.0)
+        .map_err(|e| format!("{:?} {}", e.0, e.1))
+    };
+
+    let log_length: u64 = get_transactions(0, 0)
+        .await?
+        .log_length
+        .0
+        .try_into()
+        .map_err(|_| "Ledger log length out of range".to_string())?;
+
+    let mut end = log_length;
+    while end > 0 {
+        let start = end.saturating_sub(LEDGER_TRANSACTIONS_PER_REQUEST);
+        let response = get_transactions(start, end - start).await?;
+
+        for (i, transaction) in response.transactions.iter().enumerate().rev() {
+            if transaction
+                .transfer
+                .as_ref()
+                .is_some_and(|ledger_transfer| transfer.is_sent_as(ic_cdk::id(), ledger_transfer))
+            {
+                return Ok(Some(response.first_index.clone() + Nat::from(i)));
+            }
+            if transaction.timestamp < earliest_timestamp {
+                return Ok(None);
+            }
+        }
+
+        // archived transactions are not read, the transfer may be among them
+        if !response.archived_transactions.is_empty() {
+            return Err("Ledger transactions of the transfer are archived".into());
+        }
+        end = start;
+    }
+
+    Ok(None)
+}
+
+/// Returns false if the receiving canister has to be notified again
+async fn notify_receiver_of_creator_token_transfer(transfer_id: u64) -> bool {
+    let Some((token_root, target_canister)) = CANISTER_DATA.with_borrow(|canister_data| {
+        canister_data
+            .creator_token_transfers
+            .get(transfer_id)
+            .map(|transfer| (transfer.token_root, transfer.target_canister))
+    }) else {
+        return true;
+    };
+
+    let res = ic_cdk::call::<_, (Result<bool, CdaoTokenError>,)>(
+        target_canister,
+        "add_token",
+        (token_root,),
+    )
+    .await
+    .map_err(|e| format!("{:?} {}", e.0, e.1))
+    .and_then(|res| res.0.map_err(|e| format!("{:?}", e)));
+
+    CANISTER_DATA.with_borrow_mut(|canister_data| match res {
+        Ok(_) => {
+            canister_data
+                .creator_token_transfers
+                .mark_completed(transfer_id, get_current_system_time());
+            true
+        }
+        Err(e) => {
+            canister_data
+                .creator_token_transfers
+                .mark_notification_failed(transfer_id, e, get_current_system_time());
+            false
+        }
+    })
+}
+
+fn schedule_creator_token_transfer_retry() {
+    if CREATOR_TOKEN_TRANSFER_RETRY_TIMER.get().is_some() {
+        return;
+    }
+
+    let timer_id = ic_cdk_timers::set_timer(
+        Duration::from_secs(CREATOR_TOKEN_TRANSFER_RETRY_AFTER_SECS),
+        || ic_cdk::spawn(retry_creator_token_transfer_notifications()),
+    );
+    CREATOR_TOKEN_TRANSFER_RETRY_TIMER.set(Some(timer_id));
+}
+
+async fn retry_creator_token_transfer_notifications() {
+    CREATOR_TOKEN_TRANSFER_RETRY_TIMER.set(None);
+
+    let deduplication_window = Duration::from_secs(CREATOR_TOKEN_LEDGER_DEDUPLICATION_WINDOW_SECS);
+    for transfer_id in get_retryable_transfer_ids() {
+        let pending_transfer = CANISTER_DATA.with_borrow(|canister_data| {
+            canister_data
+                .creator_token_transfers
+                .get(transfer_id)
+                .filter(|transfer| {
+                    matches!(
+                        transfer.status,
+                        CreatorTokenTransferStatus::TransferPending { .. }
+                    )
+                })
+                .map(|transfer| {
+                    transfer.is_past_deduplication_window(
+                        deduplication_window,
+                        get_current_system_time(),
+                    )
+                })
+        });
+        let is_transferred = match pending_transfer {
+            None => true,
+            // the ledger refuses to deduplicate it, sending it again could transfer twice
+            Some(true) => resolve_creator_token_transfer_from_ledger(transfer_id).await,
+            Some(false) => send_creator_token_transfer(transfer_id).await.is_ok(),
+        };
+        if !is_transferred {
+            continue;
+        }
+
+        notify_receiver_of_creator_token_transfer(transfer_id).await;
+    }
+
+    if !get_retryable_transfer_ids().is_empty() {
+        schedule_creator_token_transfer_retry();
+    }
+}
+
+fn get_retryable_transfer_ids() -> Vec<u64> {
+    CANISTER_DATA.with_borrow(|canister_data| {
+        canister_data
+            .creator_token_transfers
+            .get_retryable_transfer_ids(MAX_ATTEMPTS_FOR_CREATOR_TOKEN_TRANSFER_NOTIFICATION)
+    })
+}
+
+/// Timers do not survive upgrades, reschedule transfers and notifications that are
+/// still pending
+pub(crate) fn restart_creator_token_transfer_retry_after_upgrade() {
+    if !get_retryable_transfer_ids().is_empty() {
+        schedule_creator_token_transfer_retry();
+    }
+}
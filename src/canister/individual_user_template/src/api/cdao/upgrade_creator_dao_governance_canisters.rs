@@ -13,9 +13,12 @@ use ic_cdk_macros::update;
 use ic_sns_governance::{init::GovernanceCanisterInitPayloadBuilder, pb::v1::governance::Version};
 
 use shared_utils::{
-    common::utils::{
-        permissions::is_caller_controller_or_global_admin, task::run_task_concurrently,
-        upgrade_canister::upgrade_canister_util,
+    common::{
+        types::wasm::SnsWasmType,
+        utils::{
+            permissions::is_caller_controller_or_global_admin, task::run_task_concurrently,
+            upgrade_canister::upgrade_canister_util,
+        },
     },
     constant::{
         SNS_TOKEN_ARCHIVE_MODULE_HASH, SNS_TOKEN_GOVERNANCE_MODULE_HASH,
@@ -51,7 +54,7 @@ pub async fn upgrade_creator_dao_governance_canisters(wasm_module: Vec<u8>) -> R
             .await;
 
             match result {
-                Ok(()) => Ok(*governance_canister_id),
+                Ok(upgraded) => Ok((*governance_canister_id, upgraded)),
                 Err(e) => Err((*governance_canister_id, e)),
             }
         });
@@ -60,10 +63,15 @@ pub async fn upgrade_creator_dao_governance_canisters(wasm_module: Vec<u8>) -> R
         futures,
         10,
         |result| match result {
-            Ok(canister_id) => ic_cdk::println!(
-                "Governance canister {} upgraded Successfully",
-                canister_id.to_text()
-            ),
+            Ok((canister_id, upgraded)) => {
+                if upgraded {
+                    clear_governance_version(canister_id);
+                }
+                ic_cdk::println!(
+                    "Governance canister {} upgraded Successfully",
+                    canister_id.to_text()
+                )
+            }
             Err(e) => ic_cdk::println!(
                 "Failed upgrading governance Canister {} . Error: {}",
                 e.0,
@@ -77,17 +85,28 @@ pub async fn upgrade_creator_dao_governance_canisters(wasm_module: Vec<u8>) -> R
     Ok(())
 }
 
+/// The wasm passed here has no version, so the recorded governance version is cleared for
+/// `upgrade_creator_dao_sns_canisters` to not skip the canister
+fn clear_governance_version(governance_canister_id: Principal) {
+    CANISTER_DATA.with_borrow_mut(|canister_data| {
+        if let Some(canisters) = canister_data
+            .cdao_canisters
+            .iter_mut()
+            .find(|canisters| canisters.governance == governance_canister_id)
+        {
+            canisters.sns_versions.remove(&SnsWasmType::Governance);
+        }
+    })
+}
+
+/// Returns whether the canister was upgraded, governance canisters with a deployed version
+/// are skipped
 async fn recharge_and_upgrade_canister(
     canister_id: Principal,
     wasm_module: Vec<u8>,
     recharge_amount: u128,
-) -> Result<(), String> {
-    //Add cycles in individual canister
-    request_cycles_from_subnet_orchestrator(recharge_amount).await?;
-
-    deposit_cycles(CanisterIdRecord { canister_id }, recharge_amount)
-        .await
-        .map_err(|e| e.1)?;
+) -> Result<bool, String> {
+    recharge_sns_canister(canister_id, recharge_amount).await?;
 
     let sns_governance = sns_governance::Service(canister_id);
 
@@ -97,7 +116,7 @@ async fn recharge_and_upgrade_canister(
         .map_err(|e| e.1)?;
 
     if sns_running_version.deployed_version.is_some() {
-        return Ok(());
+        return Ok(false);
     }
 
     let gov_hash = hex::decode(SNS_TOKEN_GOVERNANCE_MODULE_HASH).unwrap();
@@ -128,6 +147,20 @@ async fn recharge_and_upgrade_canister(
     };
 
     upgrade_canister_util(install_code_argument)
+        .await
+        .map_err(|e| e.1)?;
+
+    Ok(true)
+}
+
+pub(super) async fn recharge_sns_canister(
+    canister_id: Principal,
+    recharge_amount: u128,
+) -> Result<(), String> {
+    //Add cycles in individual canister
+    request_cycles_from_subnet_orchestrator(recharge_amount).await?;
+
+    deposit_cycles(CanisterIdRecord { canister_id }, recharge_amount)
         .await
         .map_err(|e| e.1)
 }
@@ -0,0 +1,203 @@
+use candid::{Encode, Principal};
+use ic_cdk::api::management_canister::main::{
+    canister_status, CanisterIdRecord, CanisterInstallMode, InstallCodeArgument,
+};
+use ic_cdk_macros::update;
+use ic_icrc1_index_ng::IndexArg;
+use ic_icrc1_ledger::LedgerArgument;
+use ic_sns_governance::{init::GovernanceCanisterInitPayloadBuilder, pb::v1::governance::Version};
+use sha2::{Digest, Sha256};
+use shared_utils::{
+    canister_specific::individual_user_template::types::cdao::DeployedCdaoCanisters,
+    common::{
+        types::wasm::SnsWasmType,
+        utils::{
+            permissions::is_caller_controller_or_global_admin, task::run_task_concurrently,
+            upgrade_canister::upgrade_canister_util,
+        },
+    },
+    constant::SNS_TOKEN_ARCHIVE_MODULE_HASH,
+    types::sns_canisters::sns_governance::{self, GetRunningSnsVersionArg},
+};
+
+use crate::{util::cycles::notify_to_recharge_canister, CANISTER_DATA};
+
+use super::upgrade_creator_dao_governance_canisters::recharge_sns_canister;
+
+const SNS_CANISTER_UPGRADE_RECHARGE_AMOUNT: u128 = 400_000_000_000; //400B
+
+/// Upgrades the given canister type of every creator token that is not on `version` yet
+/// and records the version on success. Governance keeps the hashes of the wasms its SNS
+/// runs in its deployed version, which it only takes while it is upgraded. Our governance
+/// wasm is not registered with SNS-W, so upgrading any other canister type clears the
+/// recorded governance version and the next governance rollout refreshes those hashes.
+/// Fails with the canisters that could not be upgraded so that a rerun only retries those.
+#[update(guard = "is_caller_controller_or_global_admin")]
+pub async fn upgrade_creator_dao_sns_canisters(
+    wasm_type: SnsWasmType,
+    version: String,
+    wasm_module: Vec<u8>,
+) -> Result<(), String> {
+    notify_to_recharge_canister();
+
+    let upgrade_arg = get_sns_canister_upgrade_arg(wasm_type)?;
+
+    let pending_canisters: Vec<DeployedCdaoCanisters> =
+        CANISTER_DATA.with_borrow(|canister_data| {
+            canister_data
+                .cdao_canisters
+                .iter()
+                .filter(|canisters| !canisters.is_on_version(wasm_type, &version))
+                .cloned()
+                .collect()
+        });
+
+    let futures = pending_canisters.into_iter().map(|canisters| {
+        let wasm_module = wasm_module.clone();
+        let upgrade_arg = upgrade_arg.clone();
+        async move {
+            let result =
+                recharge_and_upgrade_sns_canister(&canisters, wasm_type, wasm_module, upgrade_arg)
+                    .await;
+
+            match result {
+                Ok(()) => Ok(canisters.root),
+                Err(e) => Err((canisters.get_canister_id(wasm_type), e)),
+            }
+        }
+    });
+
+    let mut failed_canisters = vec![];
+    run_task_concurrently(
+        futures,
+        10,
+        |result| match result {
+            Ok(root) => CANISTER_DATA.with_borrow_mut(|canister_data| {
+                if let Some(canisters) = canister_data
+                    .cdao_canisters
+                    .iter_mut()
+                    .find(|canisters| canisters.root == root)
+                {
+                    canisters.sns_versions.insert(wasm_type, version.clone());
+                    if wasm_type != SnsWasmType::Governance {
+                        canisters.sns_versions.remove(&SnsWasmType::Governance);
+                    }
+                }
+            }),
+            Err((canister_id, e)) => {
+                ic_cdk::println!(
+                    "Failed upgrading {:?} canister {}. Error: {}",
+                    wasm_type,
+                    canister_id,
+                    e
+                );
+                failed_canisters.push(canister_id.to_text());
+            }
+        },
+        || false,
+    )
+    .await;
+
+    if failed_canisters.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Failed upgrading {:?} canisters: {}",
+            wasm_type,
+            failed_canisters.join(", ")
+        ))
+    }
+}
+
+/// SNS canisters keep their state in stable memory, only the ledger and the index
+/// expect an explicit upgrade variant
+fn get_sns_canister_upgrade_arg(wasm_type: SnsWasmType) -> Result<Vec<u8>, String> {
+    match wasm_type {
+        SnsWasmType::Ledger => Encode!(&LedgerArgument::Upgrade(None)),
+        SnsWasmType::Index => Encode!(&None::<IndexArg>),
+        SnsWasmType::Governance | SnsWasmType::Root | SnsWasmType::Swap => Encode!(),
+    }
+    .map_err(|e| e.to_string())
+}
+
+async fn recharge_and_upgrade_sns_canister(
+    canisters: &DeployedCdaoCanisters,
+    wasm_type: SnsWasmType,
+    wasm_module: Vec<u8>,
+    upgrade_arg: Vec<u8>,
+) -> Result<(), String> {
+    let canister_id = canisters.get_canister_id(wasm_type);
+    recharge_sns_canister(canister_id, SNS_CANISTER_UPGRADE_RECHARGE_AMOUNT).await?;
+
+    if wasm_type == SnsWasmType::Governance {
+        let governance_wasm_hash = Sha256::digest(&wasm_module).to_vec();
+        let deployed_version = get_deployed_version(canisters, governance_wasm_hash).await?;
+        return upgrade_governance_canister(canisters.governance, wasm_module, deployed_version)
+            .await;
+    }
+
+    upgrade_canister_util(InstallCodeArgument {
+        mode: CanisterInstallMode::Upgrade(None),
+        canister_id,
+        wasm_module,
+        arg: upgrade_arg,
+    })
+    .await
+    .map_err(|e| e.1)
+}
+
+async fn upgrade_governance_canister(
+    governance: Principal,
+    wasm_module: Vec<u8>,
+    deployed_version: Version,
+) -> Result<(), String> {
+    let mut governance_init_payload = GovernanceCanisterInitPayloadBuilder::new().build();
+    governance_init_payload.deployed_version = Some(deployed_version);
+
+    let upgrade_arg = candid::encode_one(governance_init_payload).map_err(|e| e.to_string())?;
+
+    upgrade_canister_util(InstallCodeArgument {
+        mode: CanisterInstallMode::Upgrade(None),
+        canister_id: governance,
+        wasm_module,
+        arg: upgrade_arg,
+    })
+    .await
+    .map_err(|e| e.1)
+}
+
+/// Hashes of the wasms the SNS is running with the one governance is about to be
+/// upgraded to
+async fn get_deployed_version(
+    canisters: &DeployedCdaoCanisters,
+    governance_wasm_hash: Vec<u8>,
+) -> Result<Version, String> {
+    // archives are spawned by the ledger and not upgraded here
+    let (running_version,) = sns_governance::Service(canisters.governance)
+        .get_running_sns_version(GetRunningSnsVersionArg {})
+        .await
+        .map_err(|e| e.1)?;
+    let archive_wasm_hash = match running_version.deployed_version {
+        Some(deployed_version) => deployed_version.archive_wasm_hash.into_vec(),
+        None => hex::decode(SNS_TOKEN_ARCHIVE_MODULE_HASH).unwrap(),
+    };
+
+    Ok(Version {
+        governance_wasm_hash,
+        ledger_wasm_hash: get_module_hash(canisters.ledger).await?,
+        root_wasm_hash: get_module_hash(canisters.root).await?,
+        swap_wasm_hash: get_module_hash(canisters.swap).await?,
+        index_wasm_hash: get_module_hash(canisters.index).await?,
+        archive_wasm_hash,
+    })
+}
+
+async fn get_module_hash(canister_id: Principal) -> Result<Vec<u8>, String> {
+    let (status,) = canister_status(CanisterIdRecord { canister_id })
+        .await
+        .map_err(|e| e.1)?;
+
+    status
+        .module_hash
+        .ok_or_else(|| format!("No wasm installed on canister {}", canister_id))
+}
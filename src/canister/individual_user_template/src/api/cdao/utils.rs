@@ -4,20 +4,27 @@ use shared_utils::common::utils::{
     task::run_task_concurrently, upgrade_canister::try_stopping_canister_with_retries,
 };
 
-use crate::CANISTER_DATA;
-
-use super::SubnetOrchestrator;
+use crate::{util::subnet_orchestrator::SubnetOrchestrator, CANISTER_DATA};
 
 pub(crate) async fn uninstall_code_and_return_empty_canisters_to_subnet_backup_pool(
     canister_ids: Vec<Principal>,
 ) {
+    try_uninstall_code_and_return_empty_canisters_to_subnet_backup_pool(canister_ids).await;
+}
+
+/// Returns the canisters that could not be returned to the subnet backup pool,
+/// they are kept as empty canisters of this canister instead.
+pub(crate) async fn try_uninstall_code_and_return_empty_canisters_to_subnet_backup_pool(
+    canister_ids: Vec<Principal>,
+) -> Vec<Principal> {
     let mut failed_to_return_canister_ids = vec![];
     let subnet_orchestrator_res = SubnetOrchestrator::new().inspect_err(|_e| {
         CANISTER_DATA.with_borrow_mut(|canister_data| {
             canister_data
                 .empty_canisters
                 .append_empty_canisters(canister_ids.clone())
-        })
+        });
+        failed_to_return_canister_ids.extend(canister_ids.iter().copied());
     });
 
     if let Ok(subnet_orchestrator) = subnet_orchestrator_res {
@@ -57,7 +64,9 @@ pub(crate) async fn uninstall_code_and_return_empty_canisters_to_subnet_backup_p
         CANISTER_DATA.with_borrow_mut(|canister_data| {
             canister_data
                 .empty_canisters
-                .append_empty_canisters(failed_to_return_canister_ids);
+                .append_empty_canisters(failed_to_return_canister_ids.clone());
         });
     }
+
+    failed_to_return_canister_ids
 }
@@ -4,7 +4,10 @@ use shared_utils::canister_specific::individual_user_template::types::{
     profile::UserCanisterDetails, session::SessionType,
 };
 
-use crate::{util::cycles::notify_to_recharge_canister, CANISTER_DATA};
+use crate::{
+    util::{cycles::notify_to_recharge_canister, subnet_orchestrator::SubnetOrchestrator},
+    CANISTER_DATA, PUMP_N_DUMP,
+};
 
 #[update]
 async fn update_referrer_details(referrer: UserCanisterDetails) -> Result<String, String> {
@@ -16,6 +19,10 @@ async fn update_referrer_details(referrer: UserCanisterDetails) -> Result<String
         return Err("Unauthorized".into());
     }
 
+    if referrer.profile_owner == caller() || referrer.user_canister_id == ic_cdk::id() {
+        return Err("Cannot refer yourself".into());
+    }
+
     let (canister_session_type_result,): (Result<SessionType, String>,) =
         call(referrer.user_canister_id, "get_session_type", ())
             .await
@@ -27,14 +34,34 @@ async fn update_referrer_details(referrer: UserCanisterDetails) -> Result<String
     }
 
     CANISTER_DATA.with_borrow_mut(|canister_data| {
+        if canister_data.session_type != Some(SessionType::RegisteredSession) {
+            return Err("Login required".to_string());
+        }
+
         let profile = &mut canister_data.profile;
 
-        if profile.referrer_details.is_some() {
-            return Err("Referrer is already set".into());
+        match &profile.referrer_details {
+            // retrying with the same referrer re-requests a reward that was not credited yet
+            Some(current_referrer) if *current_referrer == referrer => {
+                let referee_rewarded = PUMP_N_DUMP.with_borrow(|pump_and_dump| {
+                    pump_and_dump.referral_rewards.contains_key(&caller())
+                });
+
+                if referee_rewarded {
+                    return Err("Referrer is already set".into());
+                }
+            }
+            Some(_) => return Err("Referrer is already set".into()),
+            None => profile.referrer_details = Some(referrer.clone()),
         }
 
-        profile.referrer_details = Some(referrer);
+        Ok(())
+    })?;
+
+    let subnet_orchestrator = SubnetOrchestrator::new()?;
+    subnet_orchestrator
+        .issue_rewards_for_referral(referrer)
+        .await?;
 
-        Ok("Success".into())
-    })
+    Ok("Success".into())
 }
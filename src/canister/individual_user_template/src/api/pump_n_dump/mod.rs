@@ -1,12 +1,15 @@
+use std::{cell::Cell, time::Duration};
+
 use candid::{Nat, Principal};
 use ic_cdk_macros::{query, update};
+use ic_cdk_timers::TimerId;
 use icrc_ledger_types::{
     icrc1::account::Account,
     icrc2::transfer_from::{TransferFromArgs, TransferFromError},
 };
 use shared_utils::{
     canister_specific::individual_user_template::types::{
-        cents::CentsToken, pump_n_dump::{BalanceInfo, ParticipatedGameInfo, PumpNDumpStateDiff, PumpsAndDumps}, session::SessionType, token::TokenTransactions
+        cents::CentsToken, pump_n_dump::{BalanceInfo, GameHistoryEntry, GameHistoryFilter, ParticipatedGameInfo, PumpNDumpStateDiff, PumpsAndDumps, TokenGameStats}, session::SessionType, token::TokenTransactions
     },
     common::{
         types::{
@@ -18,10 +21,18 @@ use shared_utils::{
             system_time::get_current_system_time,
         },
     },
-    pagination,
+    constant::{MAX_POSTS_IN_ONE_REQUEST, PUMP_N_DUMP_EARNINGS_SUMMARY_INTERVAL_SECS},
+    pagination::{self, PaginationError},
+};
+
+use crate::{
+    data_model::pump_n_dump::NatStore, util::subnet_orchestrator::SubnetOrchestrator,
+    CANISTER_DATA, PUMP_N_DUMP,
 };
 
-use crate::{data_model::pump_n_dump::NatStore, CANISTER_DATA, PUMP_N_DUMP};
+thread_local! {
+    static EARNINGS_SUMMARY_TIMER: Cell<Option<TimerId>> = const { Cell::new(None) };
+}
 
 async fn process_gdolr_withdrawl(amount: u128, withdrawable_balance_checker: impl FnOnce(&CentsToken) -> Nat) -> Result<(), String> {
     let (profile_owner, user_index) = CANISTER_DATA.with_borrow(|cdata| {
@@ -44,9 +55,7 @@ async fn process_gdolr_withdrawl(amount: u128, withdrawable_balance_checker: imp
         pd.cents.handle_token_event(TokenEvent::Withdraw {
             amount,
             event_type: WithdrawEvent::WithdrawRequest,
-        });
-
-        Ok(())
+        }).map_err(|e| format!("{:?}", e))
     })?;
 
     let res = ic_cdk::call::<_, (Result<(), String>,)>(
@@ -62,7 +71,7 @@ async fn process_gdolr_withdrawl(amount: u128, withdrawable_balance_checker: imp
                 pd.cents.handle_token_event(TokenEvent::Withdraw {
                     amount,
                     event_type: WithdrawEvent::WithdrawRequestFailed,
-                })
+                }).expect("Crediting cents never fails")
             });
             Err(e)
         }
@@ -84,23 +93,89 @@ pub async fn redeem_gdolr_v2(amount: u128) -> Result<(), String> {
 pub fn reconcile_user_state(games: Vec<PumpNDumpStateDiff>) -> Result<(), String> {
     CANISTER_DATA.with_borrow(|cdata| is_caller_global_admin_v2(&cdata.known_principal_ids))?;
 
-    PUMP_N_DUMP.with_borrow_mut(|pump_and_dump| {
-        for game in games {
-            let token_events = game.get_token_events_from_pump_dump_state_diff();
+    let current_time = get_current_system_time();
 
-            for token_event in token_events {
-                pump_and_dump.cents.handle_token_event(token_event);
+    PUMP_N_DUMP.with_borrow_mut(|pump_and_dump| {
+        // checked on a copy without the history first so a rejected debit leaves none of
+        // the games applied
+        let mut cents = pump_and_dump.cents.clone_without_transaction_history();
+        for game in games.iter() {
+            for token_event in game.get_token_events_from_pump_dump_state_diff() {
+                cents
+                    .handle_token_event(token_event)
+                    .map_err(|e| format!("{:?}", e))?;
             }
+        }
+        for game in games.iter() {
+            for token_event in game.get_token_events_from_pump_dump_state_diff() {
+                pump_and_dump
+                    .cents
+                    .handle_token_event(token_event)
+                    .map_err(|e| format!("{:?}", e))?;
+            }
+        }
 
+        for game in games {
             if let PumpNDumpStateDiff::Participant(info) = game {
-                pump_and_dump.games.push(info);
+                pump_and_dump
+                    .games
+                    .push(GameHistoryEntry::new(info, current_time));
                 pump_and_dump.total_dumps += info.dumps;
                 pump_and_dump.total_pumps += info.pumps;
             }
         }
 
-        Ok(())
-    })
+        pump_and_dump.earnings_summary_pending = true;
+
+        Ok::<_, String>(())
+    })?;
+
+    schedule_earnings_summary();
+
+    Ok(())
+}
+
+/// Timers do not survive upgrades, a summary that was waiting is scheduled again
+pub(crate) fn restart_earnings_summary_after_upgrade() {
+    schedule_earnings_summary();
+}
+
+/// Summaries are batched, at most one is pushed every `PUMP_N_DUMP_EARNINGS_SUMMARY_INTERVAL_SECS`
+fn schedule_earnings_summary() {
+    let is_pending = PUMP_N_DUMP.with_borrow(|pd| pd.earnings_summary_pending);
+    if !is_pending || EARNINGS_SUMMARY_TIMER.get().is_some() {
+        return;
+    }
+
+    let timer = ic_cdk_timers::set_timer(
+        Duration::from_secs(PUMP_N_DUMP_EARNINGS_SUMMARY_INTERVAL_SECS),
+        || {
+            EARNINGS_SUMMARY_TIMER.set(None);
+            send_earnings_summary_to_subnet_orchestrator();
+        },
+    );
+    EARNINGS_SUMMARY_TIMER.set(Some(timer));
+}
+
+fn send_earnings_summary_to_subnet_orchestrator() {
+    PUMP_N_DUMP.with_borrow_mut(|pd| pd.earnings_summary_pending = false);
+
+    let Some(user_principal_id) =
+        CANISTER_DATA.with_borrow(|cdata| cdata.profile.principal_id)
+    else {
+        return;
+    };
+
+    let earnings_summary = PUMP_N_DUMP
+        .with_borrow(|pd| pd.get_earnings_summary(user_principal_id, ic_cdk::id()));
+
+    let res = SubnetOrchestrator::new().and_then(|subnet_orchestrator| {
+        subnet_orchestrator.send_pump_n_dump_earnings_summary(earnings_summary)
+    });
+
+    if let Err(e) = res {
+        ic_cdk::println!("Error sending earnings summary to subnet orchestrator {}", e)
+    }
 }
 
 #[update]
@@ -168,7 +243,7 @@ pub async fn stake_dollr_for_gdollr(amount: u128) -> Result<(), String> {
             amount: amount as u64,
             from_account: caller,
             timestamp: get_current_system_time(),
-        });
+        }).expect("Crediting cents never fails");
     });
 
     Ok(())
@@ -206,11 +281,47 @@ pub fn played_game_info_with_pagination_cursor(
 
         Ok(
             pd.games[from_inclusive_index as usize..(from_inclusive_index + limit) as usize]
-                .to_vec(),
+                .iter()
+                .map(|entry| entry.game_info)
+                .collect(),
         )
     })
 }
 
+#[query]
+pub fn get_game_history_with_pagination_cursor(
+    filter: GameHistoryFilter,
+    from_inclusive_index: u64,
+    limit: u64,
+) -> Result<Vec<GameHistoryEntry>, PaginationError> {
+    PUMP_N_DUMP.with_borrow(|pd| {
+        let mut games = pd
+            .get_game_history(&filter)
+            .skip(from_inclusive_index as usize);
+
+        // one more than allowed is taken to tell a page that is too large apart
+        let page: Vec<GameHistoryEntry> = games
+            .by_ref()
+            .take(limit.min(MAX_POSTS_IN_ONE_REQUEST + 1) as usize)
+            .copied()
+            .collect();
+
+        if page.is_empty() && games.next().is_none() {
+            return Err(PaginationError::ReachedEndOfItemsList);
+        }
+        if page.len() as u64 > MAX_POSTS_IN_ONE_REQUEST {
+            return Err(PaginationError::ExceededMaxNumberOfItemsAllowedInOneRequest);
+        }
+
+        Ok(page)
+    })
+}
+
+#[query]
+pub fn get_token_game_stats(filter: GameHistoryFilter) -> Vec<TokenGameStats> {
+    PUMP_N_DUMP.with_borrow(|pd| pd.get_token_game_stats(&filter))
+}
+
 #[query]
 pub fn pd_balance_info() -> BalanceInfo {
     PUMP_N_DUMP.with_borrow(|pd| BalanceInfo {
@@ -0,0 +1,480 @@
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    ops::Bound::{Excluded, Unbounded},
+    time::{Duration, SystemTime},
+};
+
+use candid::Principal;
+use ic_cdk_macros::{query, update};
+use serde::Serialize;
+use serde_json_any_key::*;
+use sha2::{Digest, Sha256};
+use shared_utils::{
+    canister_specific::individual_user_template::types::{
+        cdao::DeployedCdaoCanisters,
+        cents::CentsToken,
+        follow::{FollowEntryDetail, FollowEntryId, FollowList},
+        migration::MigrationInfo,
+        profile::UserProfile,
+        pump_n_dump::GameHistoryEntry,
+        token::TokenHolding,
+    },
+    common::{
+        types::snapshot::{
+            SnapshotError, SnapshotGenerationState, SnapshotManifest, SnapshotProgress,
+            SnapshotSource,
+        },
+        utils::{snapshot_store::SnapshotStore, system_time::get_current_system_time},
+    },
+    constant::{SNAPSHOT_GENERATION_BATCH_SIZE, SNAPSHOT_TRANSFER_CHUNK_SIZE},
+};
+
+use crate::{
+    data_model::{
+        memory::{
+            get_my_data_export_chunks_memory, get_my_data_export_memory,
+            get_my_data_export_next_id_memory, Memory,
+        },
+        pump_n_dump::TokenBetGame,
+        CanisterData,
+    },
+    util::{cycles::notify_to_recharge_canister, guards::is_caller_profile_owner},
+    CANISTER_DATA, MY_DATA_EXPORT_STORE, PUMP_N_DUMP,
+};
+
+use super::{
+    CanisterDataForSnapshot, PostForSnapshot, TokenBalanceForSnapshot, SNAPSHOT_SCHEMA_VERSION,
+};
+
+/// The first section of an export: everything this canister holds about its user but the
+/// lists that can grow without bound, which follow in batches. The sections use the
+/// snapshot types, so their shape follows `SNAPSHOT_SCHEMA_VERSION`.
+#[derive(Serialize)]
+pub struct UserDataExport {
+    /// Version of the snapshot types the sections are written with
+    pub schema_version: u32,
+    pub exported_at: SystemTime,
+    /// Principal, username, display details and global stats of the profile
+    pub profile: UserProfile,
+    pub signed_up_at: Option<SystemTime>,
+    /// Whether the account was migrated from Hot or Not to Yral, and with which account
+    pub migration_info: MigrationInfo,
+    /// Cents balance, earnings and every balance change
+    pub cents: CentsToken,
+    /// Hot or Not token balance and every balance change
+    pub hot_or_not_utility_token: TokenBalanceForSnapshot,
+    /// Creator tokens deployed by the user
+    pub deployed_creator_tokens: Vec<DeployedCdaoCanisters>,
+    /// Creator tokens held by the user, keyed by the token's root
+    #[serde(with = "any_key_map")]
+    pub held_creator_tokens: BTreeMap<Principal, TokenHolding>,
+    /// Roots of every creator token the user received
+    pub received_creator_token_roots: Vec<Principal>,
+}
+
+/// An export is newline-delimited JSON, each line holds one section. `Account` comes
+/// first, the other sections follow in batches of at most `SNAPSHOT_GENERATION_BATCH_SIZE`
+/// entries, in the order of the variants.
+#[derive(Serialize)]
+pub enum UserDataExportSection {
+    Account(UserDataExport),
+    /// Every post created, deleted ones included, with its likes, shares and views
+    Posts(Vec<PostForSnapshot>),
+    /// Pump and dump games played, with the token played on and the reward
+    PumpNDumpGames(Vec<GameHistoryEntry>),
+    /// Users following the user
+    Followers(Vec<FollowEntryDetail>),
+    /// Users the user follows
+    Following(Vec<FollowEntryDetail>),
+}
+
+impl UserDataExport {
+    pub fn new(
+        canister_data: &CanisterData,
+        token_bet_game: &TokenBetGame,
+        exported_at: SystemTime,
+    ) -> Self {
+        let canister_data = CanisterDataForSnapshot::without_posts(canister_data);
+
+        Self {
+            schema_version: SNAPSHOT_SCHEMA_VERSION,
+            exported_at,
+            profile: canister_data.profile,
+            signed_up_at: canister_data.signed_up_at,
+            migration_info: canister_data.migration_info,
+            cents: token_bet_game.cents.clone(),
+            hot_or_not_utility_token: TokenBalanceForSnapshot::from(
+                &token_bet_game.hot_or_not_utility_token,
+            ),
+            deployed_creator_tokens: canister_data.cdao_canisters,
+            held_creator_tokens: canister_data.token_holdings,
+            received_creator_token_roots: canister_data.token_roots.into_keys().collect(),
+        }
+    }
+}
+
+enum NextSection {
+    Account,
+    PostsAfter(Option<u64>),
+    GamesFrom(usize),
+    FollowersAfter(Option<FollowEntryId>),
+    FollowingAfter(Option<FollowEntryId>),
+}
+
+struct MyDataExportGeneration {
+    hasher: Sha256,
+    next_section: NextSection,
+    progress: SnapshotProgress,
+}
+
+thread_local! {
+    // Generation does not survive upgrades, the export has to be started again after one.
+    // Completed exports are kept in stable memory.
+    static MY_DATA_EXPORT_GENERATION: RefCell<Option<MyDataExportGeneration>> = const { RefCell::new(None) };
+}
+
+pub(crate) fn _default_my_data_export_store() -> SnapshotStore<Memory> {
+    SnapshotStore::init(
+        get_my_data_export_memory(),
+        get_my_data_export_chunks_memory(),
+        get_my_data_export_next_id_memory(),
+        1,
+    )
+}
+
+/// Writes a JSON export of the caller's data across several messages. Poll
+/// `get_my_data_export_progress` until it is completed, then fetch it with
+/// `get_my_data_export_manifest` and `download_my_data_chunk`. A new export replaces the
+/// previous one once it is completed.
+#[update(guard = "is_caller_profile_owner")]
+fn export_my_data() -> Result<SnapshotProgress, SnapshotError> {
+    notify_to_recharge_canister();
+
+    if MY_DATA_EXPORT_GENERATION.with_borrow(|generation| {
+        generation.as_ref().is_some_and(|generation| {
+            generation.progress.state == SnapshotGenerationState::InProgress
+        })
+    }) {
+        return Err(SnapshotError::GenerationInProgress);
+    }
+
+    let total_items = CANISTER_DATA.with_borrow(|canister_data| {
+        1 + canister_data.posts_count()
+            + canister_data.follow_data.follower.sorted_index.len()
+            + canister_data.follow_data.following.sorted_index.len()
+    }) + PUMP_N_DUMP.with_borrow(|token_bet_game| token_bet_game.games.len());
+    let started_at = get_current_system_time();
+    let snapshot_id = MY_DATA_EXPORT_STORE
+        .with_borrow_mut(|store| store.begin(SnapshotSource::Generated, started_at));
+    let progress = SnapshotProgress {
+        snapshot_id,
+        state: SnapshotGenerationState::InProgress,
+        started_at,
+        finished_at: None,
+        items_written: 0,
+        total_items: total_items as u64,
+        bytes_written: 0,
+    };
+
+    MY_DATA_EXPORT_GENERATION.set(Some(MyDataExportGeneration {
+        hasher: Sha256::new(),
+        next_section: NextSection::Account,
+        progress: progress.clone(),
+    }));
+    schedule_next_export_section();
+
+    Ok(progress)
+}
+
+#[query(guard = "is_caller_profile_owner")]
+fn get_my_data_export_progress() -> Option<SnapshotProgress> {
+    MY_DATA_EXPORT_GENERATION.with_borrow(|generation| {
+        generation
+            .as_ref()
+            .map(|generation| generation.progress.clone())
+    })
+}
+
+/// Manifest of the last completed export
+#[query(guard = "is_caller_profile_owner")]
+fn get_my_data_export_manifest() -> Result<SnapshotManifest, SnapshotError> {
+    MY_DATA_EXPORT_STORE.with_borrow(|store| {
+        store.manifest(store.latest_completed_id()?, SNAPSHOT_TRANSFER_CHUNK_SIZE)
+    })
+}
+
+#[query(guard = "is_caller_profile_owner")]
+fn download_my_data_chunk(chunk_index: u64) -> Result<Vec<u8>, SnapshotError> {
+    MY_DATA_EXPORT_STORE.with_borrow(|store| {
+        store.read_chunk(
+            store.latest_completed_id()?,
+            SNAPSHOT_TRANSFER_CHUNK_SIZE,
+            chunk_index,
+        )
+    })
+}
+
+fn schedule_next_export_section() {
+    ic_cdk_timers::set_timer(Duration::ZERO, write_next_export_section);
+}
+
+fn write_next_export_section() {
+    let has_more_sections = MY_DATA_EXPORT_GENERATION.with_borrow_mut(|generation| {
+        let Some(generation) = generation else {
+            return false;
+        };
+
+        let written = CANISTER_DATA.with_borrow(|canister_data| {
+            PUMP_N_DUMP.with_borrow(|token_bet_game| {
+                MY_DATA_EXPORT_STORE.with_borrow_mut(|store| {
+                    generation.write_next_section(
+                        store,
+                        canister_data,
+                        token_bet_game,
+                        get_current_system_time(),
+                    )
+                })
+            })
+        });
+        match written {
+            Ok(has_more_sections) => has_more_sections,
+            Err(e) => {
+                ic_cdk::println!("Failed exporting user data. Error: {:?}", e);
+                MY_DATA_EXPORT_STORE
+                    .with_borrow_mut(|store| generation.fail(store, &e, get_current_system_time()));
+                false
+            }
+        }
+    });
+
+    if has_more_sections {
+        schedule_next_export_section();
+    }
+}
+
+fn follow_entries_after(
+    follow_list: &FollowList,
+    after: Option<FollowEntryId>,
+) -> Vec<(FollowEntryId, FollowEntryDetail)> {
+    let lower_bound = after.map_or(Unbounded, Excluded);
+    follow_list
+        .sorted_index
+        .range((lower_bound, Unbounded))
+        .take(SNAPSHOT_GENERATION_BATCH_SIZE)
+        .map(|(id, entry)| (*id, entry.clone()))
+        .collect()
+}
+
+impl MyDataExportGeneration {
+    /// Returns false once the export is completed
+    fn write_next_section(
+        &mut self,
+        store: &mut SnapshotStore<Memory>,
+        canister_data: &CanisterData,
+        token_bet_game: &TokenBetGame,
+        now: SystemTime,
+    ) -> Result<bool, SnapshotError> {
+        let (section, items) = match self.next_section {
+            NextSection::Account => {
+                self.next_section = NextSection::PostsAfter(None);
+                let export =
+                    UserDataExport::new(canister_data, token_bet_game, self.progress.started_at);
+                (UserDataExportSection::Account(export), 1)
+            }
+            NextSection::PostsAfter(last_post_id) => {
+                let posts: Vec<PostForSnapshot> = canister_data
+                    .iter_posts_after(last_post_id)
+                    .take(SNAPSHOT_GENERATION_BATCH_SIZE)
+                    .map(|(_, post)| PostForSnapshot::from(post))
+                    .collect();
+                let Some(last_post) = posts.last() else {
+                    self.next_section = NextSection::GamesFrom(0);
+                    return Ok(true);
+                };
+                self.next_section = NextSection::PostsAfter(Some(last_post.id));
+                let items = posts.len();
+                (UserDataExportSection::Posts(posts), items)
+            }
+            NextSection::GamesFrom(start) => {
+                let games: Vec<GameHistoryEntry> = token_bet_game
+                    .games
+                    .iter()
+                    .skip(start)
+                    .take(SNAPSHOT_GENERATION_BATCH_SIZE)
+                    .cloned()
+                    .collect();
+                if games.is_empty() {
+                    self.next_section = NextSection::FollowersAfter(None);
+                    return Ok(true);
+                }
+                self.next_section = NextSection::GamesFrom(start + games.len());
+                let items = games.len();
+                (UserDataExportSection::PumpNDumpGames(games), items)
+            }
+            NextSection::FollowersAfter(last_entry_id) => {
+                let entries =
+                    follow_entries_after(&canister_data.follow_data.follower, last_entry_id);
+                let Some((next_last_entry_id, _)) = entries.last() else {
+                    self.next_section = NextSection::FollowingAfter(None);
+                    return Ok(true);
+                };
+                self.next_section = NextSection::FollowersAfter(Some(*next_last_entry_id));
+                let items = entries.len();
+                let followers = entries.into_iter().map(|(_, entry)| entry).collect();
+                (UserDataExportSection::Followers(followers), items)
+            }
+            NextSection::FollowingAfter(last_entry_id) => {
+                let entries =
+                    follow_entries_after(&canister_data.follow_data.following, last_entry_id);
+                let Some((next_last_entry_id, _)) = entries.last() else {
+                    self.finish(store, now)?;
+                    return Ok(false);
+                };
+                self.next_section = NextSection::FollowingAfter(Some(*next_last_entry_id));
+                let items = entries.len();
+                let following = entries.into_iter().map(|(_, entry)| entry).collect();
+                (UserDataExportSection::Following(following), items)
+            }
+        };
+
+        self.write_section(store, &section, items as u64)?;
+
+        Ok(true)
+    }
+
+    fn write_section(
+        &mut self,
+        store: &mut SnapshotStore<Memory>,
+        section: &UserDataExportSection,
+        items: u64,
+    ) -> Result<(), SnapshotError> {
+        let mut line =
+            serde_json::to_vec(section).map_err(|e| SnapshotError::Serialize(e.to_string()))?;
+        line.push(b'\n');
+
+        store.write(
+            self.progress.snapshot_id,
+            self.progress.bytes_written,
+            &line,
+        )?;
+        self.hasher.update(&line);
+        self.progress.bytes_written += line.len() as u64;
+        self.progress.items_written += items;
+
+        Ok(())
+    }
+
+    fn finish(
+        &mut self,
+        store: &mut SnapshotStore<Memory>,
+        finished_at: SystemTime,
+    ) -> Result<(), SnapshotError> {
+        store.complete_hashed(
+            self.progress.snapshot_id,
+            self.hasher.clone().finalize().to_vec(),
+            finished_at,
+        )?;
+        self.progress.state = SnapshotGenerationState::Completed;
+        self.progress.finished_at = Some(finished_at);
+
+        Ok(())
+    }
+
+    /// Drops what was written of the export, the previous one is kept
+    fn fail(
+        &mut self,
+        store: &mut SnapshotStore<Memory>,
+        error: &SnapshotError,
+        finished_at: SystemTime,
+    ) {
+        store.delete(self.progress.snapshot_id);
+        self.progress.state = SnapshotGenerationState::Failed(format!("{:?}", error));
+        self.progress.finished_at = Some(finished_at);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use shared_utils::canister_specific::individual_user_template::types::post::PostDetailsFromFrontend;
+
+    use super::*;
+
+    #[test]
+    fn test_export_includes_posts_and_serializes_to_json() {
+        let mut canister_data = CanisterData::default();
+        canister_data.profile.principal_id = Some(Principal::from_slice(&[1]));
+        let post_id = canister_data.add_post_to_memory(
+            &PostDetailsFromFrontend {
+                description: "a post".into(),
+                hashtags: vec![],
+                video_uid: "video".into(),
+                creator_consent_for_inclusion_in_hot_or_not: false,
+                is_nsfw: false,
+            },
+            &SystemTime::UNIX_EPOCH,
+        );
+        let token_root = Principal::from_slice(&[2]);
+        canister_data.token_holdings.insert(
+            token_root,
+            TokenHolding {
+                root: token_root,
+                ledger: token_root,
+                symbol: "TKN".into(),
+                decimals: 8,
+                balance: 100u64.into(),
+                refreshed_at: SystemTime::UNIX_EPOCH,
+            },
+        );
+        canister_data.follow_data.follower.add(FollowEntryDetail {
+            principal_id: Principal::from_slice(&[3]),
+            canister_id: Principal::from_slice(&[4]),
+        });
+
+        let mut store = _default_my_data_export_store();
+        let snapshot_id = store.begin(SnapshotSource::Generated, SystemTime::UNIX_EPOCH);
+        let mut generation = MyDataExportGeneration {
+            hasher: Sha256::new(),
+            next_section: NextSection::Account,
+            progress: SnapshotProgress {
+                snapshot_id,
+                state: SnapshotGenerationState::InProgress,
+                started_at: SystemTime::UNIX_EPOCH,
+                finished_at: None,
+                items_written: 0,
+                total_items: 3,
+                bytes_written: 0,
+            },
+        };
+        while generation
+            .write_next_section(
+                &mut store,
+                &canister_data,
+                &TokenBetGame::default(),
+                SystemTime::UNIX_EPOCH,
+            )
+            .unwrap()
+        {}
+        assert_eq!(
+            generation.progress.state,
+            SnapshotGenerationState::Completed
+        );
+        assert_eq!(generation.progress.items_written, 3);
+
+        let export = store.read_latest().unwrap();
+        let sections: Vec<serde_json::Value> = export
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .map(|line| serde_json::from_slice(line).unwrap())
+            .collect();
+        assert_eq!(sections.len(), 3);
+
+        let account = &sections[0]["Account"];
+        assert_eq!(account["schema_version"], SNAPSHOT_SCHEMA_VERSION);
+        assert_eq!(account["held_creator_tokens"].as_object().unwrap().len(), 1);
+        assert!(account["cents"].is_object());
+        assert_eq!(sections[1]["Posts"][0]["id"], post_id);
+        assert_eq!(sections[1]["Posts"][0]["description"], "a post");
+        assert_eq!(sections[2]["Followers"].as_array().unwrap().len(), 1);
+    }
+}
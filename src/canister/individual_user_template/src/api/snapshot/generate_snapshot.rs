@@ -0,0 +1,256 @@
+use std::{cell::RefCell, ops::Bound, time::Duration};
+
+use ic_cdk_macros::{query, update};
+use ic_cdk_timers::TimerId;
+use shared_utils::{
+    canister_specific::individual_user_template::types::token::TokenTransactions,
+    common::{
+        types::snapshot::{SnapshotCanisterType, SnapshotError, SnapshotProgress},
+        utils::{
+            chunked_snapshot::ChunkedSnapshotWriter,
+            permissions::is_reclaim_canister_id_or_controller,
+            system_time::get_current_system_time,
+        },
+    },
+    constant::SNAPSHOT_GENERATION_BATCH_SIZE,
+};
+
+use crate::{
+    util::cycles::notify_to_recharge_canister, CANISTER_DATA, PUMP_N_DUMP, SNAPSHOT_STORE,
+};
+
+use super::{
+    load_snapshot::is_snapshot_load_in_progress, CanisterBackupSnapshot,
+    CanisterBackupSnapshotSection, CanisterDataForSnapshot, PostForSnapshot,
+    TokenBetGameForSnapshot, SNAPSHOT_SCHEMA_VERSION,
+};
+
+enum NextSection {
+    Base,
+    PostsAfter(Option<u64>),
+    GamesFrom(usize),
+    CentsTransactionsAfter(Option<u64>),
+}
+
+struct SnapshotGeneration {
+    writer: ChunkedSnapshotWriter,
+    next_section: NextSection,
+    timer: Option<TimerId>,
+}
+
+thread_local! {
+    // Generation does not survive upgrades, it has to be started again after one
+    static SNAPSHOT_GENERATION: RefCell<Option<SnapshotGeneration>> = const { RefCell::new(None) };
+}
+
+/// Writes a new snapshot into the snapshot store across several messages, so it does not
+/// run into the instruction limit. Poll `get_snapshot_progress` until it is completed and
+/// then fetch it with `download_snapshot`. Posts, games and cents transactions added
+/// while it is generated are included if they come after the ones already written.
+#[update(guard = "is_reclaim_canister_id_or_controller")]
+fn start_snapshot() -> Result<SnapshotProgress, SnapshotError> {
+    notify_to_recharge_canister();
+
+    if is_snapshot_generation_in_progress() {
+        return Err(SnapshotError::GenerationInProgress);
+    }
+    if is_snapshot_load_in_progress() {
+        return Err(SnapshotError::LoadInProgress);
+    }
+
+    let total_items = CANISTER_DATA.with_borrow(|canister_data| canister_data.posts_count())
+        + PUMP_N_DUMP.with_borrow(|pump_n_dump| {
+            pump_n_dump.games.len() + pump_n_dump.cents.get_transaction_history().len()
+        })
+        + 1;
+    let writer = SNAPSHOT_STORE.with_borrow_mut(|store| {
+        ChunkedSnapshotWriter::new(
+            store,
+            SnapshotCanisterType::IndividualUser,
+            SNAPSHOT_SCHEMA_VERSION,
+            get_current_system_time(),
+            total_items as u64,
+        )
+    });
+    let progress = writer.progress();
+
+    SNAPSHOT_GENERATION.with_borrow_mut(|generation| {
+        *generation = Some(SnapshotGeneration {
+            writer,
+            next_section: NextSection::Base,
+            timer: None,
+        });
+    });
+    schedule_next_snapshot_section();
+
+    Ok(progress)
+}
+
+#[query(guard = "is_reclaim_canister_id_or_controller")]
+fn get_snapshot_progress() -> Option<SnapshotProgress> {
+    SNAPSHOT_GENERATION.with_borrow(|generation| {
+        generation
+            .as_ref()
+            .map(|generation| generation.writer.progress())
+    })
+}
+
+/// Stops a generation that is still running, the snapshot it was writing is left
+/// incomplete and dropped when the next one starts
+pub(crate) fn stop_snapshot_generation() {
+    if let Some(generation) = SNAPSHOT_GENERATION.take() {
+        if let Some(timer) = generation.timer {
+            ic_cdk_timers::clear_timer(timer);
+        }
+    }
+}
+
+pub(crate) fn is_snapshot_generation_in_progress() -> bool {
+    SNAPSHOT_GENERATION.with_borrow(|generation| {
+        generation
+            .as_ref()
+            .is_some_and(|generation| generation.writer.is_in_progress())
+    })
+}
+
+fn schedule_next_snapshot_section() {
+    let timer = ic_cdk_timers::set_timer(Duration::ZERO, write_next_snapshot_section);
+    SNAPSHOT_GENERATION.with_borrow_mut(|generation| {
+        if let Some(generation) = generation {
+            generation.timer = Some(timer);
+        }
+    });
+}
+
+fn write_next_snapshot_section() {
+    let has_more_sections = SNAPSHOT_GENERATION.with_borrow_mut(|generation| {
+        let Some(generation) = generation else {
+            return false;
+        };
+        generation.timer = None;
+
+        match generation.write_next_section() {
+            Ok(has_more_sections) => has_more_sections,
+            Err(e) => {
+                ic_cdk::println!("Failed generating snapshot. Error: {:?}", e);
+                SNAPSHOT_STORE.with_borrow_mut(|store| {
+                    generation.writer.fail(store, &e, get_current_system_time())
+                });
+                false
+            }
+        }
+    });
+
+    if has_more_sections {
+        schedule_next_snapshot_section();
+    }
+}
+
+impl SnapshotGeneration {
+    /// Returns false once the snapshot is completed
+    fn write_next_section(&mut self) -> Result<bool, SnapshotError> {
+        match self.next_section {
+            NextSection::Base => {
+                let canister_data_for_snapshot =
+                    CANISTER_DATA.with_borrow(CanisterDataForSnapshot::without_posts);
+                let token_bet_game_for_snapshot =
+                    PUMP_N_DUMP.with_borrow(TokenBetGameForSnapshot::without_history);
+
+                SNAPSHOT_STORE.with_borrow_mut(|store| {
+                    self.writer.write_section(
+                        store,
+                        &CanisterBackupSnapshotSection::Base(CanisterBackupSnapshot {
+                            canister_data_for_snapshot,
+                            token_bet_game_for_snapshot,
+                        }),
+                        1,
+                    )
+                })?;
+                self.next_section = NextSection::PostsAfter(None);
+            }
+            NextSection::PostsAfter(last_post_id) => {
+                let posts: Vec<(u64, PostForSnapshot)> =
+                    CANISTER_DATA.with_borrow(|canister_data| {
+                        canister_data
+                            .iter_posts_after(last_post_id)
+                            .take(SNAPSHOT_GENERATION_BATCH_SIZE)
+                            .map(|(post_id, post)| (*post_id, PostForSnapshot::from(post)))
+                            .collect()
+                    });
+
+                let Some((next_last_post_id, _)) = posts.last() else {
+                    self.next_section = NextSection::GamesFrom(0);
+                    return Ok(true);
+                };
+                self.next_section = NextSection::PostsAfter(Some(*next_last_post_id));
+
+                let posts_len = posts.len() as u64;
+                SNAPSHOT_STORE.with_borrow_mut(|store| {
+                    self.writer.write_section(
+                        store,
+                        &CanisterBackupSnapshotSection::Posts(posts),
+                        posts_len,
+                    )
+                })?;
+            }
+            NextSection::GamesFrom(index) => {
+                let games: Vec<_> = PUMP_N_DUMP.with_borrow(|pump_n_dump| {
+                    pump_n_dump
+                        .games
+                        .iter()
+                        .skip(index)
+                        .take(SNAPSHOT_GENERATION_BATCH_SIZE)
+                        .cloned()
+                        .collect()
+                });
+
+                if games.is_empty() {
+                    self.next_section = NextSection::CentsTransactionsAfter(None);
+                    return Ok(true);
+                }
+                self.next_section = NextSection::GamesFrom(index + games.len());
+
+                let games_len = games.len() as u64;
+                SNAPSHOT_STORE.with_borrow_mut(|store| {
+                    self.writer.write_section(
+                        store,
+                        &CanisterBackupSnapshotSection::Games(games),
+                        games_len,
+                    )
+                })?;
+            }
+            NextSection::CentsTransactionsAfter(last_transaction_id) => {
+                let transactions: Vec<_> = PUMP_N_DUMP.with_borrow(|pump_n_dump| {
+                    let start = last_transaction_id.map_or(Bound::Unbounded, Bound::Excluded);
+                    pump_n_dump
+                        .cents
+                        .get_transaction_history()
+                        .range((start, Bound::Unbounded))
+                        .take(SNAPSHOT_GENERATION_BATCH_SIZE)
+                        .map(|(transaction_id, transaction)| (*transaction_id, transaction.clone()))
+                        .collect()
+                });
+
+                let Some((next_last_transaction_id, _)) = transactions.last() else {
+                    SNAPSHOT_STORE.with_borrow_mut(|store| {
+                        self.writer.finish(store, get_current_system_time())
+                    })?;
+                    return Ok(false);
+                };
+                self.next_section =
+                    NextSection::CentsTransactionsAfter(Some(*next_last_transaction_id));
+
+                let transactions_len = transactions.len() as u64;
+                SNAPSHOT_STORE.with_borrow_mut(|store| {
+                    self.writer.write_section(
+                        store,
+                        &CanisterBackupSnapshotSection::CentsTransactions(transactions),
+                        transactions_len,
+                    )
+                })?;
+            }
+        }
+
+        Ok(true)
+    }
+}
@@ -1,8 +1,23 @@
-use crate::{util::cycles::notify_to_recharge_canister, CANISTER_DATA, PUMP_N_DUMP, SNAPSHOT_DATA};
+use crate::{
+    util::cycles::notify_to_recharge_canister, CANISTER_DATA, PUMP_N_DUMP, SNAPSHOT_STORE,
+};
 use ic_cdk_macros::{query, update};
-use shared_utils::common::utils::permissions::is_reclaim_canister_id;
-
-use super::{CanisterBackupSnapshot, CanisterDataForSnapshot, TokenBetGameForSnapshot};
+use shared_utils::common::{
+    types::snapshot::{SnapshotError, SnapshotSource},
+    utils::{
+        permissions::{is_reclaim_canister_id, is_reclaim_canister_id_or_controller},
+        system_time::get_current_system_time,
+    },
+};
+
+use super::{
+    decode_json_snapshot,
+    generate_snapshot::stop_snapshot_generation,
+    load_snapshot::{is_snapshot_load_in_progress, stop_snapshot_load},
+    stored_snapshots::{get_latest_snapshot_id, read_latest_snapshot, save_snapshot},
+    transfer_snapshot::{is_snapshot_upload_in_progress, receive_snapshot, reset_snapshot_upload},
+    CanisterBackupSnapshot, CanisterDataForSnapshot, TokenBetGameForSnapshot,
+};
 
 #[deprecated(note = "use save_snapshot_json_v2")]
 #[update(guard = "is_reclaim_canister_id")]
@@ -20,9 +35,8 @@ fn save_snapshot_json() -> u32 {
 
     let len = state_bytes.len() as u32;
 
-    SNAPSHOT_DATA.with(|snapshot_data_ref_cell| {
-        *snapshot_data_ref_cell.borrow_mut() = state_bytes;
-    });
+    save_snapshot(SnapshotSource::Saved, &state_bytes)
+        .unwrap_or_else(|e| ic_cdk::trap(&format!("{:?}", e)));
 
     len
 }
@@ -31,6 +45,36 @@ fn save_snapshot_json() -> u32 {
 fn save_snapshot_json_v2() -> u32 {
     notify_to_recharge_canister();
 
+    let canister_backup_snapshot = get_canister_backup_snapshot();
+
+    let serde_str = serde_json::to_string(&canister_backup_snapshot).unwrap();
+    let state_bytes = serde_str.as_bytes().to_vec();
+
+    let len = state_bytes.len() as u32;
+
+    save_snapshot(SnapshotSource::Saved, &state_bytes)
+        .unwrap_or_else(|e| ic_cdk::trap(&format!("{:?}", e)));
+
+    len
+}
+
+/// Same data as `save_snapshot_json_v2`, written in the binary format so it can be
+/// verified when it is loaded
+#[update(guard = "is_reclaim_canister_id")]
+fn save_snapshot_cbor() -> Result<u32, SnapshotError> {
+    notify_to_recharge_canister();
+
+    let canister_backup_snapshot = get_canister_backup_snapshot();
+    let state_bytes = canister_backup_snapshot.to_bytes(get_current_system_time())?;
+
+    let len = state_bytes.len() as u32;
+
+    save_snapshot(SnapshotSource::Saved, &state_bytes)?;
+
+    Ok(len)
+}
+
+fn get_canister_backup_snapshot() -> CanisterBackupSnapshot {
     let canister_data_for_snapshot = CANISTER_DATA.with(|canister_data_ref_cell| {
         let canister_data = &*canister_data_ref_cell.borrow();
         CanisterDataForSnapshot::from(canister_data)
@@ -41,87 +85,96 @@ fn save_snapshot_json_v2() -> u32 {
         TokenBetGameForSnapshot::from(pump_n_dump)
     });
 
-    let canister_backup_snapshot = CanisterBackupSnapshot {
+    CanisterBackupSnapshot {
         canister_data_for_snapshot,
         token_bet_game_for_snapshot,
-    };
-
-    let serde_str = serde_json::to_string(&canister_backup_snapshot).unwrap();
-    let state_bytes = serde_str.as_bytes().to_vec();
-
-    let len = state_bytes.len() as u32;
-
-    SNAPSHOT_DATA.with(|snapshot_data_ref_cell| {
-        *snapshot_data_ref_cell.borrow_mut() = state_bytes;
-    });
-
-    len
+    }
 }
 
+/// Reads from the latest completed snapshot, the range is clamped to its end
 #[query(guard = "is_reclaim_canister_id")]
-fn download_snapshot(offset: u64, length: u64) -> Vec<u8> {
-    let state_bytes = SNAPSHOT_DATA.with(|snapshot_data_ref_cell| {
-        let snapshot = snapshot_data_ref_cell.borrow();
+fn download_snapshot(offset: u64, length: u64) -> Result<Vec<u8>, SnapshotError> {
+    let id = get_latest_snapshot_id()?;
 
-        snapshot[offset as usize..(offset + length) as usize].to_vec()
-    });
-
-    state_bytes
+    SNAPSHOT_STORE.with_borrow(|store| store.read(id, offset, length))
 }
 
+/// Appends to the snapshot, the offset has to be where the bytes received so far end.
+/// Offset 0 starts a new snapshot, the others continue the one being received. It only
+/// replaces the latest snapshot once `finalize_received_snapshot` checked it.
+#[deprecated(note = "use start_snapshot_upload")]
 #[update(guard = "is_reclaim_canister_id")]
 fn receive_and_save_snaphot(offset: u64, state_bytes: Vec<u8>) {
     notify_to_recharge_canister();
-    SNAPSHOT_DATA.with(|snapshot_data_ref_cell| {
-        let mut snapshot = snapshot_data_ref_cell.borrow_mut();
-        // grow snapshot if needed
-        if snapshot.len() < (offset + state_bytes.len() as u64) as usize {
-            snapshot.resize((offset + state_bytes.len() as u64) as usize, 0);
-        }
-        snapshot.splice(
-            offset as usize..(offset + state_bytes.len() as u64) as usize,
-            state_bytes,
-        );
-    });
+    if let Err(e) = receive_snapshot(offset, &state_bytes) {
+        ic_cdk::trap(&format!("{:?}", e));
+    }
 }
 
 #[deprecated(note = "use load_snapshot_v2")]
 #[update(guard = "is_reclaim_canister_id")]
-fn load_snapshot() {
-    let state_bytes =
-        SNAPSHOT_DATA.with(|snapshot_data_ref_cell| snapshot_data_ref_cell.borrow().clone());
+fn load_snapshot() -> Result<(), SnapshotError> {
+    if is_snapshot_load_in_progress() {
+        return Err(SnapshotError::LoadInProgress);
+    }
+    if is_snapshot_upload_in_progress() {
+        return Err(SnapshotError::UploadInProgress);
+    }
 
     let canister_data_snapshot: CanisterDataForSnapshot =
-        serde_json::from_str(std::str::from_utf8(&state_bytes).unwrap()).unwrap();
+        decode_json_snapshot(&read_latest_snapshot()?)?;
 
     CANISTER_DATA.with(|canister_data_ref_cell| {
         *canister_data_ref_cell.borrow_mut() = canister_data_snapshot.into();
     });
+
+    Ok(())
 }
 
+#[deprecated(note = "use load_snapshot_v3")]
 #[update(guard = "is_reclaim_canister_id")]
-fn load_snapshot_v2() {
-    let state_bytes =
-        SNAPSHOT_DATA.with(|snapshot_data_ref_cell| snapshot_data_ref_cell.borrow().clone());
+fn load_snapshot_v2() -> Result<(), SnapshotError> {
+    load_canister_backup_snapshot()
+}
 
-    let canister_backup_snapshot: CanisterBackupSnapshot =
-        serde_json::from_str(std::str::from_utf8(&state_bytes).unwrap()).unwrap();
+#[update(guard = "is_reclaim_canister_id_or_controller")]
+fn load_snapshot_v3() -> Result<(), SnapshotError> {
+    load_canister_backup_snapshot()
+}
 
-    CANISTER_DATA.with(|canister_data_ref_cell| {
-        *canister_data_ref_cell.borrow_mut() =
-            canister_backup_snapshot.canister_data_for_snapshot.into();
+/// Loads binary and JSON snapshots, the current state is only replaced if the snapshot
+/// could be read completely. The version details are kept, they describe the running
+/// wasm and not the one the snapshot was taken with.
+pub(super) fn load_canister_backup_snapshot() -> Result<(), SnapshotError> {
+    if is_snapshot_load_in_progress() {
+        return Err(SnapshotError::LoadInProgress);
+    }
+    if is_snapshot_upload_in_progress() {
+        return Err(SnapshotError::UploadInProgress);
+    }
+
+    let canister_backup_snapshot = CanisterBackupSnapshot::from_bytes(&read_latest_snapshot()?)?;
+
+    CANISTER_DATA.with_borrow_mut(|canister_data| {
+        let version_details = canister_data.version_details.clone();
+        *canister_data = canister_backup_snapshot.canister_data_for_snapshot.into();
+        canister_data.version_details = version_details;
     });
 
     PUMP_N_DUMP.with(|pump_n_dump_ref_cell| {
         *pump_n_dump_ref_cell.borrow_mut() =
             canister_backup_snapshot.token_bet_game_for_snapshot.into();
     });
+
+    Ok(())
 }
 
+/// Deletes every stored snapshot and stops the ones still being written or loaded
 #[update(guard = "is_reclaim_canister_id")]
 fn clear_snapshot() {
     notify_to_recharge_canister();
-    SNAPSHOT_DATA.with(|snapshot_data_ref_cell| {
-        *snapshot_data_ref_cell.borrow_mut() = vec![];
-    });
+    stop_snapshot_generation();
+    stop_snapshot_load();
+    reset_snapshot_upload();
+    SNAPSHOT_STORE.with_borrow_mut(|store| store.clear());
 }
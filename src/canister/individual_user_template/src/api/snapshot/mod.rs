@@ -1,30 +1,37 @@
 use std::{
-    collections::{BTreeMap, HashMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     time::SystemTime,
 };
 
 use candid::{CandidType, Deserialize, Nat, Principal};
 use ic_cdk::api::management_canister::provisional::CanisterId;
-use serde::Serialize;
+use serde::{de::DeserializeOwned, Serialize};
 use serde_json_any_key::*;
 use shared_utils::{
     canister_specific::individual_user_template::types::{
-        cdao::DeployedCdaoCanisters,
+        cdao::{CreatorTokenLimit, DeployedCdaoCanisters, SwapParticipations},
         cents::CentsToken,
         follow::{FollowEntryDetail, FollowEntryId},
         hot_or_not::{
             AggregateStats, BetDetails, GlobalBetId, GlobalRoomId, PlacedBetDetail, RoomDetailsV1,
             SlotDetailsV1, SlotId, StablePrincipal,
         },
-        migration::MigrationInfo,
+        migration::{MigrationInfo, PendingMigration},
         post::{FeedScore, Post, PostViewStatistics},
         profile::UserProfile,
-        pump_n_dump::ParticipatedGameInfo,
+        pump_n_dump::{deserialize_game_history, GameHistoryEntry},
+        referral::ReferralReward,
         session::SessionType,
+        token::{CreatorTokenTransfers, TokenBalance, TokenHolding},
     },
     common::types::{
         app_primitive_type::PostId,
         known_principal::KnownPrincipalMap,
+        snapshot::{
+            decode_chunked_snapshot, decode_snapshot, encode_snapshot, is_binary_snapshot,
+            read_snapshot_header, SnapshotCanisterType, SnapshotError,
+            SNAPSHOT_FORMAT_VERSION_CHUNKED,
+        },
         top_posts::{
             post_score_index_item::{PostScoreIndexItem, PostStatus},
             PublisherCanisterId, Score,
@@ -36,12 +43,22 @@ use shared_utils::{
 
 use crate::data_model::pump_n_dump::TokenBetGame;
 use crate::data_model::{
+    cdao_deployment::CdaoDeployments,
     CanisterData, _default_token_list,
     pump_n_dump::{NatStore, _default_lp},
 };
 
+pub mod export_my_data;
+pub mod generate_snapshot;
 pub mod get_snapshot;
+pub mod load_snapshot;
+pub mod restore_snapshot;
 pub mod serde_json_snapshot_test;
+pub mod stored_snapshots;
+pub mod transfer_snapshot;
+
+/// Bumped whenever the snapshot types change in a way older canisters cannot read
+pub const SNAPSHOT_SCHEMA_VERSION: u32 = 2;
 
 #[derive(Deserialize, Serialize)]
 pub struct CanisterBackupSnapshot {
@@ -49,6 +66,89 @@ pub struct CanisterBackupSnapshot {
     pub token_bet_game_for_snapshot: TokenBetGameForSnapshot,
 }
 
+/// Reads the snapshots written as JSON by the `save_snapshot_json*` endpoints
+pub(crate) fn decode_json_snapshot<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, SnapshotError> {
+    if bytes.is_empty() {
+        return Err(SnapshotError::Empty);
+    }
+
+    serde_json::from_slice(bytes).map_err(|e| SnapshotError::Deserialize(e.to_string()))
+}
+
+impl CanisterBackupSnapshot {
+    pub fn to_bytes(&self, created_at: SystemTime) -> Result<Vec<u8>, SnapshotError> {
+        encode_snapshot(
+            SnapshotCanisterType::IndividualUser,
+            SNAPSHOT_SCHEMA_VERSION,
+            created_at,
+            self,
+        )
+    }
+
+    /// Reads binary snapshots as well as the JSON ones written by `save_snapshot_json_v2`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SnapshotError> {
+        if bytes.is_empty() {
+            return Err(SnapshotError::Empty);
+        }
+
+        if is_binary_snapshot(bytes) {
+            let (header, _) = read_snapshot_header(bytes)?;
+            if header.format_version == SNAPSHOT_FORMAT_VERSION_CHUNKED {
+                let (_, sections) =
+                    decode_chunked_snapshot(bytes, SnapshotCanisterType::IndividualUser)?;
+                return Self::from_sections(sections);
+            }
+
+            let (_, snapshot) = decode_snapshot(bytes, SnapshotCanisterType::IndividualUser)?;
+            return Ok(snapshot);
+        }
+
+        decode_json_snapshot(bytes)
+    }
+
+    fn from_sections(sections: Vec<CanisterBackupSnapshotSection>) -> Result<Self, SnapshotError> {
+        let mut sections = sections.into_iter();
+        let Some(CanisterBackupSnapshotSection::Base(mut snapshot)) = sections.next() else {
+            return Err(SnapshotError::Deserialize(
+                "Snapshot does not start with the canister data".into(),
+            ));
+        };
+
+        for section in sections {
+            match section {
+                CanisterBackupSnapshotSection::Posts(posts) => snapshot
+                    .canister_data_for_snapshot
+                    .all_created_posts
+                    .extend(posts),
+                CanisterBackupSnapshotSection::Games(games) => {
+                    snapshot.token_bet_game_for_snapshot.games.extend(games)
+                }
+                CanisterBackupSnapshotSection::CentsTransactions(transactions) => snapshot
+                    .token_bet_game_for_snapshot
+                    .cents
+                    .extend_transaction_history(transactions),
+                CanisterBackupSnapshotSection::Base(_) => {
+                    return Err(SnapshotError::Deserialize(
+                        "Snapshot contains the canister data twice".into(),
+                    ))
+                }
+            }
+        }
+
+        Ok(snapshot)
+    }
+}
+
+/// Sections of a snapshot generated across several messages. The posts, the game
+/// history and the cents transactions are left out of the base and follow it in batches.
+#[derive(Deserialize, Serialize)]
+pub enum CanisterBackupSnapshotSection {
+    Base(CanisterBackupSnapshot),
+    Posts(Vec<(u64, PostForSnapshot)>),
+    Games(Vec<GameHistoryEntry>),
+    CentsTransactions(Vec<(u64, TokenEvent)>),
+}
+
 #[derive(Deserialize, Serialize, Clone)]
 pub struct CanisterDataForSnapshot {
     // Key is Post ID
@@ -63,18 +163,39 @@ pub struct CanisterDataForSnapshot {
     pub cdao_canisters: Vec<DeployedCdaoCanisters>,
     #[serde(with = "any_key_map")]
     pub token_roots: BTreeMap<Principal, ()>,
+    #[serde(default)]
+    pub signed_up_at: Option<SystemTime>,
+    #[serde(default)]
+    pub cdao_deployments: CdaoDeployments,
+    #[serde(default)]
+    pub creator_token_limit: CreatorTokenLimit,
+    #[serde(default, with = "any_key_map")]
+    pub token_holdings: BTreeMap<Principal, TokenHolding>,
+    #[serde(default)]
+    pub creator_token_transfers: CreatorTokenTransfers,
+    #[serde(default)]
+    pub swap_participations: SwapParticipations,
+    #[serde(default)]
+    pub pending_migration: Option<PendingMigration>,
 }
 
 #[derive(Deserialize, Serialize, Clone)]
 pub struct TokenBetGameForSnapshot {
     pub referral_reward: Nat,
     pub onboarding_reward: Nat,
-    pub games: Vec<ParticipatedGameInfo>,
+    #[serde(deserialize_with = "deserialize_game_history")]
+    pub games: Vec<GameHistoryEntry>,
     pub total_dumps: Nat,
     pub total_pumps: Nat,
     #[serde(with = "any_key_map")]
     pub liquidity_pools: BTreeMap<Principal, NatStore>,
     pub cents: CentsToken,
+    #[serde(default, with = "any_key_map")]
+    pub referral_rewards: BTreeMap<Principal, ReferralReward>,
+    #[serde(default)]
+    pub credited_airdrop_campaigns: BTreeSet<u64>,
+    #[serde(default)]
+    pub hot_or_not_utility_token: TokenBalanceForSnapshot,
 }
 
 #[derive(CandidType, Clone, Deserialize, Debug, Serialize)]
@@ -128,36 +249,63 @@ pub struct PostScoreIndexForSnapshot {
     pub item_presence_index: HashMap<(PublisherCanisterId, PostId), Score>,
 }
 
+impl From<&Post> for PostForSnapshot {
+    fn from(post: &Post) -> Self {
+        Self {
+            id: post.id,
+            description: post.description.clone(),
+            hashtags: post.hashtags.clone(),
+            video_uid: post.video_uid.clone(),
+            status: post.status,
+            created_at: post.created_at,
+            likes: post.likes.clone(),
+            share_count: post.share_count,
+            view_stats: post.view_stats.clone(),
+            is_nsfw: post.is_nsfw,
+        }
+    }
+}
+
+impl From<&PostForSnapshot> for Post {
+    fn from(post: &PostForSnapshot) -> Self {
+        Self {
+            id: post.id,
+            description: post.description.clone(),
+            hashtags: post.hashtags.clone(),
+            video_uid: post.video_uid.clone(),
+            status: post.status,
+            created_at: post.created_at,
+            likes: post.likes.clone(),
+            share_count: post.share_count,
+            view_stats: post.view_stats.clone(),
+            is_nsfw: post.is_nsfw,
+        }
+    }
+}
+
 impl From<&CanisterData> for CanisterDataForSnapshot {
     fn from(canister_data: &CanisterData) -> Self {
-        let mut all_created_posts: BTreeMap<u64, PostForSnapshot> = BTreeMap::new();
-        canister_data
+        let mut canister_data_for_snapshot = Self::without_posts(canister_data);
+        canister_data_for_snapshot.all_created_posts = canister_data
             .get_all_posts_cloned()
-            .into_iter()
-            .for_each(|(k, v)| {
-                let post_details = PostForSnapshot {
-                    id: v.id,
-                    description: v.description.clone(),
-                    hashtags: v.hashtags.clone(),
-                    video_uid: v.video_uid.clone(),
-                    status: v.status,
-                    created_at: v.created_at,
-                    likes: v.likes.clone(),
-                    share_count: v.share_count,
-                    view_stats: v.view_stats.clone(),
-                    is_nsfw: v.is_nsfw,
-                };
-
-                all_created_posts.insert(k, post_details);
-            });
+            .iter()
+            .map(|(post_id, post)| (*post_id, PostForSnapshot::from(post)))
+            .collect();
+
+        canister_data_for_snapshot
+    }
+}
 
+impl CanisterDataForSnapshot {
+    /// Everything but the posts, which can be too many to copy in a single message
+    pub(crate) fn without_posts(canister_data: &CanisterData) -> Self {
         let mut token_roots: BTreeMap<Principal, ()> = BTreeMap::new();
         canister_data.token_roots.iter().for_each(|(k, _)| {
             token_roots.insert(k, ());
         });
 
         Self {
-            all_created_posts,
+            all_created_posts: BTreeMap::new(),
             known_principal_ids: canister_data.known_principal_ids.clone(),
             profile: canister_data.profile.clone(),
             version_details: canister_data.version_details.clone(),
@@ -166,6 +314,13 @@ impl From<&CanisterData> for CanisterDataForSnapshot {
             migration_info: canister_data.migration_info,
             cdao_canisters: canister_data.cdao_canisters.clone(),
             token_roots,
+            signed_up_at: canister_data.signed_up_at,
+            cdao_deployments: canister_data.cdao_deployments.clone(),
+            creator_token_limit: canister_data.creator_token_limit,
+            token_holdings: canister_data.token_holdings.clone(),
+            creator_token_transfers: canister_data.creator_token_transfers.clone(),
+            swap_participations: canister_data.swap_participations.clone(),
+            pending_migration: canister_data.pending_migration,
         }
     }
 }
@@ -177,20 +332,7 @@ impl From<CanisterDataForSnapshot> for CanisterData {
             .all_created_posts
             .iter()
             .for_each(|(k, v)| {
-                let post_details = Post {
-                    id: v.id,
-                    description: v.description.clone(),
-                    hashtags: v.hashtags.clone(),
-                    video_uid: v.video_uid.clone(),
-                    status: v.status,
-                    created_at: v.created_at,
-                    likes: v.likes.clone(),
-                    share_count: v.share_count,
-                    view_stats: v.view_stats.clone(),
-                    is_nsfw: v.is_nsfw,
-                };
-
-                all_created_posts.insert(*k, post_details);
+                all_created_posts.insert(*k, Post::from(v));
             });
 
         let mut token_roots = _default_token_list();
@@ -209,8 +351,15 @@ impl From<CanisterDataForSnapshot> for CanisterData {
         canister_data.session_type = canister_data_for_snapshot.session_type;
         canister_data.last_access_time = canister_data_for_snapshot.last_access_time;
         canister_data.migration_info = canister_data_for_snapshot.migration_info;
+        canister_data.pending_migration = canister_data_for_snapshot.pending_migration;
         canister_data.cdao_canisters = canister_data_for_snapshot.cdao_canisters;
         canister_data.token_roots = token_roots;
+        canister_data.signed_up_at = canister_data_for_snapshot.signed_up_at;
+        canister_data.cdao_deployments = canister_data_for_snapshot.cdao_deployments;
+        canister_data.creator_token_limit = canister_data_for_snapshot.creator_token_limit;
+        canister_data.token_holdings = canister_data_for_snapshot.token_holdings;
+        canister_data.creator_token_transfers = canister_data_for_snapshot.creator_token_transfers;
+        canister_data.swap_participations = canister_data_for_snapshot.swap_participations;
 
         canister_data.set_all_created_posts(all_created_posts);
 
@@ -220,6 +369,18 @@ impl From<CanisterDataForSnapshot> for CanisterData {
 
 impl From<&TokenBetGame> for TokenBetGameForSnapshot {
     fn from(token_bet_game: &TokenBetGame) -> Self {
+        let mut token_bet_game_for_snapshot = Self::without_history(token_bet_game);
+        token_bet_game_for_snapshot.games = token_bet_game.games.clone();
+        token_bet_game_for_snapshot.cents = token_bet_game.cents.clone();
+
+        token_bet_game_for_snapshot
+    }
+}
+
+impl TokenBetGameForSnapshot {
+    /// Everything but the game history and the cents transactions, which can be too
+    /// many to copy in a single message
+    pub(crate) fn without_history(token_bet_game: &TokenBetGame) -> Self {
         let mut liquidity_pools: BTreeMap<Principal, NatStore> = BTreeMap::new();
         token_bet_game.liquidity_pools.iter().for_each(|(k, v)| {
             liquidity_pools.insert(k, v.clone());
@@ -227,12 +388,17 @@ impl From<&TokenBetGame> for TokenBetGameForSnapshot {
 
         Self {
             liquidity_pools,
-            cents: token_bet_game.cents.clone(),
+            cents: token_bet_game.cents.clone_without_transaction_history(),
             referral_reward: token_bet_game.referral_reward.clone(),
             onboarding_reward: token_bet_game.onboarding_reward.clone(),
-            games: token_bet_game.games.clone(),
+            games: vec![],
             total_dumps: token_bet_game.total_dumps.clone(),
             total_pumps: token_bet_game.total_pumps.clone(),
+            referral_rewards: token_bet_game.referral_rewards.clone(),
+            credited_airdrop_campaigns: token_bet_game.credited_airdrop_campaigns.clone(),
+            hot_or_not_utility_token: TokenBalanceForSnapshot::from(
+                &token_bet_game.hot_or_not_utility_token,
+            ),
         }
     }
 }
@@ -255,6 +421,37 @@ impl From<TokenBetGameForSnapshot> for TokenBetGame {
             games: token_bet_game_for_snapshot.games.clone(),
             total_dumps: token_bet_game_for_snapshot.total_dumps.clone(),
             total_pumps: token_bet_game_for_snapshot.total_pumps.clone(),
+            referral_rewards: token_bet_game_for_snapshot.referral_rewards.clone(),
+            credited_airdrop_campaigns: token_bet_game_for_snapshot
+                .credited_airdrop_campaigns
+                .clone(),
+            hot_or_not_utility_token: TokenBalance::from(
+                token_bet_game_for_snapshot.hot_or_not_utility_token,
+            ),
+            earnings_summary_pending: false,
+        }
+    }
+}
+
+impl From<&TokenBalance> for TokenBalanceForSnapshot {
+    fn from(token_balance: &TokenBalance) -> Self {
+        Self {
+            utility_token_balance: token_balance.utility_token_balance,
+            utility_token_transaction_history: token_balance
+                .utility_token_transaction_history
+                .clone(),
+            lifetime_earnings: token_balance.lifetime_earnings,
+        }
+    }
+}
+
+impl From<TokenBalanceForSnapshot> for TokenBalance {
+    fn from(token_balance_for_snapshot: TokenBalanceForSnapshot) -> Self {
+        Self {
+            utility_token_balance: token_balance_for_snapshot.utility_token_balance,
+            utility_token_transaction_history: token_balance_for_snapshot
+                .utility_token_transaction_history,
+            lifetime_earnings: token_balance_for_snapshot.lifetime_earnings,
         }
     }
 }
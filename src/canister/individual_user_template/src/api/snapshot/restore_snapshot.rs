@@ -0,0 +1,327 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::Duration,
+};
+
+use candid::CandidType;
+use ic_cdk_macros::update;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use shared_utils::{
+    canister_specific::individual_user_template::types::post::Post,
+    common::{types::snapshot::SnapshotError, utils::permissions::is_reclaim_canister_id},
+};
+
+use crate::{
+    api::cdao::token_holdings::schedule_token_holdings_refresh_after,
+    data_model::{pump_n_dump::TokenBetGame, CanisterData},
+    util::cycles::notify_to_recharge_canister,
+    CANISTER_DATA, PUMP_N_DUMP,
+};
+
+use super::{
+    load_snapshot::is_snapshot_load_in_progress, stored_snapshots::read_latest_snapshot,
+    transfer_snapshot::is_snapshot_upload_in_progress, CanisterBackupSnapshot,
+    CanisterDataForSnapshot, TokenBetGameForSnapshot,
+};
+
+/// Parts of the state that can be restored without touching the rest
+#[derive(
+    CandidType, Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord,
+)]
+pub enum SnapshotSection {
+    Profile,
+    Posts,
+    CdaoCanisters,
+    TokenRoots,
+    TokenBetGame,
+    KnownPrincipals,
+}
+
+/// Entries restoring the section adds, removes or changes, by key. The profile and the
+/// token bet game are compared field by field. Posts are never removed, the ones created
+/// after the snapshot are kept.
+#[derive(CandidType, Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct SnapshotSectionDiff {
+    pub section: SnapshotSection,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+/// Replaces only the selected sections with the ones in the latest snapshot, posts are
+/// merged by id instead. With `dry_run` nothing is replaced, the returned diff shows what
+/// restoring would change.
+#[update(guard = "is_reclaim_canister_id")]
+fn restore_snapshot_sections(
+    sections: Vec<SnapshotSection>,
+    dry_run: bool,
+) -> Result<Vec<SnapshotSectionDiff>, SnapshotError> {
+    notify_to_recharge_canister();
+    if is_snapshot_load_in_progress() {
+        return Err(SnapshotError::LoadInProgress);
+    }
+    if is_snapshot_upload_in_progress() {
+        return Err(SnapshotError::UploadInProgress);
+    }
+
+    let snapshot = CanisterBackupSnapshot::from_bytes(&read_latest_snapshot()?)?;
+    let sections: BTreeSet<SnapshotSection> = sections.into_iter().collect();
+
+    let current_canister_data = CANISTER_DATA.with(|canister_data_ref_cell| {
+        let canister_data = &*canister_data_ref_cell.borrow();
+        CanisterDataForSnapshot::from(canister_data)
+    });
+    let current_token_bet_game = PUMP_N_DUMP.with(|pump_n_dump_ref_cell| {
+        let pump_n_dump = &*pump_n_dump_ref_cell.borrow();
+        TokenBetGameForSnapshot::from(pump_n_dump)
+    });
+
+    let diffs = sections
+        .iter()
+        .map(|section| {
+            Ok(diff_section(
+                *section,
+                get_section_entries(*section, &current_canister_data, &current_token_bet_game)?,
+                get_section_entries(
+                    *section,
+                    &snapshot.canister_data_for_snapshot,
+                    &snapshot.token_bet_game_for_snapshot,
+                )?,
+            ))
+        })
+        .collect::<Result<Vec<_>, SnapshotError>>()?;
+
+    if dry_run {
+        return Ok(diffs);
+    }
+
+    CANISTER_DATA.with_borrow_mut(|canister_data| {
+        PUMP_N_DUMP.with_borrow_mut(|pump_n_dump| {
+            for section in sections.iter() {
+                restore_section(*section, &snapshot, canister_data, pump_n_dump);
+            }
+        })
+    });
+
+    // the cached balances of the restored tokens are fetched again
+    if sections.contains(&SnapshotSection::TokenRoots) {
+        schedule_token_holdings_refresh_after(Duration::ZERO);
+    }
+
+    Ok(diffs)
+}
+
+fn restore_section(
+    section: SnapshotSection,
+    snapshot: &CanisterBackupSnapshot,
+    canister_data: &mut CanisterData,
+    pump_n_dump: &mut TokenBetGame,
+) {
+    let canister_data_for_snapshot = &snapshot.canister_data_for_snapshot;
+    match section {
+        SnapshotSection::Profile => {
+            canister_data.profile = canister_data_for_snapshot.profile.clone();
+        }
+        SnapshotSection::Posts => {
+            let mut all_created_posts: BTreeMap<u64, Post> =
+                canister_data.get_all_posts_cloned().into_iter().collect();
+            all_created_posts.extend(
+                canister_data_for_snapshot
+                    .all_created_posts
+                    .iter()
+                    .map(|(post_id, post)| (*post_id, Post::from(post))),
+            );
+            canister_data.set_all_created_posts(all_created_posts);
+        }
+        SnapshotSection::CdaoCanisters => {
+            canister_data.cdao_canisters = canister_data_for_snapshot.cdao_canisters.clone();
+        }
+        SnapshotSection::TokenRoots => {
+            let current_token_roots: Vec<_> = canister_data
+                .token_roots
+                .iter()
+                .map(|(token_root, _)| token_root)
+                .collect();
+            for token_root in current_token_roots {
+                canister_data.token_roots.remove(&token_root);
+            }
+            for token_root in canister_data_for_snapshot.token_roots.keys() {
+                canister_data.token_roots.insert(*token_root, ());
+            }
+            canister_data.token_holdings.retain(|token_root, _| {
+                canister_data_for_snapshot
+                    .token_roots
+                    .contains_key(token_root)
+            });
+        }
+        SnapshotSection::TokenBetGame => {
+            *pump_n_dump = snapshot.token_bet_game_for_snapshot.clone().into();
+        }
+        SnapshotSection::KnownPrincipals => {
+            canister_data.known_principal_ids =
+                canister_data_for_snapshot.known_principal_ids.clone();
+        }
+    }
+}
+
+fn get_section_entries(
+    section: SnapshotSection,
+    canister_data: &CanisterDataForSnapshot,
+    token_bet_game: &TokenBetGameForSnapshot,
+) -> Result<BTreeMap<String, Value>, SnapshotError> {
+    match section {
+        SnapshotSection::Profile => get_fields(&canister_data.profile),
+        SnapshotSection::Posts => canister_data
+            .all_created_posts
+            .iter()
+            .map(|(post_id, post)| Ok((post_id.to_string(), to_canonical_value(post)?)))
+            .collect(),
+        SnapshotSection::CdaoCanisters => canister_data
+            .cdao_canisters
+            .iter()
+            .map(|cdao_canisters| {
+                Ok((
+                    cdao_canisters.root.to_text(),
+                    to_canonical_value(cdao_canisters)?,
+                ))
+            })
+            .collect(),
+        SnapshotSection::TokenRoots => Ok(canister_data
+            .token_roots
+            .keys()
+            .map(|token_root| (token_root.to_text(), Value::Null))
+            .collect()),
+        SnapshotSection::TokenBetGame => get_fields(token_bet_game),
+        SnapshotSection::KnownPrincipals => Ok(canister_data
+            .known_principal_ids
+            .iter()
+            .map(|(principal_type, principal)| {
+                (
+                    format!("{:?}", principal_type),
+                    Value::String(principal.to_text()),
+                )
+            })
+            .collect()),
+    }
+}
+
+fn get_fields<T: Serialize>(value: &T) -> Result<BTreeMap<String, Value>, SnapshotError> {
+    match to_canonical_value(value)? {
+        Value::Object(fields) => Ok(fields.into_iter().collect()),
+        value => Ok(BTreeMap::from([(String::new(), value)])),
+    }
+}
+
+// fields holding hash sets, the only lists serialized in no particular order
+const UNORDERED_LIST_FIELDS: [&str; 1] = ["likes"];
+
+/// Hash sets are serialized in no particular order, so their items are sorted. The order
+/// of every other list is compared.
+fn to_canonical_value<T: Serialize>(value: &T) -> Result<Value, SnapshotError> {
+    let mut value =
+        serde_json::to_value(value).map_err(|e| SnapshotError::Serialize(e.to_string()))?;
+    sort_unordered_lists(&mut value);
+
+    Ok(value)
+}
+
+fn sort_unordered_lists(value: &mut Value) {
+    match value {
+        Value::Array(items) => items.iter_mut().for_each(sort_unordered_lists),
+        Value::Object(fields) => {
+            for (name, field) in fields.iter_mut() {
+                sort_unordered_lists(field);
+                if let Value::Array(items) = field {
+                    if UNORDERED_LIST_FIELDS.contains(&name.as_str()) {
+                        items.sort_by_cached_key(|item| item.to_string());
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn diff_section(
+    section: SnapshotSection,
+    current: BTreeMap<String, Value>,
+    snapshot: BTreeMap<String, Value>,
+) -> SnapshotSectionDiff {
+    let mut diff = SnapshotSectionDiff {
+        section,
+        added: vec![],
+        removed: vec![],
+        changed: vec![],
+    };
+
+    for (key, current_value) in current.iter() {
+        match snapshot.get(key) {
+            // posts created after the snapshot are kept
+            None if section == SnapshotSection::Posts => {}
+            None => diff.removed.push(key.clone()),
+            Some(snapshot_value) if snapshot_value != current_value => {
+                diff.changed.push(key.clone())
+            }
+            Some(_) => {}
+        }
+    }
+    diff.added = snapshot
+        .into_keys()
+        .filter(|key| !current.contains_key(key))
+        .collect();
+
+    diff
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_diff_section_reports_entries_by_key() {
+        let current = BTreeMap::from([
+            ("1".to_string(), json!({ "likes": ["a", "b"] })),
+            ("2".to_string(), json!({ "likes": [] })),
+            ("3".to_string(), json!({ "likes": ["a"] })),
+        ]);
+        let snapshot = BTreeMap::from([
+            ("1".to_string(), json!({ "likes": ["a", "b"] })),
+            ("3".to_string(), json!({ "likes": ["c"] })),
+            ("4".to_string(), json!({ "likes": [] })),
+        ]);
+
+        let diff = diff_section(
+            SnapshotSection::CdaoCanisters,
+            current.clone(),
+            snapshot.clone(),
+        );
+        assert_eq!(diff.added, vec!["4".to_string()]);
+        assert_eq!(diff.removed, vec!["2".to_string()]);
+        assert_eq!(diff.changed, vec!["3".to_string()]);
+
+        let diff = diff_section(SnapshotSection::Posts, current, snapshot);
+        assert_eq!(diff.added, vec!["4".to_string()]);
+        assert!(diff.removed.is_empty());
+        assert_eq!(diff.changed, vec!["3".to_string()]);
+    }
+
+    #[test]
+    fn test_canonical_value_ignores_the_order_of_sets_only() {
+        let first = to_canonical_value(&json!({ "likes": ["b", "a"] })).unwrap();
+        let second = to_canonical_value(&json!({ "likes": ["a", "b"] })).unwrap();
+        assert_eq!(first, second);
+
+        let first = to_canonical_value(&json!({ "games": [{ "likes": ["b", "a"] }, 2] })).unwrap();
+        let second = to_canonical_value(&json!({ "games": [2, { "likes": ["a", "b"] }] })).unwrap();
+        assert_ne!(first, second);
+
+        let fields = get_fields(&json!({ "cents": 1, "games": [] })).unwrap();
+        assert_eq!(
+            fields.keys().collect::<Vec<_>>(),
+            vec![&"cents".to_string(), &"games".to_string()]
+        );
+    }
+}
@@ -8,9 +8,15 @@ mod test {
 
     use candid::{Nat, Principal};
     use ic_cdk::api::management_canister::main::CanisterId;
+    use ic_sns_init::pb::v1::SnsInitPayload;
+    use ic_stable_structures::VectorMemory;
+    use shared_utils::common::utils::chunked_snapshot::ChunkedSnapshotWriter;
     use shared_utils::{
         canister_specific::individual_user_template::types::{
-            cdao::{AirdropInfo, ClaimStatus, DeployedCdaoCanisters},
+            cdao::{
+                AirdropInfo, ClaimStatus, CreatorTokenLimit, DeployedCdaoCanisters,
+                SwapParticipations, SwapPaymentToken,
+            },
             cents::CentsToken,
             follow::FollowEntryDetail,
             hot_or_not::{
@@ -21,12 +27,15 @@ mod test {
             migration::MigrationInfo,
             post::PostViewStatistics,
             profile::{UserProfile, UserProfileGlobalStats},
-            pump_n_dump::{GameDirection, ParticipatedGameInfo},
+            pump_n_dump::{GameDirection, GameHistoryEntry, ParticipatedGameInfo},
+            referral::ReferralReward,
             session::SessionType,
+            token::{CreatorTokenTransfers, TokenHolding},
         },
         common::types::{
             app_primitive_type::PostId,
             known_principal::KnownPrincipalType,
+            snapshot::{SnapshotCanisterType, SnapshotError},
             top_posts::post_score_index_item::{PostScoreIndexItem, PostStatus},
             utility_token::token_event::{MintEvent, TokenEvent},
             version_details::VersionDetails,
@@ -35,8 +44,13 @@ mod test {
     use test_utils::setup::test_constants::get_mock_user_alice_canister_id;
 
     use crate::{
-        api::snapshot::{CanisterDataForSnapshot, PostForSnapshot, TokenBetGameForSnapshot},
+        api::snapshot::{
+            CanisterBackupSnapshot, CanisterBackupSnapshotSection, CanisterDataForSnapshot,
+            PostForSnapshot, TokenBalanceForSnapshot, TokenBetGameForSnapshot,
+            SNAPSHOT_SCHEMA_VERSION,
+        },
         data_model::{
+            cdao_deployment::CdaoDeployments,
             pump_n_dump::{NatStore, TokenBetGame},
             CanisterData,
         },
@@ -172,11 +186,50 @@ mod test {
             swap: temp_principal,
             index: temp_principal,
             airdrop_info,
+            sns_versions: Default::default(),
+            swap_settlement: None,
         }];
 
         let mut token_roots = BTreeMap::<Principal, ()>::new();
         token_roots.insert(temp_principal, ());
 
+        let mut creator_token_transfers = CreatorTokenTransfers::default();
+        let transfer_id = creator_token_transfers.record(
+            temp_principal,
+            temp_principal,
+            temp_principal,
+            Nat::from(100_u64),
+            None,
+            SystemTime::now(),
+        );
+        creator_token_transfers.mark_transferred(transfer_id, Nat::from(1_u64), SystemTime::now());
+
+        let mut swap_participations = SwapParticipations::default();
+        swap_participations.record(
+            temp_principal,
+            temp_principal,
+            SwapPaymentToken::Icp,
+            1,
+            1_000,
+            SystemTime::now(),
+        );
+
+        let mut token_holdings = BTreeMap::<Principal, TokenHolding>::new();
+        token_holdings.insert(
+            temp_principal,
+            TokenHolding {
+                root: temp_principal,
+                ledger: temp_principal,
+                symbol: "SIMG".to_string(),
+                decimals: 8,
+                balance: Nat::from(100_u64),
+                refreshed_at: SystemTime::now(),
+            },
+        );
+
+        let mut cdao_deployments = CdaoDeployments::default();
+        cdao_deployments.start(SnsInitPayload::default(), 100, SystemTime::now());
+
         let canister_data_snapshot = CanisterDataForSnapshot {
             all_created_posts: created_posts,
             known_principal_ids,
@@ -198,6 +251,16 @@ mod test {
             migration_info: MigrationInfo::NotMigrated,
             cdao_canisters,
             token_roots,
+            signed_up_at: Some(SystemTime::now()),
+            cdao_deployments,
+            creator_token_limit: CreatorTokenLimit {
+                network_limit: 5,
+                creator_override: Some(10),
+            },
+            token_holdings,
+            creator_token_transfers,
+            swap_participations,
+            pending_migration: None,
         };
 
         let serde_str = serde_json::to_string(&canister_data_snapshot);
@@ -270,13 +333,16 @@ mod test {
         let mut liquidity_pools: BTreeMap<Principal, NatStore> = BTreeMap::new();
         liquidity_pools.insert(temp_principal, NatStore::default());
 
-        let games = vec![ParticipatedGameInfo {
-            pumps: 10,
-            dumps: 25,
-            reward: 1000,
-            token_root: temp_principal,
-            game_direction: GameDirection::Pump,
-        }];
+        let games = vec![GameHistoryEntry::new(
+            ParticipatedGameInfo {
+                pumps: 10,
+                dumps: 25,
+                reward: 1000,
+                token_root: temp_principal,
+                game_direction: GameDirection::Pump,
+            },
+            SystemTime::now(),
+        )];
 
         let token_bet_game_snapshot = TokenBetGameForSnapshot {
             referral_reward: Nat::from(1000u32),
@@ -286,6 +352,17 @@ mod test {
             total_pumps: Nat::from(25u32),
             liquidity_pools,
             cents: CentsToken::default(),
+            referral_rewards: BTreeMap::from([(
+                temp_principal,
+                ReferralReward {
+                    referrer_user_principal_id: temp_principal,
+                    referee_user_principal_id: temp_principal,
+                    amount: Nat::from(1000u32),
+                    rewarded_at: SystemTime::now(),
+                },
+            )]),
+            credited_airdrop_campaigns: BTreeSet::from([1]),
+            hot_or_not_utility_token: TokenBalanceForSnapshot::default(),
         };
 
         let serde_str_res = serde_json::to_string(&token_bet_game_snapshot);
@@ -316,4 +393,116 @@ mod test {
             token_bet_game_snapshot_2_bytes
         );
     }
+    #[test]
+    fn test_backup_snapshot_reads_binary_and_json_formats() {
+        let mut canister_data = CanisterData::default();
+        canister_data
+            .token_roots
+            .insert(get_mock_user_alice_canister_id(), ());
+
+        let canister_backup_snapshot = CanisterBackupSnapshot {
+            canister_data_for_snapshot: CanisterDataForSnapshot::from(&canister_data),
+            token_bet_game_for_snapshot: TokenBetGameForSnapshot::from(&TokenBetGame::default()),
+        };
+        let json_bytes = serde_json::to_vec(&canister_backup_snapshot).unwrap();
+
+        let binary_bytes = canister_backup_snapshot
+            .to_bytes(SystemTime::now())
+            .unwrap();
+        assert!(binary_bytes.len() < json_bytes.len());
+
+        for bytes in [binary_bytes.clone(), json_bytes] {
+            let restored = CanisterBackupSnapshot::from_bytes(&bytes).unwrap();
+            assert_eq!(
+                serde_json::to_vec(&restored).unwrap(),
+                serde_json::to_vec(&canister_backup_snapshot).unwrap()
+            );
+        }
+
+        let mut corrupted_bytes = binary_bytes;
+        let last = corrupted_bytes.len() - 1;
+        corrupted_bytes[last] ^= 0xff;
+        assert!(matches!(
+            CanisterBackupSnapshot::from_bytes(&corrupted_bytes),
+            Err(SnapshotError::ChecksumMismatch)
+        ));
+        assert!(matches!(
+            CanisterBackupSnapshot::from_bytes(b"{\"canister_data"),
+            Err(SnapshotError::Deserialize(_))
+        ));
+    }
+
+    #[test]
+    fn test_backup_snapshot_reads_chunked_format() {
+        let posts: BTreeMap<u64, PostForSnapshot> = (0..5)
+            .map(|post_id| {
+                let post = PostForSnapshot {
+                    id: post_id,
+                    description: format!("post {}", post_id),
+                    hashtags: vec![],
+                    video_uid: post_id.to_string(),
+                    status: PostStatus::ReadyToView,
+                    created_at: SystemTime::UNIX_EPOCH,
+                    likes: HashSet::new(),
+                    share_count: 0,
+                    view_stats: PostViewStatistics {
+                        total_view_count: 0,
+                        threshold_view_count: 0,
+                        average_watch_percentage: 0,
+                    },
+                    is_nsfw: false,
+                };
+                (post_id, post)
+            })
+            .collect();
+
+        let mut canister_data_for_snapshot =
+            CanisterDataForSnapshot::from(&CanisterData::default());
+        canister_data_for_snapshot.all_created_posts = posts.clone();
+        let canister_backup_snapshot = CanisterBackupSnapshot {
+            canister_data_for_snapshot: CanisterDataForSnapshot::from(&CanisterData::from(
+                canister_data_for_snapshot,
+            )),
+            token_bet_game_for_snapshot: TokenBetGameForSnapshot::from(&TokenBetGame::default()),
+        };
+
+        let mut writer = ChunkedSnapshotWriter::new(
+            VectorMemory::default(),
+            SnapshotCanisterType::IndividualUser,
+            SNAPSHOT_SCHEMA_VERSION,
+            SystemTime::UNIX_EPOCH,
+            posts.len() as u64 + 1,
+        );
+        let mut base = CanisterBackupSnapshot {
+            canister_data_for_snapshot: canister_backup_snapshot.canister_data_for_snapshot.clone(),
+            token_bet_game_for_snapshot: canister_backup_snapshot
+                .token_bet_game_for_snapshot
+                .clone(),
+        };
+        base.canister_data_for_snapshot.all_created_posts = BTreeMap::new();
+        writer
+            .write_section(&CanisterBackupSnapshotSection::Base(base), 1)
+            .unwrap();
+        let posts: Vec<(u64, PostForSnapshot)> = posts.into_iter().collect();
+        for batch in posts.chunks(2) {
+            writer
+                .write_section(
+                    &CanisterBackupSnapshotSection::Posts(batch.to_vec()),
+                    batch.len() as u64,
+                )
+                .unwrap();
+        }
+        let snapshot_len = writer.finish(SystemTime::UNIX_EPOCH).unwrap();
+        let snapshot_bytes = writer.read(0, snapshot_len).unwrap();
+
+        let restored = CanisterBackupSnapshot::from_bytes(&snapshot_bytes).unwrap();
+        assert_eq!(
+            restored.canister_data_for_snapshot.all_created_posts.len(),
+            5
+        );
+        assert_eq!(
+            serde_json::to_vec(&restored).unwrap(),
+            serde_json::to_vec(&canister_backup_snapshot).unwrap()
+        );
+    }
 }
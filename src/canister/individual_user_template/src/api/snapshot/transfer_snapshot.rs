@@ -0,0 +1,104 @@
+use std::cell::RefCell;
+
+use ic_cdk_macros::{query, update};
+use shared_utils::{
+    common::{
+        types::snapshot::{SnapshotError, SnapshotManifest},
+        utils::{
+            permissions::{is_reclaim_canister_id, is_reclaim_canister_id_or_controller},
+            snapshot_transfer::PendingSnapshotUpload,
+            system_time::get_current_system_time,
+        },
+    },
+    constant::SNAPSHOT_TRANSFER_CHUNK_SIZE,
+};
+
+use crate::{util::cycles::notify_to_recharge_canister, SNAPSHOT_STORE};
+
+use super::{
+    load_snapshot::is_snapshot_load_in_progress, stored_snapshots::get_latest_snapshot_id,
+};
+
+thread_local! {
+    static SNAPSHOT_UPLOAD: RefCell<PendingSnapshotUpload> =
+        const { RefCell::new(PendingSnapshotUpload::new()) };
+}
+
+#[query(guard = "is_reclaim_canister_id")]
+fn get_snapshot_manifest() -> Result<SnapshotManifest, SnapshotError> {
+    let id = get_latest_snapshot_id()?;
+    SNAPSHOT_STORE.with_borrow(|store| store.manifest(id, SNAPSHOT_TRANSFER_CHUNK_SIZE))
+}
+
+#[query(guard = "is_reclaim_canister_id")]
+fn download_snapshot_chunk(chunk_index: u64) -> Result<Vec<u8>, SnapshotError> {
+    let id = get_latest_snapshot_id()?;
+    SNAPSHOT_STORE
+        .with_borrow(|store| store.read_chunk(id, SNAPSHOT_TRANSFER_CHUNK_SIZE, chunk_index))
+}
+
+#[update(guard = "is_reclaim_canister_id_or_controller")]
+fn start_snapshot_upload(manifest: SnapshotManifest) -> Result<(), SnapshotError> {
+    notify_to_recharge_canister();
+    if is_snapshot_load_in_progress() {
+        return Err(SnapshotError::LoadInProgress);
+    }
+    SNAPSHOT_STORE.with_borrow_mut(|store| {
+        SNAPSHOT_UPLOAD
+            .with_borrow_mut(|upload| upload.start(store, manifest, get_current_system_time()))
+    })
+}
+
+#[update(guard = "is_reclaim_canister_id_or_controller")]
+fn upload_snapshot_chunk(
+    chunk_index: u64,
+    chunk: Vec<u8>,
+    chunk_sha256: Vec<u8>,
+) -> Result<(), SnapshotError> {
+    notify_to_recharge_canister();
+    SNAPSHOT_STORE.with_borrow_mut(|store| {
+        SNAPSHOT_UPLOAD
+            .with_borrow_mut(|upload| upload.write_chunk(store, chunk_index, &chunk, &chunk_sha256))
+    })
+}
+
+#[update(guard = "is_reclaim_canister_id_or_controller")]
+fn finalize_snapshot_upload() -> Result<SnapshotManifest, SnapshotError> {
+    notify_to_recharge_canister();
+    SNAPSHOT_STORE.with_borrow_mut(|store| {
+        SNAPSHOT_UPLOAD.with_borrow_mut(|upload| upload.finish(store, get_current_system_time()))
+    })
+}
+
+/// Completes a snapshot sent through `receive_and_save_snaphot` once it matches the
+/// manifest, it can only be loaded after that
+#[update(guard = "is_reclaim_canister_id")]
+fn finalize_received_snapshot(
+    manifest: SnapshotManifest,
+) -> Result<SnapshotManifest, SnapshotError> {
+    notify_to_recharge_canister();
+    SNAPSHOT_STORE.with_borrow_mut(|store| {
+        SNAPSHOT_UPLOAD.with_borrow_mut(|upload| {
+            upload.finish_received(store, manifest, get_current_system_time())
+        })
+    })
+}
+
+pub(crate) fn receive_snapshot(offset: u64, bytes: &[u8]) -> Result<(), SnapshotError> {
+    if is_snapshot_load_in_progress() {
+        return Err(SnapshotError::LoadInProgress);
+    }
+    SNAPSHOT_STORE.with_borrow_mut(|store| {
+        SNAPSHOT_UPLOAD.with_borrow_mut(|upload| {
+            upload.receive(store, offset, bytes, get_current_system_time())
+        })
+    })
+}
+
+pub(crate) fn is_snapshot_upload_in_progress() -> bool {
+    SNAPSHOT_UPLOAD.with_borrow(|upload| upload.is_in_progress())
+}
+
+pub(crate) fn reset_snapshot_upload() {
+    SNAPSHOT_UPLOAD.with_borrow_mut(|upload| upload.reset());
+}
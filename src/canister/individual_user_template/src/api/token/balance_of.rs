@@ -0,0 +1,20 @@
+use candid::Nat;
+use ic_cdk_macros::query;
+use icrc_ledger_types::icrc1::account::Account;
+use shared_utils::canister_specific::individual_user_template::types::token::UtilityTokenName;
+
+use crate::{CANISTER_DATA, PUMP_N_DUMP};
+
+/// ICRC-1 style balance of the in-app token held by this canister.
+/// Only the profile owner holds a balance here, any other account gets zero.
+#[query]
+fn balance_of(token: UtilityTokenName, account: Account) -> Nat {
+    let profile_owner =
+        CANISTER_DATA.with_borrow(|canister_data| canister_data.profile.principal_id);
+
+    if profile_owner != Some(account.owner) {
+        return Nat::from(0_u32);
+    }
+
+    PUMP_N_DUMP.with_borrow(|pump_and_dump| pump_and_dump.get_token(token).balance_of())
+}
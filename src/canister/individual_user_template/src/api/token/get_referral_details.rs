@@ -0,0 +1,35 @@
+use candid::Nat;
+use ic_cdk_macros::query;
+use shared_utils::canister_specific::individual_user_template::types::referral::ReferralDetails;
+
+use crate::{CANISTER_DATA, PUMP_N_DUMP};
+
+#[query]
+fn get_referral_details() -> ReferralDetails {
+    let (referrer, profile_owner) = CANISTER_DATA.with_borrow(|canister_data| {
+        (
+            canister_data.profile.referrer_details.clone(),
+            canister_data.profile.principal_id,
+        )
+    });
+
+    PUMP_N_DUMP.with_borrow(|pump_and_dump| {
+        let (referee_rewards, referrer_rewards): (Vec<_>, Vec<_>) = pump_and_dump
+            .referral_rewards
+            .values()
+            .cloned()
+            .partition(|reward| Some(reward.referee_user_principal_id) == profile_owner);
+
+        let total_referral_earnings = pump_and_dump
+            .referral_rewards
+            .values()
+            .fold(Nat::from(0_u32), |acc, reward| acc + reward.amount.clone());
+
+        ReferralDetails {
+            referrer,
+            referee_reward: referee_rewards.into_iter().next(),
+            referrer_rewards,
+            total_referral_earnings,
+        }
+    })
+}
@@ -1,16 +1,7 @@
 use crate::{util::cycles::notify_to_recharge_canister, CANISTER_DATA, PUMP_N_DUMP};
 use candid::Principal;
 use ic_cdk_macros::update;
-use shared_utils::{
-    canister_specific::individual_user_template::types::token::TokenTransactions,
-    common::{
-        types::{
-            known_principal::KnownPrincipalType,
-            utility_token::token_event::{MintEvent, TokenEvent},
-        },
-        utils::system_time,
-    },
-};
+use shared_utils::common::{types::known_principal::KnownPrincipalType, utils::system_time};
 
 #[update]
 fn get_rewarded_for_referral(referrer: Principal, referree: Principal) {
@@ -32,15 +23,12 @@ fn get_rewarded_for_referral(referrer: Principal, referree: Principal) {
 
     let current_time = system_time::get_current_system_time_from_ic();
 
-    PUMP_N_DUMP.with_borrow_mut(|pump_and_dump| {
-        let referral_reward = pump_and_dump.referral_reward.clone();
-        pump_and_dump.cents.handle_token_event(TokenEvent::Mint {
-            amount: referral_reward.0.try_into().unwrap(),
-            details: MintEvent::Referral {
-                referrer_user_principal_id: referrer,
-                referee_user_principal_id: referree,
-            },
-            timestamp: current_time,
-        });
+    // rewards are credited at most once per referee, so retries from user index are no-ops
+    let res = PUMP_N_DUMP.with_borrow_mut(|pump_and_dump| {
+        pump_and_dump.credit_referral_reward(referrer, referree, current_time)
     });
+
+    if let Err(e) = res {
+        ic_cdk::println!("Error crediting referral reward {}", e);
+    }
 }
@@ -0,0 +1,22 @@
+use ic_cdk_macros::query;
+use shared_utils::canister_specific::individual_user_template::types::token::{
+    GetTokenTransactionsResponse, UtilityTokenName,
+};
+
+use crate::{util::guards::is_caller_profile_owner_or_controller, PUMP_N_DUMP};
+
+/// ICRC-3 style transaction log of the in-app token, at most
+/// `MAX_TOKEN_TRANSACTIONS_IN_ONE_REQUEST` transactions are returned per call. Only the
+/// profile owner and the controllers can read it.
+#[query(guard = "is_caller_profile_owner_or_controller")]
+fn get_transactions(
+    token: UtilityTokenName,
+    start: u64,
+    length: u64,
+) -> GetTokenTransactionsResponse {
+    PUMP_N_DUMP.with_borrow(|pump_and_dump| {
+        pump_and_dump
+            .get_token(token)
+            .get_transactions(start, length)
+    })
+}
@@ -1,2 +1,6 @@
+pub mod balance_of;
+pub mod get_referral_details;
 pub mod get_rewarded_for_referral;
 pub mod get_rewarded_for_signing_up;
+pub mod get_transactions;
+pub mod receive_cents_airdrop;
@@ -0,0 +1,48 @@
+use ic_cdk_macros::update;
+use shared_utils::{
+    common::{
+        types::known_principal::KnownPrincipalType,
+        utils::system_time::get_current_system_time_from_ic,
+    },
+    types::cents_airdrop_campaign::{
+        CentsAirdropCampaign, CentsAirdropClaimStatus, CentsAirdropUserState,
+    },
+};
+
+use crate::{util::cycles::notify_to_recharge_canister, CANISTER_DATA, PUMP_N_DUMP};
+
+#[update]
+fn receive_cents_airdrop(
+    campaign: CentsAirdropCampaign,
+) -> Result<CentsAirdropClaimStatus, String> {
+    notify_to_recharge_canister();
+
+    let user_state = CANISTER_DATA.with_borrow(|canister_data| {
+        let user_index = canister_data
+            .known_principal_ids
+            .get(&KnownPrincipalType::CanisterIdUserIndex)
+            .copied();
+
+        if user_index != Some(ic_cdk::caller()) {
+            return Err("Unauthorized".to_string());
+        }
+
+        if canister_data.profile.principal_id.is_none() {
+            return Err("Canister has no profile owner".to_string());
+        }
+
+        Ok(CentsAirdropUserState {
+            session_type: canister_data.session_type,
+            signed_up_at: canister_data.signed_up_at,
+            last_access_time: canister_data.last_access_time,
+        })
+    })?;
+
+    PUMP_N_DUMP.with_borrow_mut(|pump_and_dump| {
+        pump_and_dump.credit_airdrop_campaign(
+            &campaign,
+            &user_state,
+            get_current_system_time_from_ic(),
+        )
+    })
+}
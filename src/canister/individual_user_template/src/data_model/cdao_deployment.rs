@@ -0,0 +1,411 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    time::{Duration, SystemTime},
+};
+
+use candid::Principal;
+use ic_sns_init::pb::v1::SnsInitPayload;
+use serde::{Deserialize, Serialize};
+use shared_utils::{
+    canister_specific::individual_user_template::types::cdao::{
+        AirdropInfo, CdaoDeploymentStatus, CdaoDeploymentStep, DeployedCdaoCanisters,
+    },
+    constant::CREATOR_DAO_DEPLOYMENT_STALE_AFTER_SECS,
+    types::creator_dao_stats::{CreatorTokenMetadata, CreatorTokenSwapStatus},
+};
+
+pub const NUMBER_OF_SNS_CANISTERS: usize = 5;
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CdaoDeployment {
+    pub init_payload: SnsInitPayload,
+    pub swap_time: u64,
+    pub step: CdaoDeploymentStep,
+    /// governance, ledger, root, swap and index canisters in this order
+    pub canister_ids: Vec<Principal>,
+    /// Canisters the current step has already been checkpointed for
+    pub step_completed_for: BTreeSet<Principal>,
+    pub attempts: u32,
+    pub is_running: bool,
+    pub last_error: Option<String>,
+    pub created_at: SystemTime,
+    pub updated_at: SystemTime,
+    /// Fixed the first time the swap is installed, so retries keep the same swap window
+    #[serde(default)]
+    pub swap_due_at: Option<SystemTime>,
+}
+
+impl CdaoDeployment {
+    pub fn new(init_payload: SnsInitPayload, swap_time: u64, current_time: SystemTime) -> Self {
+        Self {
+            init_payload,
+            swap_time,
+            step: CdaoDeploymentStep::AllottingCanisters,
+            canister_ids: vec![],
+            step_completed_for: BTreeSet::new(),
+            attempts: 0,
+            is_running: false,
+            last_error: None,
+            created_at: current_time,
+            updated_at: current_time,
+            swap_due_at: None,
+        }
+    }
+
+    pub fn is_stale(&self, current_time: SystemTime) -> bool {
+        current_time
+            .duration_since(self.updated_at)
+            .unwrap_or_default()
+            >= Duration::from_secs(CREATOR_DAO_DEPLOYMENT_STALE_AFTER_SECS)
+    }
+
+    /// A deployment can be resumed once it is not being run anymore,
+    /// or when the run got stuck without checkpointing.
+    pub fn can_resume(&self, current_time: SystemTime) -> bool {
+        !self.step.is_finished() && (!self.is_running || self.is_stale(current_time))
+    }
+
+    /// Returns the attempt number, which a run passes back with each of its writes
+    pub fn start_attempt(&mut self, current_time: SystemTime) -> u32 {
+        self.attempts += 1;
+        self.is_running = true;
+        self.last_error = None;
+        self.updated_at = current_time;
+        self.attempts
+    }
+
+    /// A stale run resumed by a newer attempt may still be awaiting calls,
+    /// only the latest attempt is allowed to write
+    pub fn is_current_attempt(&self, attempt: u32) -> bool {
+        self.attempts == attempt
+    }
+
+    pub fn fail_attempt(&mut self, error: String, current_time: SystemTime) {
+        self.is_running = false;
+        self.last_error = Some(error);
+        self.updated_at = current_time;
+    }
+
+    /// Returns false if the canister was not added, because the deployment is not
+    /// allotting canisters anymore or already has all of them
+    pub fn add_canister(&mut self, canister_id: Principal, current_time: SystemTime) -> bool {
+        if self.step != CdaoDeploymentStep::AllottingCanisters
+            || self.canister_ids.len() >= NUMBER_OF_SNS_CANISTERS
+            || self.canister_ids.contains(&canister_id)
+        {
+            return false;
+        }
+
+        self.canister_ids.push(canister_id);
+        self.updated_at = current_time;
+        true
+    }
+
+    pub fn checkpoint_canister(&mut self, canister_id: Principal, current_time: SystemTime) {
+        if !self.canister_ids.contains(&canister_id) {
+            return;
+        }
+
+        self.step_completed_for.insert(canister_id);
+        self.updated_at = current_time;
+    }
+
+    pub fn get_pending_canisters(&self) -> Vec<Principal> {
+        self.canister_ids
+            .iter()
+            .filter(|canister_id| !self.step_completed_for.contains(canister_id))
+            .copied()
+            .collect()
+    }
+
+    pub fn advance(&mut self, current_time: SystemTime) {
+        self.step = self.step.next();
+        self.step_completed_for.clear();
+        self.is_running = !self.step.is_finished();
+        self.updated_at = current_time;
+    }
+
+    pub fn roll_back(&mut self, current_time: SystemTime) {
+        self.step = CdaoDeploymentStep::RolledBack;
+        self.is_running = false;
+        self.updated_at = current_time;
+    }
+
+    pub fn get_deployed_cdao_canisters(&self) -> Option<DeployedCdaoCanisters> {
+        let [governance, ledger, root, swap, index] = self.canister_ids[..] else {
+            return None;
+        };
+
+        Some(DeployedCdaoCanisters {
+            governance,
+            ledger,
+            root,
+            swap,
+            index,
+            airdrop_info: AirdropInfo::default(),
+            sns_versions: Default::default(),
+            swap_settlement: None,
+        })
+    }
+
+    pub fn get_swap_due_at(&mut self, current_time: SystemTime) -> SystemTime {
+        *self
+            .swap_due_at
+            .get_or_insert(current_time + Duration::from_secs(self.swap_time))
+    }
+
+    /// Only completed deployments have a token to describe
+    pub fn get_token_metadata(
+        &self,
+        creator_profile_id: Principal,
+        creator_canister_id: Principal,
+        current_time: SystemTime,
+    ) -> Option<CreatorTokenMetadata> {
+        if self.step != CdaoDeploymentStep::Completed {
+            return None;
+        }
+
+        let deployed_cdao_canisters = self.get_deployed_cdao_canisters()?;
+        let swap_due_at = self
+            .swap_due_at
+            .unwrap_or(self.created_at + Duration::from_secs(self.swap_time));
+
+        Some(CreatorTokenMetadata {
+            root: deployed_cdao_canisters.root,
+            ledger: deployed_cdao_canisters.ledger,
+            name: self.init_payload.token_name.clone().unwrap_or_default(),
+            symbol: self.init_payload.token_symbol.clone().unwrap_or_default(),
+            logo: self.init_payload.token_logo.clone(),
+            created_at: self.created_at,
+            creator_profile_id,
+            creator_canister_id,
+            swap_status: CreatorTokenSwapStatus::new(swap_due_at, current_time),
+            swap_due_at: Some(swap_due_at),
+        })
+    }
+
+    pub fn get_status(&self, deployment_id: u64) -> CdaoDeploymentStatus {
+        CdaoDeploymentStatus {
+            deployment_id,
+            step: self.step,
+            canister_ids: self.canister_ids.clone(),
+            attempts: self.attempts,
+            is_running: self.is_running,
+            last_error: self.last_error.clone(),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct CdaoDeployments {
+    deployments: BTreeMap<u64, CdaoDeployment>,
+    /// Ids are never reused, deployments can be removed and a timer or a status lookup
+    /// for a removed one must not find another
+    next_deployment_id: u64,
+}
+
+impl CdaoDeployments {
+    pub fn start(
+        &mut self,
+        init_payload: SnsInitPayload,
+        swap_time: u64,
+        current_time: SystemTime,
+    ) -> u64 {
+        let deployment_id = self.next_deployment_id;
+        self.next_deployment_id += 1;
+
+        self.deployments.insert(
+            deployment_id,
+            CdaoDeployment::new(init_payload, swap_time, current_time),
+        );
+
+        deployment_id
+    }
+
+    pub fn get(&self, deployment_id: u64) -> Option<&CdaoDeployment> {
+        self.deployments.get(&deployment_id)
+    }
+
+    pub fn get_mut(&mut self, deployment_id: u64) -> Option<&mut CdaoDeployment> {
+        self.deployments.get_mut(&deployment_id)
+    }
+
+    pub fn get_unfinished_deployment_ids(&self) -> Vec<u64> {
+        self.deployments
+            .iter()
+            .filter(|(_, deployment)| !deployment.step.is_finished())
+            .map(|(deployment_id, _)| *deployment_id)
+            .collect()
+    }
+
+    /// Metadata of a deployed token, tokens deployed before deployments were kept have none
+    pub fn get_token_metadata(
+        &self,
+        root: Principal,
+        creator_profile_id: Principal,
+        creator_canister_id: Principal,
+        current_time: SystemTime,
+    ) -> Option<CreatorTokenMetadata> {
+        self.deployments.values().find_map(|deployment| {
+            deployment
+                .get_token_metadata(creator_profile_id, creator_canister_id, current_time)
+                .filter(|token_metadata| token_metadata.root == root)
+        })
+    }
+
+    /// Drops the completed deployment of a deleted token, so it is not described again
+    pub fn remove_completed_deployment(&mut self, root: Principal) {
+        self.deployments.retain(|_, deployment| {
+            !(deployment.step == CdaoDeploymentStep::Completed
+                && deployment
+                    .get_deployed_cdao_canisters()
+                    .is_some_and(|deployed_cdao_canisters| deployed_cdao_canisters.root == root))
+        });
+    }
+
+    /// No call survives an upgrade, so no deployment can still be running afterwards
+    pub fn stop_running_deployments(&mut self) {
+        self.deployments
+            .values_mut()
+            .for_each(|deployment| deployment.is_running = false);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use test_utils::setup::test_constants::{
+        get_mock_user_alice_canister_id, get_mock_user_alice_principal_id,
+        get_mock_user_bob_canister_id,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_cdao_deployment_checkpoints() {
+        let now = SystemTime::now();
+        let mut deployments = CdaoDeployments::default();
+
+        let deployment_id = deployments.start(SnsInitPayload::default(), 100, now);
+        assert_eq!(
+            deployments.get_unfinished_deployment_ids(),
+            vec![deployment_id]
+        );
+
+        let deployment = deployments.get_mut(deployment_id).unwrap();
+        assert!(deployment.can_resume(now));
+
+        let attempt = deployment.start_attempt(now);
+        assert!(deployment.is_current_attempt(attempt));
+        assert!(!deployment.can_resume(now));
+        assert!(deployment
+            .can_resume(now + Duration::from_secs(CREATOR_DAO_DEPLOYMENT_STALE_AFTER_SECS)));
+
+        assert!(deployment.add_canister(get_mock_user_alice_canister_id(), now));
+        assert!(deployment.add_canister(get_mock_user_bob_canister_id(), now));
+        assert!(!deployment.add_canister(get_mock_user_bob_canister_id(), now));
+        deployment.advance(now);
+        assert!(!deployment.add_canister(Principal::from_slice(&[1]), now));
+        assert_eq!(deployment.step, CdaoDeploymentStep::DepositingCycles);
+
+        deployment.checkpoint_canister(get_mock_user_alice_canister_id(), now);
+        assert_eq!(
+            deployment.get_pending_canisters(),
+            vec![get_mock_user_bob_canister_id()]
+        );
+
+        assert_eq!(
+            deployment.get_swap_due_at(now),
+            now + Duration::from_secs(100)
+        );
+        assert_eq!(
+            deployment.get_swap_due_at(now + Duration::from_secs(10)),
+            now + Duration::from_secs(100)
+        );
+
+        let resumed_attempt = deployment.start_attempt(now);
+        assert!(!deployment.is_current_attempt(attempt));
+        assert!(deployment.is_current_attempt(resumed_attempt));
+
+        deployment.fail_attempt("call failed".to_string(), now);
+        assert!(deployment.can_resume(now));
+        assert_eq!(deployment.get_deployed_cdao_canisters(), None);
+
+        deployment.advance(now);
+        assert!(deployment.step_completed_for.is_empty());
+
+        assert!(deployment
+            .get_token_metadata(
+                get_mock_user_alice_principal_id(),
+                get_mock_user_alice_canister_id(),
+                now
+            )
+            .is_none());
+
+        deployment.roll_back(now);
+        assert!(!deployment.can_resume(now));
+        assert!(deployments.get_unfinished_deployment_ids().is_empty());
+    }
+
+    #[test]
+    fn test_completed_deployment_of_deleted_token_is_removed() {
+        let now = SystemTime::now();
+        let mut deployments = CdaoDeployments::default();
+
+        let deployment_id = deployments.start(SnsInitPayload::default(), 100, now);
+        let deployment = deployments.get_mut(deployment_id).unwrap();
+        let canister_ids: Vec<Principal> =
+            (1..=5_u8).map(|id| Principal::from_slice(&[id])).collect();
+        canister_ids
+            .iter()
+            .for_each(|canister_id| assert!(deployment.add_canister(*canister_id, now)));
+        assert!(!deployment.add_canister(get_mock_user_bob_canister_id(), now));
+        while deployment.step != CdaoDeploymentStep::Completed {
+            deployment.advance(now);
+        }
+        let root = deployment.get_deployed_cdao_canisters().unwrap().root;
+
+        let token_metadata = deployments
+            .get_token_metadata(
+                root,
+                get_mock_user_alice_principal_id(),
+                get_mock_user_alice_canister_id(),
+                now,
+            )
+            .unwrap();
+        assert_eq!(token_metadata.swap_status, CreatorTokenSwapStatus::Open);
+        assert_eq!(
+            token_metadata
+                .with_current_swap_status(now + Duration::from_secs(100))
+                .swap_status,
+            CreatorTokenSwapStatus::Ended
+        );
+        assert!(deployments
+            .get_token_metadata(
+                get_mock_user_bob_canister_id(),
+                get_mock_user_alice_principal_id(),
+                get_mock_user_alice_canister_id(),
+                now,
+            )
+            .is_none());
+
+        deployments.remove_completed_deployment(get_mock_user_bob_canister_id());
+        assert!(deployments.get_mut(deployment_id).is_some());
+
+        deployments.remove_completed_deployment(root);
+        assert!(deployments.get_mut(deployment_id).is_none());
+        assert!(deployments
+            .get_token_metadata(
+                root,
+                get_mock_user_alice_principal_id(),
+                get_mock_user_alice_canister_id(),
+                now,
+            )
+            .is_none());
+        assert_ne!(
+            deployments.start(SnsInitPayload::default(), 100, now),
+            deployment_id
+        );
+    }
+}
@@ -12,6 +12,17 @@ const UPGRADES: MemoryId = MemoryId::new(0);
 
 const TOKEN_LIST_MEMORY: MemoryId = MemoryId::new(9);
 const LIQUIDITY_POOL_MEMORY: MemoryId = MemoryId::new(10);
+// Memories for the snapshots kept in stable memory, their contents and the id given to
+// the next one
+const SNAPSHOT_MEMORY: MemoryId = MemoryId::new(11);
+const SNAPSHOT_CHUNKS_MEMORY: MemoryId = MemoryId::new(12);
+const SNAPSHOT_NEXT_ID_MEMORY: MemoryId = MemoryId::new(13);
+// Version of the state schema the upgrade memory was written with
+const STATE_SCHEMA_VERSION_MEMORY: MemoryId = MemoryId::new(14);
+// Memories for the user's data exports, kept apart from the snapshots of the canister
+const MY_DATA_EXPORT_MEMORY: MemoryId = MemoryId::new(15);
+const MY_DATA_EXPORT_CHUNKS_MEMORY: MemoryId = MemoryId::new(16);
+const MY_DATA_EXPORT_NEXT_ID_MEMORY: MemoryId = MemoryId::new(17);
 pub type Memory = VirtualMemory<DefaultMemoryImpl>;
 
 thread_local! {
@@ -33,6 +44,34 @@ pub fn get_lp_memory() -> Memory {
     MEMORY_MANAGER.with(|m| m.borrow_mut().get(LIQUIDITY_POOL_MEMORY))
 }
 
+pub fn get_snapshot_memory() -> Memory {
+    MEMORY_MANAGER.with(|m| m.borrow_mut().get(SNAPSHOT_MEMORY))
+}
+
+pub fn get_snapshot_chunks_memory() -> Memory {
+    MEMORY_MANAGER.with(|m| m.borrow_mut().get(SNAPSHOT_CHUNKS_MEMORY))
+}
+
+pub fn get_snapshot_next_id_memory() -> Memory {
+    MEMORY_MANAGER.with(|m| m.borrow_mut().get(SNAPSHOT_NEXT_ID_MEMORY))
+}
+
+pub fn get_my_data_export_memory() -> Memory {
+    MEMORY_MANAGER.with(|m| m.borrow_mut().get(MY_DATA_EXPORT_MEMORY))
+}
+
+pub fn get_my_data_export_chunks_memory() -> Memory {
+    MEMORY_MANAGER.with(|m| m.borrow_mut().get(MY_DATA_EXPORT_CHUNKS_MEMORY))
+}
+
+pub fn get_my_data_export_next_id_memory() -> Memory {
+    MEMORY_MANAGER.with(|m| m.borrow_mut().get(MY_DATA_EXPORT_NEXT_ID_MEMORY))
+}
+
+pub fn get_state_schema_version_memory() -> Memory {
+    MEMORY_MANAGER.with(|m| m.borrow_mut().get(STATE_SCHEMA_VERSION_MEMORY))
+}
+
 pub fn init_memory_manager() {
     MEMORY_MANAGER.with(|m| {
         *m.borrow_mut() = MemoryManager::init_with_bucket_size(DefaultMemoryImpl::default(), 1);
@@ -1,5 +1,6 @@
 use std::{
     collections::{BTreeMap, HashSet},
+    ops::Bound,
     time::SystemTime,
 };
 
@@ -8,12 +9,13 @@ use memory::get_token_list_memory;
 use serde::Serialize;
 use shared_utils::{
     canister_specific::individual_user_template::types::{
-        cdao::DeployedCdaoCanisters,
+        cdao::{CreatorTokenLimit, DeployedCdaoCanisters, SwapParticipations},
         error::GetPostsOfUserProfileError,
-        migration::MigrationInfo,
+        migration::{MigrationInfo, PendingMigration},
         post::{Post, PostDetailsForFrontend, PostDetailsFromFrontend},
         profile::{UserProfile, UserProfileDetailsForFrontend},
         session::SessionType,
+        token::{CreatorTokenTransfers, TokenHolding},
     },
     common::types::{
         known_principal::KnownPrincipalMap, top_posts::post_score_index_item::PostStatus,
@@ -22,10 +24,12 @@ use shared_utils::{
     pagination::{self, PaginationError},
 };
 
-use self::memory::Memory;
+use self::{cdao_deployment::CdaoDeployments, memory::Memory};
 
+pub mod cdao_deployment;
 pub mod memory;
 pub mod pump_n_dump;
+pub mod state_schema;
 
 #[derive(Deserialize, Serialize)]
 pub(crate) struct CanisterData {
@@ -41,12 +45,27 @@ pub(crate) struct CanisterData {
     #[serde(default)]
     pub migration_info: MigrationInfo,
     #[serde(default)]
+    pub pending_migration: Option<PendingMigration>,
+    #[serde(default)]
     pub cdao_canisters: Vec<DeployedCdaoCanisters>,
     // list of root token canisters
     #[serde(skip, default = "_default_token_list")]
     pub token_roots: ic_stable_structures::btreemap::BTreeMap<Principal, (), Memory>,
     #[serde(default)]
     pub empty_canisters: AllotedEmptyCanister,
+    #[serde(default)]
+    pub signed_up_at: Option<SystemTime>,
+    #[serde(default)]
+    pub cdao_deployments: CdaoDeployments,
+    #[serde(default)]
+    pub creator_token_limit: CreatorTokenLimit,
+    // cached balances of the tokens in token_roots
+    #[serde(default)]
+    pub token_holdings: BTreeMap<Principal, TokenHolding>,
+    #[serde(default)]
+    pub creator_token_transfers: CreatorTokenTransfers,
+    #[serde(default)]
+    pub swap_participations: SwapParticipations,
 }
 
 impl CanisterData {
@@ -76,6 +95,38 @@ impl CanisterData {
             .collect()
     }
 
+    pub(crate) fn posts_count(&self) -> usize {
+        self.all_created_posts.len()
+    }
+
+    /// Posts in the order of their id, starting after the given post
+    pub(crate) fn iter_posts_after(
+        &self,
+        after_post_id: Option<u64>,
+    ) -> impl Iterator<Item = (&u64, &Post)> {
+        let lower_bound = match after_post_id {
+            Some(post_id) => Bound::Excluded(post_id),
+            None => Bound::Unbounded,
+        };
+        self.all_created_posts
+            .range((lower_bound, Bound::Unbounded))
+    }
+
+    /// Registered users that signed up before the signup time was recorded get the time of
+    /// their first post, users without posts keep no signup time
+    pub(crate) fn backfill_signed_up_at(&mut self) {
+        if self.signed_up_at.is_some() || self.session_type != Some(SessionType::RegisteredSession)
+        {
+            return;
+        }
+
+        self.signed_up_at = self
+            .all_created_posts
+            .values()
+            .map(|post| post.created_at)
+            .min();
+    }
+
     pub fn add_post(&mut self, post: Post) -> Option<Post> {
         self.all_created_posts.insert(post.id, post)
     }
@@ -244,9 +295,16 @@ impl Default for CanisterData {
             session_type: None,
             last_access_time: None,
             migration_info: MigrationInfo::NotMigrated,
+            pending_migration: None,
             cdao_canisters: Vec::new(),
             token_roots: _default_token_list(),
             empty_canisters: AllotedEmptyCanister::default(),
+            signed_up_at: None,
+            cdao_deployments: CdaoDeployments::default(),
+            creator_token_limit: CreatorTokenLimit::default(),
+            token_holdings: BTreeMap::new(),
+            creator_token_transfers: CreatorTokenTransfers::default(),
+            swap_participations: SwapParticipations::default(),
         }
     }
 }
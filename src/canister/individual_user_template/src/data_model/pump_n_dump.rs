@@ -1,4 +1,8 @@
-use std::borrow::Cow;
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    time::SystemTime,
+};
 
 use candid::{Nat, Principal};
 use ic_stable_structures::{storable::Bound, StableBTreeMap, Storable};
@@ -7,9 +11,20 @@ use serde::{Deserialize, Serialize};
 use shared_utils::{
     canister_specific::individual_user_template::types::{
         cents::CentsToken,
-        pump_n_dump::{ParticipatedGameInfo, PumpsAndDumps},
+        pump_n_dump::{GameHistoryEntry, GameHistoryFilter, PumpsAndDumps, TokenGameStats},
+        referral::ReferralReward,
+        token::{TokenBalance, TokenTransactions, UtilityTokenName},
+    },
+    common::{
+        types::utility_token::token_event::{MintEvent, TokenEvent},
+        utils::default_pump_dump_onboarding_reward,
+    },
+    types::{
+        cents_airdrop_campaign::{
+            CentsAirdropCampaign, CentsAirdropClaimStatus, CentsAirdropUserState,
+        },
+        pump_n_dump_leaderboard::PumpNDumpEarningsSummary,
     },
-    common::utils::default_pump_dump_onboarding_reward,
 };
 
 use super::memory::{get_lp_memory, Memory};
@@ -38,13 +53,24 @@ impl Storable for NatStore {
 pub struct TokenBetGame {
     pub referral_reward: Nat,
     pub onboarding_reward: Nat,
-    pub games: Vec<ParticipatedGameInfo>,
+    pub games: Vec<GameHistoryEntry>,
     pub total_dumps: Nat,
     pub total_pumps: Nat,
     #[serde(skip, default = "_default_lp")]
     pub liquidity_pools: StableBTreeMap<Principal, NatStore, Memory>,
     #[serde(default)]
     pub cents: CentsToken,
+    // Key is the referee principal id
+    #[serde(default)]
+    pub referral_rewards: BTreeMap<Principal, ReferralReward>,
+    #[serde(default)]
+    pub credited_airdrop_campaigns: BTreeSet<u64>,
+    /// Legacy Hot or Not utility token ledger
+    #[serde(default)]
+    pub hot_or_not_utility_token: TokenBalance,
+    /// Set while an earnings summary is waiting to be pushed to the subnet orchestrator
+    #[serde(default)]
+    pub earnings_summary_pending: bool,
 }
 
 impl Default for TokenBetGame {
@@ -57,15 +83,136 @@ impl Default for TokenBetGame {
             total_pumps: 0u32.into(),
             total_dumps: 0u32.into(),
             cents: Default::default(),
+            referral_rewards: BTreeMap::new(),
+            credited_airdrop_campaigns: BTreeSet::new(),
+            hot_or_not_utility_token: TokenBalance::default(),
+            earnings_summary_pending: false,
         }
     }
 }
 
 impl TokenBetGame {
+    pub fn get_token(&self, token: UtilityTokenName) -> &dyn TokenTransactions {
+        match token {
+            UtilityTokenName::Cents => &self.cents,
+            UtilityTokenName::HotOrNotUtilityToken => &self.hot_or_not_utility_token,
+        }
+    }
+
     pub fn get_pumps_dumps(&self) -> PumpsAndDumps {
         PumpsAndDumps {
             pumps: self.total_pumps.clone(),
             dumps: self.total_dumps.clone(),
         }
     }
+
+    /// Games matching the filter, newest first
+    pub fn get_game_history(
+        &self,
+        filter: &GameHistoryFilter,
+    ) -> impl Iterator<Item = &GameHistoryEntry> + '_ {
+        let filter = filter.clone();
+        self.games
+            .iter()
+            .rev()
+            .filter(move |entry| filter.matches(entry))
+    }
+
+    pub fn get_token_game_stats(&self, filter: &GameHistoryFilter) -> Vec<TokenGameStats> {
+        TokenGameStats::from_game_history(self.games.iter(), filter)
+    }
+
+    pub fn get_earnings_summary(
+        &self,
+        user_principal_id: Principal,
+        user_canister_id: Principal,
+    ) -> PumpNDumpEarningsSummary {
+        PumpNDumpEarningsSummary {
+            user_principal_id,
+            user_canister_id,
+            net_earnings: self.cents.get_net_earnings(),
+            net_airdrop_reward: self.cents.get_net_airdrop(),
+            total_pumps: self.total_pumps.clone(),
+            total_dumps: self.total_dumps.clone(),
+        }
+    }
+
+    /// Credits the referral reward at most once per referee.
+    /// Returns false if the reward for this referee was already credited.
+    pub fn credit_referral_reward(
+        &mut self,
+        referrer: Principal,
+        referee: Principal,
+        current_time: SystemTime,
+    ) -> Result<bool, String> {
+        if self.referral_rewards.contains_key(&referee) {
+            return Ok(false);
+        }
+
+        let amount: u64 = self
+            .referral_reward
+            .0
+            .clone()
+            .try_into()
+            .map_err(|_| "Referral reward does not fit in u64".to_string())?;
+
+        self.cents
+            .handle_token_event(TokenEvent::Mint {
+                amount,
+                details: MintEvent::Referral {
+                    referrer_user_principal_id: referrer,
+                    referee_user_principal_id: referee,
+                },
+                timestamp: current_time,
+            })
+            .map_err(|e| format!("{:?}", e))?;
+
+        self.referral_rewards.insert(
+            referee,
+            ReferralReward {
+                referrer_user_principal_id: referrer,
+                referee_user_principal_id: referee,
+                amount: Nat::from(amount),
+                rewarded_at: current_time,
+            },
+        );
+
+        Ok(true)
+    }
+
+    /// Credits the campaign airdrop at most once per campaign.
+    pub fn credit_airdrop_campaign(
+        &mut self,
+        campaign: &CentsAirdropCampaign,
+        user_state: &CentsAirdropUserState,
+        current_time: SystemTime,
+    ) -> Result<CentsAirdropClaimStatus, String> {
+        if self
+            .credited_airdrop_campaigns
+            .contains(&campaign.campaign_id)
+        {
+            return Ok(CentsAirdropClaimStatus::AlreadyCredited);
+        }
+
+        if campaign.is_expired(current_time) {
+            return Ok(CentsAirdropClaimStatus::Expired);
+        }
+
+        if !campaign.eligibility.is_eligible(user_state) {
+            return Ok(CentsAirdropClaimStatus::NotEligible);
+        }
+
+        self.cents
+            .handle_token_event(TokenEvent::Mint {
+                amount: campaign.amount,
+                details: MintEvent::Airdrop {
+                    amount: campaign.amount,
+                },
+                timestamp: current_time,
+            })
+            .map_err(|e| format!("{:?}", e))?;
+        self.credited_airdrop_campaigns.insert(campaign.campaign_id);
+
+        Ok(CentsAirdropClaimStatus::Credited)
+    }
 }
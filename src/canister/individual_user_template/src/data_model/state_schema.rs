@@ -0,0 +1,562 @@
+use std::time::UNIX_EPOCH;
+
+use candid::{Nat, Principal};
+use ciborium::{de, value::Value};
+use ic_stable_structures::StableCell;
+use serde::{Deserialize, Serialize};
+use shared_utils::{
+    canister_specific::individual_user_template::types::{
+        pump_n_dump::{GameDirection, GameHistoryEntry, ParticipatedGameInfo},
+        token::TokenBalance,
+    },
+    common::utils::state_schema::{StateMigration, StateSchema},
+};
+
+use super::{
+    memory::{get_state_schema_version_memory, Memory},
+    pump_n_dump::TokenBetGame,
+    CanisterData,
+};
+
+/// The canister data and the token bet game as written to the upgrade memory, before
+/// they are read into their current types
+pub(crate) struct UpgradeState {
+    pub canister_data: Value,
+    pub token_bet_game: Value,
+}
+
+/// State changes go through a migration appended here instead of a `#[serde(default)]`
+/// or a custom deserializer. Every version needs a fixture in the tests below.
+pub(crate) const STATE_SCHEMA: StateSchema<UpgradeState> = StateSchema::new(&[
+    migrate_game_history_to_entries as StateMigration<UpgradeState>,
+    seed_hot_or_not_utility_token,
+]);
+
+// canisters written before the schema was versioned have nothing in this memory and
+// read as version 0
+fn get_state_schema_version_cell() -> StableCell<u32, Memory> {
+    StableCell::init(get_state_schema_version_memory(), 0)
+        .expect("Failed to read the state schema version")
+}
+
+pub(crate) fn get_state_schema_version() -> u32 {
+    *get_state_schema_version_cell().get()
+}
+
+pub(crate) fn set_state_schema_version(version: u32) {
+    get_state_schema_version_cell()
+        .set(version)
+        .expect("Failed to write the state schema version");
+}
+
+/// Reads the state written with the given schema version, migrating it first if it is
+/// older than the current one
+pub(crate) fn decode_upgrade_state(
+    version: u32,
+    canister_data_bytes: &[u8],
+    token_bet_game_bytes: &[u8],
+) -> Result<(CanisterData, TokenBetGame), String> {
+    if version == STATE_SCHEMA.current_version() {
+        return Ok((
+            de::from_reader(canister_data_bytes).map_err(|e| e.to_string())?,
+            de::from_reader(token_bet_game_bytes).map_err(|e| e.to_string())?,
+        ));
+    }
+
+    let mut state = UpgradeState {
+        canister_data: de::from_reader(canister_data_bytes).map_err(|e| e.to_string())?,
+        token_bet_game: de::from_reader(token_bet_game_bytes).map_err(|e| e.to_string())?,
+    };
+    STATE_SCHEMA
+        .migrate(&mut state, version)
+        .map_err(|e| format!("{:?}", e))?;
+
+    Ok((
+        state
+            .canister_data
+            .deserialized()
+            .map_err(|e| e.to_string())?,
+        state
+            .token_bet_game
+            .deserialized()
+            .map_err(|e| e.to_string())?,
+    ))
+}
+
+/// Game history entry before rewards were kept as `u128`
+#[derive(Serialize, Deserialize)]
+struct ParticipatedGameInfoV0 {
+    pumps: u64,
+    dumps: u64,
+    reward: Nat,
+    token_root: Principal,
+    game_direction: GameDirection,
+}
+
+/// Version 0 to 1. Games were stored as `ParticipatedGameInfoV0`, then as
+/// `ParticipatedGameInfo` without a timestamp, and canisters may still hold either.
+/// Games without a timestamp are set to `UNIX_EPOCH`.
+fn migrate_game_history_to_entries(state: &mut UpgradeState) -> Result<(), String> {
+    let Some(Value::Array(games)) = get_field_mut(&mut state.token_bet_game, "games") else {
+        return Ok(());
+    };
+
+    for game in games.iter_mut() {
+        if game.deserialized::<GameHistoryEntry>().is_ok() {
+            continue;
+        }
+
+        let game_info = match game.deserialized::<ParticipatedGameInfo>() {
+            Ok(game_info) => game_info,
+            Err(_) => {
+                let game_info = game
+                    .deserialized::<ParticipatedGameInfoV0>()
+                    .map_err(|e| format!("Unknown game history entry {}", e))?;
+                ParticipatedGameInfo {
+                    pumps: game_info.pumps,
+                    dumps: game_info.dumps,
+                    reward: game_info
+                        .reward
+                        .0
+                        .try_into()
+                        .map_err(|_| String::from("Game reward does not fit into u128"))?,
+                    token_root: game_info.token_root,
+                    game_direction: game_info.game_direction,
+                }
+            }
+        };
+        *game = Value::serialized(&GameHistoryEntry::new(game_info, UNIX_EPOCH))
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+/// Version 1 to 2. The Hot or Not utility token was kept in the canister data as
+/// `my_token_balance`, its balance and history are moved to the token bet game unless
+/// the token bet game already holds some.
+fn seed_hot_or_not_utility_token(state: &mut UpgradeState) -> Result<(), String> {
+    let Some(legacy_token) = take_field(&mut state.canister_data, "my_token_balance") else {
+        return Ok(());
+    };
+    let legacy_token: TokenBalance = legacy_token
+        .deserialized()
+        .map_err(|e| format!("Unknown legacy token balance {}", e))?;
+
+    let token = match get_field_mut(&mut state.token_bet_game, "hot_or_not_utility_token") {
+        Some(token) => token
+            .deserialized::<TokenBalance>()
+            .map_err(|e| format!("Unknown Hot or Not utility token {}", e))?,
+        None => TokenBalance::default(),
+    };
+    if token.utility_token_balance != 0 || !token.utility_token_transaction_history.is_empty() {
+        return Ok(());
+    }
+
+    let legacy_token = Value::serialized(&legacy_token).map_err(|e| e.to_string())?;
+    match get_field_mut(&mut state.token_bet_game, "hot_or_not_utility_token") {
+        Some(token) => *token = legacy_token,
+        None => state
+            .token_bet_game
+            .as_map_mut()
+            .ok_or(String::from("Token bet game is not a map"))?
+            .push((Value::Text("hot_or_not_utility_token".into()), legacy_token)),
+    }
+
+    Ok(())
+}
+
+fn take_field(value: &mut Value, field: &str) -> Option<Value> {
+    let fields = value.as_map_mut()?;
+    let index = fields
+        .iter()
+        .position(|(key, _)| key.as_text() == Some(field))?;
+
+    Some(fields.remove(index).1)
+}
+
+fn get_field_mut<'a>(value: &'a mut Value, field: &str) -> Option<&'a mut Value> {
+    value
+        .as_map_mut()?
+        .iter_mut()
+        .find(|(key, _)| key.as_text() == Some(field))
+        .map(|(_, value)| value)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::{BTreeMap, HashMap, HashSet},
+        time::{Duration, SystemTime},
+    };
+
+    use ciborium::ser;
+    use shared_utils::{
+        canister_specific::individual_user_template::types::{
+            cdao::ClaimStatus,
+            migration::MigrationInfo,
+            post::{Post, PostDetailsFromFrontend},
+            profile::UserProfile,
+            session::SessionType,
+        },
+        common::types::{
+            known_principal::{KnownPrincipalMap, KnownPrincipalType},
+            utility_token::token_event::TokenEvent,
+            version_details::VersionDetails,
+        },
+    };
+
+    use super::*;
+
+    fn game(reward: u128) -> ParticipatedGameInfo {
+        ParticipatedGameInfo {
+            pumps: 1,
+            dumps: 0,
+            reward,
+            token_root: Principal::from_slice(&[1]),
+            game_direction: GameDirection::Pump,
+        }
+    }
+
+    fn to_bytes<T: Serialize>(value: &T) -> Vec<u8> {
+        let mut bytes = vec![];
+        ser::into_writer(value, &mut bytes).unwrap();
+        bytes
+    }
+
+    fn state_with_games<T: Serialize>(games: Vec<T>) -> (Vec<u8>, Vec<u8>) {
+        let mut token_bet_game = Value::serialized(&TokenBetGame::default()).unwrap();
+        *get_field_mut(&mut token_bet_game, "games").unwrap() = Value::serialized(&games).unwrap();
+
+        (
+            to_bytes(&CanisterData::default()),
+            to_bytes(&token_bet_game),
+        )
+    }
+
+    /// State written with each schema version and the game history it is read as
+    fn fixtures() -> Vec<(u32, (Vec<u8>, Vec<u8>), Vec<GameHistoryEntry>)> {
+        let played_at = UNIX_EPOCH + Duration::from_secs(1000);
+        let v0_games = vec![
+            GameHistoryEntry::new(game(0), UNIX_EPOCH),
+            GameHistoryEntry::new(game(300), UNIX_EPOCH),
+        ];
+
+        vec![
+            (
+                0,
+                state_with_games(vec![
+                    ParticipatedGameInfoV0 {
+                        pumps: 1,
+                        dumps: 0,
+                        reward: Nat::from(0u64),
+                        token_root: Principal::from_slice(&[1]),
+                        game_direction: GameDirection::Pump,
+                    },
+                    ParticipatedGameInfoV0 {
+                        pumps: 1,
+                        dumps: 0,
+                        reward: Nat::from(300u64),
+                        token_root: Principal::from_slice(&[1]),
+                        game_direction: GameDirection::Pump,
+                    },
+                ]),
+                v0_games.clone(),
+            ),
+            (0, state_with_games(vec![game(0), game(300)]), v0_games),
+            (
+                0,
+                state_with_games(vec![GameHistoryEntry::new(game(300), played_at)]),
+                vec![GameHistoryEntry::new(game(300), played_at)],
+            ),
+            (
+                1,
+                state_with_games(vec![GameHistoryEntry::new(game(300), played_at)]),
+                vec![GameHistoryEntry::new(game(300), played_at)],
+            ),
+            (
+                2,
+                state_with_games(vec![GameHistoryEntry::new(game(300), played_at)]),
+                vec![GameHistoryEntry::new(game(300), played_at)],
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_every_version_has_a_fixture() {
+        let fixtures = fixtures();
+        for version in 0..=STATE_SCHEMA.current_version() {
+            assert!(
+                fixtures.iter().any(|(v, _, _)| *v == version),
+                "no fixture for state schema version {}",
+                version
+            );
+        }
+    }
+
+    #[test]
+    fn test_fixtures_of_every_version_are_read() {
+        for (version, (canister_data_bytes, token_bet_game_bytes), games) in fixtures() {
+            let (_, token_bet_game) =
+                decode_upgrade_state(version, &canister_data_bytes, &token_bet_game_bytes)
+                    .unwrap_or_else(|e| panic!("version {} failed: {}", version, e));
+            assert_eq!(token_bet_game.games, games, "version {}", version);
+        }
+    }
+
+    // The state below is laid out as the baseline release wrote it in `pre_upgrade`. Types
+    // that changed since are mirrored field by field, the others are serialized as they are.
+
+    #[derive(Serialize)]
+    struct BaselineCanisterData {
+        all_created_posts: BTreeMap<u64, Post>,
+        known_principal_ids: KnownPrincipalMap,
+        profile: UserProfile,
+        version_details: VersionDetails,
+        session_type: Option<SessionType>,
+        last_access_time: Option<SystemTime>,
+        migration_info: MigrationInfo,
+        cdao_canisters: Vec<BaselineDeployedCdaoCanisters>,
+        empty_canisters: BaselineAllotedEmptyCanister,
+    }
+
+    #[derive(Serialize)]
+    struct BaselineDeployedCdaoCanisters {
+        governance: Principal,
+        ledger: Principal,
+        root: Principal,
+        swap: Principal,
+        index: Principal,
+        airdrop_info: BaselineAirdropInfo,
+    }
+
+    #[derive(Serialize)]
+    struct BaselineAirdropInfo {
+        principals_who_successfully_claimed: HashMap<Principal, ClaimStatus>,
+    }
+
+    #[derive(Serialize)]
+    struct BaselineAllotedEmptyCanister {
+        canister_ids: HashSet<Principal>,
+    }
+
+    #[derive(Serialize)]
+    struct BaselineTokenBetGame {
+        referral_reward: Nat,
+        onboarding_reward: Nat,
+        games: Vec<ParticipatedGameInfo>,
+        total_dumps: Nat,
+        total_pumps: Nat,
+        cents: BaselineCentsToken,
+    }
+
+    #[derive(Serialize)]
+    struct BaselineCentsToken {
+        net_airdrop: Nat,
+        balance: Nat,
+        net_earnings: Nat,
+    }
+
+    fn baseline_state() -> (Vec<u8>, Vec<u8>) {
+        let created_at = UNIX_EPOCH + Duration::from_secs(1000);
+        let post = Post::new(
+            0,
+            &PostDetailsFromFrontend {
+                description: "first post".into(),
+                hashtags: vec!["yral".into()],
+                video_uid: "video".into(),
+                creator_consent_for_inclusion_in_hot_or_not: true,
+                is_nsfw: false,
+            },
+            &created_at,
+        );
+        let canister_data = BaselineCanisterData {
+            all_created_posts: BTreeMap::from([(0, post)]),
+            known_principal_ids: HashMap::from([(
+                KnownPrincipalType::CanisterIdUserIndex,
+                Principal::from_slice(&[2]),
+            )]),
+            profile: UserProfile {
+                principal_id: Some(Principal::from_slice(&[3])),
+                ..Default::default()
+            },
+            version_details: VersionDetails {
+                version_number: 1,
+                version: "v1.0.0".into(),
+            },
+            session_type: Some(SessionType::RegisteredSession),
+            last_access_time: Some(created_at),
+            migration_info: MigrationInfo::MigratedFromHotOrNot {
+                account_principal: Principal::from_slice(&[4]),
+            },
+            cdao_canisters: vec![BaselineDeployedCdaoCanisters {
+                governance: Principal::from_slice(&[5]),
+                ledger: Principal::from_slice(&[6]),
+                root: Principal::from_slice(&[7]),
+                swap: Principal::from_slice(&[8]),
+                index: Principal::from_slice(&[9]),
+                airdrop_info: BaselineAirdropInfo {
+                    principals_who_successfully_claimed: HashMap::from([(
+                        Principal::from_slice(&[10]),
+                        ClaimStatus::Claimed,
+                    )]),
+                },
+            }],
+            empty_canisters: BaselineAllotedEmptyCanister {
+                canister_ids: HashSet::from([Principal::from_slice(&[11])]),
+            },
+        };
+        let token_bet_game = BaselineTokenBetGame {
+            referral_reward: Nat::from(100u64),
+            onboarding_reward: Nat::from(1000u64),
+            games: vec![game(0), game(300)],
+            total_dumps: Nat::from(0u64),
+            total_pumps: Nat::from(2u64),
+            cents: BaselineCentsToken {
+                net_airdrop: Nat::from(1000u64),
+                balance: Nat::from(1300u64),
+                net_earnings: Nat::from(300u64),
+            },
+        };
+
+        (to_bytes(&canister_data), to_bytes(&token_bet_game))
+    }
+
+    #[test]
+    fn test_state_of_the_baseline_release_is_read() {
+        let (canister_data_bytes, token_bet_game_bytes) = baseline_state();
+        // the baseline release did not write a schema version
+        let (mut canister_data, token_bet_game) =
+            decode_upgrade_state(0, &canister_data_bytes, &token_bet_game_bytes).unwrap();
+
+        assert_eq!(
+            canister_data.get_post(&0).unwrap().description,
+            "first post"
+        );
+        assert_eq!(
+            canister_data
+                .known_principal_ids
+                .get(&KnownPrincipalType::CanisterIdUserIndex),
+            Some(&Principal::from_slice(&[2]))
+        );
+        assert_eq!(
+            canister_data.profile.principal_id,
+            Some(Principal::from_slice(&[3]))
+        );
+        assert_eq!(canister_data.version_details.version, "v1.0.0");
+        assert_eq!(
+            canister_data.session_type,
+            Some(SessionType::RegisteredSession)
+        );
+        assert_eq!(
+            canister_data.migration_info,
+            MigrationInfo::MigratedFromHotOrNot {
+                account_principal: Principal::from_slice(&[4])
+            }
+        );
+        assert!(canister_data.pending_migration.is_none());
+        assert!(canister_data.signed_up_at.is_none());
+
+        let cdao_canister = &canister_data.cdao_canisters[0];
+        assert_eq!(cdao_canister.governance, Principal::from_slice(&[5]));
+        assert_eq!(
+            cdao_canister
+                .airdrop_info
+                .get_claim_status(&Principal::from_slice(&[10])),
+            Ok(ClaimStatus::Claimed)
+        );
+        assert!(cdao_canister.sns_versions.is_empty());
+        assert_eq!(
+            canister_data.empty_canisters.get_number_of_canister(1),
+            Ok(vec![Principal::from_slice(&[11])])
+        );
+
+        assert_eq!(token_bet_game.referral_reward, Nat::from(100u64));
+        assert_eq!(token_bet_game.total_pumps, Nat::from(2u64));
+        assert_eq!(
+            token_bet_game.games,
+            vec![
+                GameHistoryEntry::new(game(0), UNIX_EPOCH),
+                GameHistoryEntry::new(game(300), UNIX_EPOCH),
+            ]
+        );
+        assert_eq!(
+            token_bet_game.cents.withdrawable_balance_v2(),
+            Nat::from(1300u64)
+        );
+        assert_eq!(token_bet_game.cents.get_net_earnings(), Nat::from(300u64));
+        assert_eq!(
+            token_bet_game
+                .hot_or_not_utility_token
+                .utility_token_balance,
+            0
+        );
+        assert!(token_bet_game.referral_rewards.is_empty());
+    }
+
+    fn legacy_token() -> TokenBalance {
+        let mut utility_token_transaction_history = BTreeMap::new();
+        utility_token_transaction_history.insert(1, TokenEvent::Burn);
+
+        TokenBalance {
+            utility_token_balance: 1500,
+            utility_token_transaction_history,
+            lifetime_earnings: 500,
+        }
+    }
+
+    fn state_with_legacy_token(hot_or_not_utility_token: TokenBalance) -> (Vec<u8>, Vec<u8>) {
+        let mut canister_data = Value::serialized(&CanisterData::default()).unwrap();
+        canister_data.as_map_mut().unwrap().push((
+            Value::Text("my_token_balance".into()),
+            Value::serialized(&legacy_token()).unwrap(),
+        ));
+        let token_bet_game = TokenBetGame {
+            hot_or_not_utility_token,
+            ..Default::default()
+        };
+
+        (to_bytes(&canister_data), to_bytes(&token_bet_game))
+    }
+
+    #[test]
+    fn test_legacy_hot_or_not_balance_is_seeded() {
+        let (canister_data_bytes, token_bet_game_bytes) =
+            state_with_legacy_token(TokenBalance::default());
+        let (_, token_bet_game) =
+            decode_upgrade_state(1, &canister_data_bytes, &token_bet_game_bytes).unwrap();
+
+        let token = token_bet_game.hot_or_not_utility_token;
+        assert_eq!(token.utility_token_balance, 1500);
+        assert_eq!(token.lifetime_earnings, 500);
+        assert_eq!(
+            token.utility_token_transaction_history,
+            legacy_token().utility_token_transaction_history
+        );
+
+        let already_seeded = TokenBalance {
+            utility_token_balance: 10,
+            ..Default::default()
+        };
+        let (canister_data_bytes, token_bet_game_bytes) = state_with_legacy_token(already_seeded);
+        let (_, token_bet_game) =
+            decode_upgrade_state(1, &canister_data_bytes, &token_bet_game_bytes).unwrap();
+        assert_eq!(
+            token_bet_game
+                .hot_or_not_utility_token
+                .utility_token_balance,
+            10
+        );
+    }
+
+    #[test]
+    fn test_state_from_a_newer_version_is_rejected() {
+        let (canister_data_bytes, token_bet_game_bytes) = state_with_games(vec![game(0)]);
+        assert!(decode_upgrade_state(
+            STATE_SCHEMA.current_version() + 1,
+            &canister_data_bytes,
+            &token_bet_game_bytes
+        )
+        .is_err());
+    }
+}
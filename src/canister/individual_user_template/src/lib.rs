@@ -2,7 +2,11 @@ use std::cell::RefCell;
 use std::{collections::BTreeMap, time::SystemTime};
 
 use api::profile::update_profile_display_details::UpdateProfileDetailsError;
+use api::snapshot::restore_snapshot::{SnapshotSection, SnapshotSectionDiff};
+use api::snapshot::export_my_data::_default_my_data_export_store;
+use api::snapshot::stored_snapshots::_default_snapshot_store;
 use candid::{Nat, Principal};
+use data_model::memory::Memory;
 use data_model::pump_n_dump::TokenBetGame;
 use data_model::CanisterData;
 use ic_cdk::api::management_canister::provisional::CanisterId;
@@ -11,20 +15,24 @@ use ic_nns_governance::pb::v1::{
     SettleNeuronsFundParticipationRequest, SettleNeuronsFundParticipationResponse,
 };
 use ic_sns_init::pb::v1::SnsInitPayload;
-use icrc_ledger_types::icrc1::transfer::Memo;
+use icrc_ledger_types::icrc1::{account::Account, transfer::Memo};
 use shared_utils::{
     canister_specific::individual_user_template::types::{
         arg::{BetMakerArg, FolloweeArg, IndividualUserTemplateInitArgs, PlaceBetArg},
-        cdao::DeployedCdaoCanisters,
+        cdao::{
+            AirdropConfig, CdaoDeploymentStatus, CdaoSwapSettlement, CreatorTokenDeletionReport,
+            CreatorTokenLimit, DeployedCdaoCanisters, SwapParticipation,
+        },
         device_id::DeviceIdentity,
         error::{
             AirdropError, BetOnCurrentlyViewingPostError, CdaoDeployError, CdaoTokenError,
-            FollowAnotherUserProfileError, GetPostsOfUserProfileError,
+            DeleteCreatorTokenError, FollowAnotherUserProfileError, GetPostsOfUserProfileError,
+            SwapParticipationError,
         },
         follow::{FollowEntryDetail, FollowEntryId},
         hot_or_not::{BetDetails, BetOutcomeForBetMaker, BettingStatus, PlacedBetDetail},
         kv_storage::{NamespaceErrors, NamespaceForFrontend},
-        migration::MigrationErrors,
+        migration::{MigrationErrors, PendingMigration},
         ml_data::{MLFeedCacheItem, SuccessHistoryItemV1, WatchHistoryItem},
         post::{
             Post, PostDetailsForFrontend, PostDetailsFromFrontend, PostViewDetailsFromFrontend,
@@ -33,20 +41,31 @@ use shared_utils::{
             UserCanisterDetails, UserProfileDetailsForFrontend, UserProfileDetailsForFrontendV2,
             UserProfileUpdateDetailsFromFrontend,
         },
-        pump_n_dump::{BalanceInfo, ParticipatedGameInfo, PumpNDumpStateDiff, PumpsAndDumps},
+        pump_n_dump::{
+            BalanceInfo, GameHistoryEntry, GameHistoryFilter, ParticipatedGameInfo,
+            PumpNDumpStateDiff, PumpsAndDumps, TokenGameStats,
+        },
+        referral::ReferralDetails,
         session::SessionType,
+        token::{
+            CreatorTokenTransfer, GetTokenTransactionsResponse, TokenHolding, UtilityTokenName,
+        },
     },
     common::types::{
         app_primitive_type::PostId,
         http::{HttpRequest, HttpResponse},
         known_principal::KnownPrincipalType,
+        snapshot::{SnapshotError, SnapshotInfo, SnapshotManifest, SnapshotProgress},
         top_posts::post_score_index_item::PostStatus,
         utility_token::token_event::TokenEvent,
+        wasm::SnsWasmType,
     },
+    common::utils::snapshot_store::SnapshotStore,
     pagination::PaginationError,
     types::canister_specific::individual_user_template::error_types::{
         GetUserUtilityTokenTransactionHistoryError, UpdateProfileSetUniqueUsernameError,
     },
+    types::cents_airdrop_campaign::{CentsAirdropCampaign, CentsAirdropClaimStatus},
     types::creator_dao_stats::IndividualUserCreatorDaoEntry,
 };
 
@@ -56,7 +75,8 @@ mod util;
 
 thread_local! {
     static CANISTER_DATA: RefCell<CanisterData> = RefCell::default();
-    static SNAPSHOT_DATA: RefCell<Vec<u8>> = RefCell::default();
+    static SNAPSHOT_STORE: RefCell<SnapshotStore<Memory>> = RefCell::new(_default_snapshot_store());
+    static MY_DATA_EXPORT_STORE: RefCell<SnapshotStore<Memory>> = RefCell::new(_default_my_data_export_store());
     static PUMP_N_DUMP: RefCell<TokenBetGame> = RefCell::default();
 }
 
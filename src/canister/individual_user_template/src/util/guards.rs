@@ -1,4 +1,4 @@
-use ic_cdk::caller;
+use ic_cdk::{api::is_controller, caller};
 
 use crate::CANISTER_DATA;
 
@@ -8,3 +8,11 @@ pub(crate) fn is_caller_profile_owner() -> Result<(), String> {
         _ => Err("Unauthorized".to_owned()),
     })
 }
+
+pub(crate) fn is_caller_profile_owner_or_controller() -> Result<(), String> {
+    if is_controller(&caller()) {
+        return Ok(());
+    }
+
+    is_caller_profile_owner()
+}
@@ -0,0 +1,660 @@
+use std::{cell::Cell, time::SystemTime};
+
+use candid::Principal;
+use ic_cdk::api::management_canister::main::{canister_info, CanisterInfoRequest};
+use shared_utils::{
+    canister_specific::individual_user_template::types::{
+        migration::{MigrationErrors, MigrationInfo, PendingMigration},
+        post::Post,
+        session::SessionType,
+        token::TokenTransactions,
+    },
+    common::{
+        types::{
+            known_principal::KnownPrincipalType, top_posts::post_score_index_item::PostStatus,
+            utility_token::token_event::TokenEvent,
+        },
+        utils::system_time::get_current_system_time,
+    },
+};
+
+use crate::{
+    data_model::{pump_n_dump::TokenBetGame, CanisterData},
+    util::subnet_orchestrator::get_all_subnet_orchestrators,
+    CANISTER_DATA, PUMP_N_DUMP,
+};
+
+thread_local! {
+    static IS_MIGRATION_TO_YRAL_IN_FLIGHT: Cell<bool> = const { Cell::new(false) };
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum SubnetType {
+    HotOrNot,
+    Yral,
+}
+
+/// An individual canister taking part in a migration, checked to belong to the Hot or Not
+/// subnet orchestrator or to a Yral subnet orchestrator
+pub(crate) struct IndividualUser {
+    pub canister_id: Principal,
+    pub profile_principal: Principal,
+    pub subnet_type: SubnetType,
+}
+
+impl IndividualUser {
+    /// Another canister is only accepted if the subnet orchestrator controlling it maps
+    /// `profile_principal` to it
+    pub async fn new(
+        canister_id: Principal,
+        profile_principal: Principal,
+    ) -> Result<Self, MigrationErrors> {
+        let hot_or_not_subnet_orchestrator = get_known_principal(
+            KnownPrincipalType::CanisterIdHotOrNotSubnetOrchestrator,
+            MigrationErrors::HotOrNotSubnetCanisterIdNotFound,
+        )?;
+
+        let (canister_info,) = canister_info(CanisterInfoRequest {
+            canister_id,
+            num_requested_changes: None,
+        })
+        .await
+        .map_err(|_| MigrationErrors::CanisterInfoFailed)?;
+
+        let subnet_type = if canister_info
+            .controllers
+            .contains(&hot_or_not_subnet_orchestrator)
+        {
+            SubnetType::HotOrNot
+        } else {
+            SubnetType::Yral
+        };
+        // any controller could claim to map the principal, only registered subnet
+        // orchestrators are asked
+        let subnet_orchestrators = match subnet_type {
+            SubnetType::HotOrNot => vec![hot_or_not_subnet_orchestrator],
+            SubnetType::Yral => {
+                let registered_subnet_orchestrators = get_all_subnet_orchestrators()
+                    .await
+                    .map_err(|e| MigrationErrors::SubnetOrchestratorsNotFound(e.1))?;
+                canister_info
+                    .controllers
+                    .into_iter()
+                    .filter(|controller| registered_subnet_orchestrators.contains(controller))
+                    .collect()
+            }
+        };
+
+        for subnet_orchestrator in subnet_orchestrators {
+            let registered_canister_id = ic_cdk::call::<_, (Option<Principal>,)>(
+                subnet_orchestrator,
+                "get_user_canister_id_from_user_principal_id",
+                (profile_principal,),
+            )
+            .await
+            .map(|res| res.0);
+
+            if let Ok(Some(registered_canister_id)) = registered_canister_id {
+                if registered_canister_id != canister_id {
+                    break;
+                }
+
+                return Ok(Self {
+                    canister_id,
+                    profile_principal,
+                    subnet_type,
+                });
+            }
+        }
+
+        Err(MigrationErrors::UserNotRegistered)
+    }
+
+    /// This canister, its subnet is known from the subnet orchestrator it was provisioned by
+    pub async fn from_canister_data() -> Result<Self, MigrationErrors> {
+        let hot_or_not_subnet_orchestrator = get_known_principal(
+            KnownPrincipalType::CanisterIdHotOrNotSubnetOrchestrator,
+            MigrationErrors::HotOrNotSubnetCanisterIdNotFound,
+        )?;
+        let user_index = get_known_principal(
+            KnownPrincipalType::CanisterIdUserIndex,
+            MigrationErrors::UserIndexCanisterIdNotFound,
+        )?;
+
+        CANISTER_DATA.with_borrow(|canister_data| {
+            if canister_data.session_type != Some(SessionType::RegisteredSession) {
+                return Err(MigrationErrors::UserNotRegistered);
+            }
+            let profile_principal = canister_data
+                .profile
+                .principal_id
+                .ok_or(MigrationErrors::UserNotRegistered)?;
+
+            Ok(Self {
+                canister_id: ic_cdk::id(),
+                profile_principal,
+                subnet_type: if user_index == hot_or_not_subnet_orchestrator {
+                    SubnetType::HotOrNot
+                } else {
+                    SubnetType::Yral
+                },
+            })
+        })
+    }
+}
+
+pub(crate) trait Migration {
+    /// Sends the posts and the Hot or Not token balance of this Hot or Not canister to the
+    /// Yral canister. Retrying a completed migration to the same account succeeds without
+    /// sending anything again, retrying an unconfirmed one sends the same amount again.
+    async fn transfer_tokens_and_posts(
+        &self,
+        from_account: Principal,
+        to_individual_user: IndividualUser,
+    ) -> Result<(), MigrationErrors>;
+
+    /// Receives the posts and the balance of a Hot or Not canister, a canister can only
+    /// receive them once, from the account its owner requested
+    async fn recieve_tokens_and_posts(
+        &self,
+        from_individual_user: IndividualUser,
+        amount: u64,
+        posts: Vec<Post>,
+    ) -> Result<(), MigrationErrors>;
+}
+
+impl Migration for IndividualUser {
+    async fn transfer_tokens_and_posts(
+        &self,
+        from_account: Principal,
+        to_individual_user: IndividualUser,
+    ) -> Result<(), MigrationErrors> {
+        if from_account != self.profile_principal {
+            return Err(MigrationErrors::Unauthorized);
+        }
+        if self.subnet_type != SubnetType::HotOrNot {
+            return Err(MigrationErrors::InvalidFromCanister);
+        }
+        if to_individual_user.subnet_type != SubnetType::Yral {
+            return Err(MigrationErrors::InvalidToCanister);
+        }
+        // a definite answer to one attempt must not roll back another one still in flight
+        if IS_MIGRATION_TO_YRAL_IN_FLIGHT.replace(true) {
+            return Err(MigrationErrors::MigrationInProgress);
+        }
+
+        let to_account = to_individual_user.profile_principal;
+
+        let res =
+            send_tokens_and_posts(from_account, to_account, to_individual_user.canister_id).await;
+        IS_MIGRATION_TO_YRAL_IN_FLIGHT.set(false);
+
+        res
+    }
+
+    async fn recieve_tokens_and_posts(
+        &self,
+        from_individual_user: IndividualUser,
+        amount: u64,
+        posts: Vec<Post>,
+    ) -> Result<(), MigrationErrors> {
+        if self.subnet_type != SubnetType::Yral {
+            return Err(MigrationErrors::InvalidToCanister);
+        }
+        if from_individual_user.subnet_type != SubnetType::HotOrNot {
+            return Err(MigrationErrors::InvalidFromCanister);
+        }
+
+        CANISTER_DATA.with_borrow_mut(|canister_data| {
+            PUMP_N_DUMP.with_borrow_mut(|token_bet_game| {
+                receive_migration_from_hot_or_not(
+                    canister_data,
+                    token_bet_game,
+                    from_individual_user.profile_principal,
+                    amount,
+                    posts,
+                    get_current_system_time(),
+                )
+            })
+        })
+    }
+}
+
+impl IndividualUser {
+    /// Only the owner of a Yral canister can agree to receive a Hot or Not account
+    pub fn request_migration_from_hot_or_not(
+        &self,
+        from_account: Principal,
+    ) -> Result<(), MigrationErrors> {
+        if self.subnet_type != SubnetType::Yral {
+            return Err(MigrationErrors::InvalidToCanister);
+        }
+
+        CANISTER_DATA.with_borrow_mut(|canister_data| {
+            request_migration_from_hot_or_not(canister_data, from_account)
+        })
+    }
+}
+
+/// The migration is rolled back only if the Yral canister refused it. If the call failed,
+/// the Yral canister may or may not have received it, so it is left to be retried.
+async fn send_tokens_and_posts(
+    from_account: Principal,
+    to_account: Principal,
+    to_canister_id: Principal,
+) -> Result<(), MigrationErrors> {
+    let migration = CANISTER_DATA.with_borrow_mut(|canister_data| {
+        PUMP_N_DUMP.with_borrow_mut(|token_bet_game| {
+            claim_migration_to_yral(
+                canister_data,
+                token_bet_game,
+                to_account,
+                get_current_system_time(),
+            )
+        })
+    })?;
+    let Some((amount, posts)) = migration else {
+        return Ok(());
+    };
+
+    let res = ic_cdk::call::<_, (Result<(), MigrationErrors>,)>(
+        to_canister_id,
+        "receive_data_from_hotornot",
+        (from_account, amount, posts),
+    )
+    .await;
+
+    match res {
+        Ok((Ok(()),)) => {
+            CANISTER_DATA.with_borrow_mut(|canister_data| {
+                complete_migration_to_yral(canister_data, to_account)
+            });
+            Ok(())
+        }
+        Ok((Err(e),)) => {
+            CANISTER_DATA.with_borrow_mut(|canister_data| {
+                PUMP_N_DUMP.with_borrow_mut(|token_bet_game| {
+                    roll_back_migration_to_yral(
+                        canister_data,
+                        token_bet_game,
+                        to_account,
+                        get_current_system_time(),
+                    )
+                })
+            })?;
+            Err(e)
+        }
+        Err(e) => Err(MigrationErrors::TransferToCanisterCallFailed(e.1)),
+    }
+}
+
+fn request_migration_from_hot_or_not(
+    canister_data: &mut CanisterData,
+    from_account: Principal,
+) -> Result<(), MigrationErrors> {
+    match (
+        canister_data.migration_info,
+        canister_data.pending_migration,
+    ) {
+        (
+            MigrationInfo::NotMigrated,
+            None | Some(PendingMigration::FromHotOrNotRequested { .. }),
+        ) => {
+            canister_data.pending_migration = Some(PendingMigration::FromHotOrNotRequested {
+                account_principal: from_account,
+            });
+            Ok(())
+        }
+        _ => Err(MigrationErrors::AlreadyUsedForMigration),
+    }
+}
+
+/// Takes the whole balance the first time, so a concurrent bet can not spend it while
+/// the migration is sent. Returns None if the migration to this account is completed.
+fn claim_migration_to_yral(
+    canister_data: &mut CanisterData,
+    token_bet_game: &mut TokenBetGame,
+    to_account: Principal,
+    current_time: SystemTime,
+) -> Result<Option<(u64, Vec<Post>)>, MigrationErrors> {
+    let amount = match (
+        canister_data.migration_info,
+        canister_data.pending_migration,
+    ) {
+        (MigrationInfo::NotMigrated, None) => {
+            let amount = token_bet_game
+                .hot_or_not_utility_token
+                .get_current_token_balance() as u64;
+            token_bet_game
+                .hot_or_not_utility_token
+                .handle_token_event(TokenEvent::Transfer {
+                    amount,
+                    to_account,
+                    timestamp: current_time,
+                })
+                .map_err(|e| MigrationErrors::TokenTransactionFailed(format!("{:?}", e)))?;
+            amount
+        }
+        (
+            MigrationInfo::NotMigrated,
+            Some(PendingMigration::ToYral {
+                account_principal,
+                amount,
+            }),
+        ) if account_principal == to_account => amount,
+        (MigrationInfo::MigratedToYral { account_principal }, _)
+            if account_principal == to_account =>
+        {
+            return Ok(None);
+        }
+        _ => return Err(MigrationErrors::AlreadyMigrated),
+    };
+    canister_data.pending_migration = Some(PendingMigration::ToYral {
+        account_principal: to_account,
+        amount,
+    });
+
+    let posts: Vec<Post> = canister_data
+        .get_all_posts_cloned()
+        .into_iter()
+        .map(|(_, post)| post)
+        .filter(|post| post.status != PostStatus::Deleted)
+        .collect();
+
+    Ok(Some((amount, posts)))
+}
+
+fn complete_migration_to_yral(canister_data: &mut CanisterData, to_account: Principal) {
+    if let Some(PendingMigration::ToYral {
+        account_principal, ..
+    }) = canister_data.pending_migration
+    {
+        if account_principal == to_account {
+            canister_data.migration_info = MigrationInfo::MigratedToYral {
+                account_principal: to_account,
+            };
+            canister_data.pending_migration = None;
+        }
+    }
+}
+
+/// Gives the balance back and lets the account migrate again. The migration stays
+/// pending if the balance could not be given back.
+fn roll_back_migration_to_yral(
+    canister_data: &mut CanisterData,
+    token_bet_game: &mut TokenBetGame,
+    to_account: Principal,
+    current_time: SystemTime,
+) -> Result<(), MigrationErrors> {
+    let Some(PendingMigration::ToYral {
+        account_principal,
+        amount,
+    }) = canister_data.pending_migration
+    else {
+        return Ok(());
+    };
+    if account_principal != to_account {
+        return Ok(());
+    }
+
+    token_bet_game
+        .hot_or_not_utility_token
+        .handle_token_event(TokenEvent::Receive {
+            amount,
+            from_account: to_account,
+            timestamp: current_time,
+        })
+        .map_err(|e| MigrationErrors::TokenTransactionFailed(format!("{:?}", e)))?;
+    canister_data.pending_migration = None;
+
+    Ok(())
+}
+
+fn receive_migration_from_hot_or_not(
+    canister_data: &mut CanisterData,
+    token_bet_game: &mut TokenBetGame,
+    from_account: Principal,
+    amount: u64,
+    posts: Vec<Post>,
+    current_time: SystemTime,
+) -> Result<(), MigrationErrors> {
+    match (
+        canister_data.migration_info,
+        canister_data.pending_migration,
+    ) {
+        (
+            MigrationInfo::NotMigrated,
+            Some(PendingMigration::FromHotOrNotRequested { account_principal }),
+        ) if account_principal == from_account => {}
+        (MigrationInfo::MigratedFromHotOrNot { account_principal }, _)
+            if account_principal == from_account =>
+        {
+            return Ok(());
+        }
+        (MigrationInfo::NotMigrated, _) => {
+            return Err(MigrationErrors::MigrationNotRequested);
+        }
+        _ => return Err(MigrationErrors::AlreadyUsedForMigration),
+    }
+
+    // credited first, nothing is received if it fails
+    token_bet_game
+        .hot_or_not_utility_token
+        .handle_token_event(TokenEvent::Receive {
+            amount,
+            from_account,
+            timestamp: current_time,
+        })
+        .map_err(|e| MigrationErrors::TokenTransactionFailed(format!("{:?}", e)))?;
+
+    canister_data.migration_info = MigrationInfo::MigratedFromHotOrNot {
+        account_principal: from_account,
+    };
+    canister_data.pending_migration = None;
+    for mut post in posts {
+        post.id = canister_data.posts_count() as u64;
+        canister_data.add_post(post);
+    }
+
+    Ok(())
+}
+
+fn get_known_principal(
+    principal_type: KnownPrincipalType,
+    err: MigrationErrors,
+) -> Result<Principal, MigrationErrors> {
+    CANISTER_DATA
+        .with_borrow(|canister_data| {
+            canister_data
+                .known_principal_ids
+                .get(&principal_type)
+                .copied()
+        })
+        .ok_or(err)
+}
+
+#[cfg(test)]
+mod test {
+    use shared_utils::canister_specific::individual_user_template::types::post::PostDetailsFromFrontend;
+    use test_utils::setup::test_constants::{
+        get_mock_user_alice_principal_id, get_mock_user_bob_principal_id,
+    };
+
+    use super::*;
+
+    fn hot_or_not_user_with_balance_and_post(
+        amount: u64,
+        current_time: SystemTime,
+    ) -> (CanisterData, TokenBetGame) {
+        let mut canister_data = CanisterData::default();
+        canister_data.add_post_to_memory(
+            &PostDetailsFromFrontend {
+                description: "a post".into(),
+                hashtags: vec![],
+                video_uid: "video".into(),
+                creator_consent_for_inclusion_in_hot_or_not: false,
+                is_nsfw: false,
+            },
+            &current_time,
+        );
+
+        let mut token_bet_game = TokenBetGame::default();
+        token_bet_game
+            .hot_or_not_utility_token
+            .handle_token_event(TokenEvent::Receive {
+                amount,
+                from_account: get_mock_user_bob_principal_id(),
+                timestamp: current_time,
+            })
+            .unwrap();
+
+        (canister_data, token_bet_game)
+    }
+
+    #[test]
+    fn test_claim_takes_the_balance_once_and_retries_send_the_same_amount() {
+        let now = SystemTime::now();
+        let to_account = get_mock_user_alice_principal_id();
+        let (mut canister_data, mut token_bet_game) =
+            hot_or_not_user_with_balance_and_post(100, now);
+
+        let (amount, posts) =
+            claim_migration_to_yral(&mut canister_data, &mut token_bet_game, to_account, now)
+                .unwrap()
+                .unwrap();
+        assert_eq!(amount, 100);
+        assert_eq!(posts.len(), 1);
+        assert_eq!(
+            token_bet_game
+                .hot_or_not_utility_token
+                .get_current_token_balance(),
+            0
+        );
+
+        // the call failed, the retry sends the amount taken by the first attempt
+        let (amount, _) =
+            claim_migration_to_yral(&mut canister_data, &mut token_bet_game, to_account, now)
+                .unwrap()
+                .unwrap();
+        assert_eq!(amount, 100);
+        assert!(matches!(
+            claim_migration_to_yral(
+                &mut canister_data,
+                &mut token_bet_game,
+                get_mock_user_bob_principal_id(),
+                now
+            ),
+            Err(MigrationErrors::AlreadyMigrated)
+        ));
+
+        assert_eq!(
+            canister_data.pending_migration,
+            Some(PendingMigration::ToYral {
+                account_principal: to_account,
+                amount: 100
+            })
+        );
+        assert_eq!(canister_data.migration_info, MigrationInfo::NotMigrated);
+
+        complete_migration_to_yral(&mut canister_data, to_account);
+        assert_eq!(
+            canister_data.migration_info,
+            MigrationInfo::MigratedToYral {
+                account_principal: to_account
+            }
+        );
+        assert_eq!(canister_data.pending_migration, None);
+        assert!(matches!(
+            claim_migration_to_yral(&mut canister_data, &mut token_bet_game, to_account, now),
+            Ok(None)
+        ));
+    }
+
+    #[test]
+    fn test_refused_migration_is_rolled_back() {
+        let now = SystemTime::now();
+        let to_account = get_mock_user_alice_principal_id();
+        let (mut canister_data, mut token_bet_game) =
+            hot_or_not_user_with_balance_and_post(100, now);
+
+        claim_migration_to_yral(&mut canister_data, &mut token_bet_game, to_account, now).unwrap();
+        roll_back_migration_to_yral(&mut canister_data, &mut token_bet_game, to_account, now)
+            .unwrap();
+
+        assert_eq!(canister_data.migration_info, MigrationInfo::NotMigrated);
+        assert_eq!(canister_data.pending_migration, None);
+        assert_eq!(
+            token_bet_game
+                .hot_or_not_utility_token
+                .get_current_token_balance(),
+            100
+        );
+
+        // rolling back again gives nothing back twice
+        roll_back_migration_to_yral(&mut canister_data, &mut token_bet_game, to_account, now)
+            .unwrap();
+        assert_eq!(
+            token_bet_game
+                .hot_or_not_utility_token
+                .get_current_token_balance(),
+            100
+        );
+    }
+
+    #[test]
+    fn test_migration_is_only_received_from_the_requested_account() {
+        let now = SystemTime::now();
+        let from_account = get_mock_user_bob_principal_id();
+        let mut canister_data = CanisterData::default();
+        let mut token_bet_game = TokenBetGame::default();
+
+        assert_eq!(
+            receive_migration_from_hot_or_not(
+                &mut canister_data,
+                &mut token_bet_game,
+                from_account,
+                100,
+                vec![],
+                now
+            ),
+            Err(MigrationErrors::MigrationNotRequested)
+        );
+
+        request_migration_from_hot_or_not(&mut canister_data, get_mock_user_alice_principal_id())
+            .unwrap();
+        assert_eq!(
+            receive_migration_from_hot_or_not(
+                &mut canister_data,
+                &mut token_bet_game,
+                from_account,
+                100,
+                vec![],
+                now
+            ),
+            Err(MigrationErrors::MigrationNotRequested)
+        );
+
+        request_migration_from_hot_or_not(&mut canister_data, from_account).unwrap();
+        for _ in 0..2 {
+            receive_migration_from_hot_or_not(
+                &mut canister_data,
+                &mut token_bet_game,
+                from_account,
+                100,
+                vec![],
+                now,
+            )
+            .unwrap();
+        }
+        assert_eq!(
+            token_bet_game
+                .hot_or_not_utility_token
+                .get_current_token_balance(),
+            100
+        );
+        assert_eq!(
+            request_migration_from_hot_or_not(&mut canister_data, from_account),
+            Err(MigrationErrors::AlreadyUsedForMigration)
+        );
+    }
+}
@@ -1,4 +1,5 @@
 pub mod cycles;
 pub(crate) mod guards;
+pub(crate) mod migration;
 pub mod score_ranking;
 pub mod subnet_orchestrator;
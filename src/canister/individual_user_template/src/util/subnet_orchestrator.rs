@@ -2,11 +2,18 @@ use std::collections::HashSet;
 
 use candid::Principal;
 use ic_cdk::{
-    api::management_canister::main::{update_settings, CanisterSettings, UpdateSettingsArgument},
+    api::{
+        call::RejectionCode,
+        management_canister::main::{update_settings, CanisterSettings, UpdateSettingsArgument},
+    },
     notify,
 };
-use shared_utils::common::{
-    types::known_principal::KnownPrincipalType, utils::task::run_task_concurrently,
+use shared_utils::{
+    canister_specific::individual_user_template::types::profile::UserCanisterDetails,
+    common::{types::known_principal::KnownPrincipalType, utils::task::run_task_concurrently},
+    types::{
+        creator_dao_stats::CreatorTokenMetadata, pump_n_dump_leaderboard::PumpNDumpEarningsSummary,
+    },
 };
 
 use crate::CANISTER_DATA;
@@ -119,11 +126,15 @@ impl SubnetOrchestrator {
         }
     }
 
-    pub fn send_creator_dao_stats(&self, root_canisters: HashSet<Principal>) -> Result<(), String> {
+    pub fn send_creator_dao_stats(
+        &self,
+        root_canisters: HashSet<Principal>,
+        token_metadata: Vec<CreatorTokenMetadata>,
+    ) -> Result<(), String> {
         notify(
             self.canister_id,
             "receive_creator_dao_stats_from_individual_canister",
-            (root_canisters,),
+            (root_canisters, Some(token_metadata)),
         )
         .map_err(|e| {
             format!(
@@ -132,4 +143,78 @@ impl SubnetOrchestrator {
             )
         })
     }
+
+    pub fn send_deleted_creator_dao_token(&self, root_canister: Principal) -> Result<(), String> {
+        notify(
+            self.canister_id,
+            "receive_deleted_creator_dao_token_from_individual_canister",
+            (root_canister,),
+        )
+        .map_err(|e| {
+            format!(
+                "error sending deleted creator token to subnet orchestrator {:?}",
+                e
+            )
+        })
+    }
+
+    pub fn send_pump_n_dump_earnings_summary(
+        &self,
+        earnings_summary: PumpNDumpEarningsSummary,
+    ) -> Result<(), String> {
+        notify(
+            self.canister_id,
+            "receive_pump_n_dump_earnings_summary_from_individual_canister",
+            (earnings_summary,),
+        )
+        .map_err(|e| {
+            format!(
+                "error sending pump and dump earnings summary to subnet orchestrator {:?}",
+                e
+            )
+        })
+    }
+
+    pub async fn issue_rewards_for_referral(
+        &self,
+        referrer: UserCanisterDetails,
+    ) -> Result<(), String> {
+        ic_cdk::call::<_, (Result<(), String>,)>(
+            self.canister_id,
+            "issue_rewards_for_referral_from_referee_canister",
+            (referrer,),
+        )
+        .await
+        .map_err(|e| format!("issue rewards for referral failed {}", e.1))?
+        .0
+    }
+}
+
+/// This canister's subnet orchestrator and every subnet orchestrator registered with
+/// the platform orchestrator, the canisters of users on other subnets are controlled by
+/// one of them
+pub(crate) async fn get_all_subnet_orchestrators() -> Result<Vec<Principal>, (RejectionCode, String)>
+{
+    let (own_subnet_orchestrator, platform_orchestrator) =
+        CANISTER_DATA.with_borrow(|canister_data| {
+            (
+                canister_data
+                    .known_principal_ids
+                    .get(&KnownPrincipalType::CanisterIdUserIndex)
+                    .copied(),
+                canister_data
+                    .known_principal_ids
+                    .get(&KnownPrincipalType::CanisterIdPlatformOrchestrator)
+                    .copied(),
+            )
+        });
+
+    let mut subnet_orchestrators: Vec<Principal> = own_subnet_orchestrator.into_iter().collect();
+    if let Some(platform_orchestrator) = platform_orchestrator {
+        let (all_subnet_orchestrators,): (Vec<Principal>,) =
+            ic_cdk::call(platform_orchestrator, "get_all_subnet_orchestrators", ()).await?;
+        subnet_orchestrators.extend(all_subnet_orchestrators);
+    }
+
+    Ok(subnet_orchestrators)
 }
@@ -7,12 +7,18 @@ use shared_utils::{
     common::utils::system_time,
 };
 
-use crate::{data_model::memory, CANISTER_DATA};
+use crate::{
+    api::snapshot::network_backup::{schedule_network_backup_poll, schedule_network_backups},
+    data_model::memory,
+    CANISTER_DATA,
+};
 
 #[post_upgrade]
 pub fn post_upgrade() {
     restore_data_from_stable_memory();
     update_version_from_args();
+    schedule_network_backups();
+    schedule_network_backup_poll();
 }
 
 fn restore_data_from_stable_memory() {
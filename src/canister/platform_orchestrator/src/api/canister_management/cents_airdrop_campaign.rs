@@ -0,0 +1,205 @@
+use candid::Principal;
+use ic_cdk::call;
+use ic_cdk_macros::{query, update};
+use shared_utils::{
+    canister_specific::user_index::types::BroadcastCallStatus,
+    common::utils::{system_time::get_current_system_time, task::run_task_concurrently},
+    types::cents_airdrop_campaign::{CentsAirdropCampaign, CentsAirdropCampaignArgs},
+};
+
+use crate::{guard::is_caller::is_caller_platform_global_admin_or_controller, CANISTER_DATA};
+
+#[update(guard = "is_caller_platform_global_admin_or_controller")]
+pub fn create_cents_airdrop_campaign(args: CentsAirdropCampaignArgs) -> Result<u64, String> {
+    let current_time = get_current_system_time();
+    if args.expires_at <= current_time {
+        return Err("Campaign expiry must be in the future".into());
+    }
+
+    if args.amount == 0 {
+        return Err("Campaign amount must be greater than zero".into());
+    }
+
+    let campaign = CANISTER_DATA.with_borrow_mut(|canister_data| {
+        // campaigns created before the counter was kept are skipped
+        let campaign_id = canister_data.next_cents_airdrop_campaign_id.max(
+            canister_data
+                .cents_airdrop_campaigns
+                .last_key_value()
+                .map(|(campaign_id, _)| campaign_id + 1)
+                .unwrap_or_default(),
+        );
+        canister_data.next_cents_airdrop_campaign_id = campaign_id + 1;
+
+        let campaign = CentsAirdropCampaign {
+            campaign_id,
+            amount: args.amount,
+            eligibility: args.eligibility,
+            expires_at: args.expires_at,
+            created_at: current_time,
+        };
+
+        canister_data
+            .cents_airdrop_campaigns
+            .insert(campaign_id, campaign.clone());
+
+        campaign
+    });
+
+    let campaign_id = campaign.campaign_id;
+    let campaign_run = CentsAirdropCampaignRun::new(campaign_id)?;
+    ic_cdk::spawn(issue_cents_airdrop_campaign_for_all_subnets(
+        campaign,
+        campaign_run,
+    ));
+
+    Ok(campaign_id)
+}
+
+/// Broadcasts the campaign again, users that were already credited are not credited twice.
+/// A campaign that is still being broadcast cannot be run again.
+#[update(guard = "is_caller_platform_global_admin_or_controller")]
+pub fn rerun_cents_airdrop_campaign(campaign_id: u64) -> Result<(), String> {
+    let campaign = CANISTER_DATA
+        .with_borrow(|canister_data| {
+            canister_data
+                .cents_airdrop_campaigns
+                .get(&campaign_id)
+                .cloned()
+        })
+        .ok_or("Campaign not found")?;
+
+    if campaign.is_expired(get_current_system_time()) {
+        return Err("Campaign expired".into());
+    }
+
+    let campaign_run = CentsAirdropCampaignRun::new(campaign_id)?;
+    ic_cdk::spawn(issue_cents_airdrop_campaign_for_all_subnets(
+        campaign,
+        campaign_run,
+    ));
+
+    Ok(())
+}
+
+#[query]
+fn get_cents_airdrop_campaigns() -> Vec<CentsAirdropCampaign> {
+    CANISTER_DATA.with_borrow(|canister_data| {
+        canister_data
+            .cents_airdrop_campaigns
+            .values()
+            .cloned()
+            .collect()
+    })
+}
+
+#[query]
+fn get_cents_airdrop_campaign_status(campaign_id: u64) -> Option<BroadcastCallStatus> {
+    CANISTER_DATA.with_borrow(|canister_data| {
+        canister_data
+            .cents_airdrop_campaign_status
+            .get(&campaign_id)
+            .cloned()
+    })
+}
+
+async fn issue_cents_airdrop_campaign_for_all_subnets(
+    campaign: CentsAirdropCampaign,
+    _campaign_run: CentsAirdropCampaignRun,
+) {
+    let campaign_id = campaign.campaign_id;
+
+    let subnet_orchestrators: Vec<Principal> = CANISTER_DATA.with_borrow_mut(|canister_data| {
+        canister_data.cents_airdrop_campaign_status.insert(
+            campaign_id,
+            BroadcastCallStatus {
+                method_name: "run_cents_airdrop_campaign".into(),
+                timestamp: get_current_system_time(),
+                ..Default::default()
+            },
+        );
+
+        canister_data
+            .all_subnet_orchestrator_canisters_list
+            .iter()
+            .copied()
+            .collect()
+    });
+
+    let futures = subnet_orchestrators.into_iter().map(|subnet_orchestrator| {
+        let campaign = campaign.clone();
+        async move {
+            let res = call::<_, (Result<(), String>,)>(
+                subnet_orchestrator,
+                "run_cents_airdrop_campaign",
+                (campaign,),
+            )
+            .await
+            .map_err(|e| e.1)
+            .and_then(|res| res.0);
+
+            match res {
+                Ok(()) => Ok(subnet_orchestrator),
+                Err(e) => Err((subnet_orchestrator, e)),
+            }
+        }
+    });
+
+    let result_callback = |res: Result<Principal, (Principal, String)>| {
+        CANISTER_DATA.with_borrow_mut(|canister_data| {
+            let Some(status) = canister_data
+                .cents_airdrop_campaign_status
+                .get_mut(&campaign_id)
+            else {
+                return;
+            };
+
+            match res {
+                Ok(canister_id) => {
+                    status.successful_canister_ids.push(canister_id);
+                    status.successful_canisters_count += 1;
+                }
+                Err(e) => {
+                    status.failed_canister_ids.push(e);
+                    status.failed_canisters_count += 1;
+                }
+            }
+            status.total_canisters += 1;
+        })
+    };
+
+    run_task_concurrently(futures, 10, result_callback, || false).await;
+}
+
+/// Keeps a campaign from being run again while its previous run is still broadcasting, the
+/// new run would reset the status the previous one is still reporting to
+struct CentsAirdropCampaignRun {
+    campaign_id: u64,
+}
+
+impl CentsAirdropCampaignRun {
+    fn new(campaign_id: u64) -> Result<Self, String> {
+        CANISTER_DATA.with_borrow_mut(|canister_data| {
+            if canister_data
+                .state_guard
+                .ongoing_cents_airdrop_campaigns
+                .insert(campaign_id)
+            {
+                Ok(Self { campaign_id })
+            } else {
+                Err(format!("Campaign {} is already running", campaign_id))
+            }
+        })
+    }
+}
+
+impl Drop for CentsAirdropCampaignRun {
+    fn drop(&mut self) {
+        CANISTER_DATA.with_borrow_mut(|canister_data| {
+            canister_data
+                .state_guard
+                .ongoing_cents_airdrop_campaigns
+                .remove(&self.campaign_id)
+        });
+    }
+}
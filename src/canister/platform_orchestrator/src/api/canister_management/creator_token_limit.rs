@@ -0,0 +1,65 @@
+use candid::Principal;
+use futures::{stream, StreamExt};
+use ic_cdk::api::management_canister::main::{canister_info, CanisterInfoRequest};
+use ic_cdk_macros::update;
+
+use crate::{
+    guard::is_caller::is_caller_platform_global_admin_or_controller,
+    utils::registered_subnet_orchestrator::RegisteredSubnetOrchestrator, CANISTER_DATA,
+};
+
+#[update(guard = "is_caller_platform_global_admin_or_controller")]
+pub fn update_creator_token_limit_for_all_subnets(limit: usize) -> Result<(), String> {
+    let mut update_futs = CANISTER_DATA.with_borrow_mut(|cdata| {
+        cdata.creator_token_limit = Some(limit);
+
+        let update_futs = cdata
+            .all_subnet_orchestrator_canisters_list
+            .clone()
+            .into_iter()
+            .map(move |can| {
+                ic_cdk::call::<_, (Result<(), String>,)>(
+                    can,
+                    "update_creator_token_limit_for_all_individual_users",
+                    (limit,),
+                )
+            });
+        let stream = stream::iter(update_futs);
+
+        Ok::<_, String>(stream.buffer_unordered(10))
+    })?;
+
+    ic_cdk::spawn(async move {
+        while let Some(res) = update_futs.next().await {
+            if let Err(e) = res {
+                ic_cdk::eprintln!(
+                    "failed to update creator token limit for subnets. code: {:?}, err: {}",
+                    e.0,
+                    e.1
+                )
+            }
+        }
+    });
+
+    Ok(())
+}
+
+#[update(guard = "is_caller_platform_global_admin_or_controller")]
+pub async fn set_creator_token_limit_override_for_individual_canister(
+    individual_canister_id: Principal,
+    limit: Option<usize>,
+) -> Result<(), String> {
+    let (canister_info,) = canister_info(CanisterInfoRequest {
+        canister_id: individual_canister_id,
+        num_requested_changes: None,
+    })
+    .await
+    .map_err(|e| e.1)?;
+
+    let subnet_orchestrator_canister_id = canister_info.controllers[0];
+    let subnet_orchestrator = RegisteredSubnetOrchestrator::new(subnet_orchestrator_canister_id)?;
+
+    subnet_orchestrator
+        .set_creator_token_limit_override_for_individual_canister(individual_canister_id, limit)
+        .await
+}
@@ -0,0 +1,35 @@
+use candid::Principal;
+use ic_cdk::api::{
+    call::RejectionCode,
+    management_canister::main::{canister_info, CanisterInfoRequest},
+};
+use ic_cdk_macros::update;
+use shared_utils::canister_specific::individual_user_template::types::{
+    cdao::CreatorTokenDeletionReport, error::DeleteCreatorTokenError,
+};
+
+use crate::{
+    guard::is_caller::is_caller_platform_global_admin_or_controller,
+    utils::registered_subnet_orchestrator::RegisteredSubnetOrchestrator,
+};
+
+#[update(guard = "is_caller_platform_global_admin_or_controller")]
+pub async fn delete_sns_creator_token_of_an_individual_canister(
+    individual_canister_id: Principal,
+    root: Principal,
+    force: bool,
+) -> Result<CreatorTokenDeletionReport, DeleteCreatorTokenError> {
+    let (canister_info,) = canister_info(CanisterInfoRequest {
+        canister_id: individual_canister_id,
+        num_requested_changes: None,
+    })
+    .await?;
+
+    let subnet_orchestrator_canister_id = canister_info.controllers[0];
+    let subnet_orchestrator = RegisteredSubnetOrchestrator::new(subnet_orchestrator_canister_id)
+        .map_err(|e| DeleteCreatorTokenError::CallError(RejectionCode::CanisterError, e))?;
+
+    subnet_orchestrator
+        .delete_sns_creator_token_for_an_individual_canister(individual_canister_id, root, force)
+        .await
+}
@@ -2,8 +2,11 @@ use ic_cdk_macros::query;
 
 use crate::CANISTER_DATA;
 
+pub mod cents_airdrop_campaign;
+mod creator_token_limit;
 pub mod delete_all_sns_creator_token_in_the_network;
 pub mod delete_all_sns_creator_token_of_an_individual_canister;
+pub mod delete_sns_creator_token_of_an_individual_canister;
 pub mod deregister_subnet_orchestrator;
 pub mod fixup_individual_cainsters_in_the_network;
 pub mod fixup_individual_canisters_in_a_subnet;
@@ -32,6 +35,7 @@ mod update_profile_owner_for_individual_users;
 pub mod update_timers_for_hon_game;
 pub mod upgrade_all_creator_dao_governance_canisters_in_the_network;
 pub mod upgrade_canisters_in_network;
+mod upgrade_creator_dao_sns_canisters_in_the_network;
 mod upgrade_individual_canisters_in_a_subnet_with_latest_wasm;
 mod upgrade_specific_individual_canister;
 mod upgrade_specific_individual_canister_with_version;
@@ -107,6 +107,8 @@ pub async fn provision_subnet_orchestrator_canister(
         access_control_map: None,
         version: CANISTER_DATA
             .with_borrow(|canister_data| canister_data.version_detail.version.clone()),
+        creator_token_limit: CANISTER_DATA
+            .with_borrow(|canister_data| canister_data.creator_token_limit),
     };
 
     let subnet_orchestrator_install_code_arg = InstallCodeArgument {
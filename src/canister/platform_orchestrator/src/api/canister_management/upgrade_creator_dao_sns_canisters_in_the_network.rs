@@ -0,0 +1,151 @@
+use candid::Principal;
+use ic_cdk::call;
+use ic_cdk_macros::{query, update};
+use shared_utils::{
+    canister_specific::user_index::types::BroadcastCallStatus,
+    common::{
+        types::wasm::{CanisterWasm, SnsWasmKey, SnsWasmType},
+        utils::{system_time::get_current_system_time, task::run_task_concurrently},
+    },
+};
+
+use crate::{guard::is_caller::is_caller_platform_global_admin_or_controller, CANISTER_DATA};
+
+#[update(guard = "is_caller_platform_global_admin_or_controller")]
+pub fn upload_sns_wasm(
+    wasm_type: SnsWasmType,
+    version: String,
+    wasm_module: Vec<u8>,
+) -> Result<(), String> {
+    let sns_wasm_key = SnsWasmKey::new(wasm_type, version.clone())?;
+
+    CANISTER_DATA.with_borrow_mut(|canister_data| {
+        canister_data.sns_wasms.insert(
+            sns_wasm_key,
+            CanisterWasm {
+                wasm_blob: wasm_module,
+                version,
+            },
+        );
+    });
+
+    Ok(())
+}
+
+#[query]
+fn get_stored_sns_wasm_versions() -> Vec<SnsWasmKey> {
+    CANISTER_DATA.with_borrow(|canister_data| {
+        canister_data
+            .sns_wasms
+            .iter()
+            .map(|(sns_wasm_key, _)| sns_wasm_key)
+            .collect()
+    })
+}
+
+/// Upgrades the given canister type of every creator token in the network to an uploaded
+/// version. Tokens already running that version are skipped by the individual canisters,
+/// so rerunning a rollout only retries the ones that failed.
+#[update(guard = "is_caller_platform_global_admin_or_controller")]
+pub fn upgrade_creator_dao_sns_canisters_in_the_network(
+    wasm_type: SnsWasmType,
+    version: String,
+) -> Result<(), String> {
+    let sns_wasm_key = SnsWasmKey::new(wasm_type, version.clone())?;
+    let canister_wasm = CANISTER_DATA
+        .with_borrow(|canister_data| canister_data.sns_wasms.get(&sns_wasm_key))
+        .ok_or("Wasm not found for the given type and version")?;
+
+    ic_cdk::spawn(upgrade_creator_dao_sns_canisters_for_all_subnets(
+        wasm_type,
+        version,
+        canister_wasm.wasm_blob,
+    ));
+
+    Ok(())
+}
+
+#[query]
+fn get_creator_dao_sns_canisters_upgrade_status(
+    wasm_type: SnsWasmType,
+) -> Option<BroadcastCallStatus> {
+    CANISTER_DATA.with_borrow(|canister_data| {
+        canister_data
+            .creator_dao_sns_canisters_upgrade_status
+            .get(&wasm_type)
+            .cloned()
+    })
+}
+
+async fn upgrade_creator_dao_sns_canisters_for_all_subnets(
+    wasm_type: SnsWasmType,
+    version: String,
+    wasm_module: Vec<u8>,
+) {
+    let subnet_orchestrators: Vec<Principal> = CANISTER_DATA.with_borrow_mut(|canister_data| {
+        canister_data
+            .creator_dao_sns_canisters_upgrade_status
+            .insert(
+                wasm_type,
+                BroadcastCallStatus {
+                    method_name: format!(
+                        "upgrade_creator_dao_sns_canisters_in_the_subnet {:?} {}",
+                        wasm_type, version
+                    ),
+                    timestamp: get_current_system_time(),
+                    ..Default::default()
+                },
+            );
+
+        canister_data
+            .all_subnet_orchestrator_canisters_list
+            .iter()
+            .copied()
+            .collect()
+    });
+
+    let futures = subnet_orchestrators.into_iter().map(|subnet_orchestrator| {
+        let version = version.clone();
+        let wasm_module = wasm_module.clone();
+        async move {
+            let res = call::<_, (Result<(), String>,)>(
+                subnet_orchestrator,
+                "upgrade_creator_dao_sns_canisters_in_the_subnet",
+                (wasm_type, version, wasm_module),
+            )
+            .await
+            .map_err(|e| e.1)
+            .and_then(|res| res.0);
+
+            match res {
+                Ok(()) => Ok(subnet_orchestrator),
+                Err(e) => Err((subnet_orchestrator, e)),
+            }
+        }
+    });
+
+    let result_callback = |res: Result<Principal, (Principal, String)>| {
+        CANISTER_DATA.with_borrow_mut(|canister_data| {
+            let Some(status) = canister_data
+                .creator_dao_sns_canisters_upgrade_status
+                .get_mut(&wasm_type)
+            else {
+                return;
+            };
+
+            match res {
+                Ok(canister_id) => {
+                    status.successful_canister_ids.push(canister_id);
+                    status.successful_canisters_count += 1;
+                }
+                Err(e) => {
+                    status.failed_canister_ids.push(e);
+                    status.failed_canisters_count += 1;
+                }
+            }
+            status.total_canisters += 1;
+        })
+    };
+
+    run_task_concurrently(futures, 10, result_callback, || false).await;
+}
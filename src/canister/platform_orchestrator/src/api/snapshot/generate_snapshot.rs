@@ -0,0 +1,136 @@
+use std::{cell::RefCell, time::Duration};
+
+use ic_cdk_macros::{query, update};
+use ic_cdk_timers::TimerId;
+use serde::{Deserialize, Serialize};
+use shared_utils::common::{
+    types::snapshot::{
+        decode_chunked_snapshot, SnapshotCanisterType, SnapshotError, SnapshotProgress,
+    },
+    utils::{
+        chunked_snapshot::ChunkedSnapshotWriter, permissions::is_reclaim_canister_id,
+        system_time::get_current_system_time,
+    },
+};
+
+use crate::{data_model::CanisterData, CANISTER_DATA, SNAPSHOT_STORE};
+
+use super::SNAPSHOT_SCHEMA_VERSION;
+
+/// Sections of a snapshot generated across several messages. None of the platform
+/// orchestrator's state grows with the number of users, so it is written as a single
+/// section for now.
+#[derive(Serialize, Deserialize)]
+pub(crate) enum CanisterDataSnapshotSection<D> {
+    Base(D),
+}
+
+struct SnapshotGeneration {
+    writer: ChunkedSnapshotWriter,
+    timer: Option<TimerId>,
+}
+
+thread_local! {
+    // Generation does not survive upgrades, it has to be started again after one
+    static SNAPSHOT_GENERATION: RefCell<Option<SnapshotGeneration>> = const { RefCell::new(None) };
+}
+
+/// Writes a new snapshot into the snapshot store in the background. Poll
+/// `get_snapshot_progress` until it is completed and then fetch it with
+/// `download_snapshot`.
+#[update(guard = "is_reclaim_canister_id")]
+fn start_snapshot() -> Result<SnapshotProgress, SnapshotError> {
+    if is_snapshot_generation_in_progress() {
+        return Err(SnapshotError::GenerationInProgress);
+    }
+
+    let writer = SNAPSHOT_STORE.with_borrow_mut(|store| {
+        ChunkedSnapshotWriter::new(
+            store,
+            SnapshotCanisterType::PlatformOrchestrator,
+            SNAPSHOT_SCHEMA_VERSION,
+            get_current_system_time(),
+            1,
+        )
+    });
+    let progress = writer.progress();
+
+    let timer = ic_cdk_timers::set_timer(Duration::ZERO, write_snapshot);
+    SNAPSHOT_GENERATION.with_borrow_mut(|generation| {
+        *generation = Some(SnapshotGeneration {
+            writer,
+            timer: Some(timer),
+        });
+    });
+
+    Ok(progress)
+}
+
+#[query(guard = "is_reclaim_canister_id")]
+fn get_snapshot_progress() -> Option<SnapshotProgress> {
+    SNAPSHOT_GENERATION.with_borrow(|generation| {
+        generation
+            .as_ref()
+            .map(|generation| generation.writer.progress())
+    })
+}
+
+/// Stops a generation that is still running, the snapshot it was writing is left
+/// incomplete and dropped when the next one starts
+pub(crate) fn stop_snapshot_generation() {
+    if let Some(generation) = SNAPSHOT_GENERATION.take() {
+        if let Some(timer) = generation.timer {
+            ic_cdk_timers::clear_timer(timer);
+        }
+    }
+}
+
+pub(crate) fn is_snapshot_generation_in_progress() -> bool {
+    SNAPSHOT_GENERATION.with_borrow(|generation| {
+        generation
+            .as_ref()
+            .is_some_and(|generation| generation.writer.is_in_progress())
+    })
+}
+
+pub(crate) fn canister_data_from_generated_snapshot(
+    bytes: &[u8],
+) -> Result<CanisterData, SnapshotError> {
+    let (_, sections) = decode_chunked_snapshot::<CanisterDataSnapshotSection<CanisterData>>(
+        bytes,
+        SnapshotCanisterType::PlatformOrchestrator,
+    )?;
+
+    match sections.into_iter().next() {
+        Some(CanisterDataSnapshotSection::Base(canister_data)) => Ok(canister_data),
+        None => Err(SnapshotError::Deserialize(
+            "Snapshot does not contain the canister data".into(),
+        )),
+    }
+}
+
+fn write_snapshot() {
+    SNAPSHOT_GENERATION.with_borrow_mut(|generation| {
+        let Some(generation) = generation else {
+            return;
+        };
+        generation.timer = None;
+
+        SNAPSHOT_STORE.with_borrow_mut(|store| {
+            let res = CANISTER_DATA
+                .with_borrow(|canister_data| {
+                    generation.writer.write_section(
+                        store,
+                        &CanisterDataSnapshotSection::Base(canister_data),
+                        1,
+                    )
+                })
+                .and_then(|_| generation.writer.finish(store, get_current_system_time()));
+
+            if let Err(e) = res {
+                ic_cdk::println!("Failed generating snapshot. Error: {:?}", e);
+                generation.writer.fail(store, &e, get_current_system_time());
+            }
+        });
+    });
+}
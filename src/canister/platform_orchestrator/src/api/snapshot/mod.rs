@@ -1,7 +1,21 @@
 use ic_cdk_macros::{query, update};
 
-use crate::{data_model::CanisterData, CANISTER_DATA, SNAPSHOT_DATA};
-use shared_utils::common::utils::permissions::is_reclaim_canister_id;
+use crate::{data_model::CanisterData, CANISTER_DATA, SNAPSHOT_STORE};
+use generate_snapshot::{canister_data_from_generated_snapshot, stop_snapshot_generation};
+use shared_utils::common::{
+    types::snapshot::{is_binary_snapshot, SnapshotError, SnapshotSource},
+    utils::permissions::is_reclaim_canister_id,
+};
+use stored_snapshots::{get_latest_snapshot_id, read_latest_snapshot, save_snapshot};
+use transfer_snapshot::{is_snapshot_upload_in_progress, receive_snapshot, reset_snapshot_upload};
+
+pub mod generate_snapshot;
+pub mod network_backup;
+pub mod stored_snapshots;
+pub mod transfer_snapshot;
+
+/// Bumped whenever the snapshot types change in a way older canisters cannot read
+pub const SNAPSHOT_SCHEMA_VERSION: u32 = 1;
 
 #[update(guard = "is_reclaim_canister_id")]
 fn save_snapshot_json() -> u32 {
@@ -14,54 +28,64 @@ fn save_snapshot_json() -> u32 {
 
     let len = state_bytes.len() as u32;
 
-    SNAPSHOT_DATA.with(|snapshot_data_ref_cell| {
-        *snapshot_data_ref_cell.borrow_mut() = state_bytes;
-    });
+    save_snapshot(SnapshotSource::Saved, &state_bytes)
+        .unwrap_or_else(|e| ic_cdk::trap(&format!("{:?}", e)));
 
     len
 }
 
+/// Reads from the latest completed snapshot, the range is clamped to its end
 #[query(guard = "is_reclaim_canister_id")]
-fn download_snapshot(offset: u64, length: u64) -> Vec<u8> {
-    let state_bytes = SNAPSHOT_DATA.with(|snapshot_data_ref_cell| {
-        let snapshot = snapshot_data_ref_cell.borrow();
+fn download_snapshot(offset: u64, length: u64) -> Result<Vec<u8>, SnapshotError> {
+    let id = get_latest_snapshot_id()?;
 
-        snapshot[offset as usize..(offset + length) as usize].to_vec()
-    });
-
-    state_bytes
+    SNAPSHOT_STORE.with_borrow(|store| store.read(id, offset, length))
 }
 
+/// Appends to the snapshot, the offset has to be where the bytes received so far end.
+/// Offset 0 starts a new snapshot, the others continue the one being received. It only
+/// replaces the latest snapshot once `finalize_received_snapshot` checked it.
+#[deprecated(note = "use start_snapshot_upload")]
 #[update(guard = "is_reclaim_canister_id")]
 fn receive_and_save_snaphot(offset: u64, state_bytes: Vec<u8>) {
-    SNAPSHOT_DATA.with(|snapshot_data_ref_cell| {
-        let mut snapshot = snapshot_data_ref_cell.borrow_mut();
-        if snapshot.len() < (offset + state_bytes.len() as u64) as usize {
-            snapshot.resize((offset + state_bytes.len() as u64) as usize, 0);
-        }
-        snapshot.splice(
-            offset as usize..(offset + state_bytes.len() as u64) as usize,
-            state_bytes,
-        );
-    });
+    if let Err(e) = receive_snapshot(offset, &state_bytes) {
+        ic_cdk::trap(&format!("{:?}", e));
+    }
 }
 
+/// Loads generated snapshots as well as the JSON ones, the current state is only
+/// replaced if the snapshot could be read completely
 #[update(guard = "is_reclaim_canister_id")]
-fn load_snapshot() {
-    let state_bytes =
-        SNAPSHOT_DATA.with(|snapshot_data_ref_cell| snapshot_data_ref_cell.borrow().clone());
+fn load_snapshot() -> Result<(), SnapshotError> {
+    if is_snapshot_upload_in_progress() {
+        return Err(SnapshotError::UploadInProgress);
+    }
 
-    let canister_data_snapshot: CanisterData =
-        serde_json::from_str(std::str::from_utf8(&state_bytes).unwrap()).unwrap();
+    let canister_data_snapshot = canister_data_from_snapshot(&read_latest_snapshot()?)?;
 
     CANISTER_DATA.with(|canister_data_ref_cell| {
         *canister_data_ref_cell.borrow_mut() = canister_data_snapshot;
     });
+
+    Ok(())
 }
 
+// snapshots generated by `start_snapshot` are binary, the older ones JSON
+fn canister_data_from_snapshot(bytes: &[u8]) -> Result<CanisterData, SnapshotError> {
+    if is_binary_snapshot(bytes) {
+        return canister_data_from_generated_snapshot(bytes);
+    }
+    if bytes.is_empty() {
+        return Err(SnapshotError::Empty);
+    }
+
+    serde_json::from_slice(bytes).map_err(|e| SnapshotError::Deserialize(e.to_string()))
+}
+
+/// Deletes every stored snapshot and stops the ones still being written
 #[update(guard = "is_reclaim_canister_id")]
 fn clear_snapshot() {
-    SNAPSHOT_DATA.with(|snapshot_data_ref_cell| {
-        *snapshot_data_ref_cell.borrow_mut() = vec![];
-    });
+    stop_snapshot_generation();
+    reset_snapshot_upload();
+    SNAPSHOT_STORE.with_borrow_mut(|store| store.clear());
 }
@@ -0,0 +1,293 @@
+use std::{cell::RefCell, time::Duration};
+
+use candid::Principal;
+use ic_cdk::call;
+use ic_cdk_macros::{query, update};
+use ic_cdk_timers::TimerId;
+use shared_utils::{
+    canister_specific::user_index::types::{BroadcastCallStatus, SubnetBackupStatus},
+    common::{
+        types::snapshot::BackupCatalogEntry,
+        utils::{system_time::get_current_system_time, task::run_task_concurrently},
+    },
+    constant::{MAX_STORED_SNAPSHOTS, NETWORK_BACKUP_POLL_INTERVAL_SECS},
+};
+
+use crate::{
+    data_model::CanisterData, guard::is_caller::is_caller_platform_global_admin_or_controller,
+    CANISTER_DATA,
+};
+
+thread_local! {
+    static NETWORK_BACKUP_TIMER: RefCell<Option<TimerId>> = const { RefCell::new(None) };
+    static NETWORK_BACKUP_POLL_TIMER: RefCell<Option<TimerId>> = const { RefCell::new(None) };
+}
+
+/// Backs up every subnet orchestrator and their individual canisters on the interval,
+/// None turns scheduled backups off
+#[update(guard = "is_caller_platform_global_admin_or_controller")]
+fn set_network_backup_interval(interval_in_seconds: Option<u64>) -> Result<(), String> {
+    if interval_in_seconds == Some(0) {
+        return Err("Backup interval must be greater than zero".into());
+    }
+
+    CANISTER_DATA.with_borrow_mut(|canister_data| {
+        canister_data.network_backup_interval = interval_in_seconds.map(Duration::from_secs);
+    });
+    schedule_network_backups();
+
+    Ok(())
+}
+
+#[query]
+fn get_network_backup_interval() -> Option<u64> {
+    CANISTER_DATA.with_borrow(|canister_data| {
+        canister_data
+            .network_backup_interval
+            .map(|interval| interval.as_secs())
+    })
+}
+
+/// Starts a backup outside of the schedule and returns its id
+#[update(guard = "is_caller_platform_global_admin_or_controller")]
+fn start_network_backup() -> u64 {
+    start_network_backup_run()
+}
+
+#[query]
+fn get_network_backup_status(backup_id: u64) -> Option<BroadcastCallStatus> {
+    CANISTER_DATA
+        .with_borrow(|canister_data| canister_data.network_backup_status.get(&backup_id).cloned())
+}
+
+/// Snapshots the scheduled backups started on a subnet orchestrator that it may still
+/// keep. The ones of individual canisters are cataloged by their subnet orchestrator.
+#[query]
+fn get_backups_of_canister(canister_id: Principal) -> Vec<BackupCatalogEntry> {
+    CANISTER_DATA.with_borrow(|canister_data| canister_data.backup_catalog.get(&canister_id))
+}
+
+/// Timers do not survive upgrades, so the schedule is set up again after every upgrade
+pub fn schedule_network_backups() {
+    if let Some(timer) = NETWORK_BACKUP_TIMER.take() {
+        ic_cdk_timers::clear_timer(timer);
+    }
+
+    let interval = CANISTER_DATA.with_borrow(|canister_data| canister_data.network_backup_interval);
+    if let Some(interval) = interval {
+        let timer = ic_cdk_timers::set_timer_interval(interval, || {
+            start_network_backup_run();
+        });
+        NETWORK_BACKUP_TIMER.set(Some(timer));
+    }
+}
+
+fn start_network_backup_run() -> u64 {
+    let backup_id = CANISTER_DATA.with_borrow_mut(|canister_data| {
+        let backup_id = canister_data
+            .network_backup_status
+            .last_key_value()
+            .map(|(backup_id, _)| backup_id + 1)
+            .unwrap_or_default();
+
+        canister_data.network_backup_status.insert(
+            backup_id,
+            BroadcastCallStatus {
+                method_name: "start_subnet_backup".into(),
+                timestamp: get_current_system_time(),
+                ..Default::default()
+            },
+        );
+        // older runs point to snapshots the canisters no longer keep
+        while canister_data.network_backup_status.len() > MAX_STORED_SNAPSHOTS {
+            if let Some((backup_id, _)) = canister_data.network_backup_status.pop_first() {
+                canister_data.network_backup_runs.remove(&backup_id);
+            }
+        }
+
+        backup_id
+    });
+
+    ic_cdk::spawn(backup_all_subnets(backup_id));
+
+    backup_id
+}
+
+async fn backup_all_subnets(backup_id: u64) {
+    let subnet_orchestrators: Vec<Principal> = CANISTER_DATA.with_borrow(|canister_data| {
+        canister_data
+            .all_subnet_orchestrator_canisters_list
+            .iter()
+            .copied()
+            .collect()
+    });
+
+    let futures = subnet_orchestrators
+        .into_iter()
+        .map(|subnet_orchestrator| async move {
+            let res = call::<_, (Result<(), String>,)>(
+                subnet_orchestrator,
+                "start_subnet_backup",
+                (backup_id,),
+            )
+            .await
+            .map_err(|e| e.1)
+            .and_then(|res| res.0);
+
+            (subnet_orchestrator, res)
+        });
+
+    // started backups are waited on by the poll, the others are failed right away
+    let result_callback = |(subnet_orchestrator, res): (Principal, Result<(), String>)| {
+        CANISTER_DATA.with_borrow_mut(|canister_data| {
+            if !canister_data.network_backup_status.contains_key(&backup_id) {
+                return;
+            }
+
+            match res {
+                Ok(()) => {
+                    canister_data
+                        .network_backup_runs
+                        .entry(backup_id)
+                        .or_default()
+                        .insert(subnet_orchestrator);
+                }
+                Err(e) => {
+                    record_subnet_backup(canister_data, backup_id, subnet_orchestrator, Err(e))
+                }
+            }
+        })
+    };
+
+    run_task_concurrently(futures, 10, result_callback, || false).await;
+
+    schedule_network_backup_poll();
+}
+
+/// Timers do not survive upgrades, backups still running on the subnets are polled again
+pub fn schedule_network_backup_poll() {
+    let has_runs =
+        CANISTER_DATA.with_borrow(|canister_data| !canister_data.network_backup_runs.is_empty());
+    if !has_runs || NETWORK_BACKUP_POLL_TIMER.with_borrow(|timer| timer.is_some()) {
+        return;
+    }
+
+    let timer = ic_cdk_timers::set_timer(
+        Duration::from_secs(NETWORK_BACKUP_POLL_INTERVAL_SECS),
+        || ic_cdk::spawn(poll_subnet_backups()),
+    );
+    NETWORK_BACKUP_POLL_TIMER.set(Some(timer));
+}
+
+/// Records the backups the subnet orchestrators have finished. A subnet orchestrator that
+/// can't be reached is checked again on the next poll.
+async fn poll_subnet_backups() {
+    NETWORK_BACKUP_POLL_TIMER.set(None);
+
+    let pending: Vec<(u64, Principal)> = CANISTER_DATA.with_borrow(|canister_data| {
+        canister_data
+            .network_backup_runs
+            .iter()
+            .flat_map(|(backup_id, subnet_orchestrators)| {
+                subnet_orchestrators
+                    .iter()
+                    .map(move |subnet_orchestrator| (*backup_id, *subnet_orchestrator))
+            })
+            .collect()
+    });
+
+    let futures = pending
+        .into_iter()
+        .map(|(backup_id, subnet_orchestrator)| async move {
+            let res = call::<_, (Option<SubnetBackupStatus>,)>(
+                subnet_orchestrator,
+                "get_subnet_backup_status",
+                (backup_id,),
+            )
+            .await;
+
+            let outcome = match res {
+                Err(_) => None,
+                Ok((None,)) => Some(Err(
+                    "Backup is not known to the subnet orchestrator".to_string()
+                )),
+                Ok((Some(status),)) if status.finished_at.is_none() => None,
+                Ok((Some(status),)) => {
+                    Some(subnet_orchestrator_backup(subnet_orchestrator, status))
+                }
+            };
+
+            (backup_id, subnet_orchestrator, outcome)
+        });
+
+    let result_callback = |(backup_id, subnet_orchestrator, outcome): (
+        u64,
+        Principal,
+        Option<Result<BackupCatalogEntry, String>>,
+    )| {
+        let Some(outcome) = outcome else {
+            return;
+        };
+
+        CANISTER_DATA.with_borrow_mut(|canister_data| {
+            let Some(subnet_orchestrators) = canister_data.network_backup_runs.get_mut(&backup_id)
+            else {
+                return;
+            };
+            if !subnet_orchestrators.remove(&subnet_orchestrator) {
+                return;
+            }
+            if subnet_orchestrators.is_empty() {
+                canister_data.network_backup_runs.remove(&backup_id);
+            }
+
+            record_subnet_backup(canister_data, backup_id, subnet_orchestrator, outcome);
+        })
+    };
+
+    run_task_concurrently(futures, 10, result_callback, || false).await;
+
+    schedule_network_backup_poll();
+}
+
+/// The subnet orchestrator's own snapshot, or why it could not be taken
+fn subnet_orchestrator_backup(
+    subnet_orchestrator: Principal,
+    status: SubnetBackupStatus,
+) -> Result<BackupCatalogEntry, String> {
+    if let Some(entry) = status.subnet_orchestrator_backup {
+        return Ok(entry);
+    }
+
+    Err(status
+        .broadcast_status
+        .failed_canister_ids
+        .into_iter()
+        .find(|(canister_id, _)| *canister_id == subnet_orchestrator)
+        .map(|(_, e)| e)
+        .unwrap_or_else(|| "Snapshot of the subnet orchestrator did not complete".into()))
+}
+
+fn record_subnet_backup(
+    canister_data: &mut CanisterData,
+    backup_id: u64,
+    subnet_orchestrator: Principal,
+    outcome: Result<BackupCatalogEntry, String>,
+) {
+    let Some(status) = canister_data.network_backup_status.get_mut(&backup_id) else {
+        return;
+    };
+
+    match outcome {
+        Ok(entry) => {
+            status.successful_canister_ids.push(subnet_orchestrator);
+            status.successful_canisters_count += 1;
+            canister_data.backup_catalog.insert(entry);
+        }
+        Err(e) => {
+            status.failed_canister_ids.push((subnet_orchestrator, e));
+            status.failed_canisters_count += 1;
+        }
+    }
+    status.total_canisters += 1;
+}
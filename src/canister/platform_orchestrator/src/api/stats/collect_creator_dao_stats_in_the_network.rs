@@ -51,6 +51,16 @@ pub fn collect_creator_dao_stats_in_the_network() {
                                     deployed_canister,
                                 );
                             });
+
+                        individual_user_creator_dao_entry
+                            .token_metadata
+                            .into_iter()
+                            .flatten()
+                            .for_each(|token_metadata| {
+                                canister_data
+                                    .creator_dao_stats
+                                    .upsert_token_metadata(token_metadata);
+                            });
                     },
                 );
             }
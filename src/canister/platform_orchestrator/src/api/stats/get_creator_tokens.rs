@@ -0,0 +1,45 @@
+use candid::Principal;
+use ic_cdk_macros::query;
+use shared_utils::{
+    common::utils::system_time::get_current_system_time,
+    pagination::PaginationError,
+    types::creator_dao_stats::{CreatorTokenMetadata, CreatorTokenSortBy},
+};
+
+use crate::CANISTER_DATA;
+
+#[query]
+pub fn get_creator_tokens(
+    sort_by: CreatorTokenSortBy,
+    from_inclusive_index: u64,
+    limit: u64,
+) -> Result<Vec<CreatorTokenMetadata>, PaginationError> {
+    CANISTER_DATA.with_borrow(|canister_data| {
+        canister_data
+            .creator_dao_stats
+            .get_creator_tokens_with_pagination_cursor(
+                sort_by,
+                from_inclusive_index,
+                limit,
+                get_current_system_time(),
+            )
+    })
+}
+
+#[query]
+pub fn get_creator_tokens_of_creator(
+    creator_profile_id: Principal,
+    from_inclusive_index: u64,
+    limit: u64,
+) -> Result<Vec<CreatorTokenMetadata>, PaginationError> {
+    CANISTER_DATA.with_borrow(|canister_data| {
+        canister_data
+            .creator_dao_stats
+            .get_creator_tokens_of_creator_with_pagination_cursor(
+                creator_profile_id,
+                from_inclusive_index,
+                limit,
+                get_current_system_time(),
+            )
+    })
+}
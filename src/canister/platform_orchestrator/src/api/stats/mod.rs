@@ -1,3 +1,7 @@
 pub mod collect_creator_dao_stats_in_the_network;
 pub mod get_creator_dao_stats;
+pub mod get_creator_tokens;
+pub mod get_pump_n_dump_leaderboard;
 pub mod receive_creator_dao_stats_from_subnet_orchestrator;
+pub mod receive_deleted_creator_dao_token_from_subnet_orchestrator;
+pub mod receive_pump_n_dump_leaderboard_updates_from_subnet_orchestrator;
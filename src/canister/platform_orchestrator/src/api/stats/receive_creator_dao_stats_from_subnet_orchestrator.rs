@@ -3,19 +3,23 @@ use std::collections::HashSet;
 
 use candid::Principal;
 use ic_cdk::caller;
+use shared_utils::types::creator_dao_stats::CreatorTokenMetadata;
 
 use crate::{utils::registered_subnet_orchestrator::RegisteredSubnetOrchestrator, CANISTER_DATA};
 
+/// `token_metadata` is None from subnet orchestrators that do not forward it yet
 #[update]
 pub fn receive_creator_dao_stats_from_subnet_orchestrator(
     individual_user_profile_id: Principal,
     root_canister_ids: HashSet<Principal>,
+    token_metadata: Option<Vec<CreatorTokenMetadata>>,
 ) -> Result<(), String> {
     let _registered_subnet_orchestrator = RegisteredSubnetOrchestrator::new(caller())?;
     CANISTER_DATA.with_borrow_mut(|canister_data| {
         canister_data.add_creator_dao_stats_recieved_from_subnet_orchestrator(
             individual_user_profile_id,
             root_canister_ids,
+            token_metadata,
         );
     });
 
@@ -0,0 +1,21 @@
+use candid::Principal;
+use ic_cdk::caller;
+use ic_cdk_macros::update;
+
+use crate::{utils::registered_subnet_orchestrator::RegisteredSubnetOrchestrator, CANISTER_DATA};
+
+#[update]
+pub fn receive_deleted_creator_dao_token_from_subnet_orchestrator(
+    individual_user_profile_id: Principal,
+    root_canister_id: Principal,
+) -> Result<(), String> {
+    let _registered_subnet_orchestrator = RegisteredSubnetOrchestrator::new(caller())?;
+
+    CANISTER_DATA.with_borrow_mut(|canister_data| {
+        canister_data
+            .creator_dao_stats
+            .remove_entry(individual_user_profile_id, root_canister_id);
+    });
+
+    Ok(())
+}
@@ -0,0 +1,22 @@
+use ic_cdk::caller;
+use ic_cdk_macros::update;
+use shared_utils::types::pump_n_dump_leaderboard::LeaderboardEntryUpdate;
+
+use crate::{utils::registered_subnet_orchestrator::RegisteredSubnetOrchestrator, CANISTER_DATA};
+
+#[update]
+pub fn receive_pump_n_dump_leaderboard_updates_from_subnet_orchestrator(
+    entry_updates: Vec<LeaderboardEntryUpdate>,
+) -> Result<(), String> {
+    let _registered_subnet_orchestrator = RegisteredSubnetOrchestrator::new(caller())?;
+
+    CANISTER_DATA.with_borrow_mut(|canister_data| {
+        entry_updates.into_iter().for_each(|entry_update| {
+            canister_data
+                .pump_n_dump_leaderboard
+                .insert_entry_update(entry_update);
+        });
+    });
+
+    Ok(())
+}
@@ -14,6 +14,15 @@ const CANISTER_UPGRADE_LOG_INDEX: MemoryId = MemoryId::new(2);
 //A memory for canister upgrade log 
 const CANISTER_UPGRADE_LOG: MemoryId = MemoryId::new(3);
 
+// A memory for the StableBTreeMap of versioned creator token SNS wasms
+const SNS_WASM_MEMORY: MemoryId = MemoryId::new(4);
+
+// Memories for the snapshots kept in stable memory and their contents
+const SNAPSHOT_MEMORY: MemoryId = MemoryId::new(5);
+const SNAPSHOT_CHUNKS_MEMORY: MemoryId = MemoryId::new(6);
+// Id given to the next snapshot in the snapshot store
+const SNAPSHOT_NEXT_ID_MEMORY: MemoryId = MemoryId::new(7);
+
 // A memory for the StableBTreeMap we're using. A new memory should be created for
 // every additional stable structure.
 
@@ -43,6 +52,22 @@ pub fn get_canister_upgrade_log_memory() -> Memory {
     MEMORY_MANAGER.with_borrow_mut(|memory_manager| memory_manager.get(CANISTER_UPGRADE_LOG))
 }
 
+pub fn get_sns_wasm_memory() -> Memory {
+    MEMORY_MANAGER.with_borrow_mut(|memory_manager| memory_manager.get(SNS_WASM_MEMORY))
+}
+
+pub fn get_snapshot_memory() -> Memory {
+    MEMORY_MANAGER.with_borrow_mut(|memory_manager| memory_manager.get(SNAPSHOT_MEMORY))
+}
+
+pub fn get_snapshot_chunks_memory() -> Memory {
+    MEMORY_MANAGER.with_borrow_mut(|memory_manager| memory_manager.get(SNAPSHOT_CHUNKS_MEMORY))
+}
+
+pub fn get_snapshot_next_id_memory() -> Memory {
+    MEMORY_MANAGER.with_borrow_mut(|memory_manager| memory_manager.get(SNAPSHOT_NEXT_ID_MEMORY))
+}
+
 pub fn init_memory_manager() {
     MEMORY_MANAGER.with(|m| {
         *m.borrow_mut() = MemoryManager::init_with_bucket_size(DefaultMemoryImpl::default(), 1);
@@ -2,8 +2,8 @@ use ciborium::de;
 use ic_stable_structures::{storable::Bound, StableBTreeMap, StableLog, Storable};
 use std::{
     borrow::Cow,
-    collections::HashSet,
-    time::{SystemTime, UNIX_EPOCH},
+    collections::{BTreeMap, BTreeSet, HashSet},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 use candid::{CandidType, Principal};
@@ -15,12 +15,18 @@ use shared_utils::{
             SubnetUpgradeReport,
         }
     ,
-    common::types::wasm::{CanisterWasm, WasmType},
-    types::creator_dao_stats::CreatorDaoTokenStats,
+    canister_specific::user_index::types::BroadcastCallStatus,
+    common::types::snapshot::BackupCatalog,
+    common::types::wasm::{CanisterWasm, SnsWasmKey, SnsWasmType, WasmType},
+    types::{
+        cents_airdrop_campaign::CentsAirdropCampaign,
+        creator_dao_stats::{CreatorDaoTokenStats, CreatorTokenMetadata},
+        pump_n_dump_leaderboard::PumpNDumpLeaderboard,
+    },
 };
 
 use self::memory::{
-    get_canister_upgrade_log_index_memory, get_canister_upgrade_log_memory,
+    get_canister_upgrade_log_index_memory, get_canister_upgrade_log_memory, get_sns_wasm_memory,
     get_subnet_orchestrator_wasm_memory, Memory,
 };
 
@@ -29,6 +35,8 @@ pub mod memory;
 #[derive(Serialize, Deserialize, Default)]
 pub struct StateGuard {
     pub ongoing_request_for_cycles_from_subnet_orchestrator: HashSet<Principal>,
+    #[serde(default)]
+    pub ongoing_cents_airdrop_campaigns: HashSet<u64>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -52,12 +60,46 @@ pub struct CanisterData {
     pub state_guard: StateGuard,
     #[serde(default)]
     pub creator_dao_stats: CreatorDaoTokenStats,
+    #[serde(default)]
+    pub pump_n_dump_leaderboard: PumpNDumpLeaderboard,
+    // Key is the campaign id
+    #[serde(default)]
+    pub cents_airdrop_campaigns: BTreeMap<u64, CentsAirdropCampaign>,
+    #[serde(default)]
+    pub next_cents_airdrop_campaign_id: u64,
+    // Broadcast status of the latest run of each campaign to the subnet orchestrators
+    #[serde(default)]
+    pub cents_airdrop_campaign_status: BTreeMap<u64, BroadcastCallStatus>,
+    #[serde(skip, default = "_default_sns_wasms")]
+    pub sns_wasms: StableBTreeMap<SnsWasmKey, CanisterWasm, Memory>,
+    // Broadcast status of the latest rollout of each SNS canister type to the subnet orchestrators
+    #[serde(default)]
+    pub creator_dao_sns_canisters_upgrade_status: BTreeMap<SnsWasmType, BroadcastCallStatus>,
+    // Interval between scheduled backups of the network, None while they are turned off
+    #[serde(default)]
+    pub network_backup_interval: Option<Duration>,
+    // Broadcast status of each backup run to the subnet orchestrators, keyed by backup id
+    #[serde(default)]
+    pub network_backup_status: BTreeMap<u64, BroadcastCallStatus>,
+    // Subnet orchestrators each backup run is still waiting on, keyed by backup id
+    #[serde(default)]
+    pub network_backup_runs: BTreeMap<u64, BTreeSet<Principal>>,
+    // Snapshots the backups started on the subnet orchestrators
+    #[serde(default)]
+    pub backup_catalog: BackupCatalog,
+    // Creator token limit of the network, None leaves the subnets on their default limit
+    #[serde(default)]
+    pub creator_token_limit: Option<usize>,
 }
 
 fn _default_wasms() -> StableBTreeMap<WasmType, CanisterWasm, Memory> {
     StableBTreeMap::init(get_subnet_orchestrator_wasm_memory())
 }
 
+fn _default_sns_wasms() -> StableBTreeMap<SnsWasmKey, CanisterWasm, Memory> {
+    StableBTreeMap::init(get_sns_wasm_memory())
+}
+
 fn _default_canister_upgrade_log() -> StableLog<CanisterUpgradeStatus, Memory, Memory> {
     StableLog::init(
         get_canister_upgrade_log_index_memory(),
@@ -81,6 +123,17 @@ impl Default for CanisterData {
             subnets_upgrade_report: SubnetUpgradeReport::default(),
             state_guard: StateGuard::default(),
             creator_dao_stats: CreatorDaoTokenStats::default(),
+            pump_n_dump_leaderboard: PumpNDumpLeaderboard::default(),
+            cents_airdrop_campaigns: BTreeMap::new(),
+            next_cents_airdrop_campaign_id: 0,
+            cents_airdrop_campaign_status: BTreeMap::new(),
+            sns_wasms: _default_sns_wasms(),
+            creator_dao_sns_canisters_upgrade_status: BTreeMap::new(),
+            network_backup_interval: None,
+            network_backup_status: BTreeMap::new(),
+            network_backup_runs: BTreeMap::new(),
+            backup_catalog: BackupCatalog::default(),
+            creator_token_limit: None,
         }
     }
 }
@@ -143,10 +196,22 @@ impl CanisterData {
         &mut self,
         individual_user_profile_id: Principal,
         root_canister_ids: HashSet<Principal>,
+        token_metadata: Option<Vec<CreatorTokenMetadata>>,
     ) {
         root_canister_ids.iter().for_each(|root_canister_id| {
             self.creator_dao_stats
                 .insert_new_entry(individual_user_profile_id, *root_canister_id);
         });
+
+        token_metadata
+            .into_iter()
+            .flatten()
+            .filter(|token_metadata| {
+                token_metadata.creator_profile_id == individual_user_profile_id
+                    && root_canister_ids.contains(&token_metadata.root)
+            })
+            .for_each(|token_metadata| {
+                self.creator_dao_stats.upsert_token_metadata(token_metadata);
+            });
     }
 }
@@ -7,18 +7,30 @@ use crate::api::generic_proposal::{
     PlatformOrchestratorGenericArgumentType, PlatformOrchestratorGenericResultType,
 };
 use crate::data_model::CanisterUpgradeStatus;
+use api::snapshot::stored_snapshots::_default_snapshot_store;
+use data_model::memory::Memory;
 use data_model::CanisterData;
 use ic_cdk_macros::export_candid;
 use shared_utils::{
+    canister_specific::individual_user_template::types::{
+        cdao::CreatorTokenDeletionReport, error::DeleteCreatorTokenError,
+    },
     canister_specific::platform_orchestrator::types::args::{
         PlatformOrchestratorInitArgs, UpgradeCanisterArg,
     },
     canister_specific::platform_orchestrator::types::SubnetUpgradeReport,
-    canister_specific::user_index::types::UpgradeStatus,
+    canister_specific::user_index::types::{BroadcastCallStatus, UpgradeStatus},
     common::types::http::{HttpRequest, HttpResponse},
     common::types::known_principal::KnownPrincipalType,
-    common::types::wasm::WasmType,
-    types::creator_dao_stats::CreatorDaoTokenStats,
+    common::types::snapshot::{
+        BackupCatalogEntry, SnapshotError, SnapshotInfo, SnapshotManifest, SnapshotProgress,
+    },
+    common::types::wasm::{SnsWasmKey, SnsWasmType, WasmType},
+    common::utils::snapshot_store::SnapshotStore,
+    pagination::PaginationError,
+    types::cents_airdrop_campaign::{CentsAirdropCampaign, CentsAirdropCampaignArgs},
+    types::creator_dao_stats::{CreatorDaoTokenStats, CreatorTokenMetadata, CreatorTokenSortBy},
+    types::pump_n_dump_leaderboard::{LeaderboardEntryUpdate, LeaderboardPage, LeaderboardPeriod},
 };
 
 mod api;
@@ -28,7 +40,7 @@ mod utils;
 
 thread_local! {
     pub static CANISTER_DATA: RefCell<CanisterData> = RefCell::default();
-    pub static SNAPSHOT_DATA: RefCell<Vec<u8>> = RefCell::default();
+    pub static SNAPSHOT_STORE: RefCell<SnapshotStore<Memory>> = RefCell::new(_default_snapshot_store());
 }
 
 export_candid!();
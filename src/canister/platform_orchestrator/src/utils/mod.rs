@@ -13,6 +13,8 @@ use shared_utils::{
     },
 };
 
+use crate::CANISTER_DATA;
+
 pub mod registered_subnet_orchestrator;
 
 pub(crate) async fn recharge_and_upgrade_subnet_orchestrator(
@@ -70,6 +72,7 @@ pub(crate) async fn upgrade_subnet_orchestrator_canister(
             known_principal_ids: None,
             access_control_map: None,
             version,
+            creator_token_limit: CANISTER_DATA.with_borrow(|cdata| cdata.creator_token_limit),
         })
         .unwrap(),
     };
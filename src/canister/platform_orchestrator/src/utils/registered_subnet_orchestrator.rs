@@ -9,7 +9,11 @@ use ic_cdk::{
     notify,
 };
 use shared_utils::{
-    common::types::wasm::WasmType, constant::SUBNET_ORCHESTRATOR_CANISTER_CYCLES_THRESHOLD,
+    canister_specific::individual_user_template::types::{
+        cdao::CreatorTokenDeletionReport, error::DeleteCreatorTokenError,
+    },
+    common::types::wasm::WasmType,
+    constant::SUBNET_ORCHESTRATOR_CANISTER_CYCLES_THRESHOLD,
 };
 
 use crate::CANISTER_DATA;
@@ -185,6 +189,36 @@ impl RegisteredSubnetOrchestrator {
         .0
     }
 
+    pub async fn delete_sns_creator_token_for_an_individual_canister(
+        &self,
+        individual_canister_id: Principal,
+        root: Principal,
+        force: bool,
+    ) -> Result<CreatorTokenDeletionReport, DeleteCreatorTokenError> {
+        ic_cdk::call::<_, (Result<CreatorTokenDeletionReport, DeleteCreatorTokenError>,)>(
+            self.canister_id,
+            "delete_sns_creator_token_of_an_individual_canister",
+            (individual_canister_id, root, force),
+        )
+        .await?
+        .0
+    }
+
+    pub async fn set_creator_token_limit_override_for_individual_canister(
+        &self,
+        individual_canister_id: Principal,
+        limit: Option<usize>,
+    ) -> Result<(), String> {
+        ic_cdk::call::<_, (Result<(), String>,)>(
+            self.canister_id,
+            "set_creator_token_limit_override_for_individual_canister",
+            (individual_canister_id, limit),
+        )
+        .await
+        .map_err(|e| e.1)?
+        .0
+    }
+
     pub async fn delete_all_sns_creator_token_in_the_network(&self) -> Result<(), String> {
         ic_cdk::call::<_, ()>(
             self.canister_id,
@@ -23,6 +23,7 @@ fn init_impl(init_args: UserIndexInitArgs, data: &mut CanisterData) {
         });
     data.allow_upgrades_for_individual_canisters = true;
     data.last_run_upgrade_status.version = init_args.version;
+    data.creator_token_limit = init_args.creator_token_limit;
 }
 
 #[cfg(test)]
@@ -72,6 +73,7 @@ mod test {
             known_principal_ids: Some(known_principal_ids),
             access_control_map: Some(access_control_map),
             version: String::from("v1.0.0"),
+            creator_token_limit: Some(5),
         };
         let mut data = CanisterData::default();
 
@@ -93,6 +95,7 @@ mod test {
                 .unwrap(),
             &get_mock_canister_id_user_index()
         );
-        assert!(data.last_run_upgrade_status.version.eq("v1.0.0"))
+        assert!(data.last_run_upgrade_status.version.eq("v1.0.0"));
+        assert_eq!(data.creator_token_limit, Some(5));
     }
 }
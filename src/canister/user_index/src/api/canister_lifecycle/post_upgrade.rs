@@ -5,12 +5,27 @@ use ic_cdk_macros::post_upgrade;
 use ic_stable_structures::Memory;
 use shared_utils::canister_specific::user_index::types::args::UserIndexInitArgs;
 
-use crate::{data_model::memory, CANISTER_DATA};
+use crate::{
+    api::snapshot::subnet_backup::restart_subnet_backups_after_upgrade, data_model::memory,
+    CANISTER_DATA,
+};
 
 #[post_upgrade]
 fn post_upgrade() {
     restore_data_from_stable_memory();
     update_version_from_args();
+    end_interrupted_sns_canisters_upgrades();
+    restart_subnet_backups_after_upgrade();
+}
+
+/// Rollouts don't survive an upgrade, they are rerun with the stored wasm
+fn end_interrupted_sns_canisters_upgrades() {
+    CANISTER_DATA.with_borrow_mut(|canister_data| {
+        canister_data
+            .creator_dao_sns_canisters_upgrade_status
+            .values_mut()
+            .for_each(|status| status.in_progress = false);
+    });
 }
 
 fn update_version_from_args() {
@@ -20,6 +35,10 @@ fn update_version_from_args() {
         let version_details = &mut canister_data.version_details;
         version_details.version = upgrade_args.version;
         version_details.version_number += 1;
+
+        if let Some(creator_token_limit) = upgrade_args.creator_token_limit {
+            canister_data.creator_token_limit = Some(creator_token_limit);
+        }
     });
 }
 
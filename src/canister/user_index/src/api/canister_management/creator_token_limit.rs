@@ -0,0 +1,53 @@
+use candid::Principal;
+use futures::{stream, StreamExt};
+use ic_cdk_macros::update;
+use shared_utils::common::utils::permissions::{
+    is_caller_controller, is_caller_controller_or_global_admin,
+};
+
+use crate::{
+    util::types::registered_individual_user_canister::RegisteredIndividualUserCanister,
+    CANISTER_DATA,
+};
+
+#[update(guard = "is_caller_controller")]
+pub fn update_creator_token_limit_for_all_individual_users(limit: usize) -> Result<(), String> {
+    let mut update_futs = CANISTER_DATA.with_borrow_mut(|cdata| {
+        cdata.creator_token_limit = Some(limit);
+        let cans = cdata
+            .user_principal_id_to_canister_id_map
+            .values()
+            .copied()
+            .chain(cdata.available_canisters.iter().copied())
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(move |can| ic_cdk::call::<_, ()>(can, "update_creator_token_limit", (limit,)));
+        let stream = stream::iter(cans);
+        stream.buffer_unordered(10)
+    });
+
+    ic_cdk::spawn(async move {
+        while let Some(res) = update_futs.next().await {
+            if let Err(e) = res {
+                ic_cdk::eprintln!(
+                    "failed to update_creator_token_limit. code: {:?}, err: {}",
+                    e.0,
+                    e.1
+                )
+            }
+        }
+    });
+
+    Ok(())
+}
+
+#[update(guard = "is_caller_controller_or_global_admin")]
+pub async fn set_creator_token_limit_override_for_individual_canister(
+    individual_canister_id: Principal,
+    limit: Option<usize>,
+) -> Result<(), String> {
+    let individual_user_canister = RegisteredIndividualUserCanister::new(individual_canister_id)?;
+    individual_user_canister
+        .set_creator_token_limit_override(limit)
+        .await
+}
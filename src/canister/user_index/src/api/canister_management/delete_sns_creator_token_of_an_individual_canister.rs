@@ -0,0 +1,25 @@
+use candid::Principal;
+use ic_cdk::api::call::RejectionCode;
+use ic_cdk_macros::update;
+use shared_utils::{
+    canister_specific::individual_user_template::types::{
+        cdao::CreatorTokenDeletionReport, error::DeleteCreatorTokenError,
+    },
+    common::utils::permissions::is_caller_controller_or_global_admin,
+};
+
+use crate::util::types::registered_individual_user_canister::RegisteredIndividualUserCanister;
+
+#[update(guard = "is_caller_controller_or_global_admin")]
+pub async fn delete_sns_creator_token_of_an_individual_canister(
+    individual_canister_id: Principal,
+    root: Principal,
+    force: bool,
+) -> Result<CreatorTokenDeletionReport, DeleteCreatorTokenError> {
+    let individual_user_canister = RegisteredIndividualUserCanister::new(individual_canister_id)
+        .map_err(|e| DeleteCreatorTokenError::CallError(RejectionCode::CanisterError, e))?;
+
+    individual_user_canister
+        .delete_sns_creator_token(root, force)
+        .await
+}
@@ -32,8 +32,10 @@ use crate::{
 
 pub mod allot_empty_canister;
 pub mod create_pool_of_available_canisters;
+pub mod creator_token_limit;
 pub mod delete_all_sns_creator_token_in_the_network;
 pub mod delete_all_sns_creator_token_of_an_individual_canister;
+pub mod delete_sns_creator_token_of_an_individual_canister;
 pub mod fixup_individual_canisters_mappings;
 pub mod get_last_broadcast_call_status;
 pub mod get_subnet_available_capacity;
@@ -50,6 +52,7 @@ pub mod reset_user_canister_ml_feed_cache;
 pub mod start_upgrades_for_individual_canisters;
 pub mod update_user_canister_restart_timers;
 pub mod upgrade_all_creator_dao_governance_canisters_in_the_network;
+pub mod upgrade_creator_dao_sns_canisters_in_the_subnet;
 
 #[update]
 pub async fn get_user_canister_status(
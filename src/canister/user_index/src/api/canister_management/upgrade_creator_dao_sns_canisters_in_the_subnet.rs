@@ -0,0 +1,228 @@
+use candid::Principal;
+use ic_cdk::call;
+use ic_cdk_macros::{query, update};
+use shared_utils::{
+    canister_specific::{
+        individual_user_template::types::cdao::DeployedCdaoCanisters,
+        user_index::types::{BroadcastCallStatus, CreatorDaoSnsCanistersUpgradeStatus},
+    },
+    common::{
+        types::wasm::{CanisterWasm, SnsWasmKey, SnsWasmType},
+        utils::{
+            permissions::is_caller_controller_or_global_admin,
+            system_time::get_current_system_time, task::run_task_concurrently,
+        },
+    },
+};
+
+use crate::CANISTER_DATA;
+
+/// Stores the wasm under its version and upgrades that canister type of every creator
+/// token in the subnet to it.
+#[update(guard = "is_caller_controller_or_global_admin")]
+pub fn upgrade_creator_dao_sns_canisters_in_the_subnet(
+    wasm_type: SnsWasmType,
+    version: String,
+    wasm_module: Vec<u8>,
+) -> Result<(), String> {
+    let sns_wasm_key = SnsWasmKey::new(wasm_type, version.clone())?;
+    start_rollout(wasm_type, &version)?;
+
+    CANISTER_DATA.with_borrow_mut(|canister_data| {
+        canister_data.sns_wasms.insert(
+            sns_wasm_key,
+            CanisterWasm {
+                wasm_blob: wasm_module.clone(),
+                version: version.clone(),
+            },
+        );
+    });
+
+    ic_cdk::spawn(upgrade_creator_dao_sns_canisters_for_individual_canisters(
+        wasm_type,
+        version,
+        wasm_module,
+    ));
+
+    Ok(())
+}
+
+/// Rolls out a version that was stored by an earlier upgrade, used to retry failed
+/// canisters or to go back to a previous version.
+#[update(guard = "is_caller_controller_or_global_admin")]
+pub fn upgrade_creator_dao_sns_canisters_in_the_subnet_with_stored_version(
+    wasm_type: SnsWasmType,
+    version: String,
+) -> Result<(), String> {
+    let sns_wasm_key = SnsWasmKey::new(wasm_type, version.clone())?;
+    let canister_wasm = CANISTER_DATA
+        .with_borrow(|canister_data| canister_data.sns_wasms.get(&sns_wasm_key))
+        .ok_or("Wasm not found for the given type and version")?;
+    start_rollout(wasm_type, &version)?;
+
+    ic_cdk::spawn(upgrade_creator_dao_sns_canisters_for_individual_canisters(
+        wasm_type,
+        version,
+        canister_wasm.wasm_blob,
+    ));
+
+    Ok(())
+}
+
+#[query]
+fn get_stored_sns_wasm_versions() -> Vec<SnsWasmKey> {
+    CANISTER_DATA.with_borrow(|canister_data| {
+        canister_data
+            .sns_wasms
+            .iter()
+            .map(|(sns_wasm_key, _)| sns_wasm_key)
+            .collect()
+    })
+}
+
+#[query]
+fn get_creator_dao_sns_canisters_upgrade_status(
+    wasm_type: SnsWasmType,
+) -> Option<CreatorDaoSnsCanistersUpgradeStatus> {
+    CANISTER_DATA.with_borrow(|canister_data| {
+        canister_data
+            .creator_dao_sns_canisters_upgrade_status
+            .get(&wasm_type)
+            .cloned()
+    })
+}
+
+/// Resets the status of the canister type for a new rollout. Rollouts of the same type
+/// would otherwise report into the same status.
+fn start_rollout(wasm_type: SnsWasmType, version: &str) -> Result<(), String> {
+    CANISTER_DATA.with_borrow_mut(|canister_data| {
+        let status = canister_data
+            .creator_dao_sns_canisters_upgrade_status
+            .entry(wasm_type)
+            .or_default();
+        if status.in_progress {
+            return Err(format!(
+                "An upgrade of the {:?} canisters is already in progress",
+                wasm_type
+            ));
+        }
+
+        *status = CreatorDaoSnsCanistersUpgradeStatus {
+            broadcast_status: BroadcastCallStatus {
+                method_name: format!(
+                    "upgrade_creator_dao_sns_canisters {:?} {}",
+                    wasm_type, version
+                ),
+                timestamp: get_current_system_time(),
+                ..Default::default()
+            },
+            up_to_date_canisters_count: 0,
+            in_progress: true,
+        };
+
+        Ok(())
+    })
+}
+
+async fn upgrade_creator_dao_sns_canisters_for_individual_canisters(
+    wasm_type: SnsWasmType,
+    version: String,
+    wasm_module: Vec<u8>,
+) {
+    let individual_canisters: Vec<Principal> = CANISTER_DATA.with_borrow(|canister_data| {
+        canister_data
+            .user_principal_id_to_canister_id_map
+            .values()
+            .copied()
+            .collect()
+    });
+
+    let futures = individual_canisters.into_iter().map(|individual_canister| {
+        let version = version.clone();
+        let wasm_module = wasm_module.clone();
+        async move {
+            upgrade_creator_dao_sns_canisters_of_individual_canister(
+                individual_canister,
+                wasm_type,
+                version,
+                wasm_module,
+            )
+            .await
+            .map(|upgraded| (individual_canister, upgraded))
+            .map_err(|e| (individual_canister, e))
+        }
+    });
+
+    let result_callback = |res: Result<(Principal, bool), (Principal, String)>| {
+        CANISTER_DATA.with_borrow_mut(|canister_data| {
+            let Some(status) = canister_data
+                .creator_dao_sns_canisters_upgrade_status
+                .get_mut(&wasm_type)
+            else {
+                return;
+            };
+
+            match res {
+                Ok((canister_id, true)) => {
+                    status
+                        .broadcast_status
+                        .successful_canister_ids
+                        .push(canister_id);
+                    status.broadcast_status.successful_canisters_count += 1;
+                }
+                Ok((_, false)) => status.up_to_date_canisters_count += 1,
+                Err(e) => {
+                    status.broadcast_status.failed_canister_ids.push(e);
+                    status.broadcast_status.failed_canisters_count += 1;
+                }
+            }
+            status.broadcast_status.total_canisters += 1;
+        })
+    };
+
+    run_task_concurrently(futures, 10, result_callback, || false).await;
+
+    CANISTER_DATA.with_borrow_mut(|canister_data| {
+        if let Some(status) = canister_data
+            .creator_dao_sns_canisters_upgrade_status
+            .get_mut(&wasm_type)
+        {
+            status.in_progress = false;
+        }
+    });
+}
+
+/// Only sends the wasm to individual canisters with creator tokens that are not on the
+/// version yet. Returns whether there was anything to upgrade.
+async fn upgrade_creator_dao_sns_canisters_of_individual_canister(
+    individual_canister: Principal,
+    wasm_type: SnsWasmType,
+    version: String,
+    wasm_module: Vec<u8>,
+) -> Result<bool, String> {
+    let (cdao_canisters,) = call::<_, (Vec<DeployedCdaoCanisters>,)>(
+        individual_canister,
+        "deployed_cdao_canisters",
+        (),
+    )
+    .await
+    .map_err(|e| e.1)?;
+
+    if cdao_canisters
+        .iter()
+        .all(|canisters| canisters.is_on_version(wasm_type, &version))
+    {
+        return Ok(false);
+    }
+
+    call::<_, (Result<(), String>,)>(
+        individual_canister,
+        "upgrade_creator_dao_sns_canisters",
+        (wasm_type, version, wasm_module),
+    )
+    .await
+    .map_err(|e| e.1)?
+    .0?;
+
+    Ok(true)
+}
@@ -0,0 +1,156 @@
+use candid::Principal;
+use ic_cdk::call;
+use ic_cdk_macros::{query, update};
+use shared_utils::{
+    canister_specific::user_index::types::{BroadcastCallStatus, CentsAirdropCampaignStatus},
+    common::utils::{
+        permissions::is_caller_controller, system_time::get_current_system_time,
+        task::run_task_concurrently,
+    },
+    types::cents_airdrop_campaign::{CentsAirdropCampaign, CentsAirdropClaimStatus},
+};
+
+use crate::{
+    util::types::subnet_orchestrator_operation::SubnetOrchestratorOperation, CANISTER_DATA,
+};
+
+#[update(guard = "is_caller_controller")]
+pub fn run_cents_airdrop_campaign(campaign: CentsAirdropCampaign) -> Result<(), String> {
+    if campaign.is_expired(get_current_system_time()) {
+        return Err("Campaign expired".into());
+    }
+
+    let campaign_run = CentsAirdropCampaignRun::new(campaign.campaign_id)?;
+    ic_cdk::spawn(issue_cents_airdrop_campaign_for_individual_canisters(
+        campaign,
+        campaign_run,
+    ));
+
+    Ok(())
+}
+
+#[query]
+fn get_cents_airdrop_campaign_status(campaign_id: u64) -> Option<CentsAirdropCampaignStatus> {
+    CANISTER_DATA.with_borrow(|canister_data| {
+        canister_data
+            .cents_airdrop_campaign_status
+            .get(&campaign_id)
+            .cloned()
+    })
+}
+
+async fn issue_cents_airdrop_campaign_for_individual_canisters(
+    campaign: CentsAirdropCampaign,
+    _campaign_run: CentsAirdropCampaignRun,
+) {
+    let campaign_id = campaign.campaign_id;
+
+    // individual canisters credit a campaign at most once, so rerunning a campaign only
+    // reaches the canisters that failed or were not eligible before
+    let all_canisters: Vec<Principal> = CANISTER_DATA.with_borrow_mut(|canister_data| {
+        canister_data.cents_airdrop_campaign_status.insert(
+            campaign_id,
+            CentsAirdropCampaignStatus {
+                broadcast_status: BroadcastCallStatus {
+                    method_name: "receive_cents_airdrop".into(),
+                    timestamp: get_current_system_time(),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        );
+
+        canister_data
+            .user_principal_id_to_canister_id_map
+            .values()
+            .copied()
+            .collect()
+    });
+
+    let futures = all_canisters.into_iter().map(|individual_canister| {
+        let campaign = campaign.clone();
+        async move {
+            let res = call::<_, (Result<CentsAirdropClaimStatus, String>,)>(
+                individual_canister,
+                "receive_cents_airdrop",
+                (campaign,),
+            )
+            .await
+            .map_err(|e| e.1)
+            .and_then(|res| res.0);
+
+            match res {
+                Ok(claim_status) => Ok((individual_canister, claim_status)),
+                Err(e) => Err((individual_canister, e)),
+            }
+        }
+    });
+
+    let result_callback =
+        |res: Result<(Principal, CentsAirdropClaimStatus), (Principal, String)>| {
+            CANISTER_DATA.with_borrow_mut(|canister_data| {
+                let Some(status) = canister_data
+                    .cents_airdrop_campaign_status
+                    .get_mut(&campaign_id)
+                else {
+                    return;
+                };
+
+                match res {
+                    Ok((canister_id, CentsAirdropClaimStatus::Credited)) => {
+                        status
+                            .broadcast_status
+                            .successful_canister_ids
+                            .push(canister_id);
+                        status.broadcast_status.successful_canisters_count += 1;
+                    }
+                    Ok((_, CentsAirdropClaimStatus::AlreadyCredited)) => {
+                        status.already_credited_canisters_count += 1;
+                    }
+                    Ok((_, CentsAirdropClaimStatus::NotEligible)) => {
+                        status.not_eligible_canisters_count += 1;
+                    }
+                    Ok((_, CentsAirdropClaimStatus::Expired)) => {
+                        status.expired_canisters_count += 1;
+                    }
+                    Err(e) => {
+                        status.broadcast_status.failed_canister_ids.push(e);
+                        status.broadcast_status.failed_canisters_count += 1;
+                    }
+                }
+                status.broadcast_status.total_canisters += 1;
+            })
+        };
+
+    run_task_concurrently(futures, 10, result_callback, || false).await;
+}
+
+/// Keeps a campaign from being run again while its previous run is still broadcasting, the
+/// new run would reset the status the previous one is still reporting to
+struct CentsAirdropCampaignRun {
+    campaign_id: u64,
+}
+
+impl CentsAirdropCampaignRun {
+    fn new(campaign_id: u64) -> Result<Self, String> {
+        CANISTER_DATA.with_borrow_mut(|canister_data| {
+            if canister_data.on_going_operation.insert(
+                SubnetOrchestratorOperation::RunCentsAirdropCampaign(campaign_id),
+            ) {
+                Ok(Self { campaign_id })
+            } else {
+                Err(format!("Campaign {} is already running", campaign_id))
+            }
+        })
+    }
+}
+
+impl Drop for CentsAirdropCampaignRun {
+    fn drop(&mut self) {
+        CANISTER_DATA.with_borrow_mut(|canister_data| {
+            canister_data.on_going_operation.remove(
+                &SubnetOrchestratorOperation::RunCentsAirdropCampaign(self.campaign_id),
+            )
+        });
+    }
+}
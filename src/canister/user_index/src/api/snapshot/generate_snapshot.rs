@@ -0,0 +1,291 @@
+use std::{cell::RefCell, ops::Bound, time::Duration};
+
+use candid::Principal;
+use ic_cdk_macros::{query, update};
+use ic_cdk_timers::TimerId;
+use serde::{Deserialize, Serialize};
+use shared_utils::{
+    common::{
+        types::snapshot::{
+            decode_chunked_snapshot, SnapshotCanisterType, SnapshotError, SnapshotProgress,
+        },
+        utils::{
+            chunked_snapshot::ChunkedSnapshotWriter, permissions::is_reclaim_canister_id,
+            system_time::get_current_system_time,
+        },
+    },
+    constant::SNAPSHOT_GENERATION_BATCH_SIZE,
+};
+
+use crate::{data_model::CanisterData, CANISTER_DATA, SNAPSHOT_STORE};
+
+use super::{load_snapshot::is_snapshot_load_in_progress, SNAPSHOT_SCHEMA_VERSION};
+
+/// Sections of a snapshot generated across several messages, the user maps are left
+/// out of the base and follow it in batches
+#[derive(Serialize, Deserialize)]
+pub(crate) enum CanisterDataSnapshotSection<D> {
+    Base(D),
+    UserCanisters(Vec<(Principal, Principal)>),
+    UniqueUserNames(Vec<(String, Principal)>),
+}
+
+enum NextSection {
+    Base,
+    UserCanistersAfter(Option<Principal>),
+    UniqueUserNamesAfter(Option<String>),
+}
+
+struct SnapshotGeneration {
+    writer: ChunkedSnapshotWriter,
+    next_section: NextSection,
+    timer: Option<TimerId>,
+}
+
+thread_local! {
+    // Generation does not survive upgrades, it has to be started again after one
+    static SNAPSHOT_GENERATION: RefCell<Option<SnapshotGeneration>> = const { RefCell::new(None) };
+}
+
+/// Writes a new snapshot into the snapshot store across several messages, so it does not run
+/// into the instruction limit. Poll `get_snapshot_progress` until it is completed and
+/// then fetch it with `download_snapshot`.
+#[update(guard = "is_reclaim_canister_id")]
+fn start_snapshot() -> Result<SnapshotProgress, SnapshotError> {
+    start_snapshot_generation()
+}
+
+pub(crate) fn start_snapshot_generation() -> Result<SnapshotProgress, SnapshotError> {
+    if is_snapshot_generation_in_progress() {
+        return Err(SnapshotError::GenerationInProgress);
+    }
+    if is_snapshot_load_in_progress() {
+        return Err(SnapshotError::LoadInProgress);
+    }
+
+    let total_items = CANISTER_DATA.with_borrow(|canister_data| {
+        (canister_data.user_principal_id_to_canister_id_map.len()
+            + canister_data
+                .unique_user_name_to_user_principal_id_map
+                .len()
+            + 1) as u64
+    });
+    let writer = SNAPSHOT_STORE.with_borrow_mut(|store| {
+        ChunkedSnapshotWriter::new(
+            store,
+            SnapshotCanisterType::SubnetOrchestrator,
+            SNAPSHOT_SCHEMA_VERSION,
+            get_current_system_time(),
+            total_items,
+        )
+    });
+    let progress = writer.progress();
+
+    SNAPSHOT_GENERATION.with_borrow_mut(|generation| {
+        *generation = Some(SnapshotGeneration {
+            writer,
+            next_section: NextSection::Base,
+            timer: None,
+        });
+    });
+    schedule_next_snapshot_section();
+
+    Ok(progress)
+}
+
+#[query(guard = "is_reclaim_canister_id")]
+fn get_snapshot_progress() -> Option<SnapshotProgress> {
+    snapshot_generation_progress()
+}
+
+pub(crate) fn snapshot_generation_progress() -> Option<SnapshotProgress> {
+    SNAPSHOT_GENERATION.with_borrow(|generation| {
+        generation
+            .as_ref()
+            .map(|generation| generation.writer.progress())
+    })
+}
+
+/// Stops a generation that is still running, the snapshot it was writing is left
+/// incomplete and dropped when the next one starts
+pub(crate) fn stop_snapshot_generation() {
+    if let Some(generation) = SNAPSHOT_GENERATION.take() {
+        if let Some(timer) = generation.timer {
+            ic_cdk_timers::clear_timer(timer);
+        }
+    }
+}
+
+pub(crate) fn canister_data_from_generated_snapshot(
+    bytes: &[u8],
+) -> Result<CanisterData, SnapshotError> {
+    let (_, sections) = decode_chunked_snapshot::<CanisterDataSnapshotSection<CanisterData>>(
+        bytes,
+        SnapshotCanisterType::SubnetOrchestrator,
+    )?;
+
+    let mut sections = sections.into_iter();
+    let Some(CanisterDataSnapshotSection::Base(mut canister_data)) = sections.next() else {
+        return Err(SnapshotError::Deserialize(
+            "Snapshot does not start with the canister data".into(),
+        ));
+    };
+
+    for section in sections {
+        match section {
+            CanisterDataSnapshotSection::UserCanisters(user_canisters) => canister_data
+                .user_principal_id_to_canister_id_map
+                .extend(user_canisters),
+            CanisterDataSnapshotSection::UniqueUserNames(unique_user_names) => canister_data
+                .unique_user_name_to_user_principal_id_map
+                .extend(unique_user_names),
+            CanisterDataSnapshotSection::Base(_) => {
+                return Err(SnapshotError::Deserialize(
+                    "Snapshot contains the canister data twice".into(),
+                ))
+            }
+        }
+    }
+
+    Ok(canister_data)
+}
+
+pub(crate) fn is_snapshot_generation_in_progress() -> bool {
+    SNAPSHOT_GENERATION.with_borrow(|generation| {
+        generation
+            .as_ref()
+            .is_some_and(|generation| generation.writer.is_in_progress())
+    })
+}
+
+fn schedule_next_snapshot_section() {
+    let timer = ic_cdk_timers::set_timer(Duration::ZERO, write_next_snapshot_section);
+    SNAPSHOT_GENERATION.with_borrow_mut(|generation| {
+        if let Some(generation) = generation {
+            generation.timer = Some(timer);
+        }
+    });
+}
+
+fn write_next_snapshot_section() {
+    let has_more_sections = SNAPSHOT_GENERATION.with_borrow_mut(|generation| {
+        let Some(generation) = generation else {
+            return false;
+        };
+        generation.timer = None;
+
+        match generation.write_next_section() {
+            Ok(has_more_sections) => has_more_sections,
+            Err(e) => {
+                ic_cdk::println!("Failed generating snapshot. Error: {:?}", e);
+                SNAPSHOT_STORE.with_borrow_mut(|store| {
+                    generation.writer.fail(store, &e, get_current_system_time())
+                });
+                false
+            }
+        }
+    });
+
+    if has_more_sections {
+        schedule_next_snapshot_section();
+    }
+}
+
+fn lower_bound_after<K>(last_key: Option<K>) -> Bound<K> {
+    match last_key {
+        Some(key) => Bound::Excluded(key),
+        None => Bound::Unbounded,
+    }
+}
+
+impl SnapshotGeneration {
+    /// Returns false once the snapshot is completed
+    fn write_next_section(&mut self) -> Result<bool, SnapshotError> {
+        match &self.next_section {
+            NextSection::Base => {
+                // the maps are written in their own sections, they are only taken out
+                // for the time it takes to write the rest
+                CANISTER_DATA.with_borrow_mut(|canister_data| {
+                    let user_canisters =
+                        std::mem::take(&mut canister_data.user_principal_id_to_canister_id_map);
+                    let unique_user_names = std::mem::take(
+                        &mut canister_data.unique_user_name_to_user_principal_id_map,
+                    );
+
+                    let res = SNAPSHOT_STORE.with_borrow_mut(|store| {
+                        self.writer.write_section(
+                            store,
+                            &CanisterDataSnapshotSection::Base(&*canister_data),
+                            1,
+                        )
+                    });
+
+                    canister_data.user_principal_id_to_canister_id_map = user_canisters;
+                    canister_data.unique_user_name_to_user_principal_id_map = unique_user_names;
+                    res
+                })?;
+                self.next_section = NextSection::UserCanistersAfter(None);
+            }
+            NextSection::UserCanistersAfter(last_user) => {
+                let user_canisters: Vec<(Principal, Principal)> =
+                    CANISTER_DATA.with_borrow(|canister_data| {
+                        canister_data
+                            .user_principal_id_to_canister_id_map
+                            .range((lower_bound_after(*last_user), Bound::Unbounded))
+                            .take(SNAPSHOT_GENERATION_BATCH_SIZE)
+                            .map(|(user, canister)| (*user, *canister))
+                            .collect()
+                    });
+
+                let Some((next_last_user, _)) = user_canisters.last() else {
+                    self.next_section = NextSection::UniqueUserNamesAfter(None);
+                    return Ok(true);
+                };
+                self.next_section = NextSection::UserCanistersAfter(Some(*next_last_user));
+
+                let user_canisters_len = user_canisters.len() as u64;
+                SNAPSHOT_STORE.with_borrow_mut(|store| {
+                    self.writer.write_section(
+                        store,
+                        &CanisterDataSnapshotSection::<()>::UserCanisters(user_canisters),
+                        user_canisters_len,
+                    )
+                })?;
+            }
+            NextSection::UniqueUserNamesAfter(last_user_name) => {
+                let unique_user_names: Vec<(String, Principal)> =
+                    CANISTER_DATA.with_borrow(|canister_data| {
+                        canister_data
+                            .unique_user_name_to_user_principal_id_map
+                            .range::<String, _>((
+                                lower_bound_after(last_user_name.as_ref()),
+                                Bound::Unbounded,
+                            ))
+                            .take(SNAPSHOT_GENERATION_BATCH_SIZE)
+                            .map(|(user_name, user)| (user_name.clone(), *user))
+                            .collect()
+                    });
+
+                let Some((next_last_user_name, _)) = unique_user_names.last() else {
+                    SNAPSHOT_STORE.with_borrow_mut(|store| {
+                        self.writer.finish(store, get_current_system_time())
+                    })?;
+                    return Ok(false);
+                };
+                self.next_section =
+                    NextSection::UniqueUserNamesAfter(Some(next_last_user_name.clone()));
+
+                let unique_user_names_len = unique_user_names.len() as u64;
+                SNAPSHOT_STORE.with_borrow_mut(|store| {
+                    self.writer.write_section(
+                        store,
+                        &CanisterDataSnapshotSection::<()>::UniqueUserNames(unique_user_names),
+                        unique_user_names_len,
+                    )
+                })?;
+            }
+        }
+
+        Ok(true)
+    }
+}
@@ -0,0 +1,207 @@
+use std::{cell::RefCell, time::Duration};
+
+use ic_cdk_macros::{query, update};
+use ic_cdk_timers::TimerId;
+use ic_stable_structures::Memory;
+use shared_utils::{
+    common::{
+        types::snapshot::{
+            SnapshotCanisterType, SnapshotError, SnapshotGenerationState, SnapshotProgress,
+        },
+        utils::{
+            chunked_snapshot::ChunkedSnapshotReader, permissions::is_reclaim_canister_id,
+            snapshot_store::SnapshotStore, system_time::get_current_system_time,
+        },
+    },
+    constant::SNAPSHOT_LOAD_VERIFY_BATCH_BYTES,
+};
+
+use crate::{data_model::CanisterData, CANISTER_DATA, SNAPSHOT_STORE};
+
+use super::{
+    generate_snapshot::CanisterDataSnapshotSection, load_latest_snapshot,
+    stored_snapshots::get_latest_snapshot_id, transfer_snapshot::is_snapshot_upload_in_progress,
+};
+
+struct SnapshotLoad {
+    reader: ChunkedSnapshotReader,
+    is_base_loaded: bool,
+    timer: Option<TimerId>,
+}
+
+thread_local! {
+    // Loading does not survive upgrades, it has to be started again after one
+    static SNAPSHOT_LOAD: RefCell<Option<SnapshotLoad>> = const { RefCell::new(None) };
+}
+
+/// Loads the latest snapshot across several messages, so it does not run into the
+/// instruction limit. Poll `get_snapshot_load_progress` until it is completed. The
+/// snapshot is verified before anything is replaced, the user maps are then filled in
+/// batches. JSON snapshots are loaded right away.
+#[update(guard = "is_reclaim_canister_id")]
+fn start_snapshot_load() -> Result<SnapshotProgress, SnapshotError> {
+    if is_snapshot_load_in_progress() {
+        return Err(SnapshotError::LoadInProgress);
+    }
+    if is_snapshot_upload_in_progress() {
+        return Err(SnapshotError::UploadInProgress);
+    }
+
+    let snapshot_id = get_latest_snapshot_id()?;
+    let started_at = get_current_system_time();
+    let reader = SNAPSHOT_STORE.with_borrow(|store| {
+        ChunkedSnapshotReader::new(
+            store,
+            snapshot_id,
+            SnapshotCanisterType::SubnetOrchestrator,
+            started_at,
+        )
+    });
+    let reader = match reader {
+        Ok(reader) => reader,
+        Err(SnapshotError::InvalidHeader(_)) => {
+            load_latest_snapshot()?;
+            let snapshot_len = SNAPSHOT_STORE
+                .with_borrow(|store| store.get(snapshot_id))
+                .map(|info| info.size)
+                .unwrap_or_default();
+            return Ok(SnapshotProgress {
+                snapshot_id,
+                state: SnapshotGenerationState::Completed,
+                started_at,
+                finished_at: Some(get_current_system_time()),
+                items_written: 1,
+                total_items: 1,
+                bytes_written: snapshot_len,
+            });
+        }
+        Err(e) => return Err(e),
+    };
+    let progress = reader.progress();
+
+    SNAPSHOT_LOAD.with_borrow_mut(|load| {
+        *load = Some(SnapshotLoad {
+            reader,
+            is_base_loaded: false,
+            timer: None,
+        });
+    });
+    schedule_next_snapshot_load_step();
+
+    Ok(progress)
+}
+
+#[query(guard = "is_reclaim_canister_id")]
+fn get_snapshot_load_progress() -> Option<SnapshotProgress> {
+    SNAPSHOT_LOAD.with_borrow(|load| load.as_ref().map(|load| load.reader.progress()))
+}
+
+pub(crate) fn is_snapshot_load_in_progress() -> bool {
+    SNAPSHOT_LOAD.with_borrow(|load| {
+        load.as_ref()
+            .is_some_and(|load| load.reader.is_in_progress())
+    })
+}
+
+/// Stops a load that is still running, the state is left with the sections loaded so far
+pub(crate) fn stop_snapshot_load() {
+    if let Some(load) = SNAPSHOT_LOAD.take() {
+        if let Some(timer) = load.timer {
+            ic_cdk_timers::clear_timer(timer);
+        }
+    }
+}
+
+fn schedule_next_snapshot_load_step() {
+    let timer = ic_cdk_timers::set_timer(Duration::ZERO, load_next_snapshot_step);
+    SNAPSHOT_LOAD.with_borrow_mut(|load| {
+        if let Some(load) = load {
+            load.timer = Some(timer);
+        }
+    });
+}
+
+fn load_next_snapshot_step() {
+    let has_more_steps = SNAPSHOT_LOAD.with_borrow_mut(|load| {
+        let Some(load) = load else {
+            return false;
+        };
+        load.timer = None;
+
+        match SNAPSHOT_STORE.with_borrow(|store| load.load_next_step(store)) {
+            Ok(has_more_steps) => has_more_steps,
+            Err(e) => {
+                ic_cdk::println!("Failed loading snapshot. Error: {:?}", e);
+                load.reader.fail(&e, get_current_system_time());
+                false
+            }
+        }
+    });
+
+    if has_more_steps {
+        schedule_next_snapshot_load_step();
+    }
+}
+
+impl SnapshotLoad {
+    /// Verifies part of the snapshot or loads its next section. Returns false once the
+    /// snapshot is loaded.
+    fn load_next_step<M: Memory>(
+        &mut self,
+        store: &SnapshotStore<M>,
+    ) -> Result<bool, SnapshotError> {
+        if !self.reader.is_verified() {
+            self.reader
+                .verify_next(store, SNAPSHOT_LOAD_VERIFY_BATCH_BYTES)?;
+            return Ok(true);
+        }
+
+        let Some(section) = self
+            .reader
+            .read_next_section::<_, CanisterDataSnapshotSection<CanisterData>>(store)?
+        else {
+            if !self.is_base_loaded {
+                return Err(SnapshotError::Deserialize(
+                    "Snapshot does not contain the canister data".into(),
+                ));
+            }
+            self.reader.finish(get_current_system_time());
+            return Ok(false);
+        };
+
+        match section {
+            CanisterDataSnapshotSection::Base(_) if self.is_base_loaded => {
+                return Err(SnapshotError::Deserialize(
+                    "Snapshot contains the canister data twice".into(),
+                ));
+            }
+            CanisterDataSnapshotSection::Base(canister_data_snapshot) => {
+                CANISTER_DATA.with_borrow_mut(|canister_data| {
+                    *canister_data = canister_data_snapshot;
+                });
+                self.is_base_loaded = true;
+            }
+            _ if !self.is_base_loaded => {
+                return Err(SnapshotError::Deserialize(
+                    "Snapshot does not start with the canister data".into(),
+                ));
+            }
+            CanisterDataSnapshotSection::UserCanisters(user_canisters) => {
+                CANISTER_DATA.with_borrow_mut(|canister_data| {
+                    canister_data
+                        .user_principal_id_to_canister_id_map
+                        .extend(user_canisters)
+                });
+            }
+            CanisterDataSnapshotSection::UniqueUserNames(unique_user_names) => {
+                CANISTER_DATA.with_borrow_mut(|canister_data| {
+                    canister_data
+                        .unique_user_name_to_user_principal_id_map
+                        .extend(unique_user_names)
+                });
+            }
+        }
+
+        Ok(true)
+    }
+}
@@ -0,0 +1,414 @@
+use std::cell::RefCell;
+
+use candid::Principal;
+use ic_cdk::{
+    api::management_canister::main::{uninstall_code, CanisterIdRecord},
+    call,
+};
+use ic_cdk_macros::{query, update};
+use shared_utils::{
+    canister_specific::{
+        individual_user_template::types::profile::UserProfileDetailsForFrontend,
+        user_index::types::{RestoredUserCanister, UsernameConflict},
+    },
+    common::{
+        types::{
+            snapshot::{
+                SnapshotError, SnapshotGenerationState, SnapshotInfo, SnapshotManifest,
+                SnapshotProgress,
+            },
+            wasm::WasmType,
+        },
+        utils::{
+            permissions::{is_caller_controller_or_global_admin, is_reclaim_canister_id},
+            snapshot_store::SnapshotStore,
+            snapshot_transfer::{get_chunk_sha256, PendingSnapshotUpload},
+            system_time::get_current_system_time,
+        },
+    },
+    constant::{MAX_STORED_RESTORE_SNAPSHOTS, SNAPSHOT_TRANSFER_CHUNK_SIZE},
+};
+
+use crate::{
+    data_model::{
+        memory::{
+            get_restore_snapshot_chunks_memory, get_restore_snapshot_memory,
+            get_restore_snapshot_next_id_memory, Memory,
+        },
+        CanisterData,
+    },
+    util::{
+        canister_management::{install_canister_wasm, recharge_canister_for_installing_wasm},
+        types::subnet_orchestrator_operation::SubnetOrchestratorOperation,
+    },
+    CANISTER_DATA, RESTORE_SNAPSHOT_STORE,
+};
+
+// every check is a round trip to the restored canister, which loads a section meanwhile
+const MAX_SNAPSHOT_LOAD_PROGRESS_CHECKS: u32 = 1000;
+
+thread_local! {
+    static RESTORE_SNAPSHOT_UPLOAD: RefCell<PendingSnapshotUpload> =
+        const { RefCell::new(PendingSnapshotUpload::new()) };
+}
+
+/// Snapshots of user canisters are kept apart from the snapshots of this canister, so
+/// they are never loaded here and don't push its own snapshots out
+pub(crate) fn _default_restore_snapshot_store() -> SnapshotStore<Memory> {
+    SnapshotStore::init(
+        get_restore_snapshot_memory(),
+        get_restore_snapshot_chunks_memory(),
+        get_restore_snapshot_next_id_memory(),
+        MAX_STORED_RESTORE_SNAPSHOTS,
+    )
+}
+
+#[update(guard = "is_reclaim_canister_id")]
+fn start_restore_snapshot_upload(manifest: SnapshotManifest) -> Result<(), SnapshotError> {
+    RESTORE_SNAPSHOT_STORE.with_borrow_mut(|store| {
+        RESTORE_SNAPSHOT_UPLOAD
+            .with_borrow_mut(|upload| upload.start(store, manifest, get_current_system_time()))
+    })
+}
+
+#[update(guard = "is_reclaim_canister_id")]
+fn upload_restore_snapshot_chunk(
+    chunk_index: u64,
+    chunk: Vec<u8>,
+    chunk_sha256: Vec<u8>,
+) -> Result<(), SnapshotError> {
+    RESTORE_SNAPSHOT_STORE.with_borrow_mut(|store| {
+        RESTORE_SNAPSHOT_UPLOAD
+            .with_borrow_mut(|upload| upload.write_chunk(store, chunk_index, &chunk, &chunk_sha256))
+    })
+}
+
+#[update(guard = "is_reclaim_canister_id")]
+fn finalize_restore_snapshot_upload() -> Result<SnapshotManifest, SnapshotError> {
+    RESTORE_SNAPSHOT_STORE.with_borrow_mut(|store| {
+        RESTORE_SNAPSHOT_UPLOAD
+            .with_borrow_mut(|upload| upload.finish(store, get_current_system_time()))
+    })
+}
+
+#[query(guard = "is_reclaim_canister_id")]
+fn list_restore_snapshots() -> Vec<SnapshotInfo> {
+    RESTORE_SNAPSHOT_STORE.with_borrow(|store| store.list())
+}
+
+#[update(guard = "is_reclaim_canister_id")]
+fn delete_restore_snapshot(id: u64) -> Result<(), SnapshotError> {
+    RESTORE_SNAPSHOT_STORE.with_borrow_mut(|store| store.try_delete(id))
+}
+
+/// Recovers the account of a user whose canister was lost onto a canister from the backup
+/// pool. The snapshot is uploaded to this canister first, through
+/// `start_restore_snapshot_upload`, so it is not bound by the size of a single message. The
+/// latest individual wasm is installed, the snapshot is uploaded and loaded across several
+/// messages, and the user's principal and username are pointed at the new canister. The
+/// snapshot is deleted once restored, the pool canister is wiped and returned to the pool
+/// on failure.
+#[update(guard = "is_caller_controller_or_global_admin")]
+async fn restore_user_canister_from_snapshot(
+    user_principal_id: Principal,
+    snapshot_id: u64,
+) -> Result<RestoredUserCanister, String> {
+    let _restore_operation = RestoreUserCanister::new(user_principal_id)?;
+
+    let manifest = RESTORE_SNAPSHOT_STORE.with_borrow(|store| {
+        let snapshot = store
+            .get(snapshot_id)
+            .ok_or(format!("Snapshot {} not found", snapshot_id))?;
+        if snapshot.completed_at.is_none() {
+            return Err(format!(
+                "Snapshot {} is not a finalized upload",
+                snapshot_id
+            ));
+        }
+
+        store
+            .manifest(snapshot_id, SNAPSHOT_TRANSFER_CHUNK_SIZE)
+            .map_err(|e| format!("{:?}", e))
+    })?;
+
+    let canister_id = CANISTER_DATA.with_borrow_mut(|canister_data| {
+        let canister_id = canister_data
+            .backup_canister_pool
+            .iter()
+            .next()
+            .copied()
+            .ok_or(String::from("No Backup Canisters Available"))?;
+        canister_data.backup_canister_pool.remove(&canister_id);
+
+        Ok::<_, String>(canister_id)
+    })?;
+
+    let unique_user_name =
+        match restore_snapshot_onto_canister(canister_id, user_principal_id, snapshot_id, manifest)
+            .await
+        {
+            Ok(unique_user_name) => unique_user_name,
+            Err(e) => {
+                return_canister_to_backup_pool(canister_id).await;
+                return Err(e);
+            }
+        };
+
+    let username_conflict = CANISTER_DATA.with_borrow_mut(|canister_data| {
+        register_restored_user_canister(
+            canister_data,
+            user_principal_id,
+            canister_id,
+            unique_user_name,
+        )
+    });
+    RESTORE_SNAPSHOT_STORE.with_borrow_mut(|store| store.delete(snapshot_id));
+
+    Ok(RestoredUserCanister {
+        canister_id,
+        username_conflict,
+    })
+}
+
+/// Points the user at the restored canister. The username is only pointed at the user if
+/// no one else took it in the meantime, otherwise the conflict is returned.
+fn register_restored_user_canister(
+    canister_data: &mut CanisterData,
+    user_principal_id: Principal,
+    canister_id: Principal,
+    unique_user_name: Option<String>,
+) -> Option<UsernameConflict> {
+    canister_data
+        .user_principal_id_to_canister_id_map
+        .insert(user_principal_id, canister_id);
+
+    let unique_user_name = unique_user_name?;
+    if let Some(taken_by) = canister_data
+        .unique_user_name_to_user_principal_id_map
+        .get(&unique_user_name)
+        .filter(|principal_id| **principal_id != user_principal_id)
+    {
+        return Some(UsernameConflict {
+            unique_user_name,
+            taken_by: *taken_by,
+        });
+    }
+
+    canister_data
+        .unique_user_name_to_user_principal_id_map
+        .retain(|_, principal_id| *principal_id != user_principal_id);
+    canister_data
+        .unique_user_name_to_user_principal_id_map
+        .insert(unique_user_name, user_principal_id);
+
+    None
+}
+
+/// Returns the username of the restored profile
+async fn restore_snapshot_onto_canister(
+    canister_id: Principal,
+    user_principal_id: Principal,
+    snapshot_id: u64,
+    manifest: SnapshotManifest,
+) -> Result<Option<String>, String> {
+    let individual_user_template_canister_wasm = CANISTER_DATA.with_borrow(|canister_data| {
+        canister_data
+            .wasms
+            .get(&WasmType::IndividualUserWasm)
+            .cloned()
+            .ok_or(String::from("Individual user wasm not found"))
+    })?;
+
+    recharge_canister_for_installing_wasm(canister_id).await?;
+    install_canister_wasm(
+        canister_id,
+        Some(user_principal_id),
+        individual_user_template_canister_wasm.version,
+        individual_user_template_canister_wasm.wasm_blob,
+    )
+    .await
+    .map_err(|e| e.1)?;
+
+    call_snapshot_endpoint(canister_id, "start_snapshot_upload", (manifest.clone(),)).await?;
+    for chunk_index in 0..manifest.chunk_count {
+        // read from the store again for every chunk, the snapshot can be deleted meanwhile
+        let chunk = RESTORE_SNAPSHOT_STORE
+            .with_borrow(|store| store.read_chunk(snapshot_id, manifest.chunk_size, chunk_index))
+            .map_err(|e| format!("{:?}", e))?;
+        let chunk_sha256 = get_chunk_sha256(&chunk);
+        call_snapshot_endpoint(
+            canister_id,
+            "upload_snapshot_chunk",
+            (chunk_index, chunk, chunk_sha256),
+        )
+        .await?;
+    }
+    call::<_, (Result<SnapshotManifest, SnapshotError>,)>(
+        canister_id,
+        "finalize_snapshot_upload",
+        (),
+    )
+    .await
+    .map_err(|e| e.1)?
+    .0
+    .map_err(|e| format!("{:?}", e))?;
+    load_snapshot_on_canister(canister_id).await?;
+
+    let (profile,): (UserProfileDetailsForFrontend,) = call(canister_id, "get_profile_details", ())
+        .await
+        .map_err(|e| e.1)?;
+    if profile.principal_id != user_principal_id {
+        return Err(format!(
+            "Snapshot belongs to {} instead of {}",
+            profile.principal_id, user_principal_id
+        ));
+    }
+
+    Ok(profile.unique_user_name)
+}
+
+/// Starts loading the uploaded snapshot and waits until the canister has loaded it
+async fn load_snapshot_on_canister(canister_id: Principal) -> Result<(), String> {
+    let (progress,) = call::<_, (Result<SnapshotProgress, SnapshotError>,)>(
+        canister_id,
+        "start_snapshot_load",
+        (),
+    )
+    .await
+    .map_err(|e| e.1)?;
+    let mut progress = Some(progress.map_err(|e| format!("start_snapshot_load failed: {:?}", e))?);
+
+    for _ in 0..MAX_SNAPSHOT_LOAD_PROGRESS_CHECKS {
+        match progress.map(|progress| progress.state) {
+            Some(SnapshotGenerationState::Completed) => return Ok(()),
+            Some(SnapshotGenerationState::Failed(e)) => {
+                return Err(format!("Loading the snapshot failed: {}", e))
+            }
+            Some(SnapshotGenerationState::InProgress) => {}
+            None => return Err(String::from("Snapshot load was stopped")),
+        }
+
+        (progress,) = call(canister_id, "get_snapshot_load_progress", ())
+            .await
+            .map_err(|e| e.1)?;
+    }
+
+    Err(String::from("Timed out waiting for the snapshot to load"))
+}
+
+async fn call_snapshot_endpoint<T: candid::utils::ArgumentEncoder>(
+    canister_id: Principal,
+    method: &str,
+    args: T,
+) -> Result<(), String> {
+    call::<_, (Result<(), SnapshotError>,)>(canister_id, method, args)
+        .await
+        .map_err(|e| e.1)?
+        .0
+        .map_err(|e| format!("{} failed: {:?}", method, e))
+}
+
+async fn return_canister_to_backup_pool(canister_id: Principal) {
+    match uninstall_code(CanisterIdRecord { canister_id }).await {
+        Ok(()) => {
+            CANISTER_DATA.with_borrow_mut(|canister_data| {
+                canister_data.backup_canister_pool.insert(canister_id)
+            });
+        }
+        Err(e) => ic_cdk::println!(
+            "Error uninstalling code of canister {} {}",
+            canister_id,
+            e.1
+        ),
+    }
+}
+
+/// Keeps two restores of the same user from racing for the user's canister entry
+struct RestoreUserCanister {
+    user_principal_id: Principal,
+}
+
+impl RestoreUserCanister {
+    fn new(user_principal_id: Principal) -> Result<Self, String> {
+        CANISTER_DATA.with_borrow_mut(|canister_data| {
+            if canister_data.on_going_operation.insert(
+                SubnetOrchestratorOperation::RestoreUserCanister(user_principal_id),
+            ) {
+                Ok(Self { user_principal_id })
+            } else {
+                Err(format!(
+                    "ongoing operation for restoring user canister of {}",
+                    user_principal_id
+                ))
+            }
+        })
+    }
+}
+
+impl Drop for RestoreUserCanister {
+    fn drop(&mut self) {
+        CANISTER_DATA.with_borrow_mut(|canister_data| {
+            canister_data.on_going_operation.remove(
+                &SubnetOrchestratorOperation::RestoreUserCanister(self.user_principal_id),
+            )
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use test_utils::setup::test_constants::{
+        get_mock_user_alice_canister_id, get_mock_user_alice_principal_id,
+        get_mock_user_bob_principal_id,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_restored_user_keeps_username_only_if_not_taken() {
+        let mut canister_data = CanisterData::default();
+        let alice_principal_id = get_mock_user_alice_principal_id();
+        let bob_principal_id = get_mock_user_bob_principal_id();
+        let canister_id = get_mock_user_alice_canister_id();
+        canister_data
+            .unique_user_name_to_user_principal_id_map
+            .insert("old_alice".into(), alice_principal_id);
+
+        let conflict = register_restored_user_canister(
+            &mut canister_data,
+            alice_principal_id,
+            canister_id,
+            Some("cool_alice".into()),
+        );
+        assert_eq!(conflict, None);
+        assert_eq!(
+            canister_data.unique_user_name_to_user_principal_id_map,
+            [("cool_alice".to_string(), alice_principal_id)].into()
+        );
+
+        canister_data
+            .unique_user_name_to_user_principal_id_map
+            .insert("bob_the_builder".into(), bob_principal_id);
+        let conflict = register_restored_user_canister(
+            &mut canister_data,
+            alice_principal_id,
+            canister_id,
+            Some("bob_the_builder".into()),
+        );
+        assert_eq!(
+            conflict,
+            Some(UsernameConflict {
+                unique_user_name: "bob_the_builder".into(),
+                taken_by: bob_principal_id,
+            })
+        );
+        assert_eq!(
+            canister_data.unique_user_name_to_user_principal_id_map["bob_the_builder"],
+            bob_principal_id
+        );
+        assert_eq!(
+            canister_data.user_principal_id_to_canister_id_map[&alice_principal_id],
+            canister_id
+        );
+    }
+}
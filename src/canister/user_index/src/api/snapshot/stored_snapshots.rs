@@ -0,0 +1,64 @@
+use ic_cdk_macros::{query, update};
+use shared_utils::{
+    common::{
+        types::snapshot::{SnapshotError, SnapshotInfo, SnapshotSource},
+        utils::{
+            permissions::is_reclaim_canister_id, snapshot_store::SnapshotStore,
+            system_time::get_current_system_time,
+        },
+    },
+    constant::MAX_STORED_SNAPSHOTS,
+};
+
+use crate::{
+    data_model::memory::{
+        get_snapshot_chunks_memory, get_snapshot_memory, get_snapshot_next_id_memory, Memory,
+    },
+    SNAPSHOT_STORE,
+};
+
+use super::generate_snapshot::is_snapshot_generation_in_progress;
+
+pub(crate) fn _default_snapshot_store() -> SnapshotStore<Memory> {
+    SnapshotStore::init(
+        get_snapshot_memory(),
+        get_snapshot_chunks_memory(),
+        get_snapshot_next_id_memory(),
+        MAX_STORED_SNAPSHOTS,
+    )
+}
+
+#[query(guard = "is_reclaim_canister_id")]
+fn list_snapshots() -> Vec<SnapshotInfo> {
+    SNAPSHOT_STORE.with_borrow(|store| store.list())
+}
+
+#[update(guard = "is_reclaim_canister_id")]
+fn delete_snapshot(id: u64) -> Result<(), SnapshotError> {
+    SNAPSHOT_STORE.with_borrow_mut(|store| store.try_delete(id))
+}
+
+pub(crate) fn save_snapshot(
+    source: SnapshotSource,
+    bytes: &[u8],
+) -> Result<SnapshotInfo, SnapshotError> {
+    SNAPSHOT_STORE.with_borrow_mut(|store| store.save(source, bytes, get_current_system_time()))
+}
+
+/// A generation that is still running is waited for instead of handing out an older
+/// snapshot
+pub(crate) fn get_latest_snapshot_id() -> Result<u64, SnapshotError> {
+    if is_snapshot_generation_in_progress() {
+        return Err(SnapshotError::GenerationInProgress);
+    }
+
+    SNAPSHOT_STORE.with_borrow(|store| store.latest_completed_id())
+}
+
+pub(crate) fn read_latest_snapshot() -> Result<Vec<u8>, SnapshotError> {
+    if is_snapshot_generation_in_progress() {
+        return Err(SnapshotError::GenerationInProgress);
+    }
+
+    SNAPSHOT_STORE.with_borrow(|store| store.read_latest())
+}
@@ -0,0 +1,275 @@
+use std::{cell::RefCell, time::Duration};
+
+use candid::Principal;
+use ic_cdk::call;
+use ic_cdk_macros::{query, update};
+use ic_cdk_timers::TimerId;
+use shared_utils::{
+    canister_specific::user_index::types::{BroadcastCallStatus, SubnetBackupStatus},
+    common::{
+        types::snapshot::{BackupCatalogEntry, SnapshotError, SnapshotProgress},
+        utils::{
+            permissions::is_caller_controller, system_time::get_current_system_time,
+            task::run_task_concurrently,
+        },
+    },
+    constant::{BACKUP_SNAPSHOT_POLL_INTERVAL_SECS, MAX_STORED_SNAPSHOTS},
+};
+
+use crate::{
+    data_model::subnet_backup::{GeneratingSnapshot, SubnetBackupRun},
+    CANISTER_DATA,
+};
+
+use super::generate_snapshot::{snapshot_generation_progress, start_snapshot_generation};
+
+// individual canisters generating a snapshot at once
+const SUBNET_BACKUP_CONCURRENCY: usize = 10;
+
+thread_local! {
+    static SUBNET_BACKUP_TIMER: RefCell<Option<TimerId>> = const { RefCell::new(None) };
+}
+
+/// Called by the platform orchestrator for each scheduled backup. Starts the snapshot of
+/// the subnet orchestrator and queues every individual canister in the subnet, a timer
+/// then takes the backup forward. Returns once the backup is started, the platform
+/// orchestrator polls `get_subnet_backup_status` until it is finished.
+#[update(guard = "is_caller_controller")]
+fn start_subnet_backup(backup_id: u64) -> Result<(), String> {
+    if CANISTER_DATA
+        .with_borrow(|canister_data| canister_data.backup_status.contains_key(&backup_id))
+    {
+        return Err(format!("Backup {} was already started", backup_id));
+    }
+
+    // individual canisters are backed up even if the subnet orchestrator's own snapshot
+    // could not be started
+    let own_snapshot = start_snapshot_generation().map_err(|e| format!("{:?}", e));
+
+    CANISTER_DATA.with_borrow_mut(|canister_data| {
+        let mut run = SubnetBackupRun {
+            pending_canisters: canister_data
+                .user_principal_id_to_canister_id_map
+                .values()
+                .copied()
+                .collect(),
+            ..Default::default()
+        };
+        let mut status = SubnetBackupStatus {
+            broadcast_status: BroadcastCallStatus {
+                method_name: "start_snapshot".into(),
+                timestamp: get_current_system_time(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        match own_snapshot {
+            Ok(started) => {
+                run.generating.insert(
+                    ic_cdk::id(),
+                    GeneratingSnapshot {
+                        snapshot_id: started.snapshot_id,
+                        polls: 0,
+                    },
+                );
+            }
+            Err(e) => record_failure(&mut status, ic_cdk::id(), e),
+        }
+        status.pending_canisters_count = run.remaining_canisters_count();
+
+        canister_data.backup_status.insert(backup_id, status);
+        canister_data.subnet_backup_runs.insert(backup_id, run);
+        // older runs point to snapshots the canisters no longer keep
+        while canister_data.backup_status.len() > MAX_STORED_SNAPSHOTS {
+            if let Some((backup_id, _)) = canister_data.backup_status.pop_first() {
+                canister_data.subnet_backup_runs.remove(&backup_id);
+            }
+        }
+    });
+
+    schedule_subnet_backup_step();
+
+    Ok(())
+}
+
+#[query]
+fn get_subnet_backup_status(backup_id: u64) -> Option<SubnetBackupStatus> {
+    CANISTER_DATA.with_borrow(|canister_data| canister_data.backup_status.get(&backup_id).cloned())
+}
+
+/// Snapshots the scheduled backups started on the canister that it may still keep
+#[query]
+fn get_backups_of_canister(canister_id: Principal) -> Vec<BackupCatalogEntry> {
+    CANISTER_DATA.with_borrow(|canister_data| canister_data.backup_catalog.get(&canister_id))
+}
+
+/// Timers do not survive upgrades, backups left unfinished are taken forward again
+pub(crate) fn restart_subnet_backups_after_upgrade() {
+    schedule_subnet_backup_step();
+}
+
+fn schedule_subnet_backup_step() {
+    let has_runs =
+        CANISTER_DATA.with_borrow(|canister_data| !canister_data.subnet_backup_runs.is_empty());
+    if !has_runs || SUBNET_BACKUP_TIMER.with_borrow(|timer| timer.is_some()) {
+        return;
+    }
+
+    let timer = ic_cdk_timers::set_timer(
+        Duration::from_secs(BACKUP_SNAPSHOT_POLL_INTERVAL_SECS),
+        || ic_cdk::spawn(run_subnet_backup_step()),
+    );
+    SUBNET_BACKUP_TIMER.set(Some(timer));
+}
+
+/// Checks the snapshots being generated and starts the next canisters of every backup.
+/// Steps can overlap, a canister is only started and recorded once.
+async fn run_subnet_backup_step() {
+    SUBNET_BACKUP_TIMER.set(None);
+
+    let backup_ids: Vec<u64> = CANISTER_DATA
+        .with_borrow(|canister_data| canister_data.subnet_backup_runs.keys().copied().collect());
+    for backup_id in backup_ids {
+        step_subnet_backup(backup_id).await;
+    }
+
+    schedule_subnet_backup_step();
+}
+
+async fn step_subnet_backup(backup_id: u64) {
+    let Some((generating, to_start)) = CANISTER_DATA.with_borrow_mut(|canister_data| {
+        let run = canister_data.subnet_backup_runs.get_mut(&backup_id)?;
+        let to_start = run.take_canisters_to_start(SUBNET_BACKUP_CONCURRENCY);
+        Some((run.generating.keys().copied().collect::<Vec<_>>(), to_start))
+    }) else {
+        return;
+    };
+
+    let checks = generating.into_iter().map(|canister_id| async move {
+        let progress = if canister_id == ic_cdk::id() {
+            Ok(snapshot_generation_progress())
+        } else {
+            call::<_, (Option<SnapshotProgress>,)>(canister_id, "get_snapshot_progress", ())
+                .await
+                .map(|res| res.0)
+                .map_err(|e| e.1)
+        };
+        (canister_id, progress)
+    });
+    run_task_concurrently(
+        checks,
+        SUBNET_BACKUP_CONCURRENCY,
+        |(canister_id, progress)| record_progress(backup_id, canister_id, progress),
+        || false,
+    )
+    .await;
+
+    let starts = to_start.into_iter().map(|canister_id| async move {
+        let started = call::<_, (Result<SnapshotProgress, SnapshotError>,)>(
+            canister_id,
+            "start_snapshot",
+            (),
+        )
+        .await
+        .map_err(|e| e.1)
+        .and_then(|(started,)| started.map_err(|e| format!("{:?}", e)));
+        (canister_id, started)
+    });
+    run_task_concurrently(
+        starts,
+        SUBNET_BACKUP_CONCURRENCY,
+        |(canister_id, started)| record_start(backup_id, canister_id, started),
+        || false,
+    )
+    .await;
+}
+
+fn record_start(backup_id: u64, canister_id: Principal, started: Result<SnapshotProgress, String>) {
+    CANISTER_DATA.with_borrow_mut(|canister_data| {
+        let Some(run) = canister_data.subnet_backup_runs.get_mut(&backup_id) else {
+            return;
+        };
+
+        match started {
+            Ok(started) => {
+                run.generating.insert(
+                    canister_id,
+                    GeneratingSnapshot {
+                        snapshot_id: started.snapshot_id,
+                        polls: 0,
+                    },
+                );
+            }
+            Err(e) => {
+                if let Some(status) = canister_data.backup_status.get_mut(&backup_id) {
+                    record_failure(status, canister_id, e);
+                }
+            }
+        }
+    });
+    finish_subnet_backup_if_done(backup_id);
+}
+
+fn record_progress(
+    backup_id: u64,
+    canister_id: Principal,
+    progress: Result<Option<SnapshotProgress>, String>,
+) {
+    CANISTER_DATA.with_borrow_mut(|canister_data| {
+        let Some(outcome) = canister_data
+            .subnet_backup_runs
+            .get_mut(&backup_id)
+            .and_then(|run| run.record_progress(backup_id, canister_id, progress))
+        else {
+            return;
+        };
+        let Some(status) = canister_data.backup_status.get_mut(&backup_id) else {
+            return;
+        };
+
+        match outcome {
+            Ok(entry) => {
+                let broadcast_status = &mut status.broadcast_status;
+                broadcast_status.successful_canister_ids.push(canister_id);
+                broadcast_status.successful_canisters_count += 1;
+                broadcast_status.total_canisters += 1;
+                if canister_id == ic_cdk::id() {
+                    status.subnet_orchestrator_backup = Some(entry.clone());
+                }
+                canister_data.backup_catalog.insert(entry);
+            }
+            Err(e) => record_failure(status, canister_id, e),
+        }
+    });
+    finish_subnet_backup_if_done(backup_id);
+}
+
+fn record_failure(status: &mut SubnetBackupStatus, canister_id: Principal, error: String) {
+    let broadcast_status = &mut status.broadcast_status;
+    broadcast_status
+        .failed_canister_ids
+        .push((canister_id, error));
+    broadcast_status.failed_canisters_count += 1;
+    broadcast_status.total_canisters += 1;
+}
+
+fn finish_subnet_backup_if_done(backup_id: u64) {
+    CANISTER_DATA.with_borrow_mut(|canister_data| {
+        let Some(run) = canister_data.subnet_backup_runs.get(&backup_id) else {
+            return;
+        };
+        let remaining_canisters_count = run.remaining_canisters_count();
+        let is_finished = run.is_finished();
+
+        if let Some(status) = canister_data.backup_status.get_mut(&backup_id) {
+            status.pending_canisters_count = remaining_canisters_count;
+            if is_finished {
+                status.finished_at = Some(get_current_system_time());
+            }
+        }
+        if is_finished {
+            canister_data.subnet_backup_runs.remove(&backup_id);
+        }
+    });
+}
@@ -0,0 +1,27 @@
+use ic_cdk_macros::query;
+use shared_utils::{
+    common::utils::system_time::get_current_system_time,
+    pagination::PaginationError,
+    types::pump_n_dump_leaderboard::{LeaderboardPage, LeaderboardPeriod},
+};
+
+use crate::CANISTER_DATA;
+
+#[query]
+pub fn get_pump_n_dump_leaderboard(
+    period: LeaderboardPeriod,
+    from_inclusive_index: u64,
+    limit: u64,
+) -> Result<LeaderboardPage, PaginationError> {
+    CANISTER_DATA.with_borrow(|canister_data| {
+        canister_data
+            .pump_n_dump_leaderboard
+            .leaderboard
+            .get_leaderboard_page(
+                period,
+                get_current_system_time(),
+                from_inclusive_index,
+                limit,
+            )
+    })
+}
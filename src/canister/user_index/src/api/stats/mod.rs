@@ -1,2 +1,5 @@
 pub mod collect_creator_dao_stats_in_the_network;
+pub mod get_pump_n_dump_leaderboard;
 pub mod receive_creator_dao_stats_from_individual_canister;
+pub mod receive_deleted_creator_dao_token_from_individual_canister;
+pub mod receive_pump_n_dump_earnings_summary_from_individual_canister;
@@ -4,16 +4,21 @@ use ic_cdk::{caller, notify};
 use ic_cdk_macros::update;
 
 use candid::Principal;
-use shared_utils::common::types::known_principal::KnownPrincipalType;
+use shared_utils::{
+    common::types::known_principal::KnownPrincipalType,
+    types::creator_dao_stats::CreatorTokenMetadata,
+};
 
 use crate::{
     util::types::registered_individual_user_canister::RegisteredIndividualUserCanister,
     CANISTER_DATA,
 };
 
+/// `token_metadata` is None from individual canisters that do not report it yet
 #[update]
 pub fn receive_creator_dao_stats_from_individual_canister(
     root_canister_ids: HashSet<Principal>,
+    token_metadata: Option<Vec<CreatorTokenMetadata>>,
 ) -> Result<(), String> {
     let individual_user = RegisteredIndividualUserCanister::new(caller())?;
 
@@ -31,7 +36,11 @@ pub fn receive_creator_dao_stats_from_individual_canister(
     notify(
         platform_orchestrator_canister_id,
         "receive_creator_dao_stats_from_subnet_orchestrator",
-        (individual_user.profile_id, root_canister_ids),
+        (
+            individual_user.profile_id,
+            root_canister_ids,
+            token_metadata,
+        ),
     )
     .map_err(|e| format!("failed to notify platform orchestrator {:?}", e))
 }
@@ -0,0 +1,55 @@
+use ic_cdk::{caller, notify};
+use ic_cdk_macros::update;
+use shared_utils::{
+    common::{
+        types::known_principal::KnownPrincipalType, utils::system_time::get_current_system_time,
+    },
+    types::pump_n_dump_leaderboard::PumpNDumpEarningsSummary,
+};
+
+use crate::{
+    util::types::registered_individual_user_canister::RegisteredIndividualUserCanister,
+    CANISTER_DATA,
+};
+
+#[update]
+pub fn receive_pump_n_dump_earnings_summary_from_individual_canister(
+    earnings_summary: PumpNDumpEarningsSummary,
+) -> Result<(), String> {
+    let individual_user = RegisteredIndividualUserCanister::new(caller())?;
+
+    if individual_user.profile_id != earnings_summary.user_principal_id
+        || individual_user.canister_id != earnings_summary.user_canister_id
+    {
+        return Err("Earnings summary does not belong to the caller".into());
+    }
+
+    let (entry_updates, platform_orchestrator_canister_id) =
+        CANISTER_DATA.with_borrow_mut(|canister_data| {
+            let entry_updates = canister_data
+                .pump_n_dump_leaderboard
+                .update_earnings(earnings_summary, get_current_system_time());
+
+            let platform_orchestrator_canister_id = canister_data
+                .configuration
+                .known_principal_ids
+                .get(&KnownPrincipalType::CanisterIdPlatformOrchestrator)
+                .copied();
+
+            (entry_updates, platform_orchestrator_canister_id)
+        });
+
+    if entry_updates.is_empty() {
+        return Ok(());
+    }
+
+    let platform_orchestrator_canister_id =
+        platform_orchestrator_canister_id.ok_or("Platform Orchestrator Canister Id not found")?;
+
+    notify(
+        platform_orchestrator_canister_id,
+        "receive_pump_n_dump_leaderboard_updates_from_subnet_orchestrator",
+        (entry_updates,),
+    )
+    .map_err(|e| format!("failed to notify platform orchestrator {:?}", e))
+}
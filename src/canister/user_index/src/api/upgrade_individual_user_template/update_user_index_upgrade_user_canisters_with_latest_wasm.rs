@@ -58,6 +58,8 @@ pub async fn upgrade_user_canisters_with_latest_wasm(
 
     let token_bet_game_onboarding_reward = CANISTER_DATA
         .with_borrow_mut(|canister_data| canister_data.pump_dump_onboarding_reward.clone());
+    let creator_token_limit =
+        CANISTER_DATA.with_borrow(|canister_data| canister_data.creator_token_limit);
 
     let individual_user_template_upgrade_args = IndividualUserTemplateInitArgs {
         known_principal_ids: Some(configuration.known_principal_ids.clone()),
@@ -66,6 +68,7 @@ pub async fn upgrade_user_canisters_with_latest_wasm(
         url_to_send_canister_metrics_to: Some(configuration.url_to_send_canister_metrics_to),
         version: saved_upgrade_status.version.clone(),
         pump_dump_onboarding_reward: Some(token_bet_game_onboarding_reward),
+        creator_token_limit,
     };
 
     let upgrade_individual_canister_futures =
@@ -22,6 +22,8 @@ async fn upgrade_specific_individual_canister_with_wasm_version(
 
     let pump_dump_onboarding_reward = CANISTER_DATA
         .with_borrow(|canister_data| canister_data.pump_dump_onboarding_reward.clone());
+    let creator_token_limit =
+        CANISTER_DATA.with_borrow(|canister_data| canister_data.creator_token_limit);
 
     recharge_and_upgrade(
         individual_user_canister_id,
@@ -34,6 +36,7 @@ async fn upgrade_specific_individual_canister_with_wasm_version(
             url_to_send_canister_metrics_to: None,
             version,
             pump_dump_onboarding_reward: Some(pump_dump_onboarding_reward),
+            creator_token_limit,
         },
     )
     .await
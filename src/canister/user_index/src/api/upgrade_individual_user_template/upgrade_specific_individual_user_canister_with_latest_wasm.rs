@@ -43,6 +43,8 @@ async fn upgrade_specific_individual_user_canister_with_latest_wasm(
 
     let pump_dump_onboarding_reward = CANISTER_DATA
         .with_borrow_mut(|canister_data| canister_data.pump_dump_onboarding_reward.clone());
+    let creator_token_limit =
+        CANISTER_DATA.with_borrow(|canister_data| canister_data.creator_token_limit);
 
     match recharge_and_upgrade(
         user_canister_id,
@@ -55,6 +57,7 @@ async fn upgrade_specific_individual_user_canister_with_latest_wasm(
             url_to_send_canister_metrics_to: Some(configuration.url_to_send_canister_metrics_to),
             version: individual_canister_wasm.version,
             pump_dump_onboarding_reward: Some(pump_dump_onboarding_reward),
+            creator_token_limit,
         },
     )
     .await
@@ -0,0 +1,87 @@
+use candid::Principal;
+use ic_cdk_macros::query;
+use shared_utils::canister_specific::individual_user_template::types::referral::ReferralTreeNode;
+
+use crate::{
+    data_model::{referral::MAX_REFERRAL_TREE_DEPTH, CanisterData},
+    CANISTER_DATA,
+};
+
+/// Referral tree of a user registered on this subnet, down to `MAX_REFERRAL_TREE_DEPTH`.
+/// Referrals are recorded by the subnet orchestrator of the referee, so the tree only
+/// holds referees registered on this subnet. Referees on other subnets are left out, they
+/// are in the trees of their own subnet orchestrators.
+#[query]
+fn get_referral_tree(user_principal_id: Principal, depth: u8) -> Option<ReferralTreeNode> {
+    CANISTER_DATA.with_borrow(|canister_data| {
+        get_referral_tree_impl(
+            canister_data,
+            user_principal_id,
+            depth.min(MAX_REFERRAL_TREE_DEPTH),
+        )
+    })
+}
+
+fn get_referral_tree_impl(
+    canister_data: &CanisterData,
+    user_principal_id: Principal,
+    depth: u8,
+) -> Option<ReferralTreeNode> {
+    let user_canister_id = canister_data
+        .user_principal_id_to_canister_id_map
+        .get(&user_principal_id)
+        .copied()?;
+
+    let referees = if depth == 0 {
+        vec![]
+    } else {
+        canister_data
+            .referral_records
+            .get_referees(&user_principal_id)
+            .into_iter()
+            .filter_map(|referee| get_referral_tree_impl(canister_data, referee, depth - 1))
+            .collect()
+    };
+
+    Some(ReferralTreeNode {
+        user_principal_id,
+        user_canister_id,
+        referees,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use test_utils::setup::test_constants::{
+        get_mock_user_alice_canister_id, get_mock_user_alice_principal_id,
+        get_mock_user_bob_canister_id, get_mock_user_bob_principal_id,
+        get_mock_user_charlie_canister_id, get_mock_user_charlie_principal_id,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_get_referral_tree_impl() {
+        let mut canister_data = CanisterData::default();
+        let alice = get_mock_user_alice_principal_id();
+        let bob = get_mock_user_bob_principal_id();
+        let charlie = get_mock_user_charlie_principal_id();
+
+        canister_data.user_principal_id_to_canister_id_map.extend([
+            (alice, get_mock_user_alice_canister_id()),
+            (bob, get_mock_user_bob_canister_id()),
+            (charlie, get_mock_user_charlie_canister_id()),
+        ]);
+        canister_data.referral_records.insert(alice, bob);
+        canister_data.referral_records.insert(bob, charlie);
+
+        let tree = get_referral_tree_impl(&canister_data, alice, 2).unwrap();
+        assert_eq!(tree.user_canister_id, get_mock_user_alice_canister_id());
+        assert_eq!(tree.referees.len(), 1);
+        assert_eq!(tree.referees[0].user_principal_id, bob);
+        assert_eq!(tree.referees[0].referees[0].user_principal_id, charlie);
+
+        let tree = get_referral_tree_impl(&canister_data, alice, 1).unwrap();
+        assert!(tree.referees[0].referees.is_empty());
+    }
+}
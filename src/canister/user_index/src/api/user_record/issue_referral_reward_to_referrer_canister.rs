@@ -0,0 +1,42 @@
+use candid::Principal;
+use ic_cdk::{caller, notify};
+use ic_cdk_macros::update;
+use shared_utils::canister_specific::individual_user_template::types::profile::UserCanisterDetails;
+
+use crate::{util::canister_management::get_all_subnet_orchestrators, CANISTER_DATA};
+
+/// Called by the subnet orchestrator of a referee on another subnet, individual canisters
+/// only take rewards from their own subnet orchestrator
+#[update]
+pub async fn issue_referral_reward_to_referrer_canister(
+    referrer: UserCanisterDetails,
+    referee: Principal,
+) -> Result<(), String> {
+    let subnet_orchestrators = get_all_subnet_orchestrators().await?;
+    if !subnet_orchestrators.contains(&caller()) {
+        return Err("Unauthorized".into());
+    }
+
+    let referrer_canister_id = CANISTER_DATA.with_borrow(|canister_data| {
+        canister_data
+            .user_principal_id_to_canister_id_map
+            .get(&referrer.profile_owner)
+            .copied()
+    });
+
+    if referrer_canister_id != Some(referrer.user_canister_id) {
+        return Err("Referrer is not registered on this subnet".into());
+    }
+
+    notify(
+        referrer.user_canister_id,
+        "get_rewarded_for_referral",
+        (referrer.profile_owner, referee),
+    )
+    .map_err(|e| {
+        format!(
+            "failed to reward the canister {} {:?}",
+            referrer.user_canister_id, e
+        )
+    })
+}
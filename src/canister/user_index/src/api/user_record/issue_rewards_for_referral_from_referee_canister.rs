@@ -0,0 +1,135 @@
+use candid::Principal;
+use ic_cdk::{
+    api::management_canister::main::{canister_info, CanisterInfoRequest},
+    call, caller, notify,
+};
+use ic_cdk_macros::update;
+use shared_utils::canister_specific::individual_user_template::types::profile::UserCanisterDetails;
+
+use crate::{
+    util::{
+        canister_management::get_all_subnet_orchestrators,
+        types::registered_individual_user_canister::RegisteredIndividualUserCanister,
+    },
+    CANISTER_DATA,
+};
+
+/// Called by the referee canister once its referrer is set. The referrer may be on any
+/// subnet. Both the referrer and the referee canister are credited once per referee.
+#[update]
+pub async fn issue_rewards_for_referral_from_referee_canister(
+    referrer: UserCanisterDetails,
+) -> Result<(), String> {
+    let referee = RegisteredIndividualUserCanister::new(caller())?;
+
+    if referrer.profile_owner == referee.profile_id {
+        return Err("Cannot refer yourself".into());
+    }
+
+    let referrer_subnet_orchestrator = verify_referrer_canister(&referrer).await?;
+
+    let inserted = CANISTER_DATA.with_borrow_mut(|canister_data| {
+        canister_data
+            .referral_records
+            .insert(referrer.profile_owner, referee.profile_id)
+    });
+
+    if !inserted {
+        let already_rewarded_referrer = CANISTER_DATA.with_borrow(|canister_data| {
+            canister_data
+                .referral_records
+                .get_referrer(&referee.profile_id)
+        });
+
+        // the referee canister retried after a failed call, individual canisters ignore duplicate rewards
+        if already_rewarded_referrer != Some(referrer.profile_owner) {
+            return Err("Referral reward already issued".into());
+        }
+    }
+
+    let res = notify(
+        referee.canister_id,
+        "get_rewarded_for_referral",
+        (referrer.profile_owner, referee.profile_id),
+    )
+    .map_err(|e| {
+        format!(
+            "failed to reward the canister {} {:?}",
+            referee.canister_id, e
+        )
+    })
+    .and_then(|()| {
+        // referrers on other subnets are credited through their own subnet orchestrator
+        match referrer_subnet_orchestrator {
+            None => notify(
+                referrer.user_canister_id,
+                "get_rewarded_for_referral",
+                (referrer.profile_owner, referee.profile_id),
+            ),
+            Some(referrer_subnet_orchestrator) => notify(
+                referrer_subnet_orchestrator,
+                "issue_referral_reward_to_referrer_canister",
+                (referrer.clone(), referee.profile_id),
+            ),
+        }
+        .map_err(|e| {
+            format!(
+                "failed to reward the canister {} {:?}",
+                referrer.user_canister_id, e
+            )
+        })
+    });
+
+    if res.is_err() && inserted {
+        CANISTER_DATA.with_borrow_mut(|canister_data| {
+            canister_data.referral_records.remove(&referee.profile_id)
+        });
+    }
+
+    res
+}
+
+/// The referrer canister has to be the one a registered subnet orchestrator assigned to
+/// the referrer. Returns that subnet orchestrator if it is not this one.
+async fn verify_referrer_canister(
+    referrer: &UserCanisterDetails,
+) -> Result<Option<Principal>, String> {
+    let local_referrer_canister_id = CANISTER_DATA.with_borrow(|canister_data| {
+        canister_data
+            .user_principal_id_to_canister_id_map
+            .get(&referrer.profile_owner)
+            .copied()
+    });
+
+    if local_referrer_canister_id == Some(referrer.user_canister_id) {
+        return Ok(None);
+    }
+
+    let subnet_orchestrators = get_all_subnet_orchestrators().await?;
+
+    let (referrer_canister_info,) = canister_info(CanisterInfoRequest {
+        canister_id: referrer.user_canister_id,
+        num_requested_changes: None,
+    })
+    .await
+    .map_err(|e| e.1)?;
+    let referrer_subnet_orchestrator = referrer_canister_info
+        .controllers
+        .into_iter()
+        .find(|controller| subnet_orchestrators.contains(controller))
+        .ok_or("Referrer is not registered in the network")?;
+
+    let (referrer_canister_id,) = call::<_, (Option<Principal>,)>(
+        referrer_subnet_orchestrator,
+        "get_user_canister_id_from_user_principal_id",
+        (referrer.profile_owner,),
+    )
+    .await
+    .map_err(|e| e.1)?;
+
+    if referrer_canister_id != Some(referrer.user_canister_id) {
+        return Err("Referrer is not registered in the network".into());
+    }
+
+    Ok(Some(referrer_subnet_orchestrator))
+}
@@ -7,6 +7,21 @@ const UPGRADES: MemoryId = MemoryId::new(0);
 // A memory for the StableVec for individual_user wasm. 
 const INDIVIDUAL_USER_WASM_MEMORY: MemoryId = MemoryId::new(1);
 
+// A memory for the StableBTreeMap of versioned creator token SNS wasms.
+const SNS_WASM_MEMORY: MemoryId = MemoryId::new(2);
+
+// Memories for the snapshots kept in stable memory and their contents.
+const SNAPSHOT_MEMORY: MemoryId = MemoryId::new(3);
+const SNAPSHOT_CHUNKS_MEMORY: MemoryId = MemoryId::new(4);
+// Id given to the next snapshot in the snapshot store.
+const SNAPSHOT_NEXT_ID_MEMORY: MemoryId = MemoryId::new(5);
+
+// Memories for the snapshots of user canisters uploaded to restore them, kept apart from
+// the snapshots of this canister.
+const RESTORE_SNAPSHOT_MEMORY: MemoryId = MemoryId::new(6);
+const RESTORE_SNAPSHOT_CHUNKS_MEMORY: MemoryId = MemoryId::new(7);
+const RESTORE_SNAPSHOT_NEXT_ID_MEMORY: MemoryId = MemoryId::new(8);
+
 
 
 pub type Memory = VirtualMemory<DefaultMemoryImpl>;
@@ -26,6 +41,34 @@ pub fn get_wasm_memory() -> Memory {
     MEMORY_MANAGER.with_borrow_mut(|m| m.get(INDIVIDUAL_USER_WASM_MEMORY))
 }
 
+pub fn get_sns_wasm_memory() -> Memory {
+    MEMORY_MANAGER.with_borrow_mut(|m| m.get(SNS_WASM_MEMORY))
+}
+
+pub fn get_snapshot_memory() -> Memory {
+    MEMORY_MANAGER.with_borrow_mut(|m| m.get(SNAPSHOT_MEMORY))
+}
+
+pub fn get_snapshot_chunks_memory() -> Memory {
+    MEMORY_MANAGER.with_borrow_mut(|m| m.get(SNAPSHOT_CHUNKS_MEMORY))
+}
+
+pub fn get_snapshot_next_id_memory() -> Memory {
+    MEMORY_MANAGER.with_borrow_mut(|m| m.get(SNAPSHOT_NEXT_ID_MEMORY))
+}
+
+pub fn get_restore_snapshot_memory() -> Memory {
+    MEMORY_MANAGER.with_borrow_mut(|m| m.get(RESTORE_SNAPSHOT_MEMORY))
+}
+
+pub fn get_restore_snapshot_chunks_memory() -> Memory {
+    MEMORY_MANAGER.with_borrow_mut(|m| m.get(RESTORE_SNAPSHOT_CHUNKS_MEMORY))
+}
+
+pub fn get_restore_snapshot_next_id_memory() -> Memory {
+    MEMORY_MANAGER.with_borrow_mut(|m| m.get(RESTORE_SNAPSHOT_NEXT_ID_MEMORY))
+}
+
 pub fn init_memory_manager() {
     MEMORY_MANAGER.with(|m| {
         *m.borrow_mut() = MemoryManager::init_with_bucket_size(DefaultMemoryImpl::default(), 1);
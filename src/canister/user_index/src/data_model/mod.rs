@@ -4,21 +4,29 @@ use candid::{Deserialize, Nat, Principal};
 use ic_stable_structures::StableBTreeMap;
 use serde::Serialize;
 use shared_utils::canister_specific::user_index::types::{
-    BroadcastCallStatus, RecycleStatus, UpgradeStatus,
+    BroadcastCallStatus, CentsAirdropCampaignStatus, CreatorDaoSnsCanistersUpgradeStatus,
+    RecycleStatus, SubnetBackupStatus, UpgradeStatus,
 };
 use shared_utils::common::types::known_principal::KnownPrincipalType;
+use shared_utils::common::types::snapshot::BackupCatalog;
 use shared_utils::common::types::version_details::VersionDetails;
-use shared_utils::common::types::wasm::{CanisterWasm, WasmType};
+use shared_utils::common::types::wasm::{CanisterWasm, SnsWasmKey, SnsWasmType, WasmType};
 use shared_utils::common::utils::default_pump_dump_onboarding_reward;
 
 use crate::util::types::subnet_orchestrator_operation::SubnetOrchestratorOperation;
 use crate::CANISTER_DATA;
 
-use self::memory::get_wasm_memory;
+use self::memory::{get_sns_wasm_memory, get_wasm_memory};
+use self::pump_n_dump_leaderboard::SubnetPumpNDumpLeaderboard;
+use self::referral::ReferralRecords;
+use self::subnet_backup::SubnetBackupRun;
 use self::{configuration::Configuration, memory::Memory};
 
 pub mod configuration;
 pub mod memory;
+pub mod pump_n_dump_leaderboard;
+pub mod referral;
+pub mod subnet_backup;
 
 const fn _default_true() -> bool {
     return true;
@@ -50,6 +58,30 @@ pub struct CanisterData {
     pub pump_dump_onboarding_reward: Nat,
     #[serde(default)]
     pub on_going_operation: HashSet<SubnetOrchestratorOperation>,
+    #[serde(default)]
+    pub pump_n_dump_leaderboard: SubnetPumpNDumpLeaderboard,
+    #[serde(default)]
+    pub referral_records: ReferralRecords,
+    // Key is the campaign id
+    #[serde(default)]
+    pub cents_airdrop_campaign_status: BTreeMap<u64, CentsAirdropCampaignStatus>,
+    // None leaves the individual canisters on their default limit
+    #[serde(default)]
+    pub creator_token_limit: Option<usize>,
+    #[serde(skip, default = "_empty_sns_wasms")]
+    pub sns_wasms: StableBTreeMap<SnsWasmKey, CanisterWasm, Memory>,
+    // Latest rollout of each creator token canister type to the individual canisters
+    #[serde(default)]
+    pub creator_dao_sns_canisters_upgrade_status:
+        BTreeMap<SnsWasmType, CreatorDaoSnsCanistersUpgradeStatus>,
+    // Status of each backup run in the subnet, keyed by backup id
+    #[serde(default)]
+    pub backup_status: BTreeMap<u64, SubnetBackupStatus>,
+    // Backup runs that are not finished yet, keyed by backup id
+    #[serde(default)]
+    pub subnet_backup_runs: BTreeMap<u64, SubnetBackupRun>,
+    #[serde(default)]
+    pub backup_catalog: BackupCatalog,
 }
 
 impl Default for CanisterData {
@@ -68,6 +100,15 @@ impl Default for CanisterData {
             version_details: VersionDetails::default(),
             pump_dump_onboarding_reward: default_pump_dump_onboarding_reward(),
             on_going_operation: HashSet::new(),
+            pump_n_dump_leaderboard: SubnetPumpNDumpLeaderboard::default(),
+            referral_records: ReferralRecords::default(),
+            cents_airdrop_campaign_status: BTreeMap::new(),
+            creator_token_limit: None,
+            sns_wasms: _empty_sns_wasms(),
+            creator_dao_sns_canisters_upgrade_status: BTreeMap::new(),
+            backup_status: BTreeMap::new(),
+            subnet_backup_runs: BTreeMap::new(),
+            backup_catalog: BackupCatalog::default(),
         }
     }
 }
@@ -76,6 +117,10 @@ fn _empty_wasms() -> StableBTreeMap<WasmType, CanisterWasm, Memory> {
     StableBTreeMap::init(get_wasm_memory())
 }
 
+fn _empty_sns_wasms() -> StableBTreeMap<SnsWasmKey, CanisterWasm, Memory> {
+    StableBTreeMap::init(get_sns_wasm_memory())
+}
+
 pub fn get_sns_ledger() -> Option<Principal> {
     let ledger = CANISTER_DATA.with_borrow(|cdata| {
         cdata
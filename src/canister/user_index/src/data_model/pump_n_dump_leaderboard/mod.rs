@@ -0,0 +1,165 @@
+use std::{collections::HashMap, time::SystemTime};
+
+use candid::{Nat, Principal};
+use serde::{Deserialize, Serialize};
+use shared_utils::types::pump_n_dump_leaderboard::{
+    LeaderboardEntry, LeaderboardEntryUpdate, LeaderboardPeriod, PumpNDumpEarningsSummary,
+    PumpNDumpLeaderboard,
+};
+
+/// Game earnings of the users seen within the current window of a period
+#[derive(Serialize, Deserialize, Default)]
+pub struct GameEarningsInWindow {
+    window_id: u64,
+    // earnings of each user when the window started, only known for the users that were
+    // seen in the previous window
+    at_window_start: HashMap<Principal, Nat>,
+    // last earnings reported by each user seen in the window
+    latest: HashMap<Principal, Nat>,
+}
+
+impl GameEarningsInWindow {
+    /// Earnings of the user in the window, users first seen in it count from 0
+    fn update_earnings(
+        &mut self,
+        window_id: u64,
+        user_principal_id: Principal,
+        game_earnings: Nat,
+    ) -> Nat {
+        if self.window_id < window_id {
+            // only the users of the window that just ended are carried over
+            self.at_window_start = if self.window_id + 1 == window_id {
+                std::mem::take(&mut self.latest)
+            } else {
+                HashMap::new()
+            };
+            self.latest.clear();
+            self.window_id = window_id;
+        }
+
+        let earnings = match self.at_window_start.get(&user_principal_id) {
+            Some(at_window_start) if game_earnings > *at_window_start => {
+                game_earnings.clone() - at_window_start.clone()
+            }
+            Some(_) => Nat::from(0_u32),
+            None => game_earnings.clone(),
+        };
+        self.latest.insert(user_principal_id, game_earnings);
+
+        earnings
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct SubnetPumpNDumpLeaderboard {
+    pub leaderboard: PumpNDumpLeaderboard,
+    #[serde(default)]
+    game_earnings_in_window: HashMap<LeaderboardPeriod, GameEarningsInWindow>,
+}
+
+impl SubnetPumpNDumpLeaderboard {
+    /// Updates the subnet leaderboard with the summary received from an individual canister
+    /// and returns the entries that made it to the top of their period, to be forwarded to
+    /// the platform orchestrator.
+    pub fn update_earnings(
+        &mut self,
+        earnings_summary: PumpNDumpEarningsSummary,
+        current_time: SystemTime,
+    ) -> Vec<LeaderboardEntryUpdate> {
+        let user_principal_id = earnings_summary.user_principal_id;
+        let game_earnings = earnings_summary.game_earnings();
+
+        let mut entry_updates = vec![];
+
+        for period in [
+            LeaderboardPeriod::Daily,
+            LeaderboardPeriod::Weekly,
+            LeaderboardPeriod::AllTime,
+        ] {
+            let window_id = period.window_id(current_time);
+
+            let earnings = match period {
+                LeaderboardPeriod::AllTime => game_earnings.clone(),
+                _ => self
+                    .game_earnings_in_window
+                    .entry(period)
+                    .or_default()
+                    .update_earnings(window_id, user_principal_id, game_earnings.clone()),
+            };
+
+            let entry_update = LeaderboardEntryUpdate {
+                period,
+                window_id,
+                entry: LeaderboardEntry {
+                    user_principal_id,
+                    user_canister_id: earnings_summary.user_canister_id,
+                    earnings,
+                    total_pumps: earnings_summary.total_pumps.clone(),
+                    total_dumps: earnings_summary.total_dumps.clone(),
+                    updated_at: current_time,
+                },
+            };
+
+            if self.leaderboard.insert_entry_update(entry_update.clone()) {
+                entry_updates.push(entry_update);
+            }
+        }
+
+        entry_updates
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::*;
+
+    fn summary(net_earnings: u64) -> PumpNDumpEarningsSummary {
+        PumpNDumpEarningsSummary {
+            user_principal_id: Principal::from_slice(&[1]),
+            user_canister_id: Principal::from_slice(&[1, 1]),
+            net_earnings: Nat::from(net_earnings),
+            net_airdrop_reward: Nat::from(100_u32),
+            total_pumps: Nat::from(0_u32),
+            total_dumps: Nat::from(0_u32),
+        }
+    }
+
+    #[test]
+    fn test_update_earnings_computes_earnings_per_window() {
+        let mut subnet_leaderboard = SubnetPumpNDumpLeaderboard::default();
+        let day_one = UNIX_EPOCH + Duration::from_secs(24 * 60 * 60 * 10);
+        let day_two = day_one + Duration::from_secs(24 * 60 * 60);
+
+        subnet_leaderboard.update_earnings(summary(150), day_one);
+        subnet_leaderboard.update_earnings(summary(180), day_one);
+        let entry_updates = subnet_leaderboard.update_earnings(summary(200), day_two);
+
+        let daily_entry = entry_updates
+            .iter()
+            .find(|entry_update| entry_update.period == LeaderboardPeriod::Daily)
+            .unwrap();
+        let all_time_entry = entry_updates
+            .iter()
+            .find(|entry_update| entry_update.period == LeaderboardPeriod::AllTime)
+            .unwrap();
+
+        assert_eq!(daily_entry.entry.earnings, Nat::from(20_u32));
+        assert_eq!(all_time_entry.entry.earnings, Nat::from(100_u32));
+    }
+
+    #[test]
+    fn test_update_earnings_counts_users_first_seen_in_window_from_zero() {
+        let mut subnet_leaderboard = SubnetPumpNDumpLeaderboard::default();
+        let day_one = UNIX_EPOCH + Duration::from_secs(24 * 60 * 60 * 10);
+
+        let entry_updates = subnet_leaderboard.update_earnings(summary(150), day_one);
+
+        let daily_entry = entry_updates
+            .iter()
+            .find(|entry_update| entry_update.period == LeaderboardPeriod::Daily)
+            .unwrap();
+        assert_eq!(daily_entry.entry.earnings, Nat::from(50_u32));
+    }
+}
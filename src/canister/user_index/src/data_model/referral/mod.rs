@@ -0,0 +1,85 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use candid::Principal;
+use serde::{Deserialize, Serialize};
+
+pub const MAX_REFERRAL_TREE_DEPTH: u8 = 3;
+
+/// Referrals rewarded on this subnet. A referee can only be rewarded once.
+#[derive(Serialize, Deserialize, Default)]
+pub struct ReferralRecords {
+    // Key is the referee principal id
+    referrer_of: BTreeMap<Principal, Principal>,
+    // Key is the referrer principal id
+    referees_of: BTreeMap<Principal, BTreeSet<Principal>>,
+}
+
+impl ReferralRecords {
+    pub fn get_referrer(&self, referee: &Principal) -> Option<Principal> {
+        self.referrer_of.get(referee).copied()
+    }
+
+    pub fn get_referees(&self, referrer: &Principal) -> Vec<Principal> {
+        self.referees_of
+            .get(referrer)
+            .map(|referees| referees.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Returns false if the referee was already rewarded.
+    pub fn insert(&mut self, referrer: Principal, referee: Principal) -> bool {
+        if self.referrer_of.contains_key(&referee) {
+            return false;
+        }
+
+        self.referrer_of.insert(referee, referrer);
+        self.referees_of
+            .entry(referrer)
+            .or_default()
+            .insert(referee);
+
+        true
+    }
+
+    pub fn remove(&mut self, referee: &Principal) {
+        let Some(referrer) = self.referrer_of.remove(referee) else {
+            return;
+        };
+
+        if let Some(referees) = self.referees_of.get_mut(&referrer) {
+            referees.remove(referee);
+            if referees.is_empty() {
+                self.referees_of.remove(&referrer);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use test_utils::setup::test_constants::{
+        get_mock_user_alice_principal_id, get_mock_user_bob_principal_id,
+        get_mock_user_charlie_principal_id,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_referee_is_recorded_only_once() {
+        let mut referral_records = ReferralRecords::default();
+        let alice = get_mock_user_alice_principal_id();
+        let bob = get_mock_user_bob_principal_id();
+        let charlie = get_mock_user_charlie_principal_id();
+
+        assert!(referral_records.insert(alice, bob));
+        assert!(!referral_records.insert(charlie, bob));
+        assert!(referral_records.insert(alice, charlie));
+
+        assert_eq!(referral_records.get_referrer(&bob), Some(alice));
+        assert_eq!(referral_records.get_referees(&alice).len(), 2);
+
+        referral_records.remove(&bob);
+        assert_eq!(referral_records.get_referrer(&bob), None);
+        assert_eq!(referral_records.get_referees(&alice), vec![charlie]);
+    }
+}
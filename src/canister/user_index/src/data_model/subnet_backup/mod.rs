@@ -0,0 +1,203 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use candid::Principal;
+use serde::{Deserialize, Serialize};
+use shared_utils::{
+    common::types::snapshot::{BackupCatalogEntry, SnapshotGenerationState, SnapshotProgress},
+    constant::BACKUP_SNAPSHOT_MAX_POLLS,
+};
+
+/// Snapshot a canister is generating for a backup
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GeneratingSnapshot {
+    pub snapshot_id: u64,
+    /// Checks on the generation so far
+    pub polls: u32,
+}
+
+/// A backup that is not finished yet. It is kept with the canister data and moved
+/// forward by a timer, so it carries on after an upgrade.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct SubnetBackupRun {
+    /// Individual canisters whose snapshot was not started yet
+    pub pending_canisters: VecDeque<Principal>,
+    /// Canisters generating their snapshot, the subnet orchestrator included
+    pub generating: BTreeMap<Principal, GeneratingSnapshot>,
+}
+
+impl SubnetBackupRun {
+    pub fn is_finished(&self) -> bool {
+        self.pending_canisters.is_empty() && self.generating.is_empty()
+    }
+
+    pub fn remaining_canisters_count(&self) -> u64 {
+        (self.pending_canisters.len() + self.generating.len()) as u64
+    }
+
+    /// Takes the canisters to start next, so that at most `concurrency` of them are
+    /// generating a snapshot at once
+    pub fn take_canisters_to_start(&mut self, concurrency: usize) -> Vec<Principal> {
+        let count = concurrency
+            .saturating_sub(self.generating.len())
+            .min(self.pending_canisters.len());
+
+        self.pending_canisters.drain(..count).collect()
+    }
+
+    /// Records a check on a canister's snapshot generation. Returns the outcome once the
+    /// snapshot is completed or failed, the canister is then done. Checks on canisters
+    /// that are already done are ignored.
+    pub fn record_progress(
+        &mut self,
+        backup_id: u64,
+        canister_id: Principal,
+        progress: Result<Option<SnapshotProgress>, String>,
+    ) -> Option<Result<BackupCatalogEntry, String>> {
+        let snapshot = self.generating.get_mut(&canister_id)?;
+
+        let outcome = match progress.map(|progress| {
+            progress.filter(|progress| progress.snapshot_id == snapshot.snapshot_id)
+        }) {
+            Err(e) => Err(e),
+            Ok(None) => Err("Snapshot generation was stopped".into()),
+            Ok(Some(progress)) => match progress.state {
+                SnapshotGenerationState::InProgress => {
+                    snapshot.polls += 1;
+                    if snapshot.polls < BACKUP_SNAPSHOT_MAX_POLLS {
+                        return None;
+                    }
+                    Err("Snapshot generation did not complete in time".into())
+                }
+                SnapshotGenerationState::Failed(e) => Err(e),
+                SnapshotGenerationState::Completed => Ok(BackupCatalogEntry {
+                    backup_id,
+                    canister_id,
+                    snapshot_id: snapshot.snapshot_id,
+                    started_at: progress.started_at,
+                }),
+            },
+        };
+
+        self.generating.remove(&canister_id);
+        Some(outcome)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::SystemTime;
+
+    use test_utils::setup::test_constants::{
+        get_mock_user_alice_canister_id, get_mock_user_bob_canister_id,
+        get_mock_user_charlie_canister_id,
+    };
+
+    use super::*;
+
+    fn progress(snapshot_id: u64, state: SnapshotGenerationState) -> SnapshotProgress {
+        SnapshotProgress {
+            snapshot_id,
+            state,
+            started_at: SystemTime::UNIX_EPOCH,
+            finished_at: None,
+            items_written: 0,
+            total_items: 0,
+            bytes_written: 0,
+        }
+    }
+
+    #[test]
+    fn test_subnet_backup_run_starts_canisters_in_batches() {
+        let mut run = SubnetBackupRun {
+            pending_canisters: [
+                get_mock_user_alice_canister_id(),
+                get_mock_user_bob_canister_id(),
+                get_mock_user_charlie_canister_id(),
+            ]
+            .into(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            run.take_canisters_to_start(2),
+            vec![
+                get_mock_user_alice_canister_id(),
+                get_mock_user_bob_canister_id()
+            ]
+        );
+        run.generating.insert(
+            get_mock_user_alice_canister_id(),
+            GeneratingSnapshot {
+                snapshot_id: 1,
+                polls: 0,
+            },
+        );
+        run.generating.insert(
+            get_mock_user_bob_canister_id(),
+            GeneratingSnapshot {
+                snapshot_id: 1,
+                polls: 0,
+            },
+        );
+        assert!(run.take_canisters_to_start(2).is_empty());
+        assert_eq!(run.remaining_canisters_count(), 3);
+        assert!(!run.is_finished());
+    }
+
+    #[test]
+    fn test_subnet_backup_run_records_outcomes_once() {
+        let alice_canister_id = get_mock_user_alice_canister_id();
+        let bob_canister_id = get_mock_user_bob_canister_id();
+        let mut run = SubnetBackupRun::default();
+        for canister_id in [alice_canister_id, bob_canister_id] {
+            run.generating.insert(
+                canister_id,
+                GeneratingSnapshot {
+                    snapshot_id: 4,
+                    polls: 0,
+                },
+            );
+        }
+
+        assert_eq!(
+            run.record_progress(
+                7,
+                alice_canister_id,
+                Ok(Some(progress(4, SnapshotGenerationState::InProgress)))
+            ),
+            None
+        );
+        assert_eq!(
+            run.record_progress(
+                7,
+                alice_canister_id,
+                Ok(Some(progress(4, SnapshotGenerationState::Completed)))
+            ),
+            Some(Ok(BackupCatalogEntry {
+                backup_id: 7,
+                canister_id: alice_canister_id,
+                snapshot_id: 4,
+                started_at: SystemTime::UNIX_EPOCH,
+            }))
+        );
+        assert_eq!(
+            run.record_progress(
+                7,
+                alice_canister_id,
+                Ok(Some(progress(4, SnapshotGenerationState::Completed)))
+            ),
+            None
+        );
+
+        // a newer snapshot means the one of the backup was stopped
+        assert_eq!(
+            run.record_progress(
+                7,
+                bob_canister_id,
+                Ok(Some(progress(5, SnapshotGenerationState::InProgress)))
+            ),
+            Some(Err("Snapshot generation was stopped".into()))
+        );
+        assert!(run.is_finished());
+    }
+}
@@ -2,7 +2,10 @@ use std::cell::RefCell;
 use std::collections::HashMap;
 use std::collections::HashSet;
 
+use api::snapshot::restore_user_canister::_default_restore_snapshot_store;
+use api::snapshot::stored_snapshots::_default_snapshot_store;
 use candid::{Principal, Nat};
+use data_model::memory::Memory;
 use data_model::CanisterData;
 use ic_cdk::api::{
     call::CallResult,
@@ -10,13 +13,27 @@ use ic_cdk::api::{
 };
 use ic_cdk_macros::export_candid;
 use shared_utils::{
+    canister_specific::individual_user_template::types::{
+        cdao::CreatorTokenDeletionReport, error::DeleteCreatorTokenError,
+        profile::UserCanisterDetails, referral::ReferralTreeNode,
+    },
     canister_specific::user_index::types::{
-        args::UserIndexInitArgs, BroadcastCallStatus, RecycleStatus, UpgradeStatus,
+        args::UserIndexInitArgs, BroadcastCallStatus, CentsAirdropCampaignStatus,
+        CreatorDaoSnsCanistersUpgradeStatus, RecycleStatus, RestoredUserCanister,
+        SubnetBackupStatus, UpgradeStatus,
     },
     common::types::http::{HttpRequest, HttpResponse},
     common::types::known_principal::KnownPrincipalType,
+    common::types::snapshot::{
+        BackupCatalogEntry, SnapshotError, SnapshotInfo, SnapshotManifest, SnapshotProgress,
+    },
+    common::types::wasm::{SnsWasmKey, SnsWasmType},
+    common::utils::snapshot_store::SnapshotStore,
+    pagination::PaginationError,
     types::canister_specific::user_index::error_types::SetUniqueUsernameError,
-    types::creator_dao_stats::IndividualUserCreatorDaoEntry,
+    types::cents_airdrop_campaign::CentsAirdropCampaign,
+    types::creator_dao_stats::{CreatorTokenMetadata, IndividualUserCreatorDaoEntry},
+    types::pump_n_dump_leaderboard::{LeaderboardPage, LeaderboardPeriod, PumpNDumpEarningsSummary},
 };
 
 mod api;
@@ -25,7 +42,8 @@ mod util;
 
 thread_local! {
     static CANISTER_DATA: RefCell<CanisterData> = RefCell::default();
-    static SNAPSHOT_DATA: RefCell<Vec<u8>> = RefCell::default();
+    static SNAPSHOT_STORE: RefCell<SnapshotStore<Memory>> = RefCell::new(_default_snapshot_store());
+    static RESTORE_SNAPSHOT_STORE: RefCell<SnapshotStore<Memory>> = RefCell::new(_default_restore_snapshot_store());
 }
 
 export_candid!();
@@ -119,6 +119,7 @@ pub async fn install_canister_wasm(
 
     let pump_dump_onboarding_reward =
         Some(CANISTER_DATA.with_borrow(|cdata| cdata.pump_dump_onboarding_reward.clone()));
+    let creator_token_limit = CANISTER_DATA.with_borrow(|cdata| cdata.creator_token_limit);
 
     let individual_user_tempalate_init_args = IndividualUserTemplateInitArgs {
         profile_owner,
@@ -133,6 +134,7 @@ pub async fn install_canister_wasm(
         version,
         url_to_send_canister_metrics_to: Some(configuration.url_to_send_canister_metrics_to),
         pump_dump_onboarding_reward,
+        creator_token_limit,
     };
 
     // * encode argument for user canister init lifecycle method
@@ -163,6 +165,7 @@ pub async fn reinstall_canister_wasm(
 
     let pump_dump_onboarding_reward =
         Some(CANISTER_DATA.with_borrow(|cdata| cdata.pump_dump_onboarding_reward.clone()));
+    let creator_token_limit = CANISTER_DATA.with_borrow(|cdata| cdata.creator_token_limit);
 
     let individual_user_tempalate_init_args = IndividualUserTemplateInitArgs {
         profile_owner,
@@ -177,6 +180,7 @@ pub async fn reinstall_canister_wasm(
         version,
         url_to_send_canister_metrics_to: Some(configuration.url_to_send_canister_metrics_to),
         pump_dump_onboarding_reward,
+        creator_token_limit,
     };
 
     // * encode argument for user canister init lifecycle method
@@ -378,3 +382,23 @@ async fn upgrade_user_canister(
     .await
     .map_err(|e| e.1)
 }
+
+/// Every subnet orchestrator registered with the platform orchestrator, this one included
+pub async fn get_all_subnet_orchestrators() -> Result<Vec<Principal>, String> {
+    let platform_orchestrator = CANISTER_DATA
+        .with_borrow(|canister_data| {
+            canister_data
+                .configuration
+                .known_principal_ids
+                .get(&KnownPrincipalType::CanisterIdPlatformOrchestrator)
+                .copied()
+        })
+        .ok_or("Platform Orchestrator Canister Id not found")?;
+
+    let (subnet_orchestrators,) =
+        call::<_, (Vec<Principal>,)>(platform_orchestrator, "get_all_subnet_orchestrators", ())
+            .await
+            .map_err(|e| e.1)?;
+
+    Ok(subnet_orchestrators)
+}
@@ -4,7 +4,12 @@ use ic_cdk::api::management_canister::main::{
     UpdateSettingsArgument,
 };
 use serde::{Deserialize, Serialize};
-use shared_utils::cycles::calculate_threshold_and_recharge_cycles_for_canister;
+use shared_utils::{
+    canister_specific::individual_user_template::types::{
+        cdao::CreatorTokenDeletionReport, error::DeleteCreatorTokenError,
+    },
+    cycles::calculate_threshold_and_recharge_cycles_for_canister,
+};
 
 use crate::{util::canister_management::recharge_canister, CANISTER_DATA};
 
@@ -171,6 +176,33 @@ impl RegisteredIndividualUserCanister {
             .map_err(|e| e.1)
     }
 
+    pub async fn delete_sns_creator_token(
+        &self,
+        root: Principal,
+        force: bool,
+    ) -> Result<CreatorTokenDeletionReport, DeleteCreatorTokenError> {
+        ic_cdk::call::<_, (Result<CreatorTokenDeletionReport, DeleteCreatorTokenError>,)>(
+            self.canister_id,
+            "delete_creator_token",
+            (root, force),
+        )
+        .await?
+        .0
+    }
+
+    pub async fn set_creator_token_limit_override(
+        &self,
+        limit: Option<usize>,
+    ) -> Result<(), String> {
+        ic_cdk::call::<_, ()>(
+            self.canister_id,
+            "set_creator_token_limit_override",
+            (limit,),
+        )
+        .await
+        .map_err(|e| e.1)
+    }
+
     pub fn notify_to_upgrade_creator_dao_governance_canisters(
         &self,
         wasm_module: Vec<u8>,
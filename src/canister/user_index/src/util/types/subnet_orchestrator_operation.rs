@@ -6,4 +6,6 @@ use crate::CANISTER_DATA;
 #[derive(Serialize, Deserialize, Hash, PartialEq, Eq)]
 pub(crate) enum SubnetOrchestratorOperation {
     RechargeIndividualUserCanister(Principal),
+    RestoreUserCanister(Principal),
+    RunCentsAirdropCampaign(u64),
 }
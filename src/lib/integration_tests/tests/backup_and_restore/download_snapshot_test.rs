@@ -15,10 +15,17 @@ use shared_utils::{
         user_index::types::UpgradeStatus,
     },
     common::{
-        types::{known_principal::KnownPrincipalType, utility_token::token_event::TokenEvent},
-        utils::default_pump_dump_onboarding_reward,
+        types::{
+            known_principal::KnownPrincipalType,
+            snapshot::{SnapshotError, SnapshotManifest},
+            utility_token::token_event::TokenEvent,
+        },
+        utils::{
+            default_pump_dump_onboarding_reward,
+            snapshot_transfer::{get_snapshot_manifest, read_snapshot_range},
+        },
     },
-    constant::RECLAIM_CANISTER_PRINCIPAL_ID,
+    constant::{RECLAIM_CANISTER_PRINCIPAL_ID, SNAPSHOT_TRANSFER_CHUNK_SIZE},
     types::canister_specific::individual_user_template::error_types::GetUserUtilityTokenTransactionHistoryError,
 };
 use test_utils::setup::{
@@ -325,7 +332,11 @@ fn download_snapshot_test() {
             )
             .map(|reply_payload| {
                 let payload: Vec<u8> = match reply_payload {
-                    WasmResult::Reply(payload) => candid::decode_one(&payload).unwrap(),
+                    WasmResult::Reply(payload) => {
+                        candid::decode_one::<Result<Vec<u8>, SnapshotError>>(&payload)
+                            .unwrap()
+                            .unwrap()
+                    }
                     _ => panic!("\n🛑 download_snapshot failed\n"),
                 };
                 payload
@@ -345,7 +356,11 @@ fn download_snapshot_test() {
         )
         .map(|reply_payload| {
             let payload: Vec<u8> = match reply_payload {
-                WasmResult::Reply(payload) => candid::decode_one(&payload).unwrap(),
+                WasmResult::Reply(payload) => {
+                    candid::decode_one::<Result<Vec<u8>, SnapshotError>>(&payload)
+                        .unwrap()
+                        .unwrap()
+                }
                 _ => panic!("\n🛑 download_snapshot failed\n"),
             };
             payload
@@ -363,7 +378,11 @@ fn download_snapshot_test() {
         )
         .map(|reply_payload| {
             let payload: Vec<u8> = match reply_payload {
-                WasmResult::Reply(payload) => candid::decode_one(&payload).unwrap(),
+                WasmResult::Reply(payload) => {
+                    candid::decode_one::<Result<Vec<u8>, SnapshotError>>(&payload)
+                        .unwrap()
+                        .unwrap()
+                }
                 _ => panic!("\n🛑 download_snapshot failed\n"),
             };
             payload
@@ -396,12 +415,13 @@ fn download_snapshot_test() {
             candid::encode_args((0 as u64, 10 as u64)).unwrap(),
         )
         .map(|reply_payload| {
-            let payload: Vec<u8> = match reply_payload {
+            let payload: Result<Vec<u8>, SnapshotError> = match reply_payload {
                 WasmResult::Reply(payload) => candid::decode_one(&payload).unwrap(),
                 _ => panic!("\n🛑 place_bet failed\n"),
             };
             payload
-        });
+        })
+        .unwrap();
     println!("Expected err res: {:?}", res);
     assert_eq!(res.is_err(), true);
 
@@ -548,7 +568,7 @@ fn download_snapshot_test() {
             alice2_individual_template_canister_id,
             reclaim_principal_id,
             "receive_and_save_snaphot",
-            candid::encode_args((0 as u64, alice_snapshot)).unwrap(),
+            candid::encode_args((0 as u64, &alice_snapshot)).unwrap(),
         )
         .map(|reply_payload| {
             let payload: _ = match reply_payload {
@@ -558,13 +578,19 @@ fn download_snapshot_test() {
             payload
         })
         .unwrap();
+    finalize_received_snapshot(
+        &pic,
+        alice2_individual_template_canister_id,
+        reclaim_principal_id,
+        &alice_snapshot,
+    );
 
     let res: () = pic
         .update_call(
             bob2_individual_template_canister_id,
             reclaim_principal_id,
             "receive_and_save_snaphot",
-            candid::encode_args((0 as u64, bob_snapshot)).unwrap(),
+            candid::encode_args((0 as u64, &bob_snapshot)).unwrap(),
         )
         .map(|reply_payload| {
             let payload: _ = match reply_payload {
@@ -574,13 +600,19 @@ fn download_snapshot_test() {
             payload
         })
         .unwrap();
+    finalize_received_snapshot(
+        &pic,
+        bob2_individual_template_canister_id,
+        reclaim_principal_id,
+        &bob_snapshot,
+    );
 
     let res: () = pic
         .update_call(
             dan2_individual_template_canister_id,
             reclaim_principal_id,
             "receive_and_save_snaphot",
-            candid::encode_args((0 as u64, dan_snapshot)).unwrap(),
+            candid::encode_args((0 as u64, &dan_snapshot)).unwrap(),
         )
         .map(|reply_payload| {
             let payload: _ = match reply_payload {
@@ -590,10 +622,16 @@ fn download_snapshot_test() {
             payload
         })
         .unwrap();
+    finalize_received_snapshot(
+        &pic,
+        dan2_individual_template_canister_id,
+        reclaim_principal_id,
+        &dan_snapshot,
+    );
 
     // Load snapshots
 
-    let res: () = pic
+    let res: Result<(), SnapshotError> = pic
         .update_call(
             alice2_individual_template_canister_id,
             reclaim_principal_id,
@@ -608,8 +646,9 @@ fn download_snapshot_test() {
             payload
         })
         .unwrap();
+    assert_eq!(res, Ok(()));
 
-    let res: () = pic
+    let res: Result<(), SnapshotError> = pic
         .update_call(
             bob2_individual_template_canister_id,
             reclaim_principal_id,
@@ -624,8 +663,9 @@ fn download_snapshot_test() {
             payload
         })
         .unwrap();
+    assert_eq!(res, Ok(()));
 
-    let res: () = pic
+    let res: Result<(), SnapshotError> = pic
         .update_call(
             dan2_individual_template_canister_id,
             reclaim_principal_id,
@@ -640,6 +680,7 @@ fn download_snapshot_test() {
             payload
         })
         .unwrap();
+    assert_eq!(res, Ok(()));
 
     // Query Alice canister for info
 
@@ -775,7 +816,11 @@ fn all_canister_snapshot_tests() {
             )
             .unwrap();
         let chunk: Vec<u8> = match response {
-            WasmResult::Reply(payload) => candid::decode_one(&payload).unwrap(),
+            WasmResult::Reply(payload) => {
+                candid::decode_one::<Result<Vec<u8>, SnapshotError>>(&payload)
+                    .unwrap()
+                    .unwrap()
+            }
             _ => panic!("\n🛑 download_snapshot platform orchestrator failed for {offset}\n"),
         };
 
@@ -806,6 +851,12 @@ fn all_canister_snapshot_tests() {
         };
         offset += length;
     }
+    finalize_received_snapshot(
+        &pocket_ic,
+        platform_canister_id,
+        reclaim_principal_id,
+        &data,
+    );
 
     pocket_ic
         .update_call(
@@ -845,7 +896,11 @@ fn all_canister_snapshot_tests() {
             )
             .unwrap();
         let chunk: Vec<u8> = match response {
-            WasmResult::Reply(payload) => candid::decode_one(&payload).unwrap(),
+            WasmResult::Reply(payload) => {
+                candid::decode_one::<Result<Vec<u8>, SnapshotError>>(&payload)
+                    .unwrap()
+                    .unwrap()
+            }
             _ => panic!("\n🛑 download_snapshot failed for user index\n"),
         };
 
@@ -876,6 +931,12 @@ fn all_canister_snapshot_tests() {
         };
         offset += length;
     }
+    finalize_received_snapshot(
+        &pocket_ic,
+        user_index_canister_id,
+        reclaim_principal_id,
+        &data,
+    );
 
     if pocket_ic
         .update_call(
@@ -890,6 +951,36 @@ fn all_canister_snapshot_tests() {
     };
 }
 
+/// Snapshots sent through `receive_and_save_snaphot` are only loaded once they are
+/// checked against their manifest
+fn finalize_received_snapshot(
+    pocket_ic: &PocketIc,
+    canister_id: Principal,
+    sender: Principal,
+    snapshot: &[u8],
+) {
+    let manifest = get_snapshot_manifest(
+        snapshot.len() as u64,
+        SNAPSHOT_TRANSFER_CHUNK_SIZE,
+        |offset, length| Ok(read_snapshot_range(snapshot, offset, length).to_vec()),
+    )
+    .unwrap();
+
+    let response = pocket_ic
+        .update_call(
+            canister_id,
+            sender,
+            "finalize_received_snapshot",
+            encode_one(manifest.clone()).unwrap(),
+        )
+        .unwrap();
+    let res: Result<SnapshotManifest, SnapshotError> = match response {
+        WasmResult::Reply(payload) => candid::decode_one(&payload).unwrap(),
+        _ => panic!("\n🛑 finalize_received_snapshot failed\n"),
+    };
+    assert_eq!(res, Ok(manifest));
+}
+
 const PF_ORCH_WASM_PATH: &str =
     "../../../target/wasm32-unknown-unknown/release/platform_orchestrator.wasm.gz";
 
@@ -1 +1,2 @@
-pub mod download_snapshot_test;
\ No newline at end of file
+pub mod download_snapshot_test;
+pub mod restore_user_canister_test;
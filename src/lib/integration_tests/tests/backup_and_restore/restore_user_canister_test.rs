@@ -0,0 +1,318 @@
+use candid::{encode_args, encode_one, Principal};
+use pocket_ic::WasmResult;
+use shared_utils::{
+    canister_specific::{
+        individual_user_template::types::profile::UserProfileDetailsForFrontend,
+        user_index::types::RestoredUserCanister,
+    },
+    common::{
+        types::{
+            known_principal::KnownPrincipalType,
+            snapshot::{SnapshotError, SnapshotInfo, SnapshotManifest},
+        },
+        utils::snapshot_transfer::{get_chunk_sha256, read_snapshot_chunk},
+    },
+    constant::RECLAIM_CANISTER_PRINCIPAL_ID,
+};
+use test_utils::setup::{
+    env::pocket_ic_env::get_new_pocket_ic_env, test_constants::get_mock_user_alice_principal_id,
+};
+
+#[test]
+fn restore_user_canister_from_snapshot_test() {
+    let (pic, known_principals) = get_new_pocket_ic_env();
+
+    let platform_canister_id = known_principals
+        .get(&KnownPrincipalType::CanisterIdPlatformOrchestrator)
+        .cloned()
+        .unwrap();
+
+    let global_admin = known_principals
+        .get(&KnownPrincipalType::UserIdGlobalSuperAdmin)
+        .cloned()
+        .unwrap();
+
+    let application_subnets = pic.topology().get_app_subnets();
+
+    let subnet_orchestrator_canister_id = pic
+        .update_call(
+            platform_canister_id,
+            global_admin,
+            "provision_subnet_orchestrator_canister",
+            candid::encode_one(application_subnets[0]).unwrap(),
+        )
+        .map(|res| {
+            let canister_id_result: Result<Principal, String> = match res {
+                WasmResult::Reply(payload) => candid::decode_one(&payload).unwrap(),
+                _ => panic!("Canister call failed"),
+            };
+            canister_id_result.unwrap()
+        })
+        .unwrap();
+
+    for _ in 0..50 {
+        pic.tick()
+    }
+
+    let alice_principal_id = get_mock_user_alice_principal_id();
+
+    let alice_canister_id = pic
+        .update_call(
+            subnet_orchestrator_canister_id,
+            alice_principal_id,
+            "get_requester_principals_canister_id_create_if_not_exists",
+            candid::encode_one(()).unwrap(),
+        )
+        .map(|reply_payload| {
+            let response: Result<Principal, String> = match reply_payload {
+                WasmResult::Reply(payload) => candid::decode_one(&payload).unwrap(),
+                _ => panic!("\n🛑 get requester principals canister id failed\n"),
+            };
+            response
+        })
+        .unwrap()
+        .unwrap();
+
+    pic.update_call(
+        alice_canister_id,
+        alice_principal_id,
+        "update_profile_set_unique_username_once",
+        candid::encode_one(String::from("cool_alice_1234")).unwrap(),
+    )
+    .unwrap();
+
+    let alice_profile = get_profile_details(&pic, alice_canister_id);
+    assert_eq!(
+        alice_profile.unique_user_name,
+        Some("cool_alice_1234".to_string())
+    );
+
+    // Snapshot alice's canister and download it in chunks
+    let reclaim_principal_id = Principal::from_text(RECLAIM_CANISTER_PRINCIPAL_ID).unwrap();
+
+    pic.update_call(
+        alice_canister_id,
+        reclaim_principal_id,
+        "save_snapshot_cbor",
+        encode_one(()).unwrap(),
+    )
+    .map(|reply_payload| {
+        let response: Result<u32, SnapshotError> = match reply_payload {
+            WasmResult::Reply(payload) => candid::decode_one(&payload).unwrap(),
+            _ => panic!("\n🛑 save_snapshot_cbor failed\n"),
+        };
+        response.unwrap()
+    })
+    .unwrap();
+
+    let manifest = pic
+        .query_call(
+            alice_canister_id,
+            reclaim_principal_id,
+            "get_snapshot_manifest",
+            encode_one(()).unwrap(),
+        )
+        .map(|reply_payload| {
+            let response: Result<SnapshotManifest, SnapshotError> = match reply_payload {
+                WasmResult::Reply(payload) => candid::decode_one(&payload).unwrap(),
+                _ => panic!("\n🛑 get_snapshot_manifest failed\n"),
+            };
+            response.unwrap()
+        })
+        .unwrap();
+
+    let mut alice_snapshot = vec![];
+    for chunk_index in 0..manifest.chunk_count {
+        let chunk = pic
+            .query_call(
+                alice_canister_id,
+                reclaim_principal_id,
+                "download_snapshot_chunk",
+                encode_one(chunk_index).unwrap(),
+            )
+            .map(|reply_payload| {
+                let response: Result<Vec<u8>, SnapshotError> = match reply_payload {
+                    WasmResult::Reply(payload) => candid::decode_one(&payload).unwrap(),
+                    _ => panic!("\n🛑 download_snapshot_chunk failed\n"),
+                };
+                response.unwrap()
+            })
+            .unwrap();
+        alice_snapshot.extend(chunk);
+    }
+    assert_eq!(alice_snapshot.len() as u64, manifest.total_size);
+
+    // Lose alice's canister
+    pic.stop_canister(alice_canister_id, Some(subnet_orchestrator_canister_id))
+        .unwrap();
+    pic.delete_canister(alice_canister_id, Some(subnet_orchestrator_canister_id))
+        .unwrap();
+
+    // Upload the snapshot to the subnet orchestrator in chunks
+    call_snapshot_upload_endpoint(
+        &pic,
+        subnet_orchestrator_canister_id,
+        reclaim_principal_id,
+        "start_restore_snapshot_upload",
+        encode_one(manifest.clone()).unwrap(),
+    );
+    for chunk_index in 0..manifest.chunk_count {
+        let chunk = read_snapshot_chunk(&alice_snapshot, manifest.chunk_size, chunk_index).unwrap();
+        let chunk_sha256 = get_chunk_sha256(&chunk);
+        call_snapshot_upload_endpoint(
+            &pic,
+            subnet_orchestrator_canister_id,
+            reclaim_principal_id,
+            "upload_restore_snapshot_chunk",
+            encode_args((chunk_index, chunk, chunk_sha256)).unwrap(),
+        );
+    }
+    pic.update_call(
+        subnet_orchestrator_canister_id,
+        reclaim_principal_id,
+        "finalize_restore_snapshot_upload",
+        encode_one(()).unwrap(),
+    )
+    .map(|reply_payload| {
+        let response: Result<SnapshotManifest, SnapshotError> = match reply_payload {
+            WasmResult::Reply(payload) => candid::decode_one(&payload).unwrap(),
+            _ => panic!("\n🛑 finalize_restore_snapshot_upload failed\n"),
+        };
+        assert_eq!(response, Ok(manifest.clone()));
+    })
+    .unwrap();
+
+    let snapshot_id = list_snapshots(
+        &pic,
+        subnet_orchestrator_canister_id,
+        reclaim_principal_id,
+        "list_restore_snapshots",
+    )
+    .last()
+    .unwrap()
+    .id;
+    // the upload is not one of the subnet orchestrator's own snapshots
+    assert!(list_snapshots(
+        &pic,
+        subnet_orchestrator_canister_id,
+        reclaim_principal_id,
+        "list_snapshots",
+    )
+    .is_empty());
+
+    // Only admins can restore
+    let res = pic
+        .update_call(
+            subnet_orchestrator_canister_id,
+            alice_principal_id,
+            "restore_user_canister_from_snapshot",
+            encode_args((alice_principal_id, snapshot_id)).unwrap(),
+        )
+        .unwrap();
+    assert!(matches!(res, WasmResult::Reject(_)));
+
+    let restored_user_canister = pic
+        .update_call(
+            subnet_orchestrator_canister_id,
+            platform_canister_id,
+            "restore_user_canister_from_snapshot",
+            encode_args((alice_principal_id, snapshot_id)).unwrap(),
+        )
+        .map(|reply_payload| {
+            let response: Result<RestoredUserCanister, String> = match reply_payload {
+                WasmResult::Reply(payload) => candid::decode_one(&payload).unwrap(),
+                _ => panic!("\n🛑 restore_user_canister_from_snapshot failed\n"),
+            };
+            response.unwrap()
+        })
+        .unwrap();
+    let restored_canister_id = restored_user_canister.canister_id;
+    assert_ne!(restored_canister_id, alice_canister_id);
+    assert_eq!(restored_user_canister.username_conflict, None);
+
+    let restored_profile = get_profile_details(&pic, restored_canister_id);
+    assert_eq!(restored_profile, alice_profile);
+
+    let canister_id_of_alice = pic
+        .query_call(
+            subnet_orchestrator_canister_id,
+            Principal::anonymous(),
+            "get_user_canister_id_from_user_principal_id",
+            encode_one(alice_principal_id).unwrap(),
+        )
+        .map(|reply_payload| {
+            let response: Option<Principal> = match reply_payload {
+                WasmResult::Reply(payload) => candid::decode_one(&payload).unwrap(),
+                _ => panic!("\n🛑 get_user_canister_id_from_user_principal_id failed\n"),
+            };
+            response
+        })
+        .unwrap();
+    assert_eq!(canister_id_of_alice, Some(restored_canister_id));
+
+    let canister_id_of_alice_username = pic
+        .query_call(
+            subnet_orchestrator_canister_id,
+            Principal::anonymous(),
+            "get_user_canister_id_from_unique_user_name",
+            encode_one(String::from("cool_alice_1234")).unwrap(),
+        )
+        .map(|reply_payload| {
+            let response: Option<Principal> = match reply_payload {
+                WasmResult::Reply(payload) => candid::decode_one(&payload).unwrap(),
+                _ => panic!("\n🛑 get_user_canister_id_from_unique_user_name failed\n"),
+            };
+            response
+        })
+        .unwrap();
+    assert_eq!(canister_id_of_alice_username, Some(restored_canister_id));
+}
+
+fn call_snapshot_upload_endpoint(
+    pic: &pocket_ic::PocketIc,
+    canister_id: Principal,
+    sender: Principal,
+    method: &str,
+    payload: Vec<u8>,
+) {
+    pic.update_call(canister_id, sender, method, payload)
+        .map(|reply_payload| {
+            let response: Result<(), SnapshotError> = match reply_payload {
+                WasmResult::Reply(payload) => candid::decode_one(&payload).unwrap(),
+                _ => panic!("\n🛑 {} failed\n", method),
+            };
+            response.unwrap()
+        })
+        .unwrap();
+}
+
+fn list_snapshots(
+    pic: &pocket_ic::PocketIc,
+    canister_id: Principal,
+    sender: Principal,
+    method: &str,
+) -> Vec<SnapshotInfo> {
+    pic.query_call(canister_id, sender, method, encode_one(()).unwrap())
+        .map(|reply_payload| match reply_payload {
+            WasmResult::Reply(payload) => candid::decode_one(&payload).unwrap(),
+            _ => panic!("\n🛑 {} failed\n", method),
+        })
+        .unwrap()
+}
+
+fn get_profile_details(
+    pic: &pocket_ic::PocketIc,
+    canister_id: Principal,
+) -> UserProfileDetailsForFrontend {
+    pic.query_call(
+        canister_id,
+        Principal::anonymous(),
+        "get_profile_details",
+        encode_one(()).unwrap(),
+    )
+    .map(|reply_payload| match reply_payload {
+        WasmResult::Reply(payload) => candid::decode_one(&payload).unwrap(),
+        _ => panic!("\n🛑 get_profile_details failed\n"),
+    })
+    .unwrap()
+}
@@ -1,6 +1,10 @@
+#[path = "../pump_n_dump/mock_ledger/mod.rs"]
+mod mock_ledger;
+pub mod test_creator_token_swap_participation;
 pub mod test_custom_governance_upgrade;
 pub mod test_deletion_of_creator_tokens;
 pub mod test_number_of_creator_tokens;
+pub mod test_sns_canisters_upgrade;
 pub mod types;
 pub mod utils;
 
@@ -21,12 +25,22 @@ use ic_sns_swap::pb::v1::{
 };
 use sha2::{Digest, Sha256};
 use shared_utils::canister_specific::individual_user_template::types::arg::IndividualUserTemplateInitArgs;
-use shared_utils::canister_specific::individual_user_template::types::error::AirdropError;
+use shared_utils::canister_specific::individual_user_template::types::cdao::{
+    AirdropConfig, AirdropEligibility, CdaoSwapOutcome, CdaoSwapSettlement,
+    CreatorTokenDeletionReport,
+};
+use shared_utils::canister_specific::individual_user_template::types::error::{
+    AirdropError, CdaoTokenError, DeleteCreatorTokenError,
+};
+use shared_utils::canister_specific::individual_user_template::types::token::TokenHolding;
 use shared_utils::constant::{
     SNS_TOKEN_ARCHIVE_MODULE_HASH, SNS_TOKEN_GOVERNANCE_MODULE_HASH, SNS_TOKEN_INDEX_MODULE_HASH,
     SNS_TOKEN_LEDGER_MODULE_HASH, SNS_TOKEN_ROOT_MODULE_HASH, SNS_TOKEN_SWAP_MODULE_HASH,
 };
-use shared_utils::types::creator_dao_stats::CreatorDaoTokenStats;
+use shared_utils::pagination::PaginationError;
+use shared_utils::types::creator_dao_stats::{
+    CreatorDaoTokenStats, CreatorTokenMetadata, CreatorTokenSortBy,
+};
 use std::time::{Duration, UNIX_EPOCH};
 use std::{collections::HashMap, fmt::Debug, str::FromStr, time::SystemTime, vec};
 use test_utils::setup::test_constants::get_mock_user_bob_principal_id;
@@ -49,7 +63,7 @@ use test_utils::setup::{
     env::pocket_ic_env::get_new_pocket_ic_env,
     test_constants::{
         get_global_super_admin_principal_id, get_mock_user_alice_principal_id,
-        get_mock_user_charlie_principal_id,
+        get_mock_user_charlie_principal_id, get_mock_user_dan_principal_id,
     },
 };
 
@@ -229,6 +243,32 @@ fn creator_dao_tests() {
     let root_canister = deployed_canister.root;
     let gov_canister = deployed_canister.governance;
 
+    let swap_settlement = pocket_ic
+        .query_call(
+            alice_canister_id,
+            alice_principal,
+            "get_cdao_swap_settlement",
+            candid::encode_one(root_canister).unwrap(),
+        )
+        .map(|res| match res {
+            WasmResult::Reply(payload) => Decode!(&payload, Option<CdaoSwapSettlement>).unwrap(),
+            _ => panic!("\n🛑 get cdao swap settlement failed\n"),
+        })
+        .unwrap()
+        .expect("swap should be settled after finalization");
+    ic_cdk::println!("🧪 Swap settlement: {:?}", swap_settlement);
+
+    assert!(matches!(
+        swap_settlement.outcome,
+        CdaoSwapOutcome::Committed {
+            total_direct_participation_icp_e8s,
+            ..
+        } if total_direct_participation_icp_e8s > 0
+    ));
+    assert!(swap_settlement
+        .direct_participants
+        .is_some_and(|participants| !participants.is_empty()));
+
     let res = pocket_ic
         .query_call(
             ledger_canister,
@@ -250,6 +290,27 @@ fn creator_dao_tests() {
         .unwrap();
     ic_cdk::println!("🧪 SNS token Balance of alice: {:?}", res);
 
+    let alice_token_holding = pocket_ic
+        .update_call(
+            alice_canister_id,
+            alice_principal,
+            "refresh_token_holding",
+            candid::encode_one(root_canister).unwrap(),
+        )
+        .map(|res| match res {
+            WasmResult::Reply(payload) => {
+                Decode!(&payload, Result<Option<TokenHolding>, CdaoTokenError>).unwrap()
+            }
+            _ => panic!("\n🛑 refresh token holding failed\n"),
+        })
+        .unwrap()
+        .unwrap()
+        .expect("creator keeps the holding of their own token");
+
+    assert_eq!(alice_token_holding.ledger, ledger_canister);
+    assert_eq!(alice_token_holding.symbol, "SIMG");
+    assert_eq!(alice_token_holding.balance, res);
+
     let tx_fee = 1u64;
     let expected_balance = Nat::from(60_000_000_000 - tx_fee);
     ic_cdk::println!("🧪 Expected Balance: {:?}", expected_balance);
@@ -402,6 +463,91 @@ fn creator_dao_tests() {
     ic_cdk::println!("🧪 Result: {:?}", res);
     assert!(res.unwrap().is_err());
 
+    // claiming the airdrop again
+    let res = pocket_ic
+        .update_call(
+            alice_canister_id,
+            bob,
+            "request_airdrop",
+            encode_args((
+                root_canister,
+                None::<Memo>,
+                Nat::from(100u64) * 10u64.pow(decimals.into()),
+                bob_canister_id,
+            ))
+            .unwrap(),
+        )
+        .map(|reply_payload| match reply_payload {
+            WasmResult::Reply(payload) => Decode!(&payload, Result<(), AirdropError>).unwrap(),
+            _ => panic!("\n🛑 request airdrop failed\n"),
+        })
+        .unwrap();
+    assert_eq!(res, Err(AirdropError::AlreadyClaimedAirdrop));
+
+    // only registered users can claim once the creator configures it
+    let airdrop_config = AirdropConfig {
+        total_budget: Nat::from(100u64) * 10u64.pow(decimals.into()),
+        amount_per_user: Nat::from(10u64) * 10u64.pow(decimals.into()),
+        eligibility: AirdropEligibility {
+            registered_session_only: true,
+            min_account_age_secs: None,
+            followers_only: false,
+        },
+        start_time: None,
+        end_time: None,
+    };
+    let res = pocket_ic
+        .update_call(
+            alice_canister_id,
+            alice_principal,
+            "set_airdrop_config",
+            encode_args((root_canister, airdrop_config)).unwrap(),
+        )
+        .map(|reply_payload| match reply_payload {
+            WasmResult::Reply(payload) => Decode!(&payload, Result<(), AirdropError>).unwrap(),
+            _ => panic!("\n🛑 set airdrop config failed\n"),
+        })
+        .unwrap();
+    assert_eq!(res, Ok(()));
+
+    let dan = get_mock_user_dan_principal_id();
+    let dan_canister_id: Principal = pocket_ic
+        .update_call(
+            subnet_orchestrator_canister_id,
+            dan,
+            "get_requester_principals_canister_id_create_if_not_exists",
+            candid::encode_one(()).unwrap(),
+        )
+        .map(|reply_payload| {
+            let response: Result<Principal, String> = match reply_payload {
+                WasmResult::Reply(payload) => candid::decode_one(&payload).unwrap(),
+                _ => panic!("\n🛑 get requester principals canister id failed\n"),
+            };
+            response
+        })
+        .unwrap()
+        .unwrap();
+
+    let res = pocket_ic
+        .update_call(
+            alice_canister_id,
+            dan,
+            "request_airdrop",
+            encode_args((
+                root_canister,
+                None::<Memo>,
+                Nat::from(10u64) * 10u64.pow(decimals.into()),
+                dan_canister_id,
+            ))
+            .unwrap(),
+        )
+        .map(|reply_payload| match reply_payload {
+            WasmResult::Reply(payload) => Decode!(&payload, Result<(), AirdropError>).unwrap(),
+            _ => panic!("\n🛑 request airdrop failed\n"),
+        })
+        .unwrap();
+    assert!(matches!(res, Err(AirdropError::NotEligible(_))));
+
     let deployed_cdao = pocket_ic
         .query_call(
             alice_canister_id,
@@ -483,6 +629,50 @@ fn creator_dao_tests() {
 
     assert_eq!(creator_dao_stats.total_number_of_creator_dao_tokens, 1);
 
+    let creator_tokens = pocket_ic
+        .query_call(
+            platform_canister_id,
+            Principal::anonymous(),
+            "get_creator_tokens",
+            encode_args((CreatorTokenSortBy::Newest, 0_u64, 10_u64)).unwrap(),
+        )
+        .map(|wasm_result| {
+            let result: Result<Vec<CreatorTokenMetadata>, PaginationError> = match wasm_result {
+                WasmResult::Reply(payload) => candid::decode_one(&payload).unwrap(),
+                WasmResult::Reject(e) => panic!("\n failed to get creator tokens {}", e),
+            };
+            result
+        })
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(creator_tokens.len(), 1);
+    assert_eq!(creator_tokens[0].root, root_canister);
+    assert_eq!(creator_tokens[0].symbol, "SIMG");
+    assert_eq!(creator_tokens[0].creator_profile_id, alice_principal);
+    assert_eq!(creator_tokens[0].creator_canister_id, alice_canister_id);
+
+    let creator_tokens_of_bob = pocket_ic
+        .query_call(
+            platform_canister_id,
+            Principal::anonymous(),
+            "get_creator_tokens_of_creator",
+            encode_args((get_mock_user_bob_principal_id(), 0_u64, 10_u64)).unwrap(),
+        )
+        .map(|wasm_result| {
+            let result: Result<Vec<CreatorTokenMetadata>, PaginationError> = match wasm_result {
+                WasmResult::Reply(payload) => candid::decode_one(&payload).unwrap(),
+                WasmResult::Reject(e) => panic!("\n failed to get creator tokens {}", e),
+            };
+            result
+        })
+        .unwrap();
+
+    assert_eq!(
+        creator_tokens_of_bob,
+        Err(PaginationError::ReachedEndOfItemsList)
+    );
+
     pocket_ic
         .update_call(
             platform_canister_id,
@@ -513,4 +703,95 @@ fn creator_dao_tests() {
         .unwrap();
 
     assert_eq!(creator_dao_stats.total_number_of_creator_dao_tokens, 1);
+
+    pocket_ic.advance_time(Duration::from_secs(301));
+
+    let delete_result = pocket_ic
+        .update_call(
+            alice_canister_id,
+            alice_principal,
+            "delete_creator_token",
+            encode_args((root_canister, false)).unwrap(),
+        )
+        .map(|wasm_result| match wasm_result {
+            WasmResult::Reply(payload) => Decode!(
+                &payload,
+                Result<CreatorTokenDeletionReport, DeleteCreatorTokenError>
+            )
+            .unwrap(),
+            WasmResult::Reject(e) => panic!("\n failed to delete creator token {}", e),
+        })
+        .unwrap();
+
+    assert!(matches!(
+        delete_result,
+        Err(DeleteCreatorTokenError::HoldersExist(_))
+    ));
+
+    let forced_delete_result = pocket_ic
+        .update_call(
+            alice_canister_id,
+            alice_principal,
+            "delete_creator_token",
+            encode_args((root_canister, true)).unwrap(),
+        )
+        .map(|wasm_result| match wasm_result {
+            WasmResult::Reply(payload) => Decode!(
+                &payload,
+                Result<CreatorTokenDeletionReport, DeleteCreatorTokenError>
+            )
+            .unwrap(),
+            WasmResult::Reject(e) => panic!("\n failed to delete creator token {}", e),
+        })
+        .unwrap();
+
+    assert_eq!(
+        forced_delete_result,
+        Err(DeleteCreatorTokenError::Unauthorized)
+    );
+
+    let delete_report = pocket_ic
+        .update_call(
+            platform_canister_id,
+            charlie_global_admin,
+            "delete_sns_creator_token_of_an_individual_canister",
+            encode_args((alice_canister_id, root_canister, true)).unwrap(),
+        )
+        .map(|wasm_result| match wasm_result {
+            WasmResult::Reply(payload) => Decode!(
+                &payload,
+                Result<CreatorTokenDeletionReport, DeleteCreatorTokenError>
+            )
+            .unwrap(),
+            WasmResult::Reject(e) => panic!("\n failed to delete creator token {}", e),
+        })
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(delete_report.root, root_canister);
+    assert_eq!(delete_report.returned_canister_ids.len(), 5);
+    assert!(delete_report.failed_canister_ids.is_empty());
+    assert!(delete_report.reclaimed_cycles > Nat::from(0_u32));
+
+    for _ in 0..5 {
+        pocket_ic.tick();
+    }
+
+    let creator_dao_stats = pocket_ic
+        .query_call(
+            platform_canister_id,
+            charlie_global_admin,
+            "get_creator_dao_stats",
+            candid::encode_one(()).unwrap(),
+        )
+        .map(|wasm_result| {
+            let result: CreatorDaoTokenStats = match wasm_result {
+                WasmResult::Reply(payload) => candid::decode_one(&payload).unwrap(),
+                WasmResult::Reject(e) => panic!("\n failed to get creator dao stats {}", e),
+            };
+            result
+        })
+        .unwrap();
+
+    assert_eq!(creator_dao_stats.total_number_of_creator_dao_tokens, 0);
 }
@@ -0,0 +1,204 @@
+use std::time::Duration;
+
+use candid::{Nat, Principal};
+use shared_utils::{
+    canister_specific::individual_user_template::types::{
+        cdao::{
+            DeployedCdaoCanisters, SwapParticipation, SwapParticipationStatus, SwapPaymentToken,
+        },
+        error::{CdaoDeployError, SwapParticipationError},
+    },
+    common::types::known_principal::KnownPrincipalType,
+    constant::{NNS_LEDGER_CANISTER_ID, SNS_WASM_W_PRINCIPAL_ID},
+    pagination::PaginationError,
+};
+use test_utils::setup::{
+    env::pocket_ic_env::{
+        execute_query_multi, execute_update, execute_update_multi, execute_update_no_res,
+        execute_update_no_res_multi, get_new_pocket_ic_env,
+    },
+    test_constants::{
+        get_mock_user_alice_principal_id, get_mock_user_bob_principal_id,
+        get_mock_user_charlie_principal_id,
+    },
+};
+
+use crate::{
+    mock_ledger::{
+        self,
+        mock_ledger_intf::{Account, ApproveArgs, TransferArg},
+        LEDGER_FEE,
+    },
+    utils::{get_default_sns_init_args, setup_sns_w_canister_for_creator_dao},
+};
+
+#[test]
+pub fn test_creator_token_swap_participation() {
+    let (pocket_ic, known_principal) = get_new_pocket_ic_env();
+
+    let super_admin = known_principal[&KnownPrincipalType::UserIdGlobalSuperAdmin];
+    let platform_canister_id = known_principal[&KnownPrincipalType::CanisterIdPlatformOrchestrator];
+    let charlie_global_admin = get_mock_user_charlie_principal_id();
+    let application_subnets = pocket_ic.topology().get_app_subnets();
+
+    execute_update_no_res(
+        &pocket_ic,
+        super_admin,
+        platform_canister_id,
+        "add_principal_as_global_admin",
+        &charlie_global_admin,
+    );
+
+    execute_update_no_res_multi(
+        &pocket_ic,
+        super_admin,
+        platform_canister_id,
+        "update_global_known_principal",
+        (
+            KnownPrincipalType::CanisterIdSnsWasm,
+            Principal::from_text(SNS_WASM_W_PRINCIPAL_ID).unwrap(),
+        ),
+    );
+
+    let subnet_orchestrator_canister_id = execute_update::<_, Result<Principal, String>>(
+        &pocket_ic,
+        charlie_global_admin,
+        platform_canister_id,
+        "provision_subnet_orchestrator_canister",
+        &application_subnets[1],
+    )
+    .unwrap();
+
+    for _ in 0..50 {
+        pocket_ic.tick();
+    }
+
+    let alice_principal = get_mock_user_alice_principal_id();
+    let alice_canister_id = execute_update::<_, Result<Principal, String>>(
+        &pocket_ic,
+        alice_principal,
+        subnet_orchestrator_canister_id,
+        "get_requester_principals_canister_id_create_if_not_exists",
+        &(),
+    )
+    .unwrap();
+
+    let bob_principal = get_mock_user_bob_principal_id();
+    let bob_canister_id = execute_update::<_, Result<Principal, String>>(
+        &pocket_ic,
+        bob_principal,
+        subnet_orchestrator_canister_id,
+        "get_requester_principals_canister_id_create_if_not_exists",
+        &(),
+    )
+    .unwrap();
+
+    setup_sns_w_canister_for_creator_dao(&pocket_ic, super_admin);
+
+    let deployed_cdao_canisters =
+        execute_update_multi::<_, Result<DeployedCdaoCanisters, CdaoDeployError>>(
+            &pocket_ic,
+            alice_principal,
+            alice_canister_id,
+            "deploy_cdao_sns",
+            (
+                get_default_sns_init_args(super_admin, alice_principal),
+                300_u64,
+            ),
+        )
+        .unwrap();
+
+    // wait for the swap to open
+    pocket_ic.advance_time(Duration::from_secs(200));
+    pocket_ic.tick();
+
+    // swaps are paid on the NNS ledger, the mock ledger of the pump and dump tests takes
+    // its place
+    let icp_ledger = Principal::from_text(NNS_LEDGER_CANISTER_ID).unwrap();
+    mock_ledger::reinstall(&pocket_ic, icp_ledger, super_admin, super_admin);
+    execute_update_no_res(
+        &pocket_ic,
+        super_admin,
+        icp_ledger,
+        "icrc1_transfer",
+        &TransferArg {
+            to: Account {
+                owner: bob_principal,
+                subaccount: None,
+            },
+            fee: None,
+            memo: None,
+            from_subaccount: None,
+            created_at_time: None,
+            amount: Nat::from(100_000_000_u64),
+        },
+    );
+
+    let amount_e8s = 1_000_000_u64;
+
+    let res = execute_update_multi::<_, Result<SwapParticipation, SwapParticipationError>>(
+        &pocket_ic,
+        bob_principal,
+        bob_canister_id,
+        "participate_in_creator_token_swap",
+        (deployed_cdao_canisters.root, amount_e8s),
+    );
+    assert!(
+        matches!(res, Err(SwapParticipationError::TransferFrom(_))),
+        "participation without an allowance should fail, got {:?}",
+        res
+    );
+
+    execute_update_no_res(
+        &pocket_ic,
+        bob_principal,
+        icp_ledger,
+        "icrc2_approve",
+        &ApproveArgs {
+            fee: None,
+            memo: None,
+            from_subaccount: None,
+            created_at_time: None,
+            amount: Nat::from(amount_e8s + LEDGER_FEE),
+            expected_allowance: None,
+            expires_at: None,
+            spender: Account {
+                owner: bob_canister_id,
+                subaccount: None,
+            },
+        },
+    );
+
+    let participation =
+        execute_update_multi::<_, Result<SwapParticipation, SwapParticipationError>>(
+            &pocket_ic,
+            bob_principal,
+            bob_canister_id,
+            "participate_in_creator_token_swap",
+            (deployed_cdao_canisters.root, amount_e8s),
+        )
+        .unwrap();
+    assert_eq!(participation.swap_canister, deployed_cdao_canisters.swap);
+    assert_eq!(participation.payment_token, SwapPaymentToken::Icp);
+    assert_eq!(
+        participation.status,
+        SwapParticipationStatus::Committed {
+            accepted_participation_e8s: amount_e8s
+        }
+    );
+
+    let history = execute_query_multi::<_, Result<Vec<SwapParticipation>, PaginationError>>(
+        &pocket_ic,
+        bob_principal,
+        bob_canister_id,
+        "get_creator_token_swap_participations_with_pagination_cursor",
+        (0_u64, 10_u64),
+    )
+    .unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].id, participation.id);
+    assert!(matches!(
+        history[1].status,
+        SwapParticipationStatus::PaymentFailed { .. }
+    ));
+}
@@ -15,7 +15,10 @@ use ic_sns_swap::pb::v1::NeuronBasketConstructionParameters;
 use pocket_ic::WasmResult;
 use shared_utils::{
     canister_specific::individual_user_template::types::{
-        cdao::DeployedCdaoCanisters, error::CdaoDeployError,
+        cdao::{
+            CdaoDeploymentStatus, CdaoDeploymentStep, CreatorTokenLimit, DeployedCdaoCanisters,
+        },
+        error::CdaoDeployError,
     },
     common::types::known_principal::KnownPrincipalType,
     constant::{MAX_LIMIT_FOR_CREATOR_DAO_SNS_TOKEN, SNS_WASM_W_PRINCIPAL_ID},
@@ -235,6 +238,28 @@ pub fn test_number_of_creator_tokens() {
 
         assert!(creator_dao_deployed_cans_result.is_ok());
     }
+
+    for deployment_id in 0..MAX_LIMIT_FOR_CREATOR_DAO_SNS_TOKEN as u64 {
+        let deployment_status = pocket_ic
+            .query_call(
+                alice_canister_id,
+                alice_principal,
+                "get_cdao_deployment_status",
+                candid::encode_one(deployment_id).unwrap(),
+            )
+            .map(|res| match res {
+                WasmResult::Reply(payload) => {
+                    Decode!(&payload, Option<CdaoDeploymentStatus>).unwrap()
+                }
+                _ => panic!("\n🛑 get cdao deployment status failed with {:?}", res),
+            })
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(deployment_status.step, CdaoDeploymentStep::Completed);
+        assert_eq!(deployment_status.canister_ids.len(), 5);
+        assert!(!deployment_status.is_running);
+    }
     let creator_dao_deployed_cans_result = pocket_ic
         .update_call(
             alice_canister_id,
@@ -256,5 +281,59 @@ pub fn test_number_of_creator_tokens() {
 
     assert!(creator_dao_deployed_cans_result.is_err());
     assert!(matches!(
-        creator_dao_deployed_cans_result, Err(e) if e == CdaoDeployError::TokenLimit(MAX_LIMIT_FOR_CREATOR_DAO_SNS_TOKEN)))
+        creator_dao_deployed_cans_result, Err(e) if e == CdaoDeployError::TokenLimit(MAX_LIMIT_FOR_CREATOR_DAO_SNS_TOKEN)));
+
+    let override_result = pocket_ic
+        .update_call(
+            platform_canister_id,
+            charlie_global_admin,
+            "set_creator_token_limit_override_for_individual_canister",
+            candid::encode_args((
+                alice_canister_id,
+                Some(MAX_LIMIT_FOR_CREATOR_DAO_SNS_TOKEN + 1),
+            ))
+            .unwrap(),
+        )
+        .map(|res| match res {
+            WasmResult::Reply(payload) => Decode!(&payload, Result<(), String>).unwrap(),
+            _ => panic!(
+                "\n🛑 set creator token limit override failed with {:?}",
+                res
+            ),
+        })
+        .unwrap();
+
+    assert!(override_result.is_ok());
+
+    pocket_ic
+        .update_call(
+            platform_canister_id,
+            charlie_global_admin,
+            "update_creator_token_limit_for_all_subnets",
+            candid::encode_one(1_usize).unwrap(),
+        )
+        .unwrap();
+
+    for _ in 0..10 {
+        pocket_ic.tick();
+    }
+
+    let creator_token_limit = pocket_ic
+        .query_call(
+            alice_canister_id,
+            alice_principal,
+            "get_creator_token_limit",
+            candid::encode_one(()).unwrap(),
+        )
+        .map(|res| match res {
+            WasmResult::Reply(payload) => Decode!(&payload, CreatorTokenLimit).unwrap(),
+            _ => panic!("\n🛑 get creator token limit failed with {:?}", res),
+        })
+        .unwrap();
+
+    assert_eq!(creator_token_limit.network_limit, 1);
+    assert_eq!(
+        creator_token_limit.get_limit(),
+        MAX_LIMIT_FOR_CREATOR_DAO_SNS_TOKEN + 1
+    );
 }
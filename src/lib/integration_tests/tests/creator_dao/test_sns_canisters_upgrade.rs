@@ -0,0 +1,231 @@
+use candid::Principal;
+use pocket_ic::{PocketIc, WasmResult};
+use sha2::{Digest, Sha256};
+use shared_utils::{
+    canister_specific::individual_user_template::types::cdao::DeployedCdaoCanisters,
+    common::types::{known_principal::KnownPrincipalType, wasm::SnsWasmType},
+    constant::SNS_WASM_W_PRINCIPAL_ID,
+};
+use test_utils::setup::{
+    env::pocket_ic_env::get_new_pocket_ic_env,
+    test_constants::{get_mock_user_alice_principal_id, get_mock_user_charlie_principal_id},
+};
+
+use crate::utils::{setup_default_sns_creator_token, setup_sns_w_canister_for_creator_dao};
+
+#[test]
+pub fn test_upgrading_creator_sns_canisters_keeps_the_custom_governance_wasm() {
+    let (pocket_ic, known_principal) = get_new_pocket_ic_env();
+    let platform_canister_id = known_principal
+        .get(&KnownPrincipalType::CanisterIdPlatformOrchestrator)
+        .cloned()
+        .unwrap();
+
+    let super_admin = *known_principal
+        .get(&KnownPrincipalType::UserIdGlobalSuperAdmin)
+        .unwrap();
+
+    let application_subnets = pocket_ic.topology().get_app_subnets();
+
+    let charlie_global_admin = get_mock_user_charlie_principal_id();
+
+    pocket_ic
+        .update_call(
+            platform_canister_id,
+            super_admin,
+            "add_principal_as_global_admin",
+            candid::encode_one(charlie_global_admin).unwrap(),
+        )
+        .unwrap();
+
+    pocket_ic
+        .update_call(
+            platform_canister_id,
+            super_admin,
+            "update_global_known_principal",
+            candid::encode_args((
+                KnownPrincipalType::CanisterIdSnsWasm,
+                Principal::from_text(SNS_WASM_W_PRINCIPAL_ID).unwrap(),
+            ))
+            .unwrap(),
+        )
+        .unwrap();
+
+    let subnet_orchestrator_canister_id: Principal = pocket_ic
+        .update_call(
+            platform_canister_id,
+            charlie_global_admin,
+            "provision_subnet_orchestrator_canister",
+            candid::encode_one(application_subnets[1]).unwrap(),
+        )
+        .map(|res| {
+            let canister_id_result: Result<Principal, String> = match res {
+                WasmResult::Reply(payload) => candid::decode_one(&payload).unwrap(),
+                _ => panic!("Canister call failed"),
+            };
+            canister_id_result.unwrap()
+        })
+        .unwrap();
+
+    for _ in 0..50 {
+        pocket_ic.tick();
+    }
+
+    let alice_principal = get_mock_user_alice_principal_id();
+    let alice_canister_id: Principal = pocket_ic
+        .update_call(
+            subnet_orchestrator_canister_id,
+            alice_principal,
+            "get_requester_principals_canister_id_create_if_not_exists",
+            candid::encode_one(()).unwrap(),
+        )
+        .map(|reply_payload| {
+            let response: Result<Principal, String> = match reply_payload {
+                WasmResult::Reply(payload) => candid::decode_one(&payload).unwrap(),
+                _ => panic!("\n🛑 get requester principals canister id failed\n"),
+            };
+            response
+        })
+        .unwrap()
+        .unwrap();
+
+    setup_sns_w_canister_for_creator_dao(&pocket_ic, super_admin);
+
+    let deployed_canister = setup_default_sns_creator_token(
+        &pocket_ic,
+        super_admin,
+        alice_principal,
+        alice_canister_id,
+    );
+
+    let custom_governance_wasm =
+        include_bytes!("../../../../../wasms/custom-governance-canister.wasm.gz");
+    let custom_governance_wasm_hash = Sha256::digest(custom_governance_wasm).to_vec();
+
+    roll_out_sns_wasm(
+        &pocket_ic,
+        platform_canister_id,
+        charlie_global_admin,
+        SnsWasmType::Governance,
+        "1.0.0",
+        custom_governance_wasm.to_vec(),
+    );
+
+    assert_eq!(
+        get_module_hash(&pocket_ic, deployed_canister.governance, alice_canister_id),
+        custom_governance_wasm_hash
+    );
+    let cdao_canisters = get_deployed_cdao_canisters(&pocket_ic, alice_canister_id);
+    assert!(cdao_canisters[0].is_on_version(SnsWasmType::Governance, "1.0.0"));
+
+    // governance is not upgraded along with the ledger, so it keeps the custom wasm
+    let ledger_wasm = include_bytes!("../../../../../wasms/ledger.wasm.gz");
+    roll_out_sns_wasm(
+        &pocket_ic,
+        platform_canister_id,
+        charlie_global_admin,
+        SnsWasmType::Ledger,
+        "1.0.0",
+        ledger_wasm.to_vec(),
+    );
+
+    assert_eq!(
+        get_module_hash(&pocket_ic, deployed_canister.governance, alice_canister_id),
+        custom_governance_wasm_hash
+    );
+    let cdao_canisters = get_deployed_cdao_canisters(&pocket_ic, alice_canister_id);
+    assert!(cdao_canisters[0].is_on_version(SnsWasmType::Ledger, "1.0.0"));
+    assert!(!cdao_canisters[0].is_on_version(SnsWasmType::Governance, "1.0.0"));
+
+    // rerunning the governance rollout refreshes the hashes governance keeps
+    pocket_ic
+        .update_call(
+            platform_canister_id,
+            charlie_global_admin,
+            "upgrade_creator_dao_sns_canisters_in_the_network",
+            candid::encode_args((SnsWasmType::Governance, "1.0.0")).unwrap(),
+        )
+        .unwrap();
+
+    for _ in 0..110 {
+        pocket_ic.tick();
+    }
+
+    let cdao_canisters = get_deployed_cdao_canisters(&pocket_ic, alice_canister_id);
+    assert!(cdao_canisters[0].is_on_version(SnsWasmType::Governance, "1.0.0"));
+    assert_eq!(
+        get_module_hash(&pocket_ic, deployed_canister.governance, alice_canister_id),
+        custom_governance_wasm_hash
+    );
+}
+
+fn roll_out_sns_wasm(
+    pocket_ic: &PocketIc,
+    platform_canister_id: Principal,
+    global_admin: Principal,
+    wasm_type: SnsWasmType,
+    version: &str,
+    wasm_module: Vec<u8>,
+) {
+    let res = pocket_ic
+        .update_call(
+            platform_canister_id,
+            global_admin,
+            "upload_sns_wasm",
+            candid::encode_args((wasm_type, version, wasm_module)).unwrap(),
+        )
+        .map(|reply_payload| match reply_payload {
+            WasmResult::Reply(payload) => {
+                candid::decode_one::<Result<(), String>>(&payload).unwrap()
+            }
+            _ => panic!("\n🛑 upload sns wasm failed\n"),
+        })
+        .unwrap();
+    assert_eq!(res, Ok(()));
+
+    let res = pocket_ic
+        .update_call(
+            platform_canister_id,
+            global_admin,
+            "upgrade_creator_dao_sns_canisters_in_the_network",
+            candid::encode_args((wasm_type, version)).unwrap(),
+        )
+        .map(|reply_payload| match reply_payload {
+            WasmResult::Reply(payload) => {
+                candid::decode_one::<Result<(), String>>(&payload).unwrap()
+            }
+            _ => panic!("\n🛑 upgrade creator dao sns canisters failed\n"),
+        })
+        .unwrap();
+    assert_eq!(res, Ok(()));
+
+    for _ in 0..110 {
+        pocket_ic.tick();
+    }
+}
+
+fn get_module_hash(pocket_ic: &PocketIc, canister_id: Principal, controller: Principal) -> Vec<u8> {
+    pocket_ic
+        .canister_status(canister_id, Some(controller))
+        .unwrap()
+        .module_hash
+        .unwrap()
+}
+
+fn get_deployed_cdao_canisters(
+    pocket_ic: &PocketIc,
+    user_canister_id: Principal,
+) -> Vec<DeployedCdaoCanisters> {
+    pocket_ic
+        .query_call(
+            user_canister_id,
+            Principal::anonymous(),
+            "deployed_cdao_canisters",
+            candid::encode_one(()).unwrap(),
+        )
+        .map(|reply_payload| match reply_payload {
+            WasmResult::Reply(payload) => candid::decode_one(&payload).unwrap(),
+            _ => panic!("\n🛑 deployed cdao canisters failed\n"),
+        })
+        .unwrap()
+}
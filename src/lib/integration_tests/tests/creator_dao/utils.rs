@@ -185,16 +185,14 @@ pub fn setup_sns_w_canister_for_creator_dao(pocket_ic: &PocketIc, super_admin: P
     }
 }
 
-pub fn setup_default_sns_creator_token(
-    pocket_ic: &PocketIc,
+pub fn get_default_sns_init_args(
     super_admin: Principal,
     user_principal: Principal,
-    user_canister_id: Principal,
-) -> DeployedCdaoCanisters {
+) -> SnsInitPayload {
     let start = SystemTime::now();
     let tx_fee = 1u64;
 
-    let sns_init_args = SnsInitPayload {
+    SnsInitPayload {
         confirmation_text: Some("GET RICH QUICK".to_string()),
         transaction_fee_e8s: Some(tx_fee),
         token_name: Some("Simulation Governance".to_string()),
@@ -271,7 +269,17 @@ pub fn setup_default_sns_creator_token(
                 }),
             },
         )),
-    };
+    }
+}
+
+pub fn setup_default_sns_creator_token(
+    pocket_ic: &PocketIc,
+    super_admin: Principal,
+    user_principal: Principal,
+    user_canister_id: Principal,
+) -> DeployedCdaoCanisters {
+    let sns_init_args = get_default_sns_init_args(super_admin, user_principal);
+    let tx_fee = sns_init_args.transaction_fee_e8s.unwrap();
 
     let deployed_cdao_canisters = pocket_ic
         .update_call(
@@ -1,5 +1,7 @@
 mod mock_ledger;
 
+use std::time::Duration;
+
 use candid::{Nat, Principal};
 use mock_ledger::{
     mock_ledger_intf::{Account, ApproveArgs, TransferArg},
@@ -8,22 +10,30 @@ use mock_ledger::{
 use pocket_ic::PocketIc;
 use shared_utils::{
     canister_specific::individual_user_template::types::{
+        profile::UserCanisterDetails,
         pump_n_dump::{
-            BalanceInfo, GameDirection, ParticipatedGameInfo, PumpNDumpStateDiff, PumpsAndDumps,
+            BalanceInfo, GameDirection, GameHistoryEntry, GameHistoryFilter, GameOutcome,
+            ParticipatedGameInfo, PumpNDumpStateDiff, PumpsAndDumps, TokenGameStats,
         },
+        referral::{ReferralDetails, ReferralTreeNode},
         session::SessionType,
     },
     common::types::known_principal::{KnownPrincipalMap, KnownPrincipalType},
     constant::{GDOLLR_TO_E8S, GLOBAL_SUPER_ADMIN_USER_ID},
+    pagination::PaginationError,
+    types::{
+        cents_airdrop_campaign::{CentsAirdropCampaignArgs, CentsAirdropEligibility},
+        pump_n_dump_leaderboard::{LeaderboardPage, LeaderboardPeriod},
+    },
 };
 use test_utils::setup::{
     env::pocket_ic_env::{
-        execute_query, execute_update, execute_update_no_res, execute_update_no_res_multi,
-        get_new_pocket_ic_env,
+        execute_query, execute_query_multi, execute_update, execute_update_no_res,
+        execute_update_no_res_multi, get_new_pocket_ic_env,
     },
     test_constants::{
         get_global_super_admin_principal_id, get_mock_user_alice_principal_id,
-        get_mock_user_charlie_principal_id,
+        get_mock_user_bob_principal_id, get_mock_user_charlie_principal_id,
     },
 };
 
@@ -194,6 +204,35 @@ impl PumpNDumpHarness {
         .unwrap();
     }
 
+    pub fn game_history(
+        &self,
+        individual_canister: Principal,
+        filter: GameHistoryFilter,
+    ) -> Vec<GameHistoryEntry> {
+        execute_query_multi::<_, Result<Vec<GameHistoryEntry>, PaginationError>>(
+            &self.pic,
+            Principal::anonymous(),
+            individual_canister,
+            "get_game_history_with_pagination_cursor",
+            (filter, 0u64, 10u64),
+        )
+        .unwrap()
+    }
+
+    pub fn token_game_stats(
+        &self,
+        individual_canister: Principal,
+        filter: GameHistoryFilter,
+    ) -> Vec<TokenGameStats> {
+        execute_query(
+            &self.pic,
+            Principal::anonymous(),
+            individual_canister,
+            "get_token_game_stats",
+            &filter,
+        )
+    }
+
     pub fn net_earnings(&self, individual_canister: Principal) -> Nat {
         execute_query(
             &self.pic,
@@ -204,6 +243,85 @@ impl PumpNDumpHarness {
         )
     }
 
+    pub fn subnet_leaderboard(&self, period: LeaderboardPeriod) -> LeaderboardPage {
+        execute_query_multi::<_, Result<LeaderboardPage, PaginationError>>(
+            &self.pic,
+            Principal::anonymous(),
+            self.user_index,
+            "get_pump_n_dump_leaderboard",
+            (period, 0u64, 10u64),
+        )
+        .unwrap()
+    }
+
+    pub fn platform_leaderboard(&self, period: LeaderboardPeriod) -> LeaderboardPage {
+        execute_query_multi::<_, Result<LeaderboardPage, PaginationError>>(
+            &self.pic,
+            Principal::anonymous(),
+            self.known_principals[&KnownPrincipalType::CanisterIdPlatformOrchestrator],
+            "get_pump_n_dump_leaderboard",
+            (period, 0u64, 10u64),
+        )
+        .unwrap()
+    }
+
+    pub fn update_referrer_details(
+        &self,
+        referee: Principal,
+        referee_canister: Principal,
+        referrer: UserCanisterDetails,
+    ) -> Result<String, String> {
+        execute_update(
+            &self.pic,
+            referee,
+            referee_canister,
+            "update_referrer_details",
+            &referrer,
+        )
+    }
+
+    pub fn referral_details(&self, individual_canister: Principal) -> ReferralDetails {
+        execute_query(
+            &self.pic,
+            Principal::anonymous(),
+            individual_canister,
+            "get_referral_details",
+            &(),
+        )
+    }
+
+    pub fn referral_tree(&self, user: Principal) -> Option<ReferralTreeNode> {
+        execute_query_multi(
+            &self.pic,
+            Principal::anonymous(),
+            self.user_index,
+            "get_referral_tree",
+            (user, 2u8),
+        )
+    }
+
+    pub fn create_cents_airdrop_campaign(&self, args: CentsAirdropCampaignArgs) -> u64 {
+        execute_update::<_, Result<u64, String>>(
+            &self.pic,
+            get_mock_user_charlie_principal_id(),
+            self.known_principals[&KnownPrincipalType::CanisterIdPlatformOrchestrator],
+            "create_cents_airdrop_campaign",
+            &args,
+        )
+        .unwrap()
+    }
+
+    pub fn rerun_cents_airdrop_campaign(&self, campaign_id: u64) {
+        execute_update::<_, Result<(), String>>(
+            &self.pic,
+            get_mock_user_charlie_principal_id(),
+            self.known_principals[&KnownPrincipalType::CanisterIdPlatformOrchestrator],
+            "rerun_cents_airdrop_campaign",
+            &campaign_id,
+        )
+        .unwrap()
+    }
+
     pub fn update_pd_onboarding_reward_for_all_subnets(&self, new_reward: Nat) {
         let platform_admin = self.known_principals[&KnownPrincipalType::UserIdGlobalSuperAdmin];
         execute_update_no_res(
@@ -410,3 +528,247 @@ fn onboarding_reward_should_update() {
     assert_eq!(bal.balance, new_reward);
     assert_eq!(bal.net_airdrop_reward, new_reward);
 }
+
+#[test]
+fn earnings_should_be_ranked_in_subnet_and_platform_leaderboards() {
+    let harness = PumpNDumpHarness::default();
+
+    let alice = get_mock_user_alice_principal_id();
+    let alice_canister = harness.provision_individual_canister(alice);
+    let charlie = get_mock_user_charlie_principal_id();
+    let charlie_canister = harness.provision_individual_canister(charlie);
+
+    let game = |reward: u64| {
+        PumpNDumpStateDiff::Participant(ParticipatedGameInfo {
+            pumps: 1,
+            dumps: 1,
+            reward: reward as u128,
+            token_root: Principal::anonymous(),
+            game_direction: GameDirection::Pump,
+        })
+    };
+
+    harness.reconcile_user_state(alice_canister, &vec![game(10 * GDOLLR_TO_E8S)]);
+    harness.reconcile_user_state(charlie_canister, &vec![game(50 * GDOLLR_TO_E8S)]);
+
+    for _ in 0..5 {
+        harness.pic.tick();
+    }
+
+    let subnet_leaderboard = harness.subnet_leaderboard(LeaderboardPeriod::AllTime);
+    assert_eq!(subnet_leaderboard.total_entries, 2);
+    assert_eq!(subnet_leaderboard.entries[0].user_principal_id, charlie);
+    assert_eq!(
+        subnet_leaderboard.entries[0].user_canister_id,
+        charlie_canister
+    );
+    assert_eq!(
+        subnet_leaderboard.entries[0].earnings,
+        Nat::from(50 * GDOLLR_TO_E8S)
+    );
+    assert_eq!(subnet_leaderboard.entries[1].user_principal_id, alice);
+
+    let platform_leaderboard = harness.platform_leaderboard(LeaderboardPeriod::AllTime);
+    assert_eq!(platform_leaderboard.entries, subnet_leaderboard.entries);
+}
+
+#[test]
+fn referral_should_reward_referrer_and_referee_once() {
+    let harness = PumpNDumpHarness::default();
+
+    let alice = get_mock_user_alice_principal_id();
+    let alice_canister = harness.provision_individual_canister(alice);
+    let bob = get_mock_user_bob_principal_id();
+    let bob_canister = harness.provision_individual_canister(bob);
+
+    let alice_bal = harness.game_balance(alice_canister).balance;
+    let bob_bal = harness.game_balance(bob_canister).balance;
+    let referral_reward = Nat::from(1e9 as u64);
+
+    let referrer = UserCanisterDetails {
+        profile_owner: alice,
+        user_canister_id: alice_canister,
+    };
+    harness
+        .update_referrer_details(bob, bob_canister, referrer.clone())
+        .unwrap();
+
+    for _ in 0..5 {
+        harness.pic.tick();
+    }
+
+    assert_eq!(
+        harness.game_balance(alice_canister).balance,
+        alice_bal.clone() + referral_reward.clone()
+    );
+    assert_eq!(
+        harness.game_balance(bob_canister).balance,
+        bob_bal.clone() + referral_reward.clone()
+    );
+
+    // referrer can only be set once and rewards are not credited again
+    assert!(harness
+        .update_referrer_details(bob, bob_canister, referrer.clone())
+        .is_err());
+
+    for _ in 0..5 {
+        harness.pic.tick();
+    }
+
+    assert_eq!(
+        harness.game_balance(alice_canister).balance,
+        alice_bal + referral_reward.clone()
+    );
+    assert_eq!(
+        harness.game_balance(bob_canister).balance,
+        bob_bal + referral_reward.clone()
+    );
+
+    let alice_referral_details = harness.referral_details(alice_canister);
+    assert_eq!(alice_referral_details.referrer_rewards.len(), 1);
+    assert_eq!(
+        alice_referral_details.referrer_rewards[0].referee_user_principal_id,
+        bob
+    );
+    assert_eq!(
+        alice_referral_details.total_referral_earnings,
+        referral_reward
+    );
+
+    let bob_referral_details = harness.referral_details(bob_canister);
+    assert_eq!(bob_referral_details.referrer, Some(referrer));
+    assert!(bob_referral_details.referee_reward.is_some());
+
+    let referral_tree = harness.referral_tree(alice).unwrap();
+    assert_eq!(referral_tree.referees.len(), 1);
+    assert_eq!(referral_tree.referees[0].user_principal_id, bob);
+    assert_eq!(referral_tree.referees[0].user_canister_id, bob_canister);
+}
+
+#[test]
+fn airdrop_campaign_should_credit_eligible_users_once() {
+    let harness = PumpNDumpHarness::default();
+
+    let alice = get_mock_user_alice_principal_id();
+    let alice_canister = harness.provision_individual_canister(alice);
+
+    let bal = harness.game_balance(alice_canister);
+    let amount = 100 * GDOLLR_TO_E8S;
+
+    let registered_users_campaign =
+        harness.create_cents_airdrop_campaign(CentsAirdropCampaignArgs {
+            amount,
+            eligibility: CentsAirdropEligibility {
+                session_types: Some(vec![SessionType::RegisteredSession]),
+                ..Default::default()
+            },
+            expires_at: harness.pic.get_time() + Duration::from_secs(24 * 60 * 60),
+        });
+    let anonymous_users_campaign =
+        harness.create_cents_airdrop_campaign(CentsAirdropCampaignArgs {
+            amount,
+            eligibility: CentsAirdropEligibility {
+                session_types: Some(vec![SessionType::AnonymousSession]),
+                ..Default::default()
+            },
+            expires_at: harness.pic.get_time() + Duration::from_secs(24 * 60 * 60),
+        });
+    assert_ne!(registered_users_campaign, anonymous_users_campaign);
+
+    for _ in 0..10 {
+        harness.pic.tick();
+    }
+
+    let new_bal = harness.game_balance(alice_canister);
+    assert_eq!(new_bal.balance, bal.balance.clone() + amount);
+    assert_eq!(
+        new_bal.net_airdrop_reward,
+        bal.net_airdrop_reward.clone() + amount
+    );
+
+    harness.rerun_cents_airdrop_campaign(registered_users_campaign);
+
+    for _ in 0..10 {
+        harness.pic.tick();
+    }
+
+    assert_eq!(
+        harness.game_balance(alice_canister).balance,
+        bal.balance + amount
+    );
+}
+
+#[test]
+fn game_history_should_be_filtered_and_aggregated_per_token() {
+    let harness = PumpNDumpHarness::default();
+
+    let alice = get_mock_user_alice_principal_id();
+    let alice_canister = harness.provision_individual_canister(alice);
+    let token_a = get_mock_user_bob_principal_id();
+    let token_b = get_mock_user_charlie_principal_id();
+
+    let game = |token_root: Principal, game_direction: GameDirection, reward: u64| {
+        PumpNDumpStateDiff::Participant(ParticipatedGameInfo {
+            pumps: 1,
+            dumps: 1,
+            reward: reward as u128,
+            token_root,
+            game_direction,
+        })
+    };
+
+    harness.reconcile_user_state(
+        alice_canister,
+        &vec![game(token_a, GameDirection::Pump, 4 * GDOLLR_TO_E8S)],
+    );
+    let second_batch_time = harness.pic.get_time() + Duration::from_secs(60);
+    harness.pic.advance_time(Duration::from_secs(120));
+    harness.reconcile_user_state(
+        alice_canister,
+        &vec![
+            game(token_a, GameDirection::Dump, 0),
+            game(token_b, GameDirection::Pump, GDOLLR_TO_E8S),
+        ],
+    );
+
+    let history = harness.game_history(alice_canister, GameHistoryFilter::default());
+    assert_eq!(history.len(), 3);
+    // newest first
+    assert_eq!(history[0].game_info.token_root, token_b);
+    assert_eq!(history[2].outcome, GameOutcome::Won);
+    assert!(history[0].played_at > history[2].played_at);
+
+    let token_a_dumps = harness.game_history(
+        alice_canister,
+        GameHistoryFilter {
+            token_root: Some(token_a),
+            game_direction: Some(GameDirection::Dump),
+            ..Default::default()
+        },
+    );
+    assert_eq!(token_a_dumps.len(), 1);
+    assert_eq!(token_a_dumps[0].outcome, GameOutcome::Lost);
+
+    let recent_games = harness.game_history(
+        alice_canister,
+        GameHistoryFilter {
+            from_time_inclusive: Some(second_batch_time),
+            ..Default::default()
+        },
+    );
+    assert_eq!(recent_games.len(), 2);
+
+    let stats = harness.token_game_stats(
+        alice_canister,
+        GameHistoryFilter {
+            token_root: Some(token_a),
+            ..Default::default()
+        },
+    );
+    assert_eq!(stats.len(), 1);
+    assert_eq!(stats[0].games_played, 2);
+    assert_eq!(stats[0].games_won, 1);
+    assert_eq!(stats[0].total_staked, 4 * GDOLLR_TO_E8S as u128);
+    assert_eq!(stats[0].total_won, 4 * GDOLLR_TO_E8S as u128);
+    assert_eq!(stats[0].roi, 0.0);
+}
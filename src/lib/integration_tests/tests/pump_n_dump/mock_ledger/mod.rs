@@ -1,3 +1,5 @@
+// shared with the creator DAO tests, each of them only uses part of it
+#![allow(dead_code)]
 pub mod mock_ledger_intf;
 
 use candid::{Nat, Principal};
@@ -16,6 +18,28 @@ pub fn deploy(pic: &PocketIc, owner: Principal) -> Principal {
         CANISTER_INITIAL_CYCLES_FOR_SPAWNING_CANISTERS
     );
 
+    pic.install_canister(
+        icrc1,
+        ICRC1_LEDGER_WASM.into(),
+        get_ledger_args(owner),
+        None
+    );
+
+    icrc1
+}
+
+/// Replaces a ledger that is already installed, e.g. the NNS ledger, with this one
+pub fn reinstall(pic: &PocketIc, ledger: Principal, owner: Principal, controller: Principal) {
+    pic.reinstall_canister(
+        ledger,
+        ICRC1_LEDGER_WASM.into(),
+        get_ledger_args(owner),
+        Some(controller)
+    )
+    .unwrap();
+}
+
+fn get_ledger_args(owner: Principal) -> Vec<u8> {
     let init_args = InitArgs {
         token_symbol: "DOLLR".into(),
         token_name: "DOLLR".into(),
@@ -54,14 +78,5 @@ pub fn deploy(pic: &PocketIc, owner: Principal) -> Principal {
         },
     };
     let ledger_arg = LedgerArg::Init(init_args);
-    let ledger_args_raw = candid::encode_one(ledger_arg).unwrap();
-
-    pic.install_canister(
-        icrc1,
-        ICRC1_LEDGER_WASM.into(),
-        ledger_args_raw,
-        None
-    );
-
-    icrc1
+    candid::encode_one(ledger_arg).unwrap()
 }
\ No newline at end of file
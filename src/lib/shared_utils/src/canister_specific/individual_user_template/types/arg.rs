@@ -14,6 +14,8 @@ pub struct IndividualUserTemplateInitArgs {
     pub version: String,
     #[serde(default)]
     pub pump_dump_onboarding_reward: Option<Nat>,
+    #[serde(default)]
+    pub creator_token_limit: Option<usize>,
 }
 
 #[derive(Deserialize, CandidType, Clone)]
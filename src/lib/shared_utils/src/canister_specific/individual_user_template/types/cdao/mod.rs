@@ -1,9 +1,23 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::{Duration, SystemTime},
+};
 
-use candid::{CandidType, Principal};
+use candid::{CandidType, Nat, Principal};
 use ic_cdk::api;
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    common::types::wasm::SnsWasmType,
+    constant::MAX_LIMIT_FOR_CREATOR_DAO_SNS_TOKEN,
+    pagination::{self, PaginationError},
+};
+
+use super::{error::AirdropError, session::SessionType};
+
+/// Cap on a single claim for airdrops that were not configured by the creator
+pub const DEFAULT_MAX_AIRDROP_AMOUNT_PER_USER: u64 = 100_000_000_000; // 1000 * 10^8
+
 #[derive(CandidType, PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
 pub struct DeployedCdaoCanisters {
     pub governance: Principal,
@@ -14,9 +28,34 @@ pub struct DeployedCdaoCanisters {
 
     #[serde(default)]
     pub airdrop_info: AirdropInfo,
+
+    /// Version each canister was upgraded to, canisters missing here still run the
+    /// wasm they were deployed with
+    #[serde(default)]
+    pub sns_versions: BTreeMap<SnsWasmType, String>,
+
+    /// Outcome reported by the swap canister once the swap was finalized
+    #[serde(default)]
+    pub swap_settlement: Option<CdaoSwapSettlement>,
 }
 
 impl DeployedCdaoCanisters {
+    pub fn get_canister_id(&self, wasm_type: SnsWasmType) -> Principal {
+        match wasm_type {
+            SnsWasmType::Governance => self.governance,
+            SnsWasmType::Ledger => self.ledger,
+            SnsWasmType::Root => self.root,
+            SnsWasmType::Swap => self.swap,
+            SnsWasmType::Index => self.index,
+        }
+    }
+
+    pub fn is_on_version(&self, wasm_type: SnsWasmType, version: &str) -> bool {
+        self.sns_versions
+            .get(&wasm_type)
+            .is_some_and(|current_version| current_version == version)
+    }
+
     pub fn get_canister_ids(&self) -> Vec<Principal> {
         vec![
             self.governance,
@@ -28,14 +67,351 @@ impl DeployedCdaoCanisters {
     }
 }
 
+#[derive(CandidType, PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
+pub enum CdaoSwapOutcome {
+    Committed {
+        total_direct_participation_icp_e8s: u64,
+        total_neurons_fund_participation_icp_e8s: u64,
+    },
+    Aborted,
+}
+
+impl CdaoSwapOutcome {
+    pub fn is_committed(&self) -> bool {
+        matches!(self, CdaoSwapOutcome::Committed { .. })
+    }
+
+    pub fn get_icp_raised_e8s(&self) -> u64 {
+        match self {
+            CdaoSwapOutcome::Committed {
+                total_direct_participation_icp_e8s,
+                total_neurons_fund_participation_icp_e8s,
+            } => total_direct_participation_icp_e8s
+                .saturating_add(*total_neurons_fund_participation_icp_e8s),
+            CdaoSwapOutcome::Aborted => 0,
+        }
+    }
+}
+
+#[derive(CandidType, PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
+pub struct CdaoSwapSettlement {
+    pub nns_proposal_id: Option<u64>,
+    pub outcome: CdaoSwapOutcome,
+    /// Read from the swap canister after settling, None until they could be fetched
+    pub direct_participants: Option<Vec<CdaoSwapParticipant>>,
+    pub settled_at: SystemTime,
+}
+
+#[derive(CandidType, PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
+pub struct CdaoSwapParticipant {
+    pub principal_id: Principal,
+    pub amount_icp_e8s: u64,
+}
+
+/// Token a swap accepts as payment, read from the ledger the swap was deployed with
+#[derive(CandidType, PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Copy)]
+pub enum SwapPaymentToken {
+    Icp,
+    Dollr,
+}
+
+#[derive(CandidType, PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
+pub enum SwapParticipationStatus {
+    /// The swap issued a ticket, the payment was not pulled from the user yet
+    TicketCreated,
+    /// The payment reached the swap, the swap has not accounted for it yet
+    PaymentTransferred {
+        block_index: Nat,
+    },
+    /// The ledger could not be reached, the payment may or may not have been made.
+    /// Refreshing the participation retries it, the ledger deduplicates the retry.
+    PaymentUnknown {
+        error: String,
+    },
+    Committed {
+        accepted_participation_e8s: u64,
+    },
+    PaymentFailed {
+        error: String,
+    },
+}
+
+#[derive(CandidType, PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
+pub struct SwapParticipation {
+    pub id: u64,
+    pub token_root: Principal,
+    pub swap_canister: Principal,
+    pub payment_token: SwapPaymentToken,
+    pub ticket_id: u64,
+    pub amount_e8s: u64,
+    pub status: SwapParticipationStatus,
+    pub created_at: SystemTime,
+    pub updated_at: SystemTime,
+}
+
+impl SwapParticipation {
+    pub fn is_pending(&self) -> bool {
+        matches!(
+            self.status,
+            SwapParticipationStatus::TicketCreated
+                | SwapParticipationStatus::PaymentTransferred { .. }
+                | SwapParticipationStatus::PaymentUnknown { .. }
+        )
+    }
+}
+
+/// Swaps the user took part in from their canister, keyed by id
+#[derive(CandidType, PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SwapParticipations {
+    participations: BTreeMap<u64, SwapParticipation>,
+}
+
+impl SwapParticipations {
+    pub fn record(
+        &mut self,
+        token_root: Principal,
+        swap_canister: Principal,
+        payment_token: SwapPaymentToken,
+        ticket_id: u64,
+        amount_e8s: u64,
+        current_time: SystemTime,
+    ) -> u64 {
+        let id = self
+            .participations
+            .last_key_value()
+            .map(|(id, _)| id + 1)
+            .unwrap_or_default();
+
+        self.participations.insert(
+            id,
+            SwapParticipation {
+                id,
+                token_root,
+                swap_canister,
+                payment_token,
+                ticket_id,
+                amount_e8s,
+                status: SwapParticipationStatus::TicketCreated,
+                created_at: current_time,
+                updated_at: current_time,
+            },
+        );
+
+        id
+    }
+
+    pub fn get(&self, id: u64) -> Option<&SwapParticipation> {
+        self.participations.get(&id)
+    }
+
+    /// A swap only holds one open ticket per buyer, so a new participation has to wait
+    /// for the previous one on the same swap
+    pub fn get_pending_participation(
+        &self,
+        swap_canister: Principal,
+    ) -> Option<&SwapParticipation> {
+        self.participations.values().find(|participation| {
+            participation.swap_canister == swap_canister && participation.is_pending()
+        })
+    }
+
+    pub fn set_status(
+        &mut self,
+        id: u64,
+        status: SwapParticipationStatus,
+        current_time: SystemTime,
+    ) -> Option<SwapParticipation> {
+        let participation = self.participations.get_mut(&id)?;
+        participation.status = status;
+        participation.updated_at = current_time;
+
+        Some(participation.clone())
+    }
+
+    /// Newest participations first
+    pub fn get_participations_with_pagination_cursor(
+        &self,
+        from_inclusive_index: u64,
+        limit: u64,
+    ) -> Result<Vec<SwapParticipation>, PaginationError> {
+        let (from_inclusive_index, limit) = pagination::get_pagination_bounds_cursor(
+            from_inclusive_index,
+            limit,
+            self.participations.len() as u64,
+        )?;
+
+        Ok(self
+            .participations
+            .values()
+            .rev()
+            .skip(from_inclusive_index as usize)
+            .take(limit as usize)
+            .cloned()
+            .collect())
+    }
+}
+
 #[derive(CandidType, PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Default)]
 pub struct AirdropInfo {
     /// Maps each principal to their claim status
     #[serde(default)]
     pub principals_who_successfully_claimed: HashMap<Principal, ClaimStatus>,
+    #[serde(default)]
+    pub config: Option<AirdropConfig>,
+    /// Includes claims that are still being transferred
+    #[serde(default)]
+    pub total_claimed: Nat,
+}
+
+/// Rules the claimant has to satisfy. Unset rules are not checked.
+#[derive(CandidType, PartialEq, Eq, Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AirdropEligibility {
+    pub registered_session_only: bool,
+    pub min_account_age_secs: Option<u64>,
+    /// Only users following the token creator can claim
+    #[serde(default)]
+    pub followers_only: bool,
+}
+
+#[derive(CandidType, PartialEq, Eq, Debug, Serialize, Deserialize, Clone)]
+pub struct AirdropConfig {
+    pub total_budget: Nat,
+    pub amount_per_user: Nat,
+    pub eligibility: AirdropEligibility,
+    pub start_time: Option<SystemTime>,
+    pub end_time: Option<SystemTime>,
+}
+
+impl AirdropConfig {
+    pub fn validate(&self) -> Result<(), AirdropError> {
+        if self.amount_per_user == 0_u64 {
+            return Err(AirdropError::InvalidAirdropConfig(
+                "Amount per user must be greater than zero".into(),
+            ));
+        }
+
+        if self.amount_per_user > self.total_budget {
+            return Err(AirdropError::InvalidAirdropConfig(
+                "Amount per user exceeds the total budget".into(),
+            ));
+        }
+
+        if let (Some(start_time), Some(end_time)) = (self.start_time, self.end_time) {
+            if start_time >= end_time {
+                return Err(AirdropError::InvalidAirdropConfig(
+                    "Start time must be before end time".into(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Details of the claimant's canister the eligibility is checked against
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AirdropClaimantDetails {
+    pub session_type: Option<SessionType>,
+    /// Canisters without posts that signed up before the signup time was recorded have no value set
+    pub signed_up_at: Option<SystemTime>,
+    pub follows_creator: bool,
+}
+
+impl AirdropEligibility {
+    pub fn check(
+        &self,
+        claimant: &AirdropClaimantDetails,
+        current_time: SystemTime,
+    ) -> Result<(), AirdropError> {
+        if self.registered_session_only
+            && claimant.session_type != Some(SessionType::RegisteredSession)
+        {
+            return Err(AirdropError::NotEligible(
+                "Only registered users can claim this airdrop".into(),
+            ));
+        }
+
+        if self.followers_only && !claimant.follows_creator {
+            return Err(AirdropError::NotEligible(
+                "Only followers of the creator can claim this airdrop".into(),
+            ));
+        }
+
+        if let Some(min_account_age_secs) = self.min_account_age_secs {
+            // the account age can't be verified without a signup time
+            let signed_up_at = claimant.signed_up_at.ok_or_else(|| {
+                AirdropError::NotEligible("Account signup time is not known".into())
+            })?;
+            let account_age = current_time
+                .duration_since(signed_up_at)
+                .unwrap_or_default();
+            if account_age < Duration::from_secs(min_account_age_secs) {
+                return Err(AirdropError::NotEligible(
+                    "Account is too new to claim this airdrop".into(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl AirdropInfo {
+    /// Checks the claim against the configuration and marks it as claiming.
+    /// Returns the amount to transfer, which counts against the budget until released.
+    pub fn reserve_airdrop(
+        &mut self,
+        user_principal_id: Principal,
+        requested_amount: Nat,
+        claimant: &AirdropClaimantDetails,
+        current_time: SystemTime,
+    ) -> Result<Nat, AirdropError> {
+        if !self.is_airdrop_unclaimed(&user_principal_id) {
+            return Err(AirdropError::AlreadyClaimedAirdrop);
+        }
+
+        let amount = match &self.config {
+            None => requested_amount.min(DEFAULT_MAX_AIRDROP_AMOUNT_PER_USER.into()),
+            Some(config) => {
+                if config.start_time.is_some_and(|start| current_time < start) {
+                    return Err(AirdropError::AirdropNotStarted);
+                }
+
+                if config.end_time.is_some_and(|end| current_time >= end) {
+                    return Err(AirdropError::AirdropEnded);
+                }
+
+                config.eligibility.check(claimant, current_time)?;
+
+                let amount = requested_amount.min(config.amount_per_user.clone());
+                if self.total_claimed.clone() + amount.clone() > config.total_budget {
+                    return Err(AirdropError::AirdropBudgetExhausted);
+                }
+
+                amount
+            }
+        };
+
+        if amount == 0_u64 {
+            return Err(AirdropError::RequestedAmountTooLow);
+        }
+
+        self.total_claimed += amount.clone();
+        self.set_airdrop_claiming(user_principal_id);
+
+        Ok(amount)
+    }
+
+    /// Rolls back a reserved claim that could not be transferred
+    pub fn release_airdrop(&mut self, user_principal_id: Principal, amount: Nat) {
+        if self.total_claimed >= amount {
+            self.total_claimed -= amount;
+        } else {
+            self.total_claimed = Nat::from(0_u32);
+        }
+        self.set_airdrop_unclaimed(user_principal_id);
+    }
+
     pub fn get_claim_status(&self, user_principal_id: &Principal) -> Result<ClaimStatus, String> {
         self.principals_who_successfully_claimed
             .get(user_principal_id)
@@ -57,6 +433,14 @@ impl AirdropInfo {
         }
     }
 
+    /// Claims still being transferred are counted, the tokens may already have left
+    pub fn get_number_of_claimants(&self) -> u64 {
+        self.principals_who_successfully_claimed
+            .values()
+            .filter(|claim_status| **claim_status != ClaimStatus::Unclaimed)
+            .count() as u64
+    }
+
     pub fn is_airdrop_unclaimed(&self, user_principal_id: &Principal) -> bool {
         matches!(
             self.get_claim_status(user_principal_id),
@@ -114,3 +498,359 @@ pub enum ClaimStatus {
     ClaimedWithTimestamp(u64),
     Claiming,
 }
+
+/// Steps of a creator DAO deployment in the order they run.
+/// Progress is checkpointed after every step so a deployment can be resumed.
+#[derive(Serialize, Deserialize, CandidType, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CdaoDeploymentStep {
+    AllottingCanisters,
+    DepositingCycles,
+    InstallingWasms,
+    UpdatingControllers,
+    Completed,
+    RolledBack,
+}
+
+impl CdaoDeploymentStep {
+    pub fn next(self) -> Self {
+        match self {
+            CdaoDeploymentStep::AllottingCanisters => CdaoDeploymentStep::DepositingCycles,
+            CdaoDeploymentStep::DepositingCycles => CdaoDeploymentStep::InstallingWasms,
+            CdaoDeploymentStep::InstallingWasms => CdaoDeploymentStep::UpdatingControllers,
+            CdaoDeploymentStep::UpdatingControllers | CdaoDeploymentStep::Completed => {
+                CdaoDeploymentStep::Completed
+            }
+            CdaoDeploymentStep::RolledBack => CdaoDeploymentStep::RolledBack,
+        }
+    }
+
+    pub fn is_finished(self) -> bool {
+        matches!(
+            self,
+            CdaoDeploymentStep::Completed | CdaoDeploymentStep::RolledBack
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct CdaoDeploymentStatus {
+    pub deployment_id: u64,
+    pub step: CdaoDeploymentStep,
+    pub canister_ids: Vec<Principal>,
+    pub attempts: u32,
+    pub is_running: bool,
+    pub last_error: Option<String>,
+    pub created_at: SystemTime,
+    pub updated_at: SystemTime,
+}
+
+#[derive(Serialize, Deserialize, CandidType, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CreatorTokenLimit {
+    /// Set network wide from the platform orchestrator
+    pub network_limit: usize,
+    /// Set for verified creators, takes precedence over the network wide limit
+    pub creator_override: Option<usize>,
+}
+
+impl Default for CreatorTokenLimit {
+    fn default() -> Self {
+        Self {
+            network_limit: MAX_LIMIT_FOR_CREATOR_DAO_SNS_TOKEN,
+            creator_override: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, CandidType, Clone, Debug, PartialEq, Eq)]
+pub struct CreatorTokenDeletionReport {
+    pub root: Principal,
+    pub returned_canister_ids: Vec<Principal>,
+    /// Kept as empty canisters of the individual canister to be returned later
+    pub failed_canister_ids: Vec<Principal>,
+    /// Cycles held by the canisters that went back to the subnet backup pool
+    pub reclaimed_cycles: Nat,
+}
+
+impl CreatorTokenLimit {
+    pub fn get_limit(&self) -> usize {
+        self.creator_override.unwrap_or(self.network_limit)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use test_utils::setup::test_constants::{
+        get_mock_user_alice_principal_id, get_mock_user_bob_principal_id,
+        get_mock_user_charlie_principal_id,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_reserve_airdrop_with_config() {
+        let now = SystemTime::now();
+        let day = Duration::from_secs(24 * 60 * 60);
+        let mut airdrop_info = AirdropInfo {
+            config: Some(AirdropConfig {
+                total_budget: Nat::from(150_u32),
+                amount_per_user: Nat::from(100_u32),
+                eligibility: AirdropEligibility {
+                    registered_session_only: true,
+                    min_account_age_secs: Some(day.as_secs()),
+                    followers_only: true,
+                },
+                start_time: None,
+                end_time: Some(now + day),
+            }),
+            ..Default::default()
+        };
+
+        let registered = AirdropClaimantDetails {
+            session_type: Some(SessionType::RegisteredSession),
+            signed_up_at: Some(now - 2 * day),
+            follows_creator: true,
+        };
+
+        assert!(matches!(
+            airdrop_info.reserve_airdrop(
+                get_mock_user_alice_principal_id(),
+                Nat::from(100_u32),
+                &AirdropClaimantDetails {
+                    session_type: Some(SessionType::AnonymousSession),
+                    ..registered
+                },
+                now,
+            ),
+            Err(AirdropError::NotEligible(_))
+        ));
+
+        assert!(matches!(
+            airdrop_info.reserve_airdrop(
+                get_mock_user_alice_principal_id(),
+                Nat::from(100_u32),
+                &AirdropClaimantDetails {
+                    signed_up_at: Some(now),
+                    ..registered
+                },
+                now,
+            ),
+            Err(AirdropError::NotEligible(_))
+        ));
+
+        assert!(matches!(
+            airdrop_info.reserve_airdrop(
+                get_mock_user_alice_principal_id(),
+                Nat::from(100_u32),
+                &AirdropClaimantDetails {
+                    signed_up_at: None,
+                    ..registered
+                },
+                now,
+            ),
+            Err(AirdropError::NotEligible(_))
+        ));
+
+        assert!(matches!(
+            airdrop_info.reserve_airdrop(
+                get_mock_user_alice_principal_id(),
+                Nat::from(100_u32),
+                &AirdropClaimantDetails {
+                    follows_creator: false,
+                    ..registered
+                },
+                now,
+            ),
+            Err(AirdropError::NotEligible(_))
+        ));
+
+        assert_eq!(
+            airdrop_info.reserve_airdrop(
+                get_mock_user_alice_principal_id(),
+                Nat::from(1000_u32),
+                &registered,
+                now,
+            ),
+            Ok(Nat::from(100_u32))
+        );
+        assert_eq!(
+            airdrop_info.reserve_airdrop(
+                get_mock_user_alice_principal_id(),
+                Nat::from(100_u32),
+                &registered,
+                now,
+            ),
+            Err(AirdropError::AlreadyClaimedAirdrop)
+        );
+        assert_eq!(
+            airdrop_info.reserve_airdrop(
+                get_mock_user_bob_principal_id(),
+                Nat::from(100_u32),
+                &registered,
+                now,
+            ),
+            Err(AirdropError::AirdropBudgetExhausted)
+        );
+
+        airdrop_info.release_airdrop(get_mock_user_alice_principal_id(), Nat::from(100_u32));
+        assert_eq!(airdrop_info.total_claimed, Nat::from(0_u32));
+
+        assert_eq!(
+            airdrop_info.reserve_airdrop(
+                get_mock_user_charlie_principal_id(),
+                Nat::from(100_u32),
+                &registered,
+                now + day,
+            ),
+            Err(AirdropError::AirdropEnded)
+        );
+    }
+
+    #[test]
+    fn test_reserve_airdrop_without_config() {
+        let mut airdrop_info = AirdropInfo::default();
+
+        assert_eq!(
+            airdrop_info.reserve_airdrop(
+                get_mock_user_alice_principal_id(),
+                Nat::from(u64::MAX),
+                &AirdropClaimantDetails::default(),
+                SystemTime::now(),
+            ),
+            Ok(Nat::from(DEFAULT_MAX_AIRDROP_AMOUNT_PER_USER))
+        );
+    }
+
+    #[test]
+    fn test_number_of_claimants() {
+        let mut airdrop_info = AirdropInfo::default();
+        airdrop_info.set_airdrop_claiming(get_mock_user_alice_principal_id());
+        airdrop_info.set_airdrop_unclaimed(get_mock_user_bob_principal_id());
+        airdrop_info.principals_who_successfully_claimed.insert(
+            get_mock_user_charlie_principal_id(),
+            ClaimStatus::ClaimedWithTimestamp(0),
+        );
+
+        assert_eq!(airdrop_info.get_number_of_claimants(), 2);
+    }
+
+    #[test]
+    fn test_creator_token_limit_override() {
+        let mut creator_token_limit = CreatorTokenLimit::default();
+        assert_eq!(
+            creator_token_limit.get_limit(),
+            MAX_LIMIT_FOR_CREATOR_DAO_SNS_TOKEN
+        );
+
+        creator_token_limit.network_limit = 5;
+        assert_eq!(creator_token_limit.get_limit(), 5);
+
+        creator_token_limit.creator_override = Some(1);
+        assert_eq!(creator_token_limit.get_limit(), 1);
+    }
+
+    #[test]
+    fn test_sns_version_tracking() {
+        let mut deployed_cdao_canisters = DeployedCdaoCanisters {
+            governance: get_mock_user_alice_principal_id(),
+            ledger: get_mock_user_bob_principal_id(),
+            root: get_mock_user_charlie_principal_id(),
+            swap: get_mock_user_alice_principal_id(),
+            index: get_mock_user_bob_principal_id(),
+            airdrop_info: AirdropInfo::default(),
+            sns_versions: BTreeMap::new(),
+            swap_settlement: None,
+        };
+
+        assert_eq!(
+            deployed_cdao_canisters.get_canister_id(SnsWasmType::Ledger),
+            get_mock_user_bob_principal_id()
+        );
+        assert!(!deployed_cdao_canisters.is_on_version(SnsWasmType::Ledger, "1.0.0"));
+
+        deployed_cdao_canisters
+            .sns_versions
+            .insert(SnsWasmType::Ledger, "1.0.0".into());
+        assert!(deployed_cdao_canisters.is_on_version(SnsWasmType::Ledger, "1.0.0"));
+        assert!(!deployed_cdao_canisters.is_on_version(SnsWasmType::Ledger, "1.0.1"));
+        assert!(!deployed_cdao_canisters.is_on_version(SnsWasmType::Index, "1.0.0"));
+    }
+
+    #[test]
+    fn test_swap_outcome_icp_raised() {
+        let committed = CdaoSwapOutcome::Committed {
+            total_direct_participation_icp_e8s: 300,
+            total_neurons_fund_participation_icp_e8s: 0,
+        };
+        assert!(committed.is_committed());
+        assert_eq!(committed.get_icp_raised_e8s(), 300);
+
+        assert!(!CdaoSwapOutcome::Aborted.is_committed());
+        assert_eq!(CdaoSwapOutcome::Aborted.get_icp_raised_e8s(), 0);
+    }
+
+    #[test]
+    fn test_swap_participation_history() {
+        let root = get_mock_user_alice_principal_id();
+        let swap = get_mock_user_bob_principal_id();
+        let mut participations = SwapParticipations::default();
+
+        let first = participations.record(
+            root,
+            swap,
+            SwapPaymentToken::Icp,
+            0,
+            1_000,
+            SystemTime::now(),
+        );
+        assert_eq!(
+            participations.get_pending_participation(swap).map(|p| p.id),
+            Some(first)
+        );
+
+        participations.set_status(
+            first,
+            SwapParticipationStatus::PaymentUnknown {
+                error: "ledger unreachable".into(),
+            },
+            SystemTime::now(),
+        );
+        assert!(participations.get_pending_participation(swap).is_some());
+
+        participations.set_status(
+            first,
+            SwapParticipationStatus::PaymentTransferred {
+                block_index: Nat::from(3_u64),
+            },
+            SystemTime::now(),
+        );
+        assert!(participations.get_pending_participation(swap).is_some());
+
+        participations.set_status(
+            first,
+            SwapParticipationStatus::Committed {
+                accepted_participation_e8s: 1_000,
+            },
+            SystemTime::now(),
+        );
+        assert!(participations.get_pending_participation(swap).is_none());
+
+        let second = participations.record(
+            root,
+            swap,
+            SwapPaymentToken::Icp,
+            1,
+            2_000,
+            SystemTime::now(),
+        );
+        let history = participations
+            .get_participations_with_pagination_cursor(0, 10)
+            .unwrap();
+        assert_eq!(
+            history.iter().map(|p| p.id).collect::<Vec<_>>(),
+            vec![second, first]
+        );
+        assert!(participations
+            .set_status(5, SwapParticipationStatus::TicketCreated, SystemTime::now())
+            .is_none());
+    }
+}
@@ -1,4 +1,4 @@
-use std::ops::Add;
+use std::{collections::BTreeMap, ops::Add};
 
 use candid::{CandidType, Nat};
 use serde::{Deserialize, Serialize};
@@ -6,8 +6,7 @@ use serde::{Deserialize, Serialize};
 use crate::{
     common::{
         types::utility_token::token_event::{
-            HotOrNotOutcomePayoutEvent, MintEvent, PumpDumpOutcomePayoutEvent, StakeEvent,
-            TokenEvent, WithdrawEvent, HOT_OR_NOT_BET_CREATOR_COMMISSION_PERCENTAGE,
+            HotOrNotOutcomePayoutEvent, MintEvent, StakeEvent, TokenEvent, WithdrawEvent,
         },
         utils::default_pump_dump_onboarding_reward,
     },
@@ -16,7 +15,10 @@ use crate::{
 
 use super::{
     pump_n_dump::ParticipatedGameInfo,
-    token::{get_earnings_amount_from_winnings_amount, TokenTransactions},
+    token::{
+        get_commission_amount_from_room_pot_total_amount, get_earnings_amount_from_winnings_amount,
+        record_token_event, TokenEventError, TokenTransactions,
+    },
 };
 
 #[derive(Serialize, Deserialize, Clone, Default, CandidType)]
@@ -25,6 +27,8 @@ pub struct CentsToken {
     /// user balance
     balance: Nat,
     net_earnings: Nat,
+    #[serde(default)]
+    transaction_history: BTreeMap<u64, TokenEvent>,
 }
 
 impl CentsToken {
@@ -48,6 +52,30 @@ impl CentsToken {
         self.net_airdrop.clone()
     }
 
+    fn credit_airdrop(&mut self, amount: impl Into<Nat> + Clone) {
+        self.net_airdrop += amount.clone().into();
+        self.credit_earnings(amount);
+    }
+
+    fn credit_earnings(&mut self, amount: impl Into<Nat> + Clone) {
+        self.balance += amount.clone().into();
+        self.net_earnings += amount.into();
+    }
+
+    /// Fails without touching the balance if it does not cover the amount
+    fn debit(&mut self, amount: impl Into<Nat>) -> Result<(), TokenEventError> {
+        let amount = amount.into();
+        if amount > self.balance {
+            return Err(TokenEventError::InsufficientBalance {
+                balance: self.balance.clone(),
+                amount,
+            });
+        }
+        self.balance -= amount;
+
+        Ok(())
+    }
+
     pub fn reconstruct_cents_token_from_participated_game_info(
         &mut self,
         onboarding_reward: Nat,
@@ -76,56 +104,66 @@ impl CentsToken {
         self.net_airdrop = airdrop;
         self.net_earnings = net_earnings;
     }
+
+    /// Everything but the transaction history, which can be too long to copy in a single
+    /// message
+    pub fn clone_without_transaction_history(&self) -> Self {
+        Self {
+            net_airdrop: self.net_airdrop.clone(),
+            balance: self.balance.clone(),
+            net_earnings: self.net_earnings.clone(),
+            transaction_history: BTreeMap::new(),
+        }
+    }
+
+    /// Adds transactions of a history copied in batches, the balances are not touched
+    pub fn extend_transaction_history(
+        &mut self,
+        transactions: impl IntoIterator<Item = (u64, TokenEvent)>,
+    ) {
+        self.transaction_history.extend(transactions);
+    }
 }
 
 impl TokenTransactions for CentsToken {
     fn get_current_token_balance(&self) -> u128 {
-        self.balance.clone().0.try_into().unwrap()
+        self.balance.0.clone().try_into().unwrap_or(u128::MAX)
+    }
+
+    fn get_transaction_history(&self) -> &BTreeMap<u64, TokenEvent> {
+        &self.transaction_history
     }
 
-    fn handle_token_event(&mut self, token_event: TokenEvent) {
-        match token_event {
+    fn handle_token_event(&mut self, token_event: TokenEvent) -> Result<(), TokenEventError> {
+        match &token_event {
             TokenEvent::Mint {
                 details, amount, ..
             } => match details {
-                MintEvent::NewUserSignup { .. } => {
-                    self.balance += amount as u128;
-                    self.net_airdrop += amount as u128;
-                    self.net_earnings += amount as u128;
-                }
-                MintEvent::Referral { .. } => {
-                    self.net_airdrop += amount as u128;
-                    self.balance += amount as u128;
-                    self.net_earnings += amount as u128;
+                MintEvent::NewUserSignup { .. } | MintEvent::Referral { .. } => {
+                    self.credit_airdrop(*amount);
                 }
                 MintEvent::Airdrop { amount } => {
-                    self.net_airdrop += amount as u128;
-                    self.balance += amount as u128;
-                    self.net_earnings += amount as u128;
+                    self.credit_airdrop(*amount);
                 }
             },
             TokenEvent::Burn => {}
             TokenEvent::Transfer { amount, .. } => {
-                self.balance -= amount as u128;
+                self.debit(*amount)?;
             }
             TokenEvent::Receive { amount, .. } => {
-                self.balance += amount as u128;
+                self.balance += *amount;
             }
             TokenEvent::Stake {
                 details, amount, ..
             } => match details {
                 StakeEvent::BetOnHotOrNotPost { bet_amount, .. } => {
-                    self.balance -= bet_amount as u128;
+                    self.debit(*bet_amount)?;
                 }
                 StakeEvent::BetFailureRefund { bet_amount, .. } => {
-                    self.balance += bet_amount as u128;
+                    self.balance += *bet_amount;
                 }
-                StakeEvent::BetOnPumpDump {
-                    pumps,
-                    dumps,
-                    root_canister_id,
-                } => {
-                    self.balance -= amount as u128;
+                StakeEvent::BetOnPumpDump { .. } => {
+                    self.debit(*amount)?;
                 }
             },
             TokenEvent::HotOrNotOutcomePayout { details, .. } => match details {
@@ -133,43 +171,33 @@ impl TokenTransactions for CentsToken {
                     room_pot_total_amount,
                     ..
                 } => {
-                    self.balance += (room_pot_total_amount
-                        * HOT_OR_NOT_BET_CREATOR_COMMISSION_PERCENTAGE
-                        / 100) as u128;
-                    self.net_earnings += (room_pot_total_amount
-                        * HOT_OR_NOT_BET_CREATOR_COMMISSION_PERCENTAGE
-                        / 100) as u128;
+                    self.credit_earnings(get_commission_amount_from_room_pot_total_amount(
+                        *room_pot_total_amount,
+                    ));
                 }
                 HotOrNotOutcomePayoutEvent::WinningsEarnedFromBet {
                     winnings_amount, ..
                 } => {
-                    self.balance += winnings_amount as u128;
-                    self.net_earnings +=
-                        get_earnings_amount_from_winnings_amount(&winnings_amount) as u128;
+                    self.balance += *winnings_amount;
+                    self.net_earnings += get_earnings_amount_from_winnings_amount(winnings_amount);
                 }
             },
             TokenEvent::Withdraw { amount, event_type } => match event_type {
                 WithdrawEvent::WithdrawRequest => {
-                    self.balance -= amount;
+                    self.debit(*amount)?;
                 }
                 WithdrawEvent::WithdrawRequestFailed => {
-                    self.balance += amount;
-                }
-            },
-            TokenEvent::PumpDumpOutcomePayout {
-                amount,
-                payout_type,
-            } => match payout_type {
-                PumpDumpOutcomePayoutEvent::RewardFromPumpDumpGame { .. } => {
-                    self.balance += amount;
-                    self.net_earnings += amount;
-                }
-                PumpDumpOutcomePayoutEvent::CreatorRewardFromPumpDumpGame => {
-                    self.balance += amount;
-                    self.net_earnings += amount;
+                    self.balance += *amount;
                 }
             },
+            TokenEvent::PumpDumpOutcomePayout { amount, .. } => {
+                self.credit_earnings(*amount);
+            }
         }
+
+        record_token_event(&mut self.transaction_history, token_event);
+
+        Ok(())
     }
 }
 
@@ -183,10 +211,62 @@ mod test {
         constant::GDOLLR_TO_E8S,
     };
 
+    use std::time::SystemTime;
+
     use candid::{Nat, Principal};
 
+    use crate::{
+        canister_specific::individual_user_template::types::token::{
+            TokenEventError, TokenTransactions,
+        },
+        common::types::utility_token::token_event::{StakeEvent, TokenEvent, WithdrawEvent},
+    };
+
     use super::CentsToken;
 
+    #[test]
+    fn test_overdraft_is_rejected_and_refund_mints_nothing() {
+        let mut cents = CentsToken {
+            balance: Nat::from(100_u32),
+            ..Default::default()
+        };
+
+        let withdraw = |amount: u128, event_type| TokenEvent::Withdraw { amount, event_type };
+
+        assert_eq!(
+            cents.handle_token_event(withdraw(150, WithdrawEvent::WithdrawRequest)),
+            Err(TokenEventError::InsufficientBalance {
+                balance: Nat::from(100_u32),
+                amount: Nat::from(150_u32)
+            })
+        );
+        assert_eq!(cents.balance, 100_u32);
+        assert!(cents.transaction_history.is_empty());
+
+        cents
+            .handle_token_event(withdraw(100, WithdrawEvent::WithdrawRequest))
+            .unwrap();
+        assert_eq!(cents.balance, 0_u32);
+        cents
+            .handle_token_event(withdraw(100, WithdrawEvent::WithdrawRequestFailed))
+            .unwrap();
+        assert_eq!(cents.balance, 100_u32);
+
+        assert!(cents
+            .handle_token_event(TokenEvent::Stake {
+                amount: 101,
+                details: StakeEvent::BetOnPumpDump {
+                    pumps: 101,
+                    dumps: 0,
+                    root_canister_id: Principal::anonymous(),
+                },
+                timestamp: SystemTime::now(),
+            })
+            .is_err());
+        assert_eq!(cents.balance, 100_u32);
+        assert_eq!(cents.transaction_history.len(), 2);
+    }
+
     #[test]
     fn test_reconstruct_cents_token_from_participated_game_info() {
         let games = vec![
@@ -377,6 +457,7 @@ mod test {
             net_airdrop: Nat::from(2000 * GDOLLR_TO_E8S),
             balance: Nat::from(1000 * GDOLLR_TO_E8S),
             net_earnings: Nat::from(2000 * GDOLLR_TO_E8S),
+            ..Default::default()
         };
 
         cents.reconstruct_cents_token_from_participated_game_info(
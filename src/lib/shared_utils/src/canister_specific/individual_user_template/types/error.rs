@@ -1,6 +1,6 @@
-use candid::{CandidType, Deserialize};
+use candid::{CandidType, Deserialize, Principal};
 use ic_cdk::api::call::RejectionCode;
-use icrc_ledger_types::icrc1::transfer::TransferError;
+use icrc_ledger_types::{icrc1::transfer::TransferError, icrc2::transfer_from::TransferFromError};
 
 #[derive(CandidType, Deserialize, PartialEq, Eq, Debug)]
 pub enum GetPostsOfUserProfileError {
@@ -50,6 +50,13 @@ pub enum CdaoDeployError {
     InvalidInitPayload(String),
     Unauthenticated,
     CycleError(String),
+    DeploymentInProgress(u64),
+    DeploymentNotFound(u64),
+    DeploymentNotResumable(u64),
+    /// The deployment was resumed by a newer attempt, this one can no longer write to it
+    DeploymentSuperseded(u64),
+    /// The SNS WASM canister is not configured or did not return the requested wasm
+    SnsWasmUnavailable(String),
 }
 
 impl From<(RejectionCode, String)> for CdaoDeployError {
@@ -58,6 +65,26 @@ impl From<(RejectionCode, String)> for CdaoDeployError {
     }
 }
 
+#[derive(CandidType, Deserialize, PartialEq, Eq, Debug)]
+pub enum DeleteCreatorTokenError {
+    Unauthorized,
+    TokenNotFound,
+    SwapOpen,
+    /// The swap predates its lifecycle being reported, or did not report its participants
+    SwapStatusUnknown,
+    /// Number of accounts holding the token, swap participants included
+    HoldersExist(u64),
+    /// Part of the ledger's history is archived, so its holders could not be listed
+    HoldersUnknown,
+    CallError(RejectionCode, String),
+}
+
+impl From<(RejectionCode, String)> for DeleteCreatorTokenError {
+    fn from((rejection_code, error_message): (RejectionCode, String)) -> Self {
+        DeleteCreatorTokenError::CallError(rejection_code, error_message)
+    }
+}
+
 #[derive(CandidType, Deserialize, PartialEq, Eq, Debug)]
 pub enum CdaoTokenError {
     InvalidRoot,
@@ -65,6 +92,8 @@ pub enum CdaoTokenError {
     NoBalance,
     CallError(RejectionCode, String),
     Unauthenticated,
+    UnregisteredTargetCanister,
+    TransferNotFound(u64),
 }
 
 impl From<(RejectionCode, String)> for CdaoTokenError {
@@ -82,6 +111,13 @@ pub enum AirdropError {
     InvalidRoot,
     CallError(RejectionCode, String),
     Transfer(TransferError),
+    Unauthorized,
+    CanisterNotInNetwork,
+    NotEligible(String),
+    AirdropNotStarted,
+    AirdropEnded,
+    AirdropBudgetExhausted,
+    InvalidAirdropConfig(String),
 }
 
 impl From<(RejectionCode, String)> for AirdropError {
@@ -89,3 +125,24 @@ impl From<(RejectionCode, String)> for AirdropError {
         AirdropError::CallError(value.0, value.1)
     }
 }
+
+#[derive(CandidType, Deserialize, PartialEq, Eq, Debug)]
+pub enum SwapParticipationError {
+    Unauthenticated,
+    InvalidRoot,
+    UnsupportedPaymentLedger(Principal),
+    /// The ledger the participation was paid with is no longer configured
+    PaymentLedgerUnavailable,
+    ParticipationInProgress(u64),
+    ParticipationNotFound,
+    /// The swap refused to issue a ticket, the error type is the swap's `new_sale_ticket` error
+    TicketRejected(i32),
+    TransferFrom(TransferFromError),
+    CallError(RejectionCode, String),
+}
+
+impl From<(RejectionCode, String)> for SwapParticipationError {
+    fn from(value: (RejectionCode, String)) -> Self {
+        SwapParticipationError::CallError(value.0, value.1)
+    }
+}
@@ -13,6 +13,20 @@ pub enum MigrationInfo {
     },
 }
 
+/// A migration that is not finished yet. It is kept apart from `MigrationInfo`, which
+/// clients and canisters that were not upgraded yet decode from `get_profile_details_v2`.
+#[derive(Serialize, Deserialize, Clone, Copy, CandidType, Debug, PartialEq, Eq)]
+pub enum PendingMigration {
+    /// The owner of this Yral canister agreed to receive the Hot or Not account
+    FromHotOrNotRequested { account_principal: Principal },
+    /// The balance was taken and sent to the Yral account, which has not confirmed it yet.
+    /// Retrying the migration sends the same amount again.
+    ToYral {
+        account_principal: Principal,
+        amount: u64,
+    },
+}
+
 #[derive(Serialize, Deserialize, Debug, CandidType, PartialEq)]
 pub enum MigrationErrors {
     InvalidToCanister,
@@ -27,4 +41,11 @@ pub enum MigrationErrors {
     HotOrNotSubnetCanisterIdNotFound,
     UserIndexCanisterIdNotFound,
     RequestCycleFromUserIndexFailed(String),
+    /// The owner of the Yral canister did not request the migration of this account
+    MigrationNotRequested,
+    /// Another attempt of the migration is waiting on the Yral canister
+    MigrationInProgress,
+    SubnetOrchestratorsNotFound(String),
+    /// The Hot or Not token balance could not be updated
+    TokenTransactionFailed(String),
 }
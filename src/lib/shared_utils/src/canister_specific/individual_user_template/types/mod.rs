@@ -12,5 +12,6 @@ pub mod ml_data;
 pub mod post;
 pub mod profile;
 pub mod pump_n_dump;
+pub mod referral;
 pub mod session;
 pub mod token;
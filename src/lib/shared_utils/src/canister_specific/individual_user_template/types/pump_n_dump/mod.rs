@@ -1,5 +1,10 @@
+use std::{
+    collections::BTreeMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
 use candid::{CandidType, Nat, Principal};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
 use crate::{
     common::{
@@ -15,35 +20,168 @@ pub enum GameDirection {
     Dump,
 }
 
-#[derive(Serialize, Deserialize, Clone, CandidType, Debug, PartialEq, Eq)]
-pub struct ParticipatedGameInfoV0 {
+#[derive(Serialize, Deserialize, Clone, CandidType, Debug, PartialEq, Eq, Copy)]
+pub struct ParticipatedGameInfo {
     pub pumps: u64,
     pub dumps: u64,
-    pub reward: Nat,
+    pub reward: u128,
     pub token_root: Principal,
     pub game_direction: GameDirection,
 }
 
-impl From<ParticipatedGameInfoV0> for ParticipatedGameInfo {
-    //Safety: reward is always a positive therefore can be converted to u128
-    fn from(participated_game_info: ParticipatedGameInfoV0) -> Self {
-        ParticipatedGameInfo {
-            pumps: participated_game_info.pumps,
-            dumps: participated_game_info.dumps,
-            reward: participated_game_info.reward.0.try_into().unwrap(),
-            token_root: participated_game_info.token_root,
-            game_direction: participated_game_info.game_direction,
+impl ParticipatedGameInfo {
+    /// Saturates instead of overflowing, the counts are reported by the game backend
+    pub fn staked_amount(&self) -> u128 {
+        (self.pumps as u128)
+            .saturating_add(self.dumps as u128)
+            .saturating_mul(GDOLLR_TO_E8S as u128)
+    }
+
+    pub fn outcome(&self) -> GameOutcome {
+        if self.reward > self.staked_amount() {
+            GameOutcome::Won
+        } else {
+            GameOutcome::Lost
         }
     }
 }
 
-#[derive(Serialize, Deserialize, Clone, CandidType, Debug, PartialEq, Eq, Copy)]
-pub struct ParticipatedGameInfo {
-    pub pumps: u64,
-    pub dumps: u64,
-    pub reward: u128,
+#[derive(Serialize, Deserialize, Clone, Copy, CandidType, Debug, PartialEq, Eq)]
+pub enum GameOutcome {
+    Won,
+    Lost,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, CandidType, Debug, PartialEq, Eq)]
+pub struct GameHistoryEntry {
+    pub game_info: ParticipatedGameInfo,
+    /// Games played before timestamps were recorded are set to `UNIX_EPOCH`
+    pub played_at: SystemTime,
+    pub outcome: GameOutcome,
+}
+
+impl GameHistoryEntry {
+    pub fn new(game_info: ParticipatedGameInfo, played_at: SystemTime) -> Self {
+        Self {
+            game_info,
+            played_at,
+            outcome: game_info.outcome(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredGameHistoryEntry {
+    Entry(GameHistoryEntry),
+    // games were stored without timestamp and outcome before
+    Legacy(ParticipatedGameInfo),
+}
+
+/// Deserializes game history stored either as `GameHistoryEntry` or as `ParticipatedGameInfo`.
+/// Meant for snapshot data only, not for candid. Upgrade data is migrated by the
+/// individual canister's state schema instead.
+pub fn deserialize_game_history<'de, D>(deserializer: D) -> Result<Vec<GameHistoryEntry>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let stored_games = Vec::<StoredGameHistoryEntry>::deserialize(deserializer)?;
+
+    Ok(stored_games
+        .into_iter()
+        .map(|stored_game| match stored_game {
+            StoredGameHistoryEntry::Entry(entry) => entry,
+            StoredGameHistoryEntry::Legacy(game_info) => {
+                GameHistoryEntry::new(game_info, UNIX_EPOCH)
+            }
+        })
+        .collect())
+}
+
+#[derive(Serialize, Deserialize, Clone, CandidType, Debug, Default, PartialEq, Eq)]
+pub struct GameHistoryFilter {
+    pub token_root: Option<Principal>,
+    pub game_direction: Option<GameDirection>,
+    pub from_time_inclusive: Option<SystemTime>,
+    pub to_time_exclusive: Option<SystemTime>,
+}
+
+impl GameHistoryFilter {
+    pub fn matches(&self, entry: &GameHistoryEntry) -> bool {
+        self.token_root
+            .map_or(true, |token_root| entry.game_info.token_root == token_root)
+            && self.game_direction.map_or(true, |direction| {
+                entry.game_info.game_direction == direction
+            })
+            && self
+                .from_time_inclusive
+                .map_or(true, |from_time| entry.played_at >= from_time)
+            && self
+                .to_time_exclusive
+                .map_or(true, |to_time| entry.played_at < to_time)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, CandidType, Debug, PartialEq)]
+pub struct TokenGameStats {
     pub token_root: Principal,
-    pub game_direction: GameDirection,
+    pub games_played: u64,
+    pub games_won: u64,
+    pub total_staked: u128,
+    pub total_won: u128,
+    /// (total_won - total_staked) / total_staked
+    pub roi: f64,
+}
+
+impl TokenGameStats {
+    fn new(token_root: Principal) -> Self {
+        Self {
+            token_root,
+            games_played: 0,
+            games_won: 0,
+            total_staked: 0,
+            total_won: 0,
+            roi: 0.0,
+        }
+    }
+
+    /// Aggregates the games matching `filter` per token
+    pub fn from_game_history<'a>(
+        games: impl Iterator<Item = &'a GameHistoryEntry>,
+        filter: &GameHistoryFilter,
+    ) -> Vec<Self> {
+        let mut stats: BTreeMap<Principal, Self> = BTreeMap::new();
+
+        games
+            .filter(|entry| filter.matches(entry))
+            .for_each(|entry| {
+                let token_stats = stats
+                    .entry(entry.game_info.token_root)
+                    .or_insert_with(|| Self::new(entry.game_info.token_root));
+
+                token_stats.games_played += 1;
+                if entry.outcome == GameOutcome::Won {
+                    token_stats.games_won += 1;
+                }
+                token_stats.total_staked = token_stats
+                    .total_staked
+                    .saturating_add(entry.game_info.staked_amount());
+                token_stats.total_won =
+                    token_stats.total_won.saturating_add(entry.game_info.reward);
+            });
+
+        stats
+            .into_values()
+            .map(|mut token_stats| {
+                if token_stats.total_staked > 0 {
+                    token_stats.roi = (token_stats.total_won as f64
+                        - token_stats.total_staked as f64)
+                        / token_stats.total_staked as f64;
+                }
+                token_stats
+            })
+            .collect()
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, CandidType, Copy)]
@@ -105,3 +243,79 @@ pub struct BalanceInfo {
     pub balance: Nat,
     pub withdrawable: Nat,
 }
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn game(token: u8, direction: GameDirection, pumps: u64, reward: u128) -> ParticipatedGameInfo {
+        ParticipatedGameInfo {
+            pumps,
+            dumps: 0,
+            reward,
+            token_root: Principal::from_slice(&[token]),
+            game_direction: direction,
+        }
+    }
+
+    #[test]
+    fn test_legacy_game_history_is_deserialized() {
+        let legacy_games = vec![game(1, GameDirection::Pump, 1, 0)];
+        let mut bytes = vec![];
+        ciborium::ser::into_writer(&legacy_games, &mut bytes).unwrap();
+
+        let mut deserializer = ciborium::de::Deserializer::from_reader(bytes.as_slice());
+        let games = deserialize_game_history(&mut deserializer).unwrap();
+
+        assert_eq!(
+            games,
+            vec![GameHistoryEntry::new(legacy_games[0], UNIX_EPOCH)]
+        );
+        assert_eq!(games[0].outcome, GameOutcome::Lost);
+    }
+
+    #[test]
+    fn test_token_game_stats_from_game_history() {
+        let start = UNIX_EPOCH + Duration::from_secs(1000);
+        let games = vec![
+            GameHistoryEntry::new(
+                game(1, GameDirection::Pump, 1, 2 * GDOLLR_TO_E8S as u128),
+                start,
+            ),
+            GameHistoryEntry::new(game(1, GameDirection::Dump, 2, 0), start),
+            GameHistoryEntry::new(
+                game(2, GameDirection::Pump, 1, 0),
+                start + Duration::from_secs(10),
+            ),
+        ];
+
+        let stats = TokenGameStats::from_game_history(games.iter(), &GameHistoryFilter::default());
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].games_played, 2);
+        assert_eq!(stats[0].games_won, 1);
+        assert_eq!(stats[0].total_staked, 3 * GDOLLR_TO_E8S as u128);
+        assert_eq!(stats[0].total_won, 2 * GDOLLR_TO_E8S as u128);
+        assert!(stats[0].roi < 0.0);
+
+        let pumps_only = GameHistoryFilter {
+            game_direction: Some(GameDirection::Pump),
+            to_time_exclusive: Some(start + Duration::from_secs(10)),
+            ..Default::default()
+        };
+        let stats = TokenGameStats::from_game_history(games.iter(), &pumps_only);
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].roi, 1.0);
+
+        let unbounded_game = ParticipatedGameInfo {
+            dumps: u64::MAX,
+            ..game(1, GameDirection::Dump, u64::MAX, u128::MAX)
+        };
+        assert_eq!(
+            unbounded_game.staked_amount(),
+            2 * u64::MAX as u128 * GDOLLR_TO_E8S as u128
+        );
+        assert_eq!(unbounded_game.outcome(), GameOutcome::Won);
+    }
+}
@@ -0,0 +1,33 @@
+use std::time::SystemTime;
+
+use candid::{CandidType, Nat, Principal};
+use serde::{Deserialize, Serialize};
+
+use super::profile::UserCanisterDetails;
+
+/// Reward credited to a user canister for a referral, either as the referrer or as the referee.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ReferralReward {
+    pub referrer_user_principal_id: Principal,
+    pub referee_user_principal_id: Principal,
+    pub amount: Nat,
+    pub rewarded_at: SystemTime,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ReferralDetails {
+    pub referrer: Option<UserCanisterDetails>,
+    /// Reward received for signing up with a referral
+    pub referee_reward: Option<ReferralReward>,
+    /// Rewards received for referring other users
+    pub referrer_rewards: Vec<ReferralReward>,
+    pub total_referral_earnings: Nat,
+}
+
+/// Users referred by `user_principal_id`, up to the depth requested
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ReferralTreeNode {
+    pub user_principal_id: Principal,
+    pub user_canister_id: Principal,
+    pub referees: Vec<ReferralTreeNode>,
+}
@@ -1,17 +1,103 @@
-use std::collections::BTreeMap;
+use std::{
+    collections::BTreeMap,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-use candid::{CandidType, Deserialize};
+use candid::{CandidType, Deserialize, Nat, Principal};
+use icrc_ledger_types::{
+    icrc1::{account::Account, transfer::Memo},
+    icrc3::transactions::Transfer,
+};
 use serde::Serialize;
 use serde_json_any_key::*;
 
-use crate::common::types::utility_token::token_event::{
-    HotOrNotOutcomePayoutEvent, MintEvent, PumpDumpOutcomePayoutEvent, StakeEvent, TokenEvent,
-    HOT_OR_NOT_BET_CREATOR_COMMISSION_PERCENTAGE, HOT_OR_NOT_BET_WINNINGS_MULTIPLIER,
+use crate::{
+    common::types::utility_token::token_event::{
+        HotOrNotOutcomePayoutEvent, MintEvent, StakeEvent, TokenEvent,
+        HOT_OR_NOT_BET_CREATOR_COMMISSION_PERCENTAGE, HOT_OR_NOT_BET_WINNINGS_MULTIPLIER,
+    },
+    pagination::{self, PaginationError},
 };
 
+const MAX_TRANSACTION_HISTORY_LEN: usize = 1500;
+const TRANSACTION_HISTORY_LEN_AFTER_TRUNCATION: u64 = 1000;
+const MAX_CREATOR_TOKEN_TRANSFERS_KEPT: usize = 500;
+pub const MAX_TOKEN_TRANSACTIONS_IN_ONE_REQUEST: u64 = 100;
+
+/// In-app tokens held by an individual user canister
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum UtilityTokenName {
+    Cents,
+    HotOrNotUtilityToken,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TokenTransaction {
+    pub id: u64,
+    pub event: TokenEvent,
+}
+
+/// Modelled after ICRC-3 `get_transactions`. Older transactions are truncated,
+/// so `first_index` can be greater than the requested start.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct GetTokenTransactionsResponse {
+    pub log_length: u64,
+    pub first_index: u64,
+    pub transactions: Vec<TokenTransaction>,
+}
+
+/// A token event the ledger refused, it was neither applied nor recorded
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum TokenEventError {
+    InsufficientBalance { balance: Nat, amount: Nat },
+}
+
 pub trait TokenTransactions {
     fn get_current_token_balance(&self) -> u128;
-    fn handle_token_event(&mut self, token_event: TokenEvent);
+    /// Debits larger than the balance are rejected instead of emptying the balance, so
+    /// the refund that follows a failed debit can not credit more than was taken
+    fn handle_token_event(&mut self, token_event: TokenEvent) -> Result<(), TokenEventError>;
+    fn get_transaction_history(&self) -> &BTreeMap<u64, TokenEvent>;
+
+    fn balance_of(&self) -> Nat {
+        Nat::from(self.get_current_token_balance())
+    }
+
+    fn get_transactions(&self, start: u64, length: u64) -> GetTokenTransactionsResponse {
+        let history = self.get_transaction_history();
+        let log_length = history.last_key_value().map(|(id, _)| *id).unwrap_or(0);
+        let first_index = history.first_key_value().map(|(id, _)| *id).unwrap_or(0);
+
+        let transactions = history
+            .range(start..)
+            .take(length.min(MAX_TOKEN_TRANSACTIONS_IN_ONE_REQUEST) as usize)
+            .map(|(id, event)| TokenTransaction {
+                id: *id,
+                event: event.clone(),
+            })
+            .collect();
+
+        GetTokenTransactionsResponse {
+            log_length,
+            first_index,
+            transactions,
+        }
+    }
+}
+
+/// Appends the event to the history, ids start at 1.
+/// Once the history grows past its limit only the latest entries are kept.
+pub fn record_token_event(history: &mut BTreeMap<u64, TokenEvent>, token_event: TokenEvent) {
+    let last_key = *history
+        .last_key_value()
+        .unwrap_or((&0, &TokenEvent::Burn))
+        .0;
+
+    if history.len() > MAX_TRANSACTION_HISTORY_LEN {
+        history.retain(|key, _| *key > last_key - TRANSACTION_HISTORY_LEN_AFTER_TRUNCATION)
+    }
+
+    history.insert(last_key + 1, token_event);
 }
 
 impl TokenTransactions for TokenBalance {
@@ -19,44 +105,38 @@ impl TokenTransactions for TokenBalance {
         self.utility_token_balance as u128
     }
 
-    fn handle_token_event(&mut self, token_event: TokenEvent) {
+    fn get_transaction_history(&self) -> &BTreeMap<u64, TokenEvent> {
+        &self.utility_token_transaction_history
+    }
+
+    fn handle_token_event(&mut self, token_event: TokenEvent) -> Result<(), TokenEventError> {
         match &token_event {
             TokenEvent::Mint { details, .. } => match details {
-                MintEvent::NewUserSignup { .. } => {
-                    self.utility_token_balance += token_event.get_token_amount_for_token_event();
-                    self.lifetime_earnings += token_event.get_token_amount_for_token_event();
-                }
-                MintEvent::Referral { .. } => {
-                    self.utility_token_balance += token_event.get_token_amount_for_token_event();
-                    self.lifetime_earnings += token_event.get_token_amount_for_token_event();
+                MintEvent::NewUserSignup { .. } | MintEvent::Referral { .. } => {
+                    self.credit(token_event.get_token_amount_for_token_event(), true);
                 }
                 MintEvent::Airdrop { amount } => {
-                    self.utility_token_balance += amount;
-                    self.lifetime_earnings += amount;
+                    self.credit(*amount, true);
                 }
             },
             TokenEvent::Burn => {}
             TokenEvent::Transfer { amount, .. } => {
-                self.utility_token_balance -= amount;
+                self.debit(*amount)?;
             }
             TokenEvent::Receive { amount, .. } => {
-                self.utility_token_balance += amount;
+                self.credit(*amount, false);
             }
             TokenEvent::Stake {
                 details, amount, ..
             } => match details {
                 StakeEvent::BetOnHotOrNotPost { bet_amount, .. } => {
-                    self.utility_token_balance -= bet_amount;
+                    self.debit(*bet_amount)?;
                 }
                 StakeEvent::BetFailureRefund { bet_amount, .. } => {
-                    self.utility_token_balance += bet_amount;
+                    self.credit(*bet_amount, false);
                 }
-                StakeEvent::BetOnPumpDump {
-                    pumps,
-                    dumps,
-                    root_canister_id,
-                } => {
-                    self.utility_token_balance -= amount;
+                StakeEvent::BetOnPumpDump { .. } => {
+                    self.debit(*amount)?;
                 }
             },
 
@@ -65,49 +145,30 @@ impl TokenTransactions for TokenBalance {
                     room_pot_total_amount,
                     ..
                 } => {
-                    self.utility_token_balance +=
-                        room_pot_total_amount * HOT_OR_NOT_BET_CREATOR_COMMISSION_PERCENTAGE / 100;
-                    self.lifetime_earnings +=
-                        room_pot_total_amount * HOT_OR_NOT_BET_CREATOR_COMMISSION_PERCENTAGE / 100;
+                    self.credit(
+                        get_commission_amount_from_room_pot_total_amount(*room_pot_total_amount),
+                        true,
+                    );
                 }
                 HotOrNotOutcomePayoutEvent::WinningsEarnedFromBet {
                     winnings_amount, ..
                 } => {
-                    self.utility_token_balance += winnings_amount;
-                    self.lifetime_earnings +=
-                        get_earnings_amount_from_winnings_amount(winnings_amount);
-                }
-            },
-            TokenEvent::PumpDumpOutcomePayout {
-                amount,
-                payout_type,
-            } => match payout_type {
-                PumpDumpOutcomePayoutEvent::CreatorRewardFromPumpDumpGame => {
-                    self.utility_token_balance += *amount as u64;
-                    self.lifetime_earnings += *amount as u64;
-                }
-                PumpDumpOutcomePayoutEvent::RewardFromPumpDumpGame { .. } => {
-                    self.utility_token_balance += *amount as u64;
-                    self.lifetime_earnings += *amount as u64;
+                    self.credit(*winnings_amount, false);
+                    self.lifetime_earnings = self
+                        .lifetime_earnings
+                        .saturating_add(get_earnings_amount_from_winnings_amount(winnings_amount));
                 }
             },
+            TokenEvent::PumpDumpOutcomePayout { amount, .. } => {
+                self.credit(u64::try_from(*amount).unwrap_or(u64::MAX), true);
+            }
 
             TokenEvent::Withdraw { .. } => {}
         }
 
-        let utility_token_transaction_history = &mut self.utility_token_transaction_history;
-
-        let last_key = *utility_token_transaction_history
-            .last_key_value()
-            .unwrap_or((&0, &TokenEvent::Burn))
-            .0;
-
-        if utility_token_transaction_history.len() > 1500 {
-            utility_token_transaction_history.retain(|key, _| *key > last_key - 1000)
-        }
+        record_token_event(&mut self.utility_token_transaction_history, token_event);
 
-        self.utility_token_transaction_history
-            .insert(last_key + 1, token_event);
+        Ok(())
     }
 }
 
@@ -122,6 +183,293 @@ impl TokenBalance {
     pub fn get_utility_token_transaction_history(&self) -> &BTreeMap<u64, TokenEvent> {
         &self.utility_token_transaction_history
     }
+
+    fn credit(&mut self, amount: u64, is_earning: bool) {
+        self.utility_token_balance = self.utility_token_balance.saturating_add(amount);
+        if is_earning {
+            self.lifetime_earnings = self.lifetime_earnings.saturating_add(amount);
+        }
+    }
+
+    fn debit(&mut self, amount: u64) -> Result<(), TokenEventError> {
+        self.utility_token_balance = self.utility_token_balance.checked_sub(amount).ok_or(
+            TokenEventError::InsufficientBalance {
+                balance: Nat::from(self.utility_token_balance),
+                amount: Nat::from(amount),
+            },
+        )?;
+
+        Ok(())
+    }
+}
+
+/// Last known balance of a creator token held by the user, keyed by its SNS root
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct TokenHolding {
+    pub root: Principal,
+    pub ledger: Principal,
+    pub symbol: String,
+    pub decimals: u8,
+    pub balance: Nat,
+    pub refreshed_at: SystemTime,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum CreatorTokenTransferStatus {
+    /// Recorded before the ledger transfer, the ledger has not confirmed it yet.
+    /// Retrying sends the same transfer, which the ledger deduplicates.
+    TransferPending {
+        attempts: u32,
+        last_error: Option<String>,
+    },
+    /// The ledger refused the transfer, nothing was sent
+    TransferFailed {
+        error: String,
+    },
+    /// The ledger transfer went through, the receiving canister was not notified yet
+    Transferred,
+    /// The receiving canister added the token to its holdings
+    Completed,
+    NotificationFailed {
+        attempts: u32,
+        last_error: String,
+    },
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CreatorTokenTransfer {
+    pub id: u64,
+    pub token_root: Principal,
+    /// Unknown for transfers recorded only after they went through
+    #[serde(default)]
+    pub ledger: Option<Principal>,
+    pub target_canister: Principal,
+    pub amount: Nat,
+    #[serde(default)]
+    pub memo: Option<Memo>,
+    /// Set once the ledger confirmed the transfer
+    pub block_index: Option<Nat>,
+    pub status: CreatorTokenTransferStatus,
+    pub created_at: SystemTime,
+    pub updated_at: SystemTime,
+}
+
+impl CreatorTokenTransfer {
+    /// The ledger's `created_at_time` of every attempt, in nanoseconds
+    pub fn get_created_at_time(&self) -> u64 {
+        self.created_at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos() as u64
+    }
+
+    /// Once the ledger's deduplication window has passed, sending the transfer again is
+    /// refused as too old, whether or not an earlier attempt went through
+    pub fn is_past_deduplication_window(
+        &self,
+        deduplication_window: Duration,
+        current_time: SystemTime,
+    ) -> bool {
+        current_time
+            .duration_since(self.created_at)
+            .is_ok_and(|age| age > deduplication_window)
+    }
+
+    /// Whether a ledger transfer sent by `from` is one of the attempts of this transfer
+    pub fn is_sent_as(&self, from: Principal, ledger_transfer: &Transfer) -> bool {
+        ledger_transfer.from
+            == Account {
+                owner: from,
+                subaccount: None,
+            }
+            && ledger_transfer.to
+                == Account {
+                    owner: self.target_canister,
+                    subaccount: None,
+                }
+            && ledger_transfer.amount == self.amount
+            && ledger_transfer.memo == self.memo
+            && ledger_transfer.created_at_time == Some(self.get_created_at_time())
+    }
+}
+
+/// Creator token transfers sent by the user, keyed by id. Only the latest finished
+/// transfers are kept, transfers still waiting on their receiver are never dropped.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct CreatorTokenTransfers {
+    transfers: BTreeMap<u64, CreatorTokenTransfer>,
+}
+
+impl CreatorTokenTransfers {
+    /// Records the transfer before it is sent to the ledger, the creation time
+    /// doubles as the ledger's `created_at_time` for every attempt
+    pub fn record(
+        &mut self,
+        token_root: Principal,
+        ledger: Principal,
+        target_canister: Principal,
+        amount: Nat,
+        memo: Option<Memo>,
+        current_time: SystemTime,
+    ) -> u64 {
+        let id = self
+            .transfers
+            .last_key_value()
+            .map(|(id, _)| id + 1)
+            .unwrap_or_default();
+
+        self.transfers.insert(
+            id,
+            CreatorTokenTransfer {
+                id,
+                token_root,
+                ledger: Some(ledger),
+                target_canister,
+                amount,
+                memo,
+                block_index: None,
+                status: CreatorTokenTransferStatus::TransferPending {
+                    attempts: 0,
+                    last_error: None,
+                },
+                created_at: current_time,
+                updated_at: current_time,
+            },
+        );
+        self.truncate_finished_transfers();
+
+        id
+    }
+
+    pub fn get(&self, id: u64) -> Option<&CreatorTokenTransfer> {
+        self.transfers.get(&id)
+    }
+
+    pub fn mark_transferred(&mut self, id: u64, block_index: Nat, current_time: SystemTime) {
+        if let Some(transfer) = self.transfers.get_mut(&id) {
+            transfer.block_index = Some(block_index);
+            transfer.status = CreatorTokenTransferStatus::Transferred;
+            transfer.updated_at = current_time;
+        }
+    }
+
+    /// The ledger could not be reached, so the transfer may or may not have happened
+    pub fn mark_transfer_attempt_failed(
+        &mut self,
+        id: u64,
+        error: String,
+        current_time: SystemTime,
+    ) {
+        if let Some(transfer) = self.transfers.get_mut(&id) {
+            let CreatorTokenTransferStatus::TransferPending { attempts, .. } = transfer.status
+            else {
+                return;
+            };
+            transfer.status = CreatorTokenTransferStatus::TransferPending {
+                attempts: attempts + 1,
+                last_error: Some(error),
+            };
+            transfer.updated_at = current_time;
+        }
+    }
+
+    pub fn mark_transfer_failed(&mut self, id: u64, error: String, current_time: SystemTime) {
+        if let Some(transfer) = self.transfers.get_mut(&id) {
+            transfer.status = CreatorTokenTransferStatus::TransferFailed { error };
+            transfer.updated_at = current_time;
+        }
+    }
+
+    pub fn mark_completed(&mut self, id: u64, current_time: SystemTime) {
+        if let Some(transfer) = self.transfers.get_mut(&id) {
+            transfer.status = CreatorTokenTransferStatus::Completed;
+            transfer.updated_at = current_time;
+        }
+    }
+
+    pub fn mark_notification_failed(&mut self, id: u64, error: String, current_time: SystemTime) {
+        if let Some(transfer) = self.transfers.get_mut(&id) {
+            let attempts = match transfer.status {
+                CreatorTokenTransferStatus::NotificationFailed { attempts, .. } => attempts + 1,
+                _ => 1,
+            };
+            transfer.status = CreatorTokenTransferStatus::NotificationFailed {
+                attempts,
+                last_error: error,
+            };
+            transfer.updated_at = current_time;
+        }
+    }
+
+    /// Transfers whose outcome on the ledger is unknown, and transfers whose receiver
+    /// still has to be notified that have attempts left. Pending transfers are never
+    /// given up on, the ledger holds funds for them until they are resolved.
+    pub fn get_retryable_transfer_ids(&self, max_notification_attempts: u32) -> Vec<u64> {
+        self.transfers
+            .values()
+            .filter(|transfer| match transfer.status {
+                CreatorTokenTransferStatus::Transferred
+                | CreatorTokenTransferStatus::TransferPending { .. } => true,
+                CreatorTokenTransferStatus::NotificationFailed { attempts, .. } => {
+                    attempts < max_notification_attempts
+                }
+                CreatorTokenTransferStatus::TransferFailed { .. }
+                | CreatorTokenTransferStatus::Completed => false,
+            })
+            .map(|transfer| transfer.id)
+            .collect()
+    }
+
+    /// Newest transfers first
+    pub fn get_transfers_with_pagination_cursor(
+        &self,
+        from_inclusive_index: u64,
+        limit: u64,
+    ) -> Result<Vec<CreatorTokenTransfer>, PaginationError> {
+        let (from_inclusive_index, limit) = pagination::get_pagination_bounds_cursor(
+            from_inclusive_index,
+            limit,
+            self.transfers.len() as u64,
+        )?;
+
+        Ok(self
+            .transfers
+            .values()
+            .rev()
+            .skip(from_inclusive_index as usize)
+            .take(limit as usize)
+            .cloned()
+            .collect())
+    }
+
+    fn truncate_finished_transfers(&mut self) {
+        let excess = self
+            .transfers
+            .len()
+            .saturating_sub(MAX_CREATOR_TOKEN_TRANSFERS_KEPT);
+        let finished_ids: Vec<u64> = self
+            .transfers
+            .values()
+            .filter(|transfer| {
+                matches!(
+                    transfer.status,
+                    CreatorTokenTransferStatus::Completed
+                        | CreatorTokenTransferStatus::TransferFailed { .. }
+                )
+            })
+            .map(|transfer| transfer.id)
+            .take(excess)
+            .collect();
+
+        finished_ids.iter().for_each(|id| {
+            self.transfers.remove(id);
+        });
+    }
+}
+
+pub fn get_commission_amount_from_room_pot_total_amount(room_pot_total_amount: u64) -> u64 {
+    (room_pot_total_amount as u128 * HOT_OR_NOT_BET_CREATOR_COMMISSION_PERCENTAGE as u128 / 100)
+        as u64
 }
 
 pub fn get_earnings_amount_from_winnings_amount(winnings_amount: &u64) -> u64 {
@@ -153,7 +501,7 @@ mod test {
             let mut token_balance = TokenBalance::default();
 
             (0..1500).for_each(|_| {
-                token_balance.handle_token_event(TokenEvent::Burn);
+                token_balance.handle_token_event(TokenEvent::Burn).unwrap();
             });
 
             assert_eq!(token_balance.utility_token_transaction_history.len(), 1500);
@@ -166,7 +514,7 @@ mod test {
                 1500
             );
 
-            token_balance.handle_token_event(TokenEvent::Burn);
+            token_balance.handle_token_event(TokenEvent::Burn).unwrap();
             assert_eq!(token_balance.utility_token_transaction_history.len(), 1501);
             assert_eq!(
                 *token_balance
@@ -177,7 +525,7 @@ mod test {
                 1501
             );
 
-            token_balance.handle_token_event(TokenEvent::Burn);
+            token_balance.handle_token_event(TokenEvent::Burn).unwrap();
             assert_eq!(token_balance.utility_token_transaction_history.len(), 1001);
             assert_eq!(
                 *token_balance
@@ -196,7 +544,7 @@ mod test {
                 1502
             );
 
-            token_balance.handle_token_event(TokenEvent::Burn);
+            token_balance.handle_token_event(TokenEvent::Burn).unwrap();
             assert_eq!(token_balance.utility_token_transaction_history.len(), 1002);
             assert_eq!(
                 *token_balance
@@ -220,40 +568,127 @@ mod test {
         fn test_handle_token_event() {
             let mut token_balance = TokenBalance::default();
 
-            token_balance.handle_token_event(TokenEvent::Mint {
-                amount: 1000,
-                details: MintEvent::NewUserSignup {
-                    new_user_principal_id: get_mock_user_alice_principal_id(),
-                },
-                timestamp: SystemTime::now(),
-            });
+            token_balance
+                .handle_token_event(TokenEvent::Mint {
+                    amount: 1000,
+                    details: MintEvent::NewUserSignup {
+                        new_user_principal_id: get_mock_user_alice_principal_id(),
+                    },
+                    timestamp: SystemTime::now(),
+                })
+                .unwrap();
 
             assert_eq!(token_balance.utility_token_balance, 1000);
 
-            token_balance.handle_token_event(TokenEvent::Mint {
-                amount: 500,
-                details: MintEvent::Referral {
-                    referee_user_principal_id: get_mock_user_alice_principal_id(),
-                    referrer_user_principal_id: get_mock_user_bob_principal_id(),
-                },
-                timestamp: SystemTime::now(),
-            });
+            token_balance
+                .handle_token_event(TokenEvent::Mint {
+                    amount: 500,
+                    details: MintEvent::Referral {
+                        referee_user_principal_id: get_mock_user_alice_principal_id(),
+                        referrer_user_principal_id: get_mock_user_bob_principal_id(),
+                    },
+                    timestamp: SystemTime::now(),
+                })
+                .unwrap();
 
             assert_eq!(token_balance.utility_token_balance, 1500);
 
-            token_balance.handle_token_event(TokenEvent::Stake {
-                amount: 100,
-                details: StakeEvent::BetOnHotOrNotPost {
-                    post_canister_id: get_mock_user_alice_canister_id(),
-                    post_id: 1,
-                    bet_amount: 100,
-                    bet_direction: BetDirection::Hot,
-                },
-                timestamp: SystemTime::now(),
-            });
+            token_balance
+                .handle_token_event(TokenEvent::Stake {
+                    amount: 100,
+                    details: StakeEvent::BetOnHotOrNotPost {
+                        post_canister_id: get_mock_user_alice_canister_id(),
+                        post_id: 1,
+                        bet_amount: 100,
+                        bet_direction: BetDirection::Hot,
+                    },
+                    timestamp: SystemTime::now(),
+                })
+                .unwrap();
 
             assert_eq!(token_balance.utility_token_balance, 1400);
         }
+
+        #[test]
+        fn test_overdraft_is_rejected_and_refund_mints_nothing() {
+            let mut token_balance = TokenBalance {
+                utility_token_balance: 50,
+                ..Default::default()
+            };
+            let bet = StakeEvent::BetOnHotOrNotPost {
+                post_canister_id: get_mock_user_alice_canister_id(),
+                post_id: 1,
+                bet_amount: 100,
+                bet_direction: BetDirection::Hot,
+            };
+
+            assert_eq!(
+                token_balance.handle_token_event(TokenEvent::Stake {
+                    amount: 100,
+                    details: bet,
+                    timestamp: SystemTime::now(),
+                }),
+                Err(TokenEventError::InsufficientBalance {
+                    balance: Nat::from(50_u32),
+                    amount: Nat::from(100_u32)
+                })
+            );
+            assert_eq!(token_balance.balance_of(), Nat::from(50_u32));
+            assert!(token_balance.utility_token_transaction_history.is_empty());
+
+            // only a bet that was taken is refunded, the refund restores the balance it
+            // was taken from
+            token_balance
+                .handle_token_event(TokenEvent::Stake {
+                    amount: 50,
+                    details: StakeEvent::BetOnPumpDump {
+                        pumps: 1,
+                        dumps: 0,
+                        root_canister_id: get_mock_user_alice_canister_id(),
+                    },
+                    timestamp: SystemTime::now(),
+                })
+                .unwrap();
+            assert_eq!(token_balance.balance_of(), Nat::from(0_u32));
+
+            token_balance
+                .handle_token_event(TokenEvent::Stake {
+                    amount: 50,
+                    details: StakeEvent::BetFailureRefund {
+                        bet_amount: 50,
+                        post_id: 1,
+                        post_canister_id: get_mock_user_alice_canister_id(),
+                        bet_direction: BetDirection::Hot,
+                    },
+                    timestamp: SystemTime::now(),
+                })
+                .unwrap();
+            assert_eq!(token_balance.balance_of(), Nat::from(50_u32));
+        }
+
+        #[test]
+        fn test_get_transactions() {
+            let mut token_balance = TokenBalance::default();
+
+            (0..1502).for_each(|_| {
+                token_balance.handle_token_event(TokenEvent::Burn).unwrap();
+            });
+
+            let response = token_balance.get_transactions(0, 10);
+            assert_eq!(response.log_length, 1502);
+            assert_eq!(response.first_index, 502);
+            assert_eq!(response.transactions.len(), 10);
+            assert_eq!(response.transactions[0].id, 502);
+
+            let response = token_balance.get_transactions(1500, 10);
+            assert_eq!(response.transactions.len(), 3);
+
+            let response = token_balance.get_transactions(600, 1000);
+            assert_eq!(
+                response.transactions.len() as u64,
+                MAX_TOKEN_TRANSACTIONS_IN_ONE_REQUEST
+            );
+        }
     }
 
     mod test_get_earnings_amount_from_winnings_amount {
@@ -292,4 +727,140 @@ mod test {
             assert_eq!(get_earnings_amount_from_winnings_amount(&winnings), 48);
         }
     }
+
+    mod test_creator_token_transfers {
+        use std::time::SystemTime;
+
+        use test_utils::setup::test_constants::{
+            get_mock_user_alice_canister_id, get_mock_user_bob_canister_id,
+        };
+
+        use super::*;
+
+        fn record_transfer(transfers: &mut CreatorTokenTransfers) -> u64 {
+            transfers.record(
+                get_mock_user_alice_canister_id(),
+                get_mock_user_alice_canister_id(),
+                get_mock_user_bob_canister_id(),
+                Nat::from(100_u64),
+                None,
+                SystemTime::now(),
+            )
+        }
+
+        #[test]
+        fn test_transfer_is_pending_until_the_ledger_confirms_it() {
+            let mut transfers = CreatorTokenTransfers::default();
+            let id = record_transfer(&mut transfers);
+            assert_eq!(transfers.get(id).unwrap().block_index, None);
+            assert_eq!(transfers.get_retryable_transfer_ids(2), vec![id]);
+
+            // pending transfers are retried past the attempts of notifications
+            for _ in 0..3 {
+                transfers.mark_transfer_attempt_failed(id, "unreachable".into(), SystemTime::now());
+            }
+            assert_eq!(transfers.get_retryable_transfer_ids(2), vec![id]);
+
+            transfers.mark_transferred(id, Nat::from(7_u64), SystemTime::now());
+            assert_eq!(
+                transfers.get(id).unwrap().block_index,
+                Some(Nat::from(7_u64))
+            );
+            assert_eq!(
+                transfers.get(id).unwrap().status,
+                CreatorTokenTransferStatus::Transferred
+            );
+
+            // a confirmed transfer is not sent again
+            transfers.mark_transfer_attempt_failed(id, "unreachable".into(), SystemTime::now());
+            assert_eq!(
+                transfers.get(id).unwrap().status,
+                CreatorTokenTransferStatus::Transferred
+            );
+
+            let refused_id = record_transfer(&mut transfers);
+            transfers.mark_transfer_failed(
+                refused_id,
+                "InsufficientFunds".into(),
+                SystemTime::now(),
+            );
+            assert_eq!(transfers.get_retryable_transfer_ids(2), vec![id]);
+        }
+
+        #[test]
+        fn test_transfer_is_found_among_the_ledger_transfers() {
+            let mut transfers = CreatorTokenTransfers::default();
+            let id = record_transfer(&mut transfers);
+            let transfer = transfers.get(id).unwrap();
+            let from = get_mock_user_alice_canister_id();
+            let mut ledger_transfer = Transfer {
+                amount: transfer.amount.clone(),
+                from: Account {
+                    owner: from,
+                    subaccount: None,
+                },
+                to: Account {
+                    owner: transfer.target_canister,
+                    subaccount: None,
+                },
+                spender: None,
+                memo: None,
+                fee: None,
+                created_at_time: Some(transfer.get_created_at_time()),
+            };
+            assert!(transfer.is_sent_as(from, &ledger_transfer));
+            assert!(!transfer.is_sent_as(get_mock_user_bob_canister_id(), &ledger_transfer));
+
+            // another transfer of the same amount to the same canister
+            ledger_transfer.created_at_time = Some(transfer.get_created_at_time() + 1);
+            assert!(!transfer.is_sent_as(from, &ledger_transfer));
+
+            let window = Duration::from_secs(60);
+            assert!(!transfer.is_past_deduplication_window(window, transfer.created_at + window));
+            assert!(transfer.is_past_deduplication_window(
+                window,
+                transfer.created_at + window + Duration::from_secs(1)
+            ));
+        }
+
+        #[test]
+        fn test_failed_notifications_are_retried_until_attempts_run_out() {
+            let mut transfers = CreatorTokenTransfers::default();
+            let id = record_transfer(&mut transfers);
+            transfers.mark_transferred(id, Nat::from(1_u64), SystemTime::now());
+            assert_eq!(transfers.get_retryable_transfer_ids(2), vec![id]);
+
+            transfers.mark_notification_failed(id, "rejected".into(), SystemTime::now());
+            assert_eq!(transfers.get_retryable_transfer_ids(2), vec![id]);
+
+            transfers.mark_notification_failed(id, "rejected".into(), SystemTime::now());
+            assert_eq!(
+                transfers.get(id).unwrap().status,
+                CreatorTokenTransferStatus::NotificationFailed {
+                    attempts: 2,
+                    last_error: "rejected".into()
+                }
+            );
+            assert!(transfers.get_retryable_transfer_ids(2).is_empty());
+        }
+
+        #[test]
+        fn test_only_finished_transfers_are_truncated() {
+            let mut transfers = CreatorTokenTransfers::default();
+            let pending_id = record_transfer(&mut transfers);
+
+            (0..MAX_CREATOR_TOKEN_TRANSFERS_KEPT + 10).for_each(|_| {
+                let id = record_transfer(&mut transfers);
+                transfers.mark_completed(id, SystemTime::now());
+            });
+
+            assert!(transfers.transfers.len() <= MAX_CREATOR_TOKEN_TRANSFERS_KEPT + 1);
+            assert!(transfers.get(pending_id).is_some());
+
+            let latest = transfers
+                .get_transfers_with_pagination_cursor(0, 1)
+                .unwrap();
+            assert_eq!(latest[0].id, MAX_CREATOR_TOKEN_TRANSFERS_KEPT as u64 + 10);
+        }
+    }
 }
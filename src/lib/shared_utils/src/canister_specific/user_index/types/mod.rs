@@ -6,6 +6,8 @@ use std::{
 use candid::{CandidType, Deserialize, Principal};
 use serde::Serialize;
 
+use crate::common::types::snapshot::BackupCatalogEntry;
+
 pub mod args;
 
 #[derive(CandidType, Deserialize, Clone, Serialize, Debug, PartialEq)]
@@ -69,3 +71,53 @@ impl Default for BroadcastCallStatus {
         }
     }
 }
+
+/// Rollout of a creator token canister type to the individual canisters of a subnet
+#[derive(Debug, CandidType, Serialize, Deserialize, Default, Clone)]
+pub struct CreatorDaoSnsCanistersUpgradeStatus {
+    /// Successful canisters are the ones that had creator tokens to upgrade
+    pub broadcast_status: BroadcastCallStatus,
+    /// Canisters without creator tokens or with all of them on the version already
+    pub up_to_date_canisters_count: u64,
+    /// Another rollout of the canister type can only start once this one is done
+    #[serde(default)]
+    pub in_progress: bool,
+}
+
+/// Backup run on a subnet. The subnet orchestrator's own snapshot is counted along with
+/// the ones of the individual canisters.
+#[derive(Debug, CandidType, Serialize, Deserialize, Default, Clone)]
+pub struct SubnetBackupStatus {
+    pub broadcast_status: BroadcastCallStatus,
+    /// Canisters whose snapshot is not completed or failed yet
+    pub pending_canisters_count: u64,
+    /// Snapshot of the subnet orchestrator, once it is completed
+    pub subnet_orchestrator_backup: Option<BackupCatalogEntry>,
+    /// Set once every canister is done, the platform orchestrator polls for it
+    pub finished_at: Option<SystemTime>,
+}
+
+/// Cents airdrop campaign run over the individual canisters of a subnet
+#[derive(Debug, CandidType, Serialize, Deserialize, Default, Clone)]
+pub struct CentsAirdropCampaignStatus {
+    /// Successful canisters are the ones that were credited by this run
+    pub broadcast_status: BroadcastCallStatus,
+    pub already_credited_canisters_count: u64,
+    pub not_eligible_canisters_count: u64,
+    pub expired_canisters_count: u64,
+}
+
+/// A user canister restored from a snapshot by `restore_user_canister_from_snapshot`
+#[derive(Debug, CandidType, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RestoredUserCanister {
+    pub canister_id: Principal,
+    /// Set if the username of the restored profile belongs to another user by now. The
+    /// username is left with that user, it is up to the admin to resolve.
+    pub username_conflict: Option<UsernameConflict>,
+}
+
+#[derive(Debug, CandidType, Serialize, Deserialize, Clone, PartialEq)]
+pub struct UsernameConflict {
+    pub unique_user_name: String,
+    pub taken_by: Principal,
+}
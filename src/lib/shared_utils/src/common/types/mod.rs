@@ -1,6 +1,7 @@
 pub mod app_primitive_type;
 pub mod http;
 pub mod known_principal;
+pub mod snapshot;
 pub mod storable_principal;
 pub mod top_posts;
 pub mod utility_token;
@@ -0,0 +1,413 @@
+use std::{collections::BTreeMap, time::SystemTime};
+
+use candid::{CandidType, Deserialize, Principal};
+use serde::{de::DeserializeOwned, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::constant::MAX_STORED_SNAPSHOTS;
+
+/// Marks binary snapshots, snapshots without it are the older JSON ones
+pub const SNAPSHOT_MAGIC: [u8; 4] = *b"YSNP";
+pub const SNAPSHOT_FORMAT_VERSION: u16 = 1;
+/// Payload is a sequence of CBOR sections written across several messages
+pub const SNAPSHOT_FORMAT_VERSION_CHUNKED: u16 = 2;
+// magic followed by the length of the header as a u32 in little endian
+pub(crate) const SNAPSHOT_PREFIX_LEN: usize = SNAPSHOT_MAGIC.len() + 4;
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SnapshotCanisterType {
+    IndividualUser,
+    SubnetOrchestrator,
+    PlatformOrchestrator,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SnapshotHeader {
+    pub format_version: u16,
+    pub canister_type: SnapshotCanisterType,
+    /// Version of the canister's snapshot types the payload was written with
+    pub schema_version: u32,
+    pub created_at: SystemTime,
+    pub payload_len: u64,
+    #[serde(with = "serde_bytes")]
+    pub payload_sha256: Vec<u8>,
+}
+
+#[derive(CandidType, Deserialize, Debug, PartialEq, Eq)]
+pub enum SnapshotError {
+    Empty,
+    InvalidHeader(String),
+    UnsupportedFormatVersion(u16),
+    CanisterTypeMismatch {
+        expected: SnapshotCanisterType,
+        found: SnapshotCanisterType,
+    },
+    PayloadLengthMismatch {
+        expected: u64,
+        found: u64,
+    },
+    ChecksumMismatch,
+    Serialize(String),
+    Deserialize(String),
+    GenerationInProgress,
+    OutOfStableMemory,
+    InvalidManifest(String),
+    ChunkOutOfBounds(u64),
+    ChunkLengthMismatch {
+        chunk_index: u64,
+        expected: u64,
+        found: u64,
+    },
+    ChunkChecksumMismatch(u64),
+    /// Number of chunks that were not uploaded yet
+    MissingChunks(u64),
+    NoUploadInProgress,
+    UploadInProgress,
+    NotFound(u64),
+    /// The snapshot is completed and cannot be written to anymore
+    AlreadyCompleted(u64),
+    /// The snapshot is still being written
+    NotCompleted(u64),
+    /// Snapshots are only appended to, `expected` is where the bytes written so far end
+    NonContiguousWrite {
+        expected: u64,
+        found: u64,
+    },
+    /// Chunks are uploaded in order, `expected` is the next one
+    UnexpectedChunk {
+        expected: u64,
+        found: u64,
+    },
+    LoadInProgress,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SnapshotSource {
+    /// Saved in a single message by one of the `save_snapshot*` endpoints
+    Saved,
+    /// Written across several messages by `start_snapshot`
+    Generated,
+    /// Uploaded in chunks described by a manifest
+    Uploaded,
+    /// Received through `receive_and_save_snaphot` and checked by `finalize_received_snapshot`
+    Received,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SnapshotInfo {
+    pub id: u64,
+    pub source: SnapshotSource,
+    pub created_at: SystemTime,
+    /// None while the snapshot is still being written
+    pub completed_at: Option<SystemTime>,
+    pub size: u64,
+    /// SHA-256 of the whole snapshot, taken when it is completed
+    pub sha256: Option<Vec<u8>>,
+}
+
+/// A snapshot a scheduled backup started on a canister
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct BackupCatalogEntry {
+    pub backup_id: u64,
+    pub canister_id: Principal,
+    pub snapshot_id: u64,
+    pub started_at: SystemTime,
+}
+
+/// Snapshots started by scheduled backups, per canister. Canisters only keep their last
+/// few snapshots, so entries older than those are dropped as well.
+#[derive(Serialize, Deserialize, Clone, Default, Debug)]
+pub struct BackupCatalog(BTreeMap<Principal, Vec<BackupCatalogEntry>>);
+
+impl BackupCatalog {
+    pub fn insert(&mut self, entry: BackupCatalogEntry) {
+        let entries = self.0.entry(entry.canister_id).or_default();
+        entries.push(entry);
+        let excess = entries.len().saturating_sub(MAX_STORED_SNAPSHOTS);
+        entries.drain(..excess);
+    }
+
+    pub fn get(&self, canister_id: &Principal) -> Vec<BackupCatalogEntry> {
+        self.0.get(canister_id).cloned().unwrap_or_default()
+    }
+}
+
+/// Describes a snapshot for transferring it in chunks, every chunk but the last one is
+/// `chunk_size` long
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SnapshotManifest {
+    pub total_size: u64,
+    pub chunk_size: u64,
+    pub chunk_count: u64,
+    #[serde(with = "serde_bytes")]
+    pub sha256: Vec<u8>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum SnapshotGenerationState {
+    InProgress,
+    Completed,
+    Failed(String),
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SnapshotProgress {
+    pub snapshot_id: u64,
+    pub state: SnapshotGenerationState,
+    pub started_at: SystemTime,
+    pub finished_at: Option<SystemTime>,
+    pub items_written: u64,
+    /// Items counted when the generation started, items added since are written as well
+    pub total_items: u64,
+    /// Length of the snapshot, only includes the header once it is completed
+    pub bytes_written: u64,
+}
+
+pub fn is_binary_snapshot(bytes: &[u8]) -> bool {
+    bytes.starts_with(&SNAPSHOT_MAGIC)
+}
+
+/// Writes the payload as CBOR behind a header carrying its length and SHA-256
+pub fn encode_snapshot<T: Serialize>(
+    canister_type: SnapshotCanisterType,
+    schema_version: u32,
+    created_at: SystemTime,
+    payload: &T,
+) -> Result<Vec<u8>, SnapshotError> {
+    let mut payload_bytes = vec![];
+    ciborium::ser::into_writer(payload, &mut payload_bytes)
+        .map_err(|e| SnapshotError::Serialize(e.to_string()))?;
+
+    let header = SnapshotHeader {
+        format_version: SNAPSHOT_FORMAT_VERSION,
+        canister_type,
+        schema_version,
+        created_at,
+        payload_len: payload_bytes.len() as u64,
+        payload_sha256: Sha256::digest(&payload_bytes).to_vec(),
+    };
+
+    let mut snapshot = encode_snapshot_header(&header)?;
+    snapshot.extend_from_slice(&payload_bytes);
+
+    Ok(snapshot)
+}
+
+/// Returns the magic, header length and header that go in front of the payload
+pub fn encode_snapshot_header(header: &SnapshotHeader) -> Result<Vec<u8>, SnapshotError> {
+    let mut header_bytes = vec![];
+    ciborium::ser::into_writer(header, &mut header_bytes)
+        .map_err(|e| SnapshotError::Serialize(e.to_string()))?;
+
+    let mut prefix = Vec::with_capacity(SNAPSHOT_PREFIX_LEN + header_bytes.len());
+    prefix.extend_from_slice(&SNAPSHOT_MAGIC);
+    prefix.extend_from_slice(&(header_bytes.len() as u32).to_le_bytes());
+    prefix.extend_from_slice(&header_bytes);
+
+    Ok(prefix)
+}
+
+/// Reads the header of a binary snapshot and returns it with the payload bytes, without
+/// verifying the payload
+pub fn read_snapshot_header(bytes: &[u8]) -> Result<(SnapshotHeader, &[u8]), SnapshotError> {
+    let header_end = get_snapshot_header_end(bytes)?
+        .filter(|header_end| *header_end <= bytes.len())
+        .ok_or(SnapshotError::InvalidHeader(
+            "Header is longer than the snapshot".into(),
+        ))?;
+
+    let header: SnapshotHeader = ciborium::de::from_reader(&bytes[SNAPSHOT_PREFIX_LEN..header_end])
+        .map_err(|e| SnapshotError::InvalidHeader(e.to_string()))?;
+    if header.format_version != SNAPSHOT_FORMAT_VERSION
+        && header.format_version != SNAPSHOT_FORMAT_VERSION_CHUNKED
+    {
+        return Err(SnapshotError::UnsupportedFormatVersion(
+            header.format_version,
+        ));
+    }
+
+    Ok((header, &bytes[header_end..]))
+}
+
+/// Where the header ends, read from the first `SNAPSHOT_PREFIX_LEN` bytes of a binary
+/// snapshot. None if the header does not fit in memory.
+pub(crate) fn get_snapshot_header_end(bytes: &[u8]) -> Result<Option<usize>, SnapshotError> {
+    if bytes.is_empty() {
+        return Err(SnapshotError::Empty);
+    }
+    if !is_binary_snapshot(bytes) || bytes.len() < SNAPSHOT_PREFIX_LEN {
+        return Err(SnapshotError::InvalidHeader(
+            "Snapshot does not start with a header".into(),
+        ));
+    }
+
+    let mut header_len = [0; 4];
+    header_len.copy_from_slice(&bytes[SNAPSHOT_MAGIC.len()..SNAPSHOT_PREFIX_LEN]);
+
+    Ok(SNAPSHOT_PREFIX_LEN.checked_add(u32::from_le_bytes(header_len) as usize))
+}
+
+/// Verifies the snapshot was written for this type of canister and that the payload is
+/// intact before decoding it
+pub fn decode_snapshot<T: DeserializeOwned>(
+    bytes: &[u8],
+    canister_type: SnapshotCanisterType,
+) -> Result<(SnapshotHeader, T), SnapshotError> {
+    let (header, payload_bytes) = read_verified_payload(bytes, canister_type)?;
+    if header.format_version != SNAPSHOT_FORMAT_VERSION {
+        return Err(SnapshotError::UnsupportedFormatVersion(
+            header.format_version,
+        ));
+    }
+
+    let payload = ciborium::de::from_reader(payload_bytes)
+        .map_err(|e| SnapshotError::Deserialize(e.to_string()))?;
+
+    Ok((header, payload))
+}
+
+/// Decodes the sections of a snapshot generated across several messages, in the order
+/// they were written
+pub fn decode_chunked_snapshot<S: DeserializeOwned>(
+    bytes: &[u8],
+    canister_type: SnapshotCanisterType,
+) -> Result<(SnapshotHeader, Vec<S>), SnapshotError> {
+    let (header, mut payload_bytes) = read_verified_payload(bytes, canister_type)?;
+    if header.format_version != SNAPSHOT_FORMAT_VERSION_CHUNKED {
+        return Err(SnapshotError::UnsupportedFormatVersion(
+            header.format_version,
+        ));
+    }
+
+    let mut sections = vec![];
+    while !payload_bytes.is_empty() {
+        let section = ciborium::de::from_reader(&mut payload_bytes)
+            .map_err(|e| SnapshotError::Deserialize(e.to_string()))?;
+        sections.push(section);
+    }
+
+    Ok((header, sections))
+}
+
+fn read_verified_payload(
+    bytes: &[u8],
+    canister_type: SnapshotCanisterType,
+) -> Result<(SnapshotHeader, &[u8]), SnapshotError> {
+    let (header, payload_bytes) = read_snapshot_header(bytes)?;
+
+    if header.canister_type != canister_type {
+        return Err(SnapshotError::CanisterTypeMismatch {
+            expected: canister_type,
+            found: header.canister_type,
+        });
+    }
+    if header.payload_len != payload_bytes.len() as u64 {
+        return Err(SnapshotError::PayloadLengthMismatch {
+            expected: header.payload_len,
+            found: payload_bytes.len() as u64,
+        });
+    }
+    if Sha256::digest(payload_bytes).as_slice() != header.payload_sha256.as_slice() {
+        return Err(SnapshotError::ChecksumMismatch);
+    }
+
+    Ok((header, payload_bytes))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn sample_snapshot() -> Vec<u8> {
+        let payload = BTreeMap::from([(1_u64, "first".to_string()), (2, "second".to_string())]);
+        encode_snapshot(
+            SnapshotCanisterType::IndividualUser,
+            3,
+            SystemTime::UNIX_EPOCH,
+            &payload,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let snapshot = sample_snapshot();
+        assert!(is_binary_snapshot(&snapshot));
+
+        let (header, payload): (SnapshotHeader, BTreeMap<u64, String>) =
+            decode_snapshot(&snapshot, SnapshotCanisterType::IndividualUser).unwrap();
+        assert_eq!(header.schema_version, 3);
+        assert_eq!(header.format_version, SNAPSHOT_FORMAT_VERSION);
+        assert_eq!(payload.get(&2), Some(&"second".to_string()));
+    }
+
+    #[test]
+    fn test_corrupt_snapshot_is_rejected() {
+        let mut snapshot = sample_snapshot();
+        let last = snapshot.len() - 1;
+        snapshot[last] ^= 0xff;
+        assert_eq!(
+            decode_snapshot::<BTreeMap<u64, String>>(
+                &snapshot,
+                SnapshotCanisterType::IndividualUser
+            )
+            .unwrap_err(),
+            SnapshotError::ChecksumMismatch
+        );
+
+        let snapshot = sample_snapshot();
+        assert!(matches!(
+            decode_snapshot::<BTreeMap<u64, String>>(
+                &snapshot[..snapshot.len() - 1],
+                SnapshotCanisterType::IndividualUser
+            ),
+            Err(SnapshotError::PayloadLengthMismatch { .. })
+        ));
+        assert!(matches!(
+            read_snapshot_header(&snapshot[..SNAPSHOT_PREFIX_LEN + 2]),
+            Err(SnapshotError::InvalidHeader(_))
+        ));
+        assert_eq!(read_snapshot_header(&[]).unwrap_err(), SnapshotError::Empty);
+    }
+
+    #[test]
+    fn test_snapshot_of_other_canister_type_is_rejected() {
+        let snapshot = sample_snapshot();
+        assert_eq!(
+            decode_snapshot::<BTreeMap<u64, String>>(
+                &snapshot,
+                SnapshotCanisterType::SubnetOrchestrator
+            )
+            .unwrap_err(),
+            SnapshotError::CanisterTypeMismatch {
+                expected: SnapshotCanisterType::SubnetOrchestrator,
+                found: SnapshotCanisterType::IndividualUser,
+            }
+        );
+    }
+
+    #[test]
+    fn test_backup_catalog_keeps_the_last_entries() {
+        let canister_id = Principal::anonymous();
+        let mut catalog = BackupCatalog::default();
+        for backup_id in 0..(MAX_STORED_SNAPSHOTS as u64 + 2) {
+            catalog.insert(BackupCatalogEntry {
+                backup_id,
+                canister_id,
+                snapshot_id: backup_id,
+                started_at: SystemTime::UNIX_EPOCH,
+            });
+        }
+
+        let backup_ids: Vec<u64> = catalog
+            .get(&canister_id)
+            .into_iter()
+            .map(|entry| entry.backup_id)
+            .collect();
+        assert_eq!(
+            backup_ids,
+            (2..MAX_STORED_SNAPSHOTS as u64 + 2).collect::<Vec<_>>()
+        );
+        assert!(catalog.get(&Principal::management_canister()).is_empty());
+    }
+}
@@ -49,3 +49,86 @@ impl Storable for CanisterWasm {
 
     const BOUND: Bound = Bound::Unbounded;
 }
+
+/// Longest version label accepted for an SNS wasm, keeps `SnsWasmKey` within its bound
+pub const MAX_SNS_WASM_VERSION_LENGTH: usize = 64;
+
+#[derive(
+    Clone, Copy, Debug, Serialize, Deserialize, PartialEq, PartialOrd, Eq, Ord, Hash, CandidType,
+)]
+pub enum SnsWasmType {
+    Governance,
+    Ledger,
+    Root,
+    Swap,
+    Index,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, PartialOrd, Eq, Ord, CandidType)]
+pub struct SnsWasmKey {
+    pub wasm_type: SnsWasmType,
+    pub version: String,
+}
+
+impl SnsWasmKey {
+    pub fn new(wasm_type: SnsWasmType, version: String) -> Result<Self, String> {
+        if version.is_empty() || version.len() > MAX_SNS_WASM_VERSION_LENGTH {
+            return Err(format!(
+                "Version must be between 1 and {} characters",
+                MAX_SNS_WASM_VERSION_LENGTH
+            ));
+        }
+
+        Ok(Self { wasm_type, version })
+    }
+}
+
+impl Storable for SnsWasmKey {
+    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
+        let mut bytes = vec![];
+        ciborium::ser::into_writer(self, &mut bytes).unwrap();
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
+        let sns_wasm_key: SnsWasmKey = de::from_reader(bytes.as_ref()).unwrap();
+        sns_wasm_key
+    }
+
+    const BOUND: Bound = Bound::Bounded {
+        max_size: 128,
+        is_fixed_size: false,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sns_wasm_key_with_longest_version_fits_its_bound() {
+        let key = SnsWasmKey::new(
+            SnsWasmType::Governance,
+            "v".repeat(MAX_SNS_WASM_VERSION_LENGTH),
+        )
+        .unwrap();
+
+        let bytes = key.to_bytes();
+        let Bound::Bounded { max_size, .. } = SnsWasmKey::BOUND else {
+            panic!("SnsWasmKey must be bounded");
+        };
+
+        assert!(bytes.len() <= max_size as usize);
+        assert_eq!(SnsWasmKey::from_bytes(bytes), key);
+    }
+
+    #[test]
+    fn sns_wasm_key_rejects_invalid_versions() {
+        assert!(SnsWasmKey::new(SnsWasmType::Ledger, String::new()).is_err());
+        assert!(SnsWasmKey::new(
+            SnsWasmType::Ledger,
+            "v".repeat(MAX_SNS_WASM_VERSION_LENGTH + 1)
+        )
+        .is_err());
+    }
+}
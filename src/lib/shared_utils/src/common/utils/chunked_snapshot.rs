@@ -0,0 +1,456 @@
+use std::{io::Read, time::SystemTime};
+
+use ic_stable_structures::Memory;
+use serde::{de::DeserializeOwned, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::common::types::snapshot::{
+    encode_snapshot_header, get_snapshot_header_end, read_snapshot_header, SnapshotCanisterType,
+    SnapshotError, SnapshotGenerationState, SnapshotHeader, SnapshotProgress, SnapshotSource,
+    SNAPSHOT_FORMAT_VERSION_CHUNKED, SNAPSHOT_PREFIX_LEN,
+};
+
+use super::snapshot_store::SnapshotStore;
+
+/// Writes a snapshot section by section into the snapshot store, so canisters can
+/// generate snapshots larger than what a single message can serialize. The header is
+/// only known once every section is written, the store puts it in front of the payload
+/// when the snapshot is completed.
+pub struct ChunkedSnapshotWriter {
+    snapshot_id: u64,
+    canister_type: SnapshotCanisterType,
+    schema_version: u32,
+    hasher: Sha256,
+    payload_len: u64,
+    progress: SnapshotProgress,
+}
+
+impl ChunkedSnapshotWriter {
+    pub fn new<M: Memory>(
+        store: &mut SnapshotStore<M>,
+        canister_type: SnapshotCanisterType,
+        schema_version: u32,
+        started_at: SystemTime,
+        total_items: u64,
+    ) -> Self {
+        let snapshot_id = store.begin(SnapshotSource::Generated, started_at);
+
+        Self {
+            snapshot_id,
+            canister_type,
+            schema_version,
+            hasher: Sha256::new(),
+            payload_len: 0,
+            progress: SnapshotProgress {
+                snapshot_id,
+                state: SnapshotGenerationState::InProgress,
+                started_at,
+                finished_at: None,
+                items_written: 0,
+                total_items,
+                bytes_written: 0,
+            },
+        }
+    }
+
+    pub fn progress(&self) -> SnapshotProgress {
+        self.progress.clone()
+    }
+
+    pub fn is_in_progress(&self) -> bool {
+        self.progress.state == SnapshotGenerationState::InProgress
+    }
+
+    pub fn write_section<M: Memory, S: Serialize>(
+        &mut self,
+        store: &mut SnapshotStore<M>,
+        section: &S,
+        items: u64,
+    ) -> Result<(), SnapshotError> {
+        let mut section_bytes = vec![];
+        ciborium::ser::into_writer(section, &mut section_bytes)
+            .map_err(|e| SnapshotError::Serialize(e.to_string()))?;
+
+        store.write(self.snapshot_id, self.payload_len, &section_bytes)?;
+        self.hasher.update(&section_bytes);
+        self.payload_len += section_bytes.len() as u64;
+
+        self.progress.items_written += items;
+        self.progress.bytes_written = self.payload_len;
+
+        Ok(())
+    }
+
+    /// Writes the header for the sections written so far and returns the length of
+    /// the snapshot
+    pub fn finish<M: Memory>(
+        &mut self,
+        store: &mut SnapshotStore<M>,
+        finished_at: SystemTime,
+    ) -> Result<u64, SnapshotError> {
+        let header = SnapshotHeader {
+            format_version: SNAPSHOT_FORMAT_VERSION_CHUNKED,
+            canister_type: self.canister_type,
+            schema_version: self.schema_version,
+            created_at: self.progress.started_at,
+            payload_len: self.payload_len,
+            payload_sha256: self.hasher.clone().finalize().to_vec(),
+        };
+        let header_bytes = encode_snapshot_header(&header)?;
+        let info = store.complete(self.snapshot_id, header_bytes, finished_at)?;
+
+        self.progress.state = SnapshotGenerationState::Completed;
+        self.progress.finished_at = Some(finished_at);
+        self.progress.bytes_written = info.size;
+
+        Ok(info.size)
+    }
+
+    /// Drops what was written of the snapshot
+    pub fn fail<M: Memory>(
+        &mut self,
+        store: &mut SnapshotStore<M>,
+        error: &SnapshotError,
+        finished_at: SystemTime,
+    ) {
+        store.delete(self.snapshot_id);
+        self.progress.state = SnapshotGenerationState::Failed(format!("{:?}", error));
+        self.progress.finished_at = Some(finished_at);
+    }
+}
+
+// bytes read from the snapshot store at once while a section is decoded
+const SECTION_READ_BUFFER_LEN: u64 = 64 * 1024;
+
+/// Reads a snapshot written by `ChunkedSnapshotWriter` back from the snapshot store one
+/// section at a time, so canisters can load snapshots larger than what a single message
+/// can decode. The payload is verified against the header first, which can be spread
+/// across several messages as well, and no section is read before it is.
+///
+/// The progress counts the sections read as items and the payload bytes verified or
+/// read so far, the number of sections is not known before they are all read.
+pub struct ChunkedSnapshotReader {
+    snapshot_id: u64,
+    header: SnapshotHeader,
+    payload_start: u64,
+    /// Where in the payload verifying, and then reading, carries on
+    position: u64,
+    hasher: Sha256,
+    is_verified: bool,
+    progress: SnapshotProgress,
+}
+
+impl ChunkedSnapshotReader {
+    pub fn new<M: Memory>(
+        store: &SnapshotStore<M>,
+        snapshot_id: u64,
+        canister_type: SnapshotCanisterType,
+        started_at: SystemTime,
+    ) -> Result<Self, SnapshotError> {
+        let snapshot_len = store
+            .get(snapshot_id)
+            .ok_or(SnapshotError::NotFound(snapshot_id))?
+            .size;
+
+        let prefix = store.read(snapshot_id, 0, SNAPSHOT_PREFIX_LEN as u64)?;
+        let header_end = get_snapshot_header_end(&prefix)?
+            .filter(|header_end| *header_end as u64 <= snapshot_len)
+            .ok_or(SnapshotError::InvalidHeader(
+                "Header is longer than the snapshot".into(),
+            ))?;
+        let (header, _) = read_snapshot_header(&store.read(snapshot_id, 0, header_end as u64)?)?;
+
+        if header.canister_type != canister_type {
+            return Err(SnapshotError::CanisterTypeMismatch {
+                expected: canister_type,
+                found: header.canister_type,
+            });
+        }
+        if header.format_version != SNAPSHOT_FORMAT_VERSION_CHUNKED {
+            return Err(SnapshotError::UnsupportedFormatVersion(
+                header.format_version,
+            ));
+        }
+        let payload_start = header_end as u64;
+        if header.payload_len != snapshot_len - payload_start {
+            return Err(SnapshotError::PayloadLengthMismatch {
+                expected: header.payload_len,
+                found: snapshot_len - payload_start,
+            });
+        }
+
+        Ok(Self {
+            snapshot_id,
+            header,
+            payload_start,
+            position: 0,
+            hasher: Sha256::new(),
+            is_verified: false,
+            progress: SnapshotProgress {
+                snapshot_id,
+                state: SnapshotGenerationState::InProgress,
+                started_at,
+                finished_at: None,
+                items_written: 0,
+                total_items: 0,
+                bytes_written: 0,
+            },
+        })
+    }
+
+    pub fn progress(&self) -> SnapshotProgress {
+        self.progress.clone()
+    }
+
+    pub fn is_in_progress(&self) -> bool {
+        self.progress.state == SnapshotGenerationState::InProgress
+    }
+
+    pub fn is_verified(&self) -> bool {
+        self.is_verified
+    }
+
+    /// Hashes up to `max_bytes` more of the payload. Returns true once the whole payload
+    /// matches the checksum in the header.
+    pub fn verify_next<M: Memory>(
+        &mut self,
+        store: &SnapshotStore<M>,
+        max_bytes: u64,
+    ) -> Result<bool, SnapshotError> {
+        if self.is_verified {
+            return Ok(true);
+        }
+
+        let length = max_bytes.min(self.header.payload_len - self.position);
+        let bytes = store.read(self.snapshot_id, self.payload_start + self.position, length)?;
+        self.hasher.update(&bytes);
+        self.position += length;
+        self.progress.bytes_written = self.position;
+
+        if self.position < self.header.payload_len {
+            return Ok(false);
+        }
+        if std::mem::take(&mut self.hasher).finalize().as_slice()
+            != self.header.payload_sha256.as_slice()
+        {
+            return Err(SnapshotError::ChecksumMismatch);
+        }
+
+        self.is_verified = true;
+        self.position = 0;
+        Ok(true)
+    }
+
+    /// Decodes the next section, None once every section was read
+    pub fn read_next_section<M: Memory, S: DeserializeOwned>(
+        &mut self,
+        store: &SnapshotStore<M>,
+    ) -> Result<Option<S>, SnapshotError> {
+        assert!(self.is_verified(), "Snapshot read before it was verified");
+        if self.position == self.header.payload_len {
+            return Ok(None);
+        }
+
+        let mut reader = SnapshotStoreReader {
+            store,
+            snapshot_id: self.snapshot_id,
+            offset: self.payload_start + self.position,
+            end: self.payload_start + self.header.payload_len,
+            buffer: vec![],
+            buffer_position: 0,
+        };
+        let section = ciborium::de::from_reader(&mut reader)
+            .map_err(|e| SnapshotError::Deserialize(e.to_string()))?;
+        self.position = reader.get_consumed_offset() - self.payload_start;
+
+        self.progress.items_written += 1;
+        self.progress.bytes_written = self.position;
+
+        Ok(Some(section))
+    }
+
+    pub fn finish(&mut self, finished_at: SystemTime) {
+        self.progress.state = SnapshotGenerationState::Completed;
+        self.progress.finished_at = Some(finished_at);
+    }
+
+    pub fn fail(&mut self, error: &SnapshotError, finished_at: SystemTime) {
+        self.progress.state = SnapshotGenerationState::Failed(format!("{:?}", error));
+        self.progress.finished_at = Some(finished_at);
+    }
+}
+
+/// Reads the payload from the snapshot store in blocks, the decoder reads a few bytes
+/// at a time
+struct SnapshotStoreReader<'a, M: Memory> {
+    store: &'a SnapshotStore<M>,
+    snapshot_id: u64,
+    /// Where the next block starts
+    offset: u64,
+    end: u64,
+    buffer: Vec<u8>,
+    buffer_position: usize,
+}
+
+impl<M: Memory> SnapshotStoreReader<'_, M> {
+    /// Offset of the first byte the decoder did not read yet
+    fn get_consumed_offset(&self) -> u64 {
+        self.offset - (self.buffer.len() - self.buffer_position) as u64
+    }
+}
+
+impl<M: Memory> Read for SnapshotStoreReader<'_, M> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.buffer_position == self.buffer.len() {
+            let length = SECTION_READ_BUFFER_LEN.min(self.end - self.offset);
+            self.buffer = self
+                .store
+                .read(self.snapshot_id, self.offset, length)
+                .map_err(|e| std::io::Error::other(format!("{:?}", e)))?;
+            self.offset += self.buffer.len() as u64;
+            self.buffer_position = 0;
+        }
+
+        let length = buf.len().min(self.buffer.len() - self.buffer_position);
+        buf[..length]
+            .copy_from_slice(&self.buffer[self.buffer_position..self.buffer_position + length]);
+        self.buffer_position += length;
+
+        Ok(length)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+
+    use ic_stable_structures::VectorMemory;
+    use serde::Deserialize;
+
+    use crate::common::types::snapshot::decode_chunked_snapshot;
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    enum TestSection {
+        Base(String),
+        Entries(BTreeMap<u64, String>),
+    }
+
+    #[test]
+    fn test_chunked_snapshot_round_trip() {
+        let mut store = SnapshotStore::init(
+            VectorMemory::default(),
+            VectorMemory::default(),
+            VectorMemory::default(),
+            3,
+        );
+        let mut writer = ChunkedSnapshotWriter::new(
+            &mut store,
+            SnapshotCanisterType::SubnetOrchestrator,
+            1,
+            SystemTime::UNIX_EPOCH,
+            4,
+        );
+
+        let sections = vec![
+            TestSection::Base("base".into()),
+            TestSection::Entries(BTreeMap::from([(1, "a".repeat(70_000)), (2, "b".into())])),
+            TestSection::Entries(BTreeMap::from([(3, "c".into()), (4, "d".into())])),
+        ];
+        for section in sections.iter() {
+            writer.write_section(&mut store, section, 2).unwrap();
+        }
+        assert_eq!(store.latest_completed(), None);
+
+        let snapshot_len = writer.finish(&mut store, SystemTime::UNIX_EPOCH).unwrap();
+        let progress = writer.progress();
+        assert_eq!(progress.state, SnapshotGenerationState::Completed);
+        assert_eq!(progress.items_written, 6);
+
+        // read in chunks that cross the end of the header
+        let mut snapshot = vec![];
+        while (snapshot.len() as u64) < snapshot_len {
+            let chunk = store
+                .read(progress.snapshot_id, snapshot.len() as u64, 7_000)
+                .unwrap();
+            snapshot.extend(chunk);
+        }
+
+        let (header, decoded_sections): (_, Vec<TestSection>) =
+            decode_chunked_snapshot(&snapshot, SnapshotCanisterType::SubnetOrchestrator).unwrap();
+        assert_eq!(header.format_version, SNAPSHOT_FORMAT_VERSION_CHUNKED);
+        assert_eq!(decoded_sections, sections);
+
+        // and section by section from the store
+        let mut reader = ChunkedSnapshotReader::new(
+            &store,
+            progress.snapshot_id,
+            SnapshotCanisterType::SubnetOrchestrator,
+            SystemTime::UNIX_EPOCH,
+        )
+        .unwrap();
+        while !reader.verify_next(&store, 10_000).unwrap() {}
+        let mut read_sections = vec![];
+        while let Some(section) = reader.read_next_section::<_, TestSection>(&store).unwrap() {
+            read_sections.push(section);
+        }
+        assert_eq!(read_sections, sections);
+        assert_eq!(reader.progress().items_written, 3);
+    }
+
+    #[test]
+    fn test_chunked_snapshot_reader_verifies_the_payload() {
+        let mut store = SnapshotStore::init(
+            VectorMemory::default(),
+            VectorMemory::default(),
+            VectorMemory::default(),
+            3,
+        );
+        let mut writer = ChunkedSnapshotWriter::new(
+            &mut store,
+            SnapshotCanisterType::IndividualUser,
+            1,
+            SystemTime::UNIX_EPOCH,
+            1,
+        );
+        writer
+            .write_section(&mut store, &TestSection::Base("base".into()), 1)
+            .unwrap();
+        writer.finish(&mut store, SystemTime::UNIX_EPOCH).unwrap();
+        let snapshot_id = writer.progress().snapshot_id;
+
+        assert!(matches!(
+            ChunkedSnapshotReader::new(
+                &store,
+                snapshot_id,
+                SnapshotCanisterType::SubnetOrchestrator,
+                SystemTime::UNIX_EPOCH,
+            ),
+            Err(SnapshotError::CanisterTypeMismatch { .. })
+        ));
+
+        // same length, different payload
+        let mut snapshot = store.read_all(snapshot_id).unwrap();
+        let last = snapshot.len() - 1;
+        snapshot[last] ^= 1;
+        let corrupted_id = store.begin(SnapshotSource::Received, SystemTime::UNIX_EPOCH);
+        store.write(corrupted_id, 0, &snapshot).unwrap();
+        store
+            .complete(corrupted_id, vec![], SystemTime::UNIX_EPOCH)
+            .unwrap();
+
+        let mut reader = ChunkedSnapshotReader::new(
+            &store,
+            corrupted_id,
+            SnapshotCanisterType::IndividualUser,
+            SystemTime::UNIX_EPOCH,
+        )
+        .unwrap();
+        assert_eq!(
+            reader.verify_next(&store, u64::MAX),
+            Err(SnapshotError::ChecksumMismatch)
+        );
+        assert!(!reader.is_verified());
+    }
+}
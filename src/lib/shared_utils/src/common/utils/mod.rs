@@ -1,7 +1,11 @@
 use candid::Nat;
 
+pub mod chunked_snapshot;
 pub mod permissions;
+pub mod snapshot_store;
+pub mod snapshot_transfer;
 pub mod stable_memory_serializer_deserializer;
+pub mod state_schema;
 pub mod system_time;
 pub mod task;
 pub mod upgrade_canister;
@@ -15,6 +15,16 @@ pub fn is_reclaim_canister_id() -> Result<(), String> {
 }
 
 
+/// Lets the subnet orchestrator, which controls individual canisters, drive snapshots
+/// alongside the reclaim canister
+pub fn is_reclaim_canister_id_or_controller() -> Result<(), String> {
+    if is_controller(&caller()) {
+        return Ok(());
+    }
+
+    is_reclaim_canister_id()
+}
+
 pub fn is_caller_global_admin() -> Result<(), String> {
 
     let valid_canisters = vec![GLOBAL_SUPER_ADMIN_USER_ID_V1, GLOBAL_SUPER_ADMIN_USER_ID];
@@ -0,0 +1,497 @@
+use std::{borrow::Cow, time::SystemTime};
+
+use ciborium::de;
+use ic_stable_structures::{storable::Bound, Memory, StableBTreeMap, StableCell, Storable};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::common::types::snapshot::{
+    SnapshotError, SnapshotInfo, SnapshotManifest, SnapshotSource,
+};
+
+use super::snapshot_transfer::{get_chunk_range, get_manifest_with_sha256};
+
+// size of the pieces a snapshot is split into in stable memory, appending to a
+// snapshot rewrites at most one of them
+const STORE_CHUNK_SIZE: u64 = 64 * 1024;
+
+#[derive(Serialize, Deserialize)]
+struct StoredSnapshot {
+    info: SnapshotInfo,
+    /// Written once the snapshot is completed, it is read before the payload
+    #[serde(with = "serde_bytes")]
+    header: Vec<u8>,
+}
+
+impl Storable for StoredSnapshot {
+    fn to_bytes(&self) -> std::borrow::Cow<[u8]> {
+        let mut bytes = vec![];
+        ciborium::ser::into_writer(self, &mut bytes).unwrap();
+        Cow::Owned(bytes)
+    }
+
+    fn from_bytes(bytes: std::borrow::Cow<[u8]>) -> Self {
+        let stored_snapshot: StoredSnapshot = de::from_reader(bytes.as_ref()).unwrap();
+        stored_snapshot
+    }
+
+    const BOUND: Bound = Bound::Unbounded;
+}
+
+/// Keeps snapshots in stable memory, so they survive upgrades and do not take up heap.
+/// A snapshot is its header followed by its payload, the payload is written while the
+/// snapshot is in progress and the header when it is completed. Only the last
+/// `max_snapshots` completed snapshots are kept.
+pub struct SnapshotStore<M: Memory> {
+    snapshots: StableBTreeMap<u64, StoredSnapshot, M>,
+    chunks: StableBTreeMap<(u64, u64), Vec<u8>, M>,
+    /// Ids are never reused, a deleted snapshot cannot be mistaken for a newer one
+    next_id: StableCell<u64, M>,
+    max_snapshots: usize,
+}
+
+impl<M: Memory> SnapshotStore<M> {
+    pub fn init(
+        snapshots_memory: M,
+        chunks_memory: M,
+        next_id_memory: M,
+        max_snapshots: usize,
+    ) -> Self {
+        Self {
+            snapshots: StableBTreeMap::init(snapshots_memory),
+            chunks: StableBTreeMap::init(chunks_memory),
+            next_id: StableCell::init(next_id_memory, 0)
+                .expect("Failed to read the next snapshot id"),
+            max_snapshots,
+        }
+    }
+
+    /// Starts a new snapshot. Snapshots from the same source that were never
+    /// completed are dropped, they cannot be finished anymore.
+    pub fn begin(&mut self, source: SnapshotSource, created_at: SystemTime) -> u64 {
+        let abandoned_ids: Vec<u64> = self
+            .snapshots
+            .iter()
+            .filter(|(_, snapshot)| {
+                snapshot.info.source == source && snapshot.info.completed_at.is_none()
+            })
+            .map(|(id, _)| id)
+            .collect();
+        for id in abandoned_ids {
+            self.delete(id);
+        }
+
+        let id = *self.next_id.get();
+        self.next_id
+            .set(id + 1)
+            .expect("Failed to write the next snapshot id");
+        self.snapshots.insert(
+            id,
+            StoredSnapshot {
+                info: SnapshotInfo {
+                    id,
+                    source,
+                    created_at,
+                    completed_at: None,
+                    size: 0,
+                    sha256: None,
+                },
+                header: vec![],
+            },
+        );
+
+        id
+    }
+
+    /// Appends to the payload of a snapshot in progress, `offset` has to be where the
+    /// payload written so far ends
+    pub fn write(&mut self, id: u64, offset: u64, bytes: &[u8]) -> Result<(), SnapshotError> {
+        let mut snapshot = self.snapshots.get(&id).ok_or(SnapshotError::NotFound(id))?;
+        if snapshot.info.completed_at.is_some() {
+            return Err(SnapshotError::AlreadyCompleted(id));
+        }
+        // the header is only added on completion, until then the size is the payload's
+        if offset != snapshot.info.size {
+            return Err(SnapshotError::NonContiguousWrite {
+                expected: snapshot.info.size,
+                found: offset,
+            });
+        }
+        let end = offset
+            .checked_add(bytes.len() as u64)
+            .ok_or(SnapshotError::OutOfStableMemory)?;
+
+        let mut position = offset;
+        let mut remaining = bytes;
+        while !remaining.is_empty() {
+            let chunk_index = position / STORE_CHUNK_SIZE;
+            let chunk_offset = (position % STORE_CHUNK_SIZE) as usize;
+            let length = remaining
+                .len()
+                .min(STORE_CHUNK_SIZE as usize - chunk_offset);
+
+            // holds exactly `chunk_offset` bytes, as everything before was written
+            let mut chunk = self.chunks.get(&(id, chunk_index)).unwrap_or_default();
+            chunk.extend_from_slice(&remaining[..length]);
+            self.chunks.insert((id, chunk_index), chunk);
+
+            position += length as u64;
+            remaining = &remaining[length..];
+        }
+
+        snapshot.info.size = end;
+        self.snapshots.insert(id, snapshot);
+
+        Ok(())
+    }
+
+    /// Adds the header in front of the payload written so far and drops the oldest
+    /// completed snapshots beyond the ones kept. The snapshot is hashed once here, so
+    /// its manifest does not have to read it again.
+    pub fn complete(
+        &mut self,
+        id: u64,
+        header: Vec<u8>,
+        completed_at: SystemTime,
+    ) -> Result<SnapshotInfo, SnapshotError> {
+        let mut hasher = Sha256::new();
+        hasher.update(&header);
+        for (_, chunk) in self.chunks.range((id, 0)..=(id, u64::MAX)) {
+            hasher.update(chunk);
+        }
+
+        self.complete_with_sha256(id, header, hasher.finalize().to_vec(), completed_at)
+    }
+
+    /// Completes a snapshot whose payload was already hashed while it was written
+    pub fn complete_hashed(
+        &mut self,
+        id: u64,
+        sha256: Vec<u8>,
+        completed_at: SystemTime,
+    ) -> Result<SnapshotInfo, SnapshotError> {
+        self.complete_with_sha256(id, vec![], sha256, completed_at)
+    }
+
+    fn complete_with_sha256(
+        &mut self,
+        id: u64,
+        header: Vec<u8>,
+        sha256: Vec<u8>,
+        completed_at: SystemTime,
+    ) -> Result<SnapshotInfo, SnapshotError> {
+        let mut snapshot = self.snapshots.get(&id).ok_or(SnapshotError::NotFound(id))?;
+        if snapshot.info.completed_at.is_some() {
+            return Err(SnapshotError::AlreadyCompleted(id));
+        }
+        snapshot.info.size += header.len() as u64;
+        snapshot.info.completed_at = Some(completed_at);
+        snapshot.info.sha256 = Some(sha256);
+        snapshot.header = header;
+        let info = snapshot.info.clone();
+        self.snapshots.insert(id, snapshot);
+
+        let completed_ids: Vec<u64> = self
+            .snapshots
+            .iter()
+            .filter(|(_, snapshot)| snapshot.info.completed_at.is_some())
+            .map(|(id, _)| id)
+            .collect();
+        let excess = completed_ids.len().saturating_sub(self.max_snapshots);
+        for id in completed_ids.into_iter().take(excess) {
+            self.delete(id);
+        }
+
+        Ok(info)
+    }
+
+    /// Stores a snapshot written in a single message
+    pub fn save(
+        &mut self,
+        source: SnapshotSource,
+        bytes: &[u8],
+        saved_at: SystemTime,
+    ) -> Result<SnapshotInfo, SnapshotError> {
+        let id = self.begin(source, saved_at);
+        self.write(id, 0, bytes)?;
+        self.complete(id, vec![], saved_at)
+    }
+
+    /// Reads the header followed by the payload, the range is clamped to the snapshot
+    pub fn read(&self, id: u64, offset: u64, length: u64) -> Result<Vec<u8>, SnapshotError> {
+        let snapshot = self.snapshots.get(&id).ok_or(SnapshotError::NotFound(id))?;
+
+        let snapshot_len = snapshot.info.size;
+        let start = offset.min(snapshot_len);
+        let end = offset.saturating_add(length).min(snapshot_len);
+        let mut bytes = Vec::with_capacity((end - start) as usize);
+
+        let header_len = snapshot.header.len() as u64;
+        if start < header_len {
+            bytes.extend_from_slice(&snapshot.header[start as usize..end.min(header_len) as usize]);
+        }
+
+        let mut position = start.max(header_len) - header_len;
+        let payload_end = end.max(header_len) - header_len;
+        while position < payload_end {
+            let chunk_index = position / STORE_CHUNK_SIZE;
+            let chunk_offset = (position % STORE_CHUNK_SIZE) as usize;
+            let length =
+                (payload_end - position).min(STORE_CHUNK_SIZE - chunk_offset as u64) as usize;
+
+            // chunks that were never written are read as zeros
+            let chunk = self.chunks.get(&(id, chunk_index)).unwrap_or_default();
+            let available = chunk.len().saturating_sub(chunk_offset).min(length);
+            if available > 0 {
+                bytes.extend_from_slice(&chunk[chunk_offset..chunk_offset + available]);
+            }
+            bytes.resize(bytes.len() + length - available, 0);
+
+            position += length as u64;
+        }
+
+        Ok(bytes)
+    }
+
+    /// Reads the snapshot to load, which is the latest completed one
+    pub fn read_latest(&self) -> Result<Vec<u8>, SnapshotError> {
+        self.read_all(self.latest_completed_id()?)
+    }
+
+    pub fn read_all(&self, id: u64) -> Result<Vec<u8>, SnapshotError> {
+        let size = self.get(id).ok_or(SnapshotError::NotFound(id))?.size;
+        self.read(id, 0, size)
+    }
+
+    /// Uses the checksum taken when the snapshot was completed
+    pub fn manifest(&self, id: u64, chunk_size: u64) -> Result<SnapshotManifest, SnapshotError> {
+        let info = self.get(id).ok_or(SnapshotError::NotFound(id))?;
+        let sha256 = info.sha256.ok_or(SnapshotError::NotCompleted(id))?;
+
+        get_manifest_with_sha256(info.size, chunk_size, sha256)
+    }
+
+    pub fn read_chunk(
+        &self,
+        id: u64,
+        chunk_size: u64,
+        chunk_index: u64,
+    ) -> Result<Vec<u8>, SnapshotError> {
+        let size = self.get(id).ok_or(SnapshotError::NotFound(id))?.size;
+        let (offset, length) = get_chunk_range(size, chunk_size, chunk_index)?;
+        self.read(id, offset, length)
+    }
+
+    pub fn get(&self, id: u64) -> Option<SnapshotInfo> {
+        self.snapshots.get(&id).map(|snapshot| snapshot.info)
+    }
+
+    /// The most recently started snapshot that is completed
+    pub fn latest_completed(&self) -> Option<SnapshotInfo> {
+        self.snapshots
+            .iter()
+            .rev()
+            .map(|(_, snapshot)| snapshot.info)
+            .find(|info| info.completed_at.is_some())
+    }
+
+    /// The snapshot downloaded and loaded, see `latest_completed`
+    pub fn latest_completed_id(&self) -> Result<u64, SnapshotError> {
+        self.latest_completed()
+            .map(|info| info.id)
+            .ok_or(SnapshotError::Empty)
+    }
+
+    pub fn list(&self) -> Vec<SnapshotInfo> {
+        self.snapshots
+            .iter()
+            .map(|(_, snapshot)| snapshot.info)
+            .collect()
+    }
+
+    pub fn try_delete(&mut self, id: u64) -> Result<(), SnapshotError> {
+        if self.delete(id) {
+            Ok(())
+        } else {
+            Err(SnapshotError::NotFound(id))
+        }
+    }
+
+    /// Returns false if there is no snapshot with the id
+    pub fn delete(&mut self, id: u64) -> bool {
+        if self.snapshots.remove(&id).is_none() {
+            return false;
+        }
+
+        let chunk_keys: Vec<(u64, u64)> = self
+            .chunks
+            .range((id, 0)..=(id, u64::MAX))
+            .map(|(key, _)| key)
+            .collect();
+        for key in chunk_keys {
+            self.chunks.remove(&key);
+        }
+
+        true
+    }
+
+    pub fn clear(&mut self) {
+        let ids: Vec<u64> = self.snapshots.iter().map(|(id, _)| id).collect();
+        for id in ids {
+            self.delete(id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ic_stable_structures::VectorMemory;
+
+    use super::*;
+
+    fn new_store(max_snapshots: usize) -> SnapshotStore<VectorMemory> {
+        SnapshotStore::init(
+            VectorMemory::default(),
+            VectorMemory::default(),
+            VectorMemory::default(),
+            max_snapshots,
+        )
+    }
+
+    fn save(store: &mut SnapshotStore<VectorMemory>, bytes: &[u8]) -> u64 {
+        store
+            .save(SnapshotSource::Saved, bytes, SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .id
+    }
+
+    #[test]
+    fn test_snapshot_store_reads_header_and_payload() {
+        let mut store = new_store(3);
+        let id = store.begin(SnapshotSource::Generated, SystemTime::UNIX_EPOCH);
+
+        let payload: Vec<u8> = (0..200_000u32).map(|i| i as u8).collect();
+        // appended in pieces that cross the store's chunks
+        for piece in payload.chunks(30_000) {
+            let size = store.get(id).unwrap().size;
+            store.write(id, size, piece).unwrap();
+        }
+        assert_eq!(store.latest_completed(), None);
+
+        let header = vec![7; 10];
+        let info = store
+            .complete(id, header.clone(), SystemTime::UNIX_EPOCH)
+            .unwrap();
+        assert_eq!(info.size, 200_010);
+
+        let snapshot = store.read_all(id).unwrap();
+        assert_eq!(&snapshot[..10], header.as_slice());
+        assert_eq!(&snapshot[10..], payload.as_slice());
+        assert_eq!(store.read(id, 5, 10).unwrap(), snapshot[5..15].to_vec());
+        assert!(store.read(id, 200_010, 10).unwrap().is_empty());
+        assert_eq!(store.latest_completed(), Some(info.clone()));
+
+        // the checksum taken on completion describes the header and the payload
+        let sha256 = Sha256::digest(&snapshot).to_vec();
+        assert_eq!(info.sha256, Some(sha256.clone()));
+        assert_eq!(
+            store.manifest(id, 64_000).unwrap(),
+            SnapshotManifest {
+                total_size: 200_010,
+                chunk_size: 64_000,
+                chunk_count: 4,
+                sha256,
+            }
+        );
+    }
+
+    #[test]
+    fn test_snapshot_store_only_appends() {
+        let mut store = new_store(3);
+        let id = store.begin(SnapshotSource::Uploaded, SystemTime::UNIX_EPOCH);
+        store.write(id, 0, &[1, 2]).unwrap();
+
+        assert_eq!(
+            store.write(id, 100_000, &[3]),
+            Err(SnapshotError::NonContiguousWrite {
+                expected: 2,
+                found: 100_000,
+            })
+        );
+        assert_eq!(
+            store.write(id, 1, &[3]),
+            Err(SnapshotError::NonContiguousWrite {
+                expected: 2,
+                found: 1,
+            })
+        );
+
+        store.write(id, 2, &[3]).unwrap();
+        assert_eq!(
+            store.manifest(id, 64_000),
+            Err(SnapshotError::NotCompleted(id))
+        );
+        store.complete(id, vec![], SystemTime::UNIX_EPOCH).unwrap();
+        assert_eq!(
+            store.write(id, 3, &[4]),
+            Err(SnapshotError::AlreadyCompleted(id))
+        );
+        assert_eq!(store.read_all(id).unwrap(), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_snapshot_ids_are_not_reused() {
+        let snapshots_memory = VectorMemory::default();
+        let chunks_memory = VectorMemory::default();
+        let next_id_memory = VectorMemory::default();
+        let mut store = SnapshotStore::init(
+            snapshots_memory.clone(),
+            chunks_memory.clone(),
+            next_id_memory.clone(),
+            3,
+        );
+
+        let first_id = save(&mut store, &[1]);
+        let second_id = save(&mut store, &[2]);
+        assert!(store.delete(second_id));
+        let third_id = save(&mut store, &[3]);
+        assert!(third_id > second_id);
+
+        // the counter is kept in stable memory, so it survives upgrades
+        let mut store = SnapshotStore::init(snapshots_memory, chunks_memory, next_id_memory, 3);
+        assert!(store.delete(third_id));
+        assert!(save(&mut store, &[4]) > third_id);
+        assert_eq!(store.read_all(first_id).unwrap(), vec![1]);
+    }
+
+    #[test]
+    fn test_snapshot_store_keeps_the_last_snapshots() {
+        let mut store = new_store(2);
+        let abandoned_id = store.begin(SnapshotSource::Uploaded, SystemTime::UNIX_EPOCH);
+        store.write(abandoned_id, 0, &[1]).unwrap();
+
+        let first_id = save(&mut store, &[1]);
+        let in_progress_id = store.begin(SnapshotSource::Generated, SystemTime::UNIX_EPOCH);
+        let second_id = save(&mut store, &[2]);
+        let third_id = save(&mut store, &[3]);
+
+        // a new upload drops the one that was never finished
+        let upload_id = store.begin(SnapshotSource::Uploaded, SystemTime::UNIX_EPOCH);
+
+        let ids: Vec<u64> = store.list().into_iter().map(|info| info.id).collect();
+        assert_eq!(ids, vec![in_progress_id, second_id, third_id, upload_id]);
+        assert_eq!(store.get(first_id), None);
+        assert_eq!(store.read_all(third_id).unwrap(), vec![3]);
+
+        assert!(store.delete(third_id));
+        assert!(!store.delete(third_id));
+        assert_eq!(store.latest_completed().unwrap().id, second_id);
+        assert_eq!(
+            store.read(third_id, 0, 1),
+            Err(SnapshotError::NotFound(third_id))
+        );
+
+        store.clear();
+        assert!(store.list().is_empty());
+    }
+}
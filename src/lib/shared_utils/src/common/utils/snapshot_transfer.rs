@@ -0,0 +1,573 @@
+use std::time::SystemTime;
+
+use ic_stable_structures::Memory;
+use sha2::{Digest, Sha256};
+
+use crate::common::types::snapshot::{
+    SnapshotError, SnapshotInfo, SnapshotManifest, SnapshotSource,
+};
+
+use super::snapshot_store::SnapshotStore;
+
+/// Describes the snapshot read by `read`, hashing it one chunk at a time
+pub fn get_snapshot_manifest(
+    total_size: u64,
+    chunk_size: u64,
+    read: impl Fn(u64, u64) -> Result<Vec<u8>, SnapshotError>,
+) -> Result<SnapshotManifest, SnapshotError> {
+    let mut manifest = get_manifest_with_sha256(total_size, chunk_size, vec![])?;
+
+    let mut hasher = Sha256::new();
+    for chunk_index in 0..manifest.chunk_count {
+        let (offset, length) = get_chunk_range(total_size, chunk_size, chunk_index)?;
+        hasher.update(read(offset, length)?);
+    }
+    manifest.sha256 = hasher.finalize().to_vec();
+
+    Ok(manifest)
+}
+
+/// Describes a snapshot that was already hashed
+pub fn get_manifest_with_sha256(
+    total_size: u64,
+    chunk_size: u64,
+    sha256: Vec<u8>,
+) -> Result<SnapshotManifest, SnapshotError> {
+    if total_size == 0 {
+        return Err(SnapshotError::Empty);
+    }
+    if chunk_size == 0 {
+        return Err(SnapshotError::InvalidManifest(
+            "Chunk size has to be positive".into(),
+        ));
+    }
+
+    Ok(SnapshotManifest {
+        total_size,
+        chunk_size,
+        chunk_count: total_size.div_ceil(chunk_size),
+        sha256,
+    })
+}
+
+/// Returns the offset and length of the chunk
+pub fn get_chunk_range(
+    total_size: u64,
+    chunk_size: u64,
+    chunk_index: u64,
+) -> Result<(u64, u64), SnapshotError> {
+    let offset = chunk_index
+        .checked_mul(chunk_size)
+        .filter(|offset| *offset < total_size)
+        .ok_or(SnapshotError::ChunkOutOfBounds(chunk_index))?;
+
+    Ok((offset, chunk_size.min(total_size - offset)))
+}
+
+pub fn read_snapshot_chunk(
+    snapshot: &[u8],
+    chunk_size: u64,
+    chunk_index: u64,
+) -> Result<Vec<u8>, SnapshotError> {
+    let (offset, length) = get_chunk_range(snapshot.len() as u64, chunk_size, chunk_index)?;
+
+    Ok(read_snapshot_range(snapshot, offset, length).to_vec())
+}
+
+/// The checksum `upload_snapshot_chunk` expects with each chunk
+pub fn get_chunk_sha256(chunk: &[u8]) -> Vec<u8> {
+    Sha256::digest(chunk).to_vec()
+}
+
+/// Clamps the range to the snapshot instead of trapping on reads past its end
+pub fn read_snapshot_range(snapshot: &[u8], offset: u64, length: u64) -> &[u8] {
+    let start = offset.min(snapshot.len() as u64) as usize;
+    let end = offset.saturating_add(length).min(snapshot.len() as u64) as usize;
+
+    &snapshot[start..end]
+}
+
+/// Appends the chunks of a snapshot described by a manifest to the snapshot store, in
+/// order, and only completes the snapshot once all of them match the manifest. The
+/// chunks are hashed as they arrive, so finishing does not read the snapshot again.
+pub struct SnapshotUpload {
+    snapshot_id: u64,
+    manifest: SnapshotManifest,
+    next_chunk: u64,
+    last_chunk_sha256: Option<Vec<u8>>,
+    hasher: Sha256,
+}
+
+impl SnapshotUpload {
+    pub fn start<M: Memory>(
+        store: &mut SnapshotStore<M>,
+        manifest: SnapshotManifest,
+        started_at: SystemTime,
+    ) -> Result<Self, SnapshotError> {
+        if manifest.total_size == 0 {
+            return Err(SnapshotError::Empty);
+        }
+        if manifest.chunk_size == 0 {
+            return Err(SnapshotError::InvalidManifest(
+                "Chunk size has to be positive".into(),
+            ));
+        }
+        if manifest.chunk_count != manifest.total_size.div_ceil(manifest.chunk_size) {
+            return Err(SnapshotError::InvalidManifest(
+                "Chunk count does not match the total size".into(),
+            ));
+        }
+        if manifest.sha256.len() != Sha256::output_size() {
+            return Err(SnapshotError::InvalidManifest(
+                "Checksum is not a SHA-256 hash".into(),
+            ));
+        }
+
+        Ok(Self {
+            snapshot_id: store.begin(SnapshotSource::Uploaded, started_at),
+            next_chunk: 0,
+            last_chunk_sha256: None,
+            hasher: Sha256::new(),
+            manifest,
+        })
+    }
+
+    pub fn snapshot_id(&self) -> u64 {
+        self.snapshot_id
+    }
+
+    pub fn manifest(&self) -> &SnapshotManifest {
+        &self.manifest
+    }
+
+    /// Chunks are uploaded in order. The last chunk can be uploaded again, in case the
+    /// reply to it was lost, and is only written once.
+    pub fn write_chunk<M: Memory>(
+        &mut self,
+        store: &mut SnapshotStore<M>,
+        chunk_index: u64,
+        chunk: &[u8],
+        chunk_sha256: &[u8],
+    ) -> Result<(), SnapshotError> {
+        let (offset, length) = get_chunk_range(
+            self.manifest.total_size,
+            self.manifest.chunk_size,
+            chunk_index,
+        )?;
+        if chunk.len() as u64 != length {
+            return Err(SnapshotError::ChunkLengthMismatch {
+                chunk_index,
+                expected: length,
+                found: chunk.len() as u64,
+            });
+        }
+        if get_chunk_sha256(chunk) != chunk_sha256 {
+            return Err(SnapshotError::ChunkChecksumMismatch(chunk_index));
+        }
+
+        if chunk_index + 1 == self.next_chunk
+            && self.last_chunk_sha256.as_deref() == Some(chunk_sha256)
+        {
+            return Ok(());
+        }
+        if chunk_index != self.next_chunk {
+            return Err(SnapshotError::UnexpectedChunk {
+                expected: self.next_chunk,
+                found: chunk_index,
+            });
+        }
+
+        store.write(self.snapshot_id, offset, chunk)?;
+        self.hasher.update(chunk);
+        self.next_chunk += 1;
+        self.last_chunk_sha256 = Some(chunk_sha256.to_vec());
+
+        Ok(())
+    }
+
+    /// Checks that every chunk was uploaded and that together they match the manifest,
+    /// then completes the snapshot in the store
+    pub fn finish<M: Memory>(
+        &self,
+        store: &mut SnapshotStore<M>,
+        finished_at: SystemTime,
+    ) -> Result<SnapshotInfo, SnapshotError> {
+        let missing_chunks = self.manifest.chunk_count - self.next_chunk;
+        if missing_chunks > 0 {
+            return Err(SnapshotError::MissingChunks(missing_chunks));
+        }
+        let sha256 = self.hasher.clone().finalize().to_vec();
+        if sha256 != self.manifest.sha256 {
+            return Err(SnapshotError::ChecksumMismatch);
+        }
+
+        store.complete_hashed(self.snapshot_id, sha256, finished_at)
+    }
+}
+
+/// A snapshot sent through the legacy `receive_and_save_snaphot`. It comes without a
+/// manifest, so it is hashed as it arrives and only completed once it matches the
+/// manifest the sender passes to `finalize_received_snapshot`.
+pub struct SnapshotReceive {
+    snapshot_id: u64,
+    size: u64,
+    hasher: Sha256,
+}
+
+impl SnapshotReceive {
+    pub fn start<M: Memory>(store: &mut SnapshotStore<M>, started_at: SystemTime) -> Self {
+        Self {
+            snapshot_id: store.begin(SnapshotSource::Received, started_at),
+            size: 0,
+            hasher: Sha256::new(),
+        }
+    }
+
+    pub fn snapshot_id(&self) -> u64 {
+        self.snapshot_id
+    }
+
+    /// `offset` has to be where the bytes received so far end
+    pub fn write<M: Memory>(
+        &mut self,
+        store: &mut SnapshotStore<M>,
+        offset: u64,
+        bytes: &[u8],
+    ) -> Result<(), SnapshotError> {
+        store.write(self.snapshot_id, offset, bytes)?;
+        self.hasher.update(bytes);
+        self.size += bytes.len() as u64;
+
+        Ok(())
+    }
+
+    pub fn finish<M: Memory>(
+        &self,
+        store: &mut SnapshotStore<M>,
+        manifest: &SnapshotManifest,
+        finished_at: SystemTime,
+    ) -> Result<SnapshotInfo, SnapshotError> {
+        if manifest.total_size != self.size {
+            return Err(SnapshotError::InvalidManifest(
+                "Total size does not match the received snapshot".into(),
+            ));
+        }
+        let sha256 = self.hasher.clone().finalize().to_vec();
+        if sha256 != manifest.sha256 {
+            return Err(SnapshotError::ChecksumMismatch);
+        }
+
+        store.complete_hashed(self.snapshot_id, sha256, finished_at)
+    }
+}
+
+enum PendingTransfer {
+    Upload(SnapshotUpload),
+    Receive(SnapshotReceive),
+}
+
+/// The transfer behind the `*_snapshot_upload` endpoints, or the legacy
+/// `receive_and_save_snaphot`, kept on the heap until it is finished. Starting a
+/// transfer drops the one that was not finished.
+#[derive(Default)]
+pub struct PendingSnapshotUpload(Option<PendingTransfer>);
+
+impl PendingSnapshotUpload {
+    pub const fn new() -> Self {
+        Self(None)
+    }
+
+    pub fn start<M: Memory>(
+        &mut self,
+        store: &mut SnapshotStore<M>,
+        manifest: SnapshotManifest,
+        started_at: SystemTime,
+    ) -> Result<(), SnapshotError> {
+        self.0 = Some(PendingTransfer::Upload(SnapshotUpload::start(
+            store, manifest, started_at,
+        )?));
+
+        Ok(())
+    }
+
+    pub fn write_chunk<M: Memory>(
+        &mut self,
+        store: &mut SnapshotStore<M>,
+        chunk_index: u64,
+        chunk: &[u8],
+        chunk_sha256: &[u8],
+    ) -> Result<(), SnapshotError> {
+        let Some(PendingTransfer::Upload(upload)) = self.0.as_mut() else {
+            return Err(SnapshotError::NoUploadInProgress);
+        };
+
+        upload.write_chunk(store, chunk_index, chunk, chunk_sha256)
+    }
+
+    /// Makes the uploaded snapshot the latest one. An upload that is missing chunks or
+    /// does not match its manifest is kept, the missing chunks can still be sent.
+    pub fn finish<M: Memory>(
+        &mut self,
+        store: &mut SnapshotStore<M>,
+        finished_at: SystemTime,
+    ) -> Result<SnapshotManifest, SnapshotError> {
+        let Some(PendingTransfer::Upload(upload)) = self.0.as_ref() else {
+            return Err(SnapshotError::NoUploadInProgress);
+        };
+        upload.finish(store, finished_at)?;
+        let manifest = upload.manifest().clone();
+        self.0 = None;
+
+        Ok(manifest)
+    }
+
+    /// Appends to a snapshot sent through the legacy endpoint. Offset 0 starts a new
+    /// snapshot, the others continue the one being received.
+    pub fn receive<M: Memory>(
+        &mut self,
+        store: &mut SnapshotStore<M>,
+        offset: u64,
+        bytes: &[u8],
+        received_at: SystemTime,
+    ) -> Result<(), SnapshotError> {
+        if let Some(PendingTransfer::Upload(_)) = self.0 {
+            return Err(SnapshotError::UploadInProgress);
+        }
+        if offset == 0 {
+            self.0 = Some(PendingTransfer::Receive(SnapshotReceive::start(
+                store,
+                received_at,
+            )));
+        }
+        let Some(PendingTransfer::Receive(receive)) = self.0.as_mut() else {
+            return Err(SnapshotError::NoUploadInProgress);
+        };
+
+        receive.write(store, offset, bytes)
+    }
+
+    /// Makes the received snapshot the latest one once it matches the manifest
+    pub fn finish_received<M: Memory>(
+        &mut self,
+        store: &mut SnapshotStore<M>,
+        manifest: SnapshotManifest,
+        finished_at: SystemTime,
+    ) -> Result<SnapshotManifest, SnapshotError> {
+        let Some(PendingTransfer::Receive(receive)) = self.0.as_ref() else {
+            return Err(SnapshotError::NoUploadInProgress);
+        };
+        receive.finish(store, &manifest, finished_at)?;
+        self.0 = None;
+
+        Ok(manifest)
+    }
+
+    /// Loading is refused while a transfer is not finished, the transferred snapshot is
+    /// the one expected to be loaded
+    pub fn is_in_progress(&self) -> bool {
+        self.0.is_some()
+    }
+
+    pub fn reset(&mut self) {
+        self.0 = None;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use ic_stable_structures::VectorMemory;
+
+    use super::*;
+
+    const CHUNK_SIZE: u64 = 4;
+
+    fn sample_snapshot() -> (Vec<u8>, SnapshotManifest) {
+        let snapshot: Vec<u8> = (0..10).collect();
+        let manifest = get_snapshot_manifest(snapshot.len() as u64, CHUNK_SIZE, |offset, len| {
+            Ok(read_snapshot_range(&snapshot, offset, len).to_vec())
+        })
+        .unwrap();
+
+        (snapshot, manifest)
+    }
+
+    fn new_store() -> SnapshotStore<VectorMemory> {
+        SnapshotStore::init(
+            VectorMemory::default(),
+            VectorMemory::default(),
+            VectorMemory::default(),
+            3,
+        )
+    }
+
+    fn get_chunk(snapshot: &[u8], chunk_index: u64) -> (&[u8], Vec<u8>) {
+        let chunk = read_snapshot_range(snapshot, chunk_index * CHUNK_SIZE, CHUNK_SIZE);
+        (chunk, Sha256::digest(chunk).to_vec())
+    }
+
+    #[test]
+    fn test_snapshot_upload_in_order() {
+        let (snapshot, manifest) = sample_snapshot();
+        assert_eq!(manifest.chunk_count, 3);
+
+        let mut store = new_store();
+        let mut upload =
+            SnapshotUpload::start(&mut store, manifest.clone(), SystemTime::UNIX_EPOCH).unwrap();
+
+        let (chunk, chunk_sha256) = get_chunk(&snapshot, 2);
+        assert_eq!(
+            upload.write_chunk(&mut store, 2, chunk, &chunk_sha256),
+            Err(SnapshotError::UnexpectedChunk {
+                expected: 0,
+                found: 2,
+            })
+        );
+        // the last chunk sent again is skipped, an earlier one is rejected
+        for chunk_index in [0, 1, 1, 2] {
+            let (chunk, chunk_sha256) = get_chunk(&snapshot, chunk_index);
+            upload
+                .write_chunk(&mut store, chunk_index, chunk, &chunk_sha256)
+                .unwrap();
+        }
+        let (chunk, chunk_sha256) = get_chunk(&snapshot, 0);
+        assert_eq!(
+            upload.write_chunk(&mut store, 0, chunk, &chunk_sha256),
+            Err(SnapshotError::UnexpectedChunk {
+                expected: 3,
+                found: 0,
+            })
+        );
+
+        let info = upload.finish(&mut store, SystemTime::UNIX_EPOCH).unwrap();
+        assert_eq!(store.read_all(info.id).unwrap(), snapshot);
+        assert_eq!(store.manifest(info.id, CHUNK_SIZE), Ok(manifest));
+    }
+
+    #[test]
+    fn test_snapshot_upload_rejects_invalid_chunks() {
+        let (snapshot, manifest) = sample_snapshot();
+        let mut store = new_store();
+        let mut upload =
+            SnapshotUpload::start(&mut store, manifest.clone(), SystemTime::UNIX_EPOCH).unwrap();
+
+        let (chunk, chunk_sha256) = get_chunk(&snapshot, 0);
+        assert_eq!(
+            upload.write_chunk(&mut store, 0, &chunk[1..], &chunk_sha256),
+            Err(SnapshotError::ChunkLengthMismatch {
+                chunk_index: 0,
+                expected: CHUNK_SIZE,
+                found: CHUNK_SIZE - 1,
+            })
+        );
+        assert_eq!(
+            upload.write_chunk(&mut store, 0, &[0; CHUNK_SIZE as usize], &chunk_sha256),
+            Err(SnapshotError::ChunkChecksumMismatch(0))
+        );
+        assert_eq!(
+            upload.write_chunk(&mut store, 3, chunk, &chunk_sha256),
+            Err(SnapshotError::ChunkOutOfBounds(3))
+        );
+
+        upload
+            .write_chunk(&mut store, 0, chunk, &chunk_sha256)
+            .unwrap();
+        assert_eq!(
+            upload.finish(&mut store, SystemTime::UNIX_EPOCH),
+            Err(SnapshotError::MissingChunks(2))
+        );
+        assert_eq!(store.latest_completed(), None);
+
+        assert!(matches!(
+            SnapshotUpload::start(
+                &mut store,
+                SnapshotManifest {
+                    chunk_count: 2,
+                    ..manifest
+                },
+                SystemTime::UNIX_EPOCH
+            ),
+            Err(SnapshotError::InvalidManifest(_))
+        ));
+    }
+
+    #[test]
+    fn test_snapshot_upload_not_matching_the_manifest_is_not_completed() {
+        let (snapshot, manifest) = sample_snapshot();
+        let mut store = new_store();
+        let mut upload = SnapshotUpload::start(
+            &mut store,
+            SnapshotManifest {
+                sha256: Sha256::digest(b"another snapshot").to_vec(),
+                ..manifest
+            },
+            SystemTime::UNIX_EPOCH,
+        )
+        .unwrap();
+        for chunk_index in 0..3 {
+            let (chunk, chunk_sha256) = get_chunk(&snapshot, chunk_index);
+            upload
+                .write_chunk(&mut store, chunk_index, chunk, &chunk_sha256)
+                .unwrap();
+        }
+
+        assert_eq!(
+            upload.finish(&mut store, SystemTime::UNIX_EPOCH),
+            Err(SnapshotError::ChecksumMismatch)
+        );
+        assert_eq!(store.latest_completed(), None);
+    }
+
+    #[test]
+    fn test_read_snapshot_range_is_clamped() {
+        let (snapshot, _) = sample_snapshot();
+
+        assert_eq!(read_snapshot_range(&snapshot, 8, 4), &[8, 9]);
+        assert!(read_snapshot_range(&snapshot, 20, 4).is_empty());
+        assert!(read_snapshot_range(&snapshot, 2, u64::MAX).len() == 8);
+    }
+
+    #[test]
+    fn test_received_snapshot_is_only_completed_with_a_matching_manifest() {
+        let (snapshot, manifest) = sample_snapshot();
+        let mut store = new_store();
+        let mut pending = PendingSnapshotUpload::new();
+
+        pending
+            .receive(&mut store, 0, &snapshot[..6], SystemTime::UNIX_EPOCH)
+            .unwrap();
+        assert!(pending
+            .receive(&mut store, 4, &snapshot[6..], SystemTime::UNIX_EPOCH)
+            .is_err());
+        assert_eq!(
+            pending.finish_received(&mut store, manifest.clone(), SystemTime::UNIX_EPOCH),
+            Err(SnapshotError::InvalidManifest(
+                "Total size does not match the received snapshot".into()
+            ))
+        );
+        pending
+            .receive(&mut store, 6, &snapshot[6..], SystemTime::UNIX_EPOCH)
+            .unwrap();
+        assert_eq!(
+            pending.finish_received(
+                &mut store,
+                SnapshotManifest {
+                    sha256: Sha256::digest(b"another snapshot").to_vec(),
+                    ..manifest.clone()
+                },
+                SystemTime::UNIX_EPOCH
+            ),
+            Err(SnapshotError::ChecksumMismatch)
+        );
+        assert_eq!(store.latest_completed(), None);
+        assert!(pending.is_in_progress());
+
+        assert_eq!(
+            pending.finish_received(&mut store, manifest.clone(), SystemTime::UNIX_EPOCH),
+            Ok(manifest)
+        );
+        let info = store.latest_completed().unwrap();
+        assert_eq!(info.source, SnapshotSource::Received);
+        assert_eq!(store.read_all(info.id).unwrap(), snapshot);
+        assert!(!pending.is_in_progress());
+    }
+}
@@ -0,0 +1,109 @@
+/// Moves the state one schema version forward
+pub type StateMigration<S> = fn(&mut S) -> Result<(), String>;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum StateSchemaError {
+    /// The state was written by a canister with a newer schema, it cannot be downgraded
+    NewerVersion {
+        found: u32,
+        current: u32,
+    },
+    MigrationFailed {
+        from_version: u32,
+        reason: String,
+    },
+}
+
+/// Ordered migrations of the state a canister keeps across upgrades. The migration at
+/// index `i` moves the state from version `i` to version `i + 1`, so the current version
+/// is the number of migrations. Migrations are only ever appended.
+pub struct StateSchema<S: 'static> {
+    migrations: &'static [StateMigration<S>],
+}
+
+impl<S> StateSchema<S> {
+    pub const fn new(migrations: &'static [StateMigration<S>]) -> Self {
+        Self { migrations }
+    }
+
+    pub fn current_version(&self) -> u32 {
+        self.migrations.len() as u32
+    }
+
+    /// Runs every migration from `version` to the current version in order
+    pub fn migrate(&self, state: &mut S, version: u32) -> Result<(), StateSchemaError> {
+        if version > self.current_version() {
+            return Err(StateSchemaError::NewerVersion {
+                found: version,
+                current: self.current_version(),
+            });
+        }
+
+        for (from_version, migration) in self.migrations.iter().enumerate().skip(version as usize) {
+            migration(state).map_err(|reason| StateSchemaError::MigrationFailed {
+                from_version: from_version as u32,
+                reason,
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SCHEMA: StateSchema<Vec<u32>> = StateSchema::new(&[
+        |state| {
+            state.push(1);
+            Ok(())
+        },
+        |state| {
+            state.push(2);
+            Ok(())
+        },
+        |state| {
+            if state.contains(&0) {
+                return Err("unexpected 0".into());
+            }
+            state.push(3);
+            Ok(())
+        },
+    ]);
+
+    #[test]
+    fn test_migrations_run_in_order_from_the_stored_version() {
+        assert_eq!(SCHEMA.current_version(), 3);
+
+        let mut state = vec![];
+        SCHEMA.migrate(&mut state, 0).unwrap();
+        assert_eq!(state, vec![1, 2, 3]);
+
+        let mut state = vec![];
+        SCHEMA.migrate(&mut state, 2).unwrap();
+        assert_eq!(state, vec![3]);
+
+        let mut state = vec![];
+        SCHEMA.migrate(&mut state, 3).unwrap();
+        assert!(state.is_empty());
+    }
+
+    #[test]
+    fn test_migration_errors() {
+        assert_eq!(
+            SCHEMA.migrate(&mut vec![], 4),
+            Err(StateSchemaError::NewerVersion {
+                found: 4,
+                current: 3
+            })
+        );
+        assert_eq!(
+            SCHEMA.migrate(&mut vec![0], 1),
+            Err(StateSchemaError::MigrationFailed {
+                from_version: 2,
+                reason: "unexpected 0".into()
+            })
+        );
+    }
+}
@@ -39,7 +39,37 @@ pub const RESERVED_NUMBER_OF_INSTRUCTIONS_FOR_INSTALL_CODE: u128 = 300_000_000_0
 pub const THRESHOLD_NUMBER_OF_DAYS_TO_KEEP_CANISTER_RUNNING: u128 = 1;
 pub const MAX_NUMBER_OF_DAYS_TO_KEEP_CANISTER_RUNNING: u128 = 7;
 
+// default, the limit can be changed network wide and overridden per creator
 pub const MAX_LIMIT_FOR_CREATOR_DAO_SNS_TOKEN: usize = 2;
+pub const MAX_ATTEMPTS_FOR_CREATOR_DAO_DEPLOYMENT: u32 = 3;
+// deployments not checkpointed for this long are considered stuck
+pub const CREATOR_DAO_DEPLOYMENT_STALE_AFTER_SECS: u64 = 15 * 60;
+// cached creator token balances are refreshed at most this often in the background
+pub const TOKEN_HOLDINGS_REFRESH_INTERVAL_SECS: u64 = 24 * 60 * 60;
+// receiving canisters that could not be notified of a creator token transfer are retried
+pub const MAX_ATTEMPTS_FOR_CREATOR_TOKEN_TRANSFER_NOTIFICATION: u32 = 5;
+pub const CREATOR_TOKEN_TRANSFER_RETRY_AFTER_SECS: u64 = 5 * 60;
+// creator token ledgers deduplicate transfers created within this window, older ones are
+// looked up in the ledger's transactions instead of being sent again
+pub const CREATOR_TOKEN_LEDGER_DEDUPLICATION_WINDOW_SECS: u64 = 24 * 60 * 60;
+// pump and dump earnings summaries are batched and pushed to the subnet orchestrator this often
+pub const PUMP_N_DUMP_EARNINGS_SUMMARY_INTERVAL_SECS: u64 = 5 * 60;
+// entries written to a snapshot in each message while it is generated in the background
+pub const SNAPSHOT_GENERATION_BATCH_SIZE: usize = 500;
+// snapshot bytes checked in each message before a snapshot is loaded in the background
+pub const SNAPSHOT_LOAD_VERIFY_BATCH_BYTES: u64 = 16 * 1024 * 1024;
+// stays below the message size limit for snapshot chunks
+pub const SNAPSHOT_TRANSFER_CHUNK_SIZE: u64 = 1024 * 1024;
+// completed snapshots kept in stable memory, older ones are dropped
+pub const MAX_STORED_SNAPSHOTS: usize = 3;
+// snapshots of lost user canisters uploaded to the subnet orchestrator to restore them
+pub const MAX_STORED_RESTORE_SNAPSHOTS: usize = 10;
+// a scheduled backup waits this long between checks on a canister's snapshot generation
+pub const BACKUP_SNAPSHOT_POLL_INTERVAL_SECS: u64 = 5;
+// checks after which a scheduled backup gives up on a canister's snapshot generation
+pub const BACKUP_SNAPSHOT_MAX_POLLS: u32 = 120;
+// the platform orchestrator waits this long between checks on the subnet backups
+pub const NETWORK_BACKUP_POLL_INTERVAL_SECS: u64 = 60;
 
 pub const SNS_TOKEN_GOVERNANCE_MODULE_HASH: &'static str =
     "51fd3d1a529f3f7bad808b19074e761ce3538282ac8189bd7067b4156360c279";
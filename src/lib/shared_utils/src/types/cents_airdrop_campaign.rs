@@ -0,0 +1,145 @@
+use std::time::SystemTime;
+
+use candid::CandidType;
+use serde::{Deserialize, Serialize};
+
+use crate::canister_specific::individual_user_template::types::session::SessionType;
+
+/// Rules a user canister has to satisfy to receive a campaign airdrop.
+/// Unset rules are not checked.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+pub struct CentsAirdropEligibility {
+    pub session_types: Option<Vec<SessionType>>,
+    pub signed_up_after: Option<SystemTime>,
+    pub signed_up_before: Option<SystemTime>,
+    pub active_since: Option<SystemTime>,
+}
+
+/// Details of the user canister an eligibility is checked against
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CentsAirdropUserState {
+    pub session_type: Option<SessionType>,
+    /// Canisters that signed up before the signup time was recorded have no value set
+    pub signed_up_at: Option<SystemTime>,
+    pub last_access_time: Option<SystemTime>,
+}
+
+impl CentsAirdropEligibility {
+    pub fn is_eligible(&self, user_state: &CentsAirdropUserState) -> bool {
+        if let Some(session_types) = &self.session_types {
+            let Some(session_type) = user_state.session_type else {
+                return false;
+            };
+
+            if !session_types.contains(&session_type) {
+                return false;
+            }
+        }
+
+        if let Some(signed_up_after) = self.signed_up_after {
+            match user_state.signed_up_at {
+                Some(signed_up_at) if signed_up_at >= signed_up_after => {}
+                _ => return false,
+            }
+        }
+
+        // users without a recorded signup time signed up before it was being recorded
+        if let (Some(signed_up_before), Some(signed_up_at)) =
+            (self.signed_up_before, user_state.signed_up_at)
+        {
+            if signed_up_at >= signed_up_before {
+                return false;
+            }
+        }
+
+        if let Some(active_since) = self.active_since {
+            match user_state.last_access_time {
+                Some(last_access_time) if last_access_time >= active_since => {}
+                _ => return false,
+            }
+        }
+
+        true
+    }
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CentsAirdropCampaignArgs {
+    pub amount: u64,
+    pub eligibility: CentsAirdropEligibility,
+    pub expires_at: SystemTime,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct CentsAirdropCampaign {
+    pub campaign_id: u64,
+    pub amount: u64,
+    pub eligibility: CentsAirdropEligibility,
+    pub expires_at: SystemTime,
+    pub created_at: SystemTime,
+}
+
+impl CentsAirdropCampaign {
+    pub fn is_expired(&self, current_time: SystemTime) -> bool {
+        current_time >= self.expires_at
+    }
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub enum CentsAirdropClaimStatus {
+    Credited,
+    AlreadyCredited,
+    NotEligible,
+    Expired,
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::*;
+
+    #[test]
+    fn test_is_eligible() {
+        let day = Duration::from_secs(24 * 60 * 60);
+        let user_state = CentsAirdropUserState {
+            session_type: Some(SessionType::RegisteredSession),
+            signed_up_at: Some(UNIX_EPOCH + 10 * day),
+            last_access_time: Some(UNIX_EPOCH + 20 * day),
+        };
+
+        assert!(CentsAirdropEligibility::default().is_eligible(&user_state));
+
+        let registered_only = CentsAirdropEligibility {
+            session_types: Some(vec![SessionType::RegisteredSession]),
+            ..Default::default()
+        };
+        assert!(registered_only.is_eligible(&user_state));
+        assert!(!registered_only.is_eligible(&CentsAirdropUserState {
+            session_type: Some(SessionType::AnonymousSession),
+            ..user_state
+        }));
+
+        let early_users = CentsAirdropEligibility {
+            signed_up_before: Some(UNIX_EPOCH + 5 * day),
+            ..Default::default()
+        };
+        assert!(!early_users.is_eligible(&user_state));
+        assert!(early_users.is_eligible(&CentsAirdropUserState {
+            signed_up_at: None,
+            ..user_state
+        }));
+
+        let new_users = CentsAirdropEligibility {
+            signed_up_after: Some(UNIX_EPOCH + 5 * day),
+            ..Default::default()
+        };
+        assert!(new_users.is_eligible(&user_state));
+
+        let active_users = CentsAirdropEligibility {
+            active_since: Some(UNIX_EPOCH + 21 * day),
+            ..Default::default()
+        };
+        assert!(!active_users.is_eligible(&user_state));
+    }
+}
@@ -1,8 +1,13 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    time::SystemTime,
+};
 
 use candid::{CandidType, Principal};
 use serde::{Deserialize, Serialize};
 
+use crate::pagination::{self, PaginationError};
+
 type IndividualProfileId = Principal;
 type RootCanisterId = Principal;
 
@@ -10,12 +15,68 @@ type RootCanisterId = Principal;
 pub struct CreatorDaoTokenStats {
     creator_dao_token_sns_canisters: HashMap<IndividualProfileId, IndividualUserCreatorDaoEntry>,
     pub total_number_of_creator_dao_tokens: u64,
+    #[serde(default)]
+    creator_tokens: BTreeMap<RootCanisterId, CreatorTokenMetadata>,
 }
 
 #[derive(CandidType, Serialize, Deserialize, Debug, Clone)]
 pub struct IndividualUserCreatorDaoEntry {
     pub individual_profile_id: IndividualProfileId,
     pub deployed_canisters: HashSet<RootCanisterId>,
+    /// None from individual canisters that do not report token metadata yet
+    #[serde(default)]
+    pub token_metadata: Option<Vec<CreatorTokenMetadata>>,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CreatorTokenSwapStatus {
+    Open,
+    Ended,
+}
+
+impl CreatorTokenSwapStatus {
+    pub fn new(swap_due_at: SystemTime, current_time: SystemTime) -> Self {
+        if current_time < swap_due_at {
+            Self::Open
+        } else {
+            Self::Ended
+        }
+    }
+}
+
+/// Forwarded by the individual canisters with every creator dao stats push
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct CreatorTokenMetadata {
+    pub root: RootCanisterId,
+    pub ledger: Principal,
+    pub name: String,
+    pub symbol: String,
+    pub logo: Option<String>,
+    pub created_at: SystemTime,
+    pub creator_profile_id: IndividualProfileId,
+    pub creator_canister_id: Principal,
+    /// Derived from `swap_due_at` whenever the token is queried
+    pub swap_status: CreatorTokenSwapStatus,
+    /// None for tokens reported before it was forwarded, those keep the reported status
+    #[serde(default)]
+    pub swap_due_at: Option<SystemTime>,
+}
+
+impl CreatorTokenMetadata {
+    pub fn with_current_swap_status(mut self, current_time: SystemTime) -> Self {
+        if let Some(swap_due_at) = self.swap_due_at {
+            self.swap_status = CreatorTokenSwapStatus::new(swap_due_at, current_time);
+        }
+        self
+    }
+}
+
+#[derive(CandidType, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CreatorTokenSortBy {
+    /// Most recently created tokens first
+    Newest,
+    /// Tokens grouped by their creator, most recently created first within a creator
+    Creator,
 }
 
 impl CreatorDaoTokenStats {
@@ -41,10 +102,228 @@ impl CreatorDaoTokenStats {
                 IndividualUserCreatorDaoEntry {
                     individual_profile_id: individual_user_profile_id,
                     deployed_canisters: vec![root_canister_id].into_iter().collect(),
+                    token_metadata: None,
                 },
             );
 
             self.total_number_of_creator_dao_tokens += 1;
         }
     }
+
+    pub fn remove_entry(
+        &mut self,
+        individual_user_profile_id: IndividualProfileId,
+        root_canister_id: RootCanisterId,
+    ) {
+        let removed = self
+            .creator_dao_token_sns_canisters
+            .get_mut(&individual_user_profile_id)
+            .is_some_and(|individual_user_creator_dao_entry| {
+                individual_user_creator_dao_entry
+                    .deployed_canisters
+                    .remove(&root_canister_id)
+            });
+
+        if removed {
+            self.total_number_of_creator_dao_tokens =
+                self.total_number_of_creator_dao_tokens.saturating_sub(1);
+        }
+
+        self.creator_tokens.remove(&root_canister_id);
+    }
+
+    /// Also records the token in the per creator entries, so tokens only ever
+    /// reported through metadata are counted as well.
+    pub fn upsert_token_metadata(&mut self, token_metadata: CreatorTokenMetadata) {
+        self.insert_new_entry(token_metadata.creator_profile_id, token_metadata.root);
+        self.creator_tokens
+            .insert(token_metadata.root, token_metadata);
+    }
+
+    pub fn get_creator_tokens_with_pagination_cursor(
+        &self,
+        sort_by: CreatorTokenSortBy,
+        from_inclusive_index: u64,
+        limit: u64,
+        current_time: SystemTime,
+    ) -> Result<Vec<CreatorTokenMetadata>, PaginationError> {
+        let mut creator_tokens: Vec<&CreatorTokenMetadata> = self.creator_tokens.values().collect();
+
+        match sort_by {
+            CreatorTokenSortBy::Newest => {
+                creator_tokens.sort_by(|a, b| b.created_at.cmp(&a.created_at))
+            }
+            CreatorTokenSortBy::Creator => creator_tokens.sort_by(|a, b| {
+                a.creator_profile_id
+                    .cmp(&b.creator_profile_id)
+                    .then(b.created_at.cmp(&a.created_at))
+            }),
+        }
+
+        Self::paginate(creator_tokens, from_inclusive_index, limit, current_time)
+    }
+
+    pub fn get_creator_tokens_of_creator_with_pagination_cursor(
+        &self,
+        creator_profile_id: IndividualProfileId,
+        from_inclusive_index: u64,
+        limit: u64,
+        current_time: SystemTime,
+    ) -> Result<Vec<CreatorTokenMetadata>, PaginationError> {
+        let mut creator_tokens: Vec<&CreatorTokenMetadata> = self
+            .creator_tokens
+            .values()
+            .filter(|token_metadata| token_metadata.creator_profile_id == creator_profile_id)
+            .collect();
+
+        creator_tokens.sort_by(|a, b| b.created_at.cmp(&a.created_at));
+
+        Self::paginate(creator_tokens, from_inclusive_index, limit, current_time)
+    }
+
+    fn paginate(
+        creator_tokens: Vec<&CreatorTokenMetadata>,
+        from_inclusive_index: u64,
+        limit: u64,
+        current_time: SystemTime,
+    ) -> Result<Vec<CreatorTokenMetadata>, PaginationError> {
+        let (from_inclusive_index, limit) = pagination::get_pagination_bounds_cursor(
+            from_inclusive_index,
+            limit,
+            creator_tokens.len() as u64,
+        )?;
+
+        Ok(creator_tokens
+            .into_iter()
+            .skip(from_inclusive_index as usize)
+            .take(limit as usize)
+            .map(|token_metadata| {
+                token_metadata
+                    .clone()
+                    .with_current_swap_status(current_time)
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::*;
+
+    fn token_metadata(root: u8, creator: u8, created_at_secs: u64) -> CreatorTokenMetadata {
+        CreatorTokenMetadata {
+            root: Principal::from_slice(&[root]),
+            ledger: Principal::from_slice(&[root, root]),
+            name: format!("token {root}"),
+            symbol: format!("T{root}"),
+            logo: None,
+            created_at: UNIX_EPOCH + Duration::from_secs(created_at_secs),
+            creator_profile_id: Principal::from_slice(&[creator, 0]),
+            creator_canister_id: Principal::from_slice(&[creator, 1]),
+            swap_status: CreatorTokenSwapStatus::Open,
+            swap_due_at: None,
+        }
+    }
+
+    fn roots(creator_tokens: Vec<CreatorTokenMetadata>) -> Vec<Principal> {
+        creator_tokens
+            .into_iter()
+            .map(|token_metadata| token_metadata.root)
+            .collect()
+    }
+
+    #[test]
+    fn test_creator_tokens_discovery() {
+        let mut stats = CreatorDaoTokenStats::default();
+        stats.upsert_token_metadata(token_metadata(1, 2, 10));
+        stats.upsert_token_metadata(token_metadata(2, 1, 20));
+        stats.upsert_token_metadata(token_metadata(3, 2, 30));
+
+        let mut ended = token_metadata(3, 2, 30);
+        ended.swap_due_at = Some(UNIX_EPOCH + Duration::from_secs(40));
+        stats.upsert_token_metadata(ended);
+        let now = UNIX_EPOCH + Duration::from_secs(50);
+
+        assert_eq!(stats.total_number_of_creator_dao_tokens, 3);
+
+        let newest = stats
+            .get_creator_tokens_with_pagination_cursor(CreatorTokenSortBy::Newest, 0, 10, now)
+            .unwrap();
+        assert_eq!(newest[0].swap_status, CreatorTokenSwapStatus::Ended);
+        assert_eq!(newest[1].swap_status, CreatorTokenSwapStatus::Open);
+        assert_eq!(
+            roots(newest),
+            vec![
+                Principal::from_slice(&[3]),
+                Principal::from_slice(&[2]),
+                Principal::from_slice(&[1])
+            ]
+        );
+
+        assert_eq!(
+            roots(
+                stats
+                    .get_creator_tokens_with_pagination_cursor(
+                        CreatorTokenSortBy::Creator,
+                        1,
+                        2,
+                        now
+                    )
+                    .unwrap()
+            ),
+            vec![Principal::from_slice(&[3]), Principal::from_slice(&[1])]
+        );
+
+        assert_eq!(
+            roots(
+                stats
+                    .get_creator_tokens_of_creator_with_pagination_cursor(
+                        Principal::from_slice(&[2, 0]),
+                        0,
+                        10,
+                        now
+                    )
+                    .unwrap()
+            ),
+            vec![Principal::from_slice(&[3]), Principal::from_slice(&[1])]
+        );
+
+        assert_eq!(
+            stats.get_creator_tokens_with_pagination_cursor(CreatorTokenSortBy::Newest, 3, 10, now),
+            Err(PaginationError::ReachedEndOfItemsList)
+        );
+
+        stats.remove_entry(Principal::from_slice(&[2, 0]), Principal::from_slice(&[3]));
+        stats.remove_entry(Principal::from_slice(&[2, 0]), Principal::from_slice(&[3]));
+        assert_eq!(stats.total_number_of_creator_dao_tokens, 2);
+        assert_eq!(
+            roots(
+                stats
+                    .get_creator_tokens_with_pagination_cursor(
+                        CreatorTokenSortBy::Newest,
+                        0,
+                        10,
+                        now
+                    )
+                    .unwrap()
+            ),
+            vec![Principal::from_slice(&[2]), Principal::from_slice(&[1])]
+        );
+    }
+
+    #[test]
+    fn test_creator_token_swap_status() {
+        let swap_due_at = UNIX_EPOCH + Duration::from_secs(100);
+
+        assert_eq!(
+            CreatorTokenSwapStatus::new(swap_due_at, UNIX_EPOCH),
+            CreatorTokenSwapStatus::Open
+        );
+        assert_eq!(
+            CreatorTokenSwapStatus::new(swap_due_at, swap_due_at),
+            CreatorTokenSwapStatus::Ended
+        );
+    }
 }
@@ -1,3 +1,5 @@
 pub mod canister_specific;
+pub mod cents_airdrop_campaign;
 pub mod creator_dao_stats;
+pub mod pump_n_dump_leaderboard;
 pub mod sns_canisters;
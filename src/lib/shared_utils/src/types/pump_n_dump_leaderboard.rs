@@ -0,0 +1,311 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use candid::{CandidType, Nat, Principal};
+use serde::{Deserialize, Serialize};
+
+use crate::pagination::{self, PaginationError};
+
+pub const PUMP_N_DUMP_LEADERBOARD_SIZE: usize = 100;
+
+const SECONDS_IN_A_DAY: u64 = 24 * 60 * 60;
+const SECONDS_IN_A_WEEK: u64 = 7 * SECONDS_IN_A_DAY;
+
+/// Summary pushed by an individual canister to its subnet orchestrator
+/// periodically while its pump and dump state changes.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PumpNDumpEarningsSummary {
+    pub user_principal_id: Principal,
+    pub user_canister_id: Principal,
+    pub net_earnings: Nat,
+    pub net_airdrop_reward: Nat,
+    pub total_pumps: Nat,
+    pub total_dumps: Nat,
+}
+
+impl PumpNDumpEarningsSummary {
+    /// Earnings made by playing (or creating tokens for) pump and dump, airdrops excluded.
+    pub fn game_earnings(&self) -> Nat {
+        if self.net_earnings <= self.net_airdrop_reward {
+            Nat::from(0_u32)
+        } else {
+            self.net_earnings.clone() - self.net_airdrop_reward.clone()
+        }
+    }
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LeaderboardPeriod {
+    Daily,
+    Weekly,
+    AllTime,
+}
+
+impl LeaderboardPeriod {
+    /// Windows are aligned to UTC day/week boundaries so that every subnet and the
+    /// platform orchestrator agree on which window an entry belongs to.
+    pub fn window_id(&self, at: SystemTime) -> u64 {
+        let seconds = at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+
+        match self {
+            LeaderboardPeriod::Daily => seconds / SECONDS_IN_A_DAY,
+            LeaderboardPeriod::Weekly => seconds / SECONDS_IN_A_WEEK,
+            LeaderboardPeriod::AllTime => 0,
+        }
+    }
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LeaderboardEntry {
+    pub user_principal_id: Principal,
+    pub user_canister_id: Principal,
+    /// Earnings made within the window of the leaderboard
+    pub earnings: Nat,
+    pub total_pumps: Nat,
+    pub total_dumps: Nat,
+    pub updated_at: SystemTime,
+}
+
+/// Entry sent from a subnet orchestrator to the platform orchestrator
+/// whenever it makes it into the subnet's top entries for a period.
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LeaderboardEntryUpdate {
+    pub period: LeaderboardPeriod,
+    pub window_id: u64,
+    pub entry: LeaderboardEntry,
+}
+
+#[derive(CandidType, Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LeaderboardPage {
+    pub period: LeaderboardPeriod,
+    pub window_id: u64,
+    pub total_entries: u64,
+    pub entries: Vec<LeaderboardEntry>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct LeaderboardWindow {
+    window_id: u64,
+    // sorted by earnings in descending order
+    entries: Vec<LeaderboardEntry>,
+}
+
+impl LeaderboardWindow {
+    pub fn window_id(&self) -> u64 {
+        self.window_id
+    }
+
+    pub fn entries(&self) -> &[LeaderboardEntry] {
+        &self.entries
+    }
+
+    /// Clears the window if `window_id` is newer than the current one.
+    /// Returns true if the window was rolled over.
+    pub fn roll_over_if_required(&mut self, window_id: u64) -> bool {
+        if window_id > self.window_id {
+            self.window_id = window_id;
+            self.entries.clear();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Inserts or replaces the entry for the user and keeps only the top entries.
+    /// Earnings within a window never decrease, so dropping entries that fall out of
+    /// the top never loses a user who could climb back in without a new update.
+    /// Returns true if the entry is part of the top entries after insertion.
+    pub fn upsert_entry(&mut self, entry: LeaderboardEntry) -> bool {
+        self.entries
+            .retain(|e| e.user_principal_id != entry.user_principal_id);
+
+        let position = self
+            .entries
+            .iter()
+            .position(|e| e.earnings < entry.earnings)
+            .unwrap_or(self.entries.len());
+
+        if position >= PUMP_N_DUMP_LEADERBOARD_SIZE {
+            return false;
+        }
+
+        self.entries.insert(position, entry);
+        self.entries.truncate(PUMP_N_DUMP_LEADERBOARD_SIZE);
+
+        true
+    }
+
+    pub fn get_entries_with_pagination_cursor(
+        &self,
+        from_inclusive_index: u64,
+        limit: u64,
+    ) -> Result<Vec<LeaderboardEntry>, PaginationError> {
+        let (from_inclusive_index, limit) = pagination::get_pagination_bounds_cursor(
+            from_inclusive_index,
+            limit,
+            self.entries.len() as u64,
+        )?;
+
+        Ok(
+            self.entries[from_inclusive_index as usize..(from_inclusive_index + limit) as usize]
+                .to_vec(),
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct PumpNDumpLeaderboard {
+    daily: LeaderboardWindow,
+    weekly: LeaderboardWindow,
+    all_time: LeaderboardWindow,
+}
+
+impl PumpNDumpLeaderboard {
+    pub fn get_window(&self, period: LeaderboardPeriod) -> &LeaderboardWindow {
+        match period {
+            LeaderboardPeriod::Daily => &self.daily,
+            LeaderboardPeriod::Weekly => &self.weekly,
+            LeaderboardPeriod::AllTime => &self.all_time,
+        }
+    }
+
+    pub fn get_window_mut(&mut self, period: LeaderboardPeriod) -> &mut LeaderboardWindow {
+        match period {
+            LeaderboardPeriod::Daily => &mut self.daily,
+            LeaderboardPeriod::Weekly => &mut self.weekly,
+            LeaderboardPeriod::AllTime => &mut self.all_time,
+        }
+    }
+
+    /// Merges an entry computed for `window_id` of `period`.
+    /// Entries for windows older than the current one are dropped.
+    pub fn insert_entry_update(&mut self, entry_update: LeaderboardEntryUpdate) -> bool {
+        let window = self.get_window_mut(entry_update.period);
+        window.roll_over_if_required(entry_update.window_id);
+
+        if entry_update.window_id < window.window_id() {
+            return false;
+        }
+
+        window.upsert_entry(entry_update.entry)
+    }
+
+    pub fn get_leaderboard_page(
+        &self,
+        period: LeaderboardPeriod,
+        current_time: SystemTime,
+        from_inclusive_index: u64,
+        limit: u64,
+    ) -> Result<LeaderboardPage, PaginationError> {
+        let window = self.get_window(period);
+        let current_window_id = period.window_id(current_time);
+
+        // no update received yet in the current window
+        if window.window_id() < current_window_id {
+            return Err(PaginationError::ReachedEndOfItemsList);
+        }
+
+        let entries = window.get_entries_with_pagination_cursor(from_inclusive_index, limit)?;
+
+        Ok(LeaderboardPage {
+            period,
+            window_id: window.window_id(),
+            total_entries: window.entries().len() as u64,
+            entries,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn entry(user: u8, earnings: u64) -> LeaderboardEntry {
+        LeaderboardEntry {
+            user_principal_id: Principal::from_slice(&[user]),
+            user_canister_id: Principal::from_slice(&[user, user]),
+            earnings: Nat::from(earnings),
+            total_pumps: Nat::from(0_u32),
+            total_dumps: Nat::from(0_u32),
+            updated_at: UNIX_EPOCH,
+        }
+    }
+
+    #[test]
+    fn test_upsert_entry_keeps_entries_sorted_and_unique() {
+        let mut window = LeaderboardWindow::default();
+
+        assert!(window.upsert_entry(entry(1, 10)));
+        assert!(window.upsert_entry(entry(2, 30)));
+        assert!(window.upsert_entry(entry(3, 20)));
+        assert!(window.upsert_entry(entry(1, 40)));
+
+        let earnings: Vec<Nat> = window
+            .entries()
+            .iter()
+            .map(|e| e.earnings.clone())
+            .collect();
+        assert_eq!(
+            earnings,
+            vec![Nat::from(40_u32), Nat::from(30_u32), Nat::from(20_u32)]
+        );
+    }
+
+    #[test]
+    fn test_upsert_entry_truncates_to_leaderboard_size() {
+        let mut window = LeaderboardWindow::default();
+
+        (0..PUMP_N_DUMP_LEADERBOARD_SIZE as u64).for_each(|i| {
+            assert!(window.upsert_entry(entry(i as u8, 100 + i)));
+        });
+
+        assert!(!window.upsert_entry(entry(250, 1)));
+        assert!(window.upsert_entry(entry(251, 1000)));
+        assert_eq!(window.entries().len(), PUMP_N_DUMP_LEADERBOARD_SIZE);
+        assert_eq!(window.entries()[0].earnings, Nat::from(1000_u32));
+    }
+
+    #[test]
+    fn test_insert_entry_update_rolls_over_windows() {
+        let mut leaderboard = PumpNDumpLeaderboard::default();
+        let day_one = UNIX_EPOCH + Duration::from_secs(SECONDS_IN_A_DAY * 10);
+        let day_two = day_one + Duration::from_secs(SECONDS_IN_A_DAY);
+
+        leaderboard.insert_entry_update(LeaderboardEntryUpdate {
+            period: LeaderboardPeriod::Daily,
+            window_id: LeaderboardPeriod::Daily.window_id(day_one),
+            entry: entry(1, 10),
+        });
+
+        assert!(leaderboard
+            .get_leaderboard_page(LeaderboardPeriod::Daily, day_one, 0, 10)
+            .is_ok());
+        assert_eq!(
+            leaderboard.get_leaderboard_page(LeaderboardPeriod::Daily, day_two, 0, 10),
+            Err(PaginationError::ReachedEndOfItemsList)
+        );
+
+        leaderboard.insert_entry_update(LeaderboardEntryUpdate {
+            period: LeaderboardPeriod::Daily,
+            window_id: LeaderboardPeriod::Daily.window_id(day_two),
+            entry: entry(2, 5),
+        });
+
+        // stale update for the previous day is ignored
+        assert!(!leaderboard.insert_entry_update(LeaderboardEntryUpdate {
+            period: LeaderboardPeriod::Daily,
+            window_id: LeaderboardPeriod::Daily.window_id(day_one),
+            entry: entry(3, 50),
+        }));
+
+        let page = leaderboard
+            .get_leaderboard_page(LeaderboardPeriod::Daily, day_two, 0, 10)
+            .unwrap();
+        assert_eq!(page.total_entries, 1);
+        assert_eq!(
+            page.entries[0].user_principal_id,
+            Principal::from_slice(&[2])
+        );
+    }
+}